@@ -18,7 +18,11 @@
       queuePersistSetup,
       deleteAccount,
       oauthLogin,
-      importClaudeCliCredentials,
+      importCliCredentials,
+      saveAccount,
+      setAccountCliLink,
+      activateCliAccount,
+      setAccountPreference,
       oauthExchangeCode,
       log,
       makeId,
@@ -41,7 +45,96 @@
         savedTokenMask,
       });
       const hasToken = row.dataset.hasToken === '1';
-      return { id, name, token, hasToken };
+      const cliLinked = row.dataset.cliLinked === '1';
+      const cliSyncedAt = Number(row.dataset.cliSyncedAt) || null;
+      const mutedUntil = Number(row.dataset.mutedUntil) || null;
+      const priority = Number(row.querySelector('.account-priority')?.value) || 0;
+      const reserve = Boolean(row.querySelector('.account-reserve')?.checked);
+      const reloginReason = row.dataset.reloginReason || null;
+      return { id, name, token, hasToken, cliLinked, cliSyncedAt, mutedUntil, priority, reserve, reloginReason };
+    }
+
+    function formatSyncTime(ms) {
+      const d = new Date(ms);
+      return `${String(d.getHours()).padStart(2, '0')}:${String(d.getMinutes()).padStart(2, '0')}`;
+    }
+
+    function renderCliLinkState(row) {
+      const stateEl = row.querySelector('.cli-link-state');
+      if (!stateEl) return;
+      const syncedAt = Number(row.dataset.cliSyncedAt) || 0;
+      if (row.dataset.cliLinked !== '1') {
+        stateEl.textContent = '';
+      } else {
+        stateEl.textContent = syncedAt ? `🔗 最終同期 ${formatSyncTime(syncedAt)}` : '🔗 同期待ち';
+      }
+    }
+
+    function setRowCliLinked(row, linked, syncedAt) {
+      row.dataset.cliLinked = linked ? '1' : '0';
+      row.dataset.cliSyncedAt = linked && syncedAt ? String(syncedAt) : '';
+      const toggle = row.querySelector('.cli-link-toggle');
+      if (toggle) toggle.checked = linked;
+      renderCliLinkState(row);
+    }
+
+    // "Needs re-login" is set by the backend when the refresh token is rejected.
+    function setRowRelogin(row, reason) {
+      row.dataset.reloginReason = reason || '';
+      const stateEl = row.querySelector('.relogin-state');
+      if (!stateEl) return;
+      stateEl.textContent = reason ? '🔑 再ログインが必要' : '';
+      stateEl.title = reason ? `トークンを更新できません: ${reason}` : '';
+    }
+
+    function setReloginReason(service, id, reason) {
+      const row = findRow(service, id);
+      if (row) setRowRelogin(row, reason);
+    }
+
+    // Reflect a `cli-account-switched` event: only the activated row stays linked.
+    function applyCliSwitch({ service, id } = {}) {
+      if (!serviceMeta[service]) return;
+      const list = query(serviceMeta[service].listId);
+      for (const row of list.querySelectorAll('.account-row')) {
+        const active = row.dataset.accountId === id;
+        if (active) {
+          row.dataset.hasToken = '1';
+        }
+        setRowCliLinked(row, active, active ? (Number(row.dataset.cliSyncedAt) || Date.now()) : null);
+      }
+    }
+
+    // Keep the row in step with the card's mute menu, so `collectAccounts` carries it.
+    function setMutedUntil(service, id, mutedUntil) {
+      const row = findRow(service, id);
+      if (row) row.dataset.mutedUntil = mutedUntil ? String(mutedUntil) : '';
+    }
+
+    function findRow(service, id) {
+      const list = query(serviceMeta[service].listId);
+      return Array.from(list.querySelectorAll('.account-row'))
+        .find((candidate) => candidate.dataset.accountId === id) || null;
+    }
+
+    // Reflect a `cli-credentials-synced` event. Returns false when the row is gone.
+    function applyCliSync(service, id, { syncedAt, error } = {}) {
+      const row = findRow(service, id);
+      if (!row) return false;
+      const statusEl = row.querySelector('.oauth-status');
+      if (error) {
+        if (statusEl) {
+          statusEl.textContent = `CLI 同期失敗: ${error}`;
+          statusEl.dataset.status = 'error';
+        }
+        return true;
+      }
+      row.dataset.cliSyncedAt = syncedAt ? String(syncedAt) : '';
+      row.dataset.hasToken = '1';
+      const tokenInput = row.querySelector('.account-token');
+      if (tokenInput) { tokenInput.value = savedTokenMask; row.dataset.tokenMasked = '1'; }
+      renderCliLinkState(row);
+      return true;
     }
 
     function readAccountsFromDom(service) {
@@ -51,9 +144,7 @@
     }
 
     function upsertDomTokenState(service, id, hasToken) {
-      const list = query(serviceMeta[service].listId);
-      const row = Array.from(list.querySelectorAll('.account-row'))
-        .find((candidate) => candidate.dataset.accountId === id);
+      const row = findRow(service, id);
       if (!row) return;
       row.dataset.hasToken = hasToken ? '1' : '0';
       const tokenInput = row.querySelector('.account-token');
@@ -77,6 +168,10 @@
         row.className = 'account-row';
         row.dataset.accountId = acc.id || makeAccountId(service);
         row.dataset.hasToken = acc.hasToken ? '1' : '0';
+        row.dataset.cliLinked = acc.cliLinked ? '1' : '0';
+        row.dataset.cliSyncedAt = acc.cliSyncedAt ? String(acc.cliSyncedAt) : '';
+        row.dataset.mutedUntil = acc.mutedUntil ? String(acc.mutedUntil) : '';
+        row.dataset.reloginReason = acc.reloginReason || '';
 
         const tokenView = deriveTokenInputValue({
           hasToken: acc.hasToken,
@@ -84,16 +179,37 @@
           savedTokenMask,
         });
         row.dataset.tokenMasked = tokenView.tokenMasked ? '1' : '0';
-        const canImportClaudeCli = service === 'claude' && typeof importClaudeCliCredentials === 'function';
+        const canImportClaudeCli = service === 'claude' && typeof importCliCredentials === 'function';
+        const canImportCodexCli = service === 'codex' && typeof importCliCredentials === 'function';
+        const canLinkCli = (service === 'claude' || service === 'codex') && typeof setAccountCliLink === 'function';
+        const canActivateCli = service === 'claude' && typeof activateCliAccount === 'function';
 
         row.innerHTML = `
           <input class="account-name" type="text" maxlength="256" placeholder="表示名" value="${escHtml(acc.name || '')}">
           <input class="account-token" type="password" maxlength="16384" autocomplete="off" placeholder="eyJhbG... / sk-..." value="${escHtml(tokenView.tokenValue)}">
           <button class="btn-mini btn-oauth-login" type="button" title="ログインURLをコピー">🔗 URLコピー</button>
           ${canImportClaudeCli ? '<button class="btn-mini btn-cli-import" type="button" title="Claude CLI から取り込み">📥 CLI取込</button>' : ''}
+          ${canImportCodexCli ? `<span class="cli-import">
+            <select class="cli-import-mode" title="Codex CLI からの取り込み方法">
+              <option value="accessOnly">アクセストークンのみ</option>
+              <option value="takeOver">更新も引き継ぐ (CLI は再ログインが必要)</option>
+            </select>
+            <button class="btn-mini btn-cli-import" type="button" title="Codex CLI から取り込み">📥 CLI取込</button>
+          </span>` : ''}
+          ${canLinkCli ? `<label class="cli-link" title="CLI の認証情報ファイルを監視し、更新されたトークンを自動で取り込みます（このアプリからは更新しません）">
+            <input class="cli-link-toggle" type="checkbox"${acc.cliLinked ? ' checked' : ''}> CLI連携
+          </label>
+          <span class="cli-link-state"></span>` : ''}
+          ${canActivateCli ? '<button class="btn-mini btn-cli-activate" type="button" title="このアカウントで Claude Code CLI にログインし直します (~/.claude/.credentials.json を書き換え、元のファイルは .bak に退避)">▶ CLIで使う</button>' : ''}
+          ${typeof setAccountPreference === 'function' ? `<span class="account-preference">
+            <input class="account-priority" type="number" min="-10" max="10" step="1" value="${Number(acc.priority) || 0}" title="おすすめ順の優先度 (-10〜10)">
+            <label class="account-reserve-label" title="他に使えるアカウントが無いときだけおすすめします"><input class="account-reserve" type="checkbox"${acc.reserve ? ' checked' : ''}> 予備</label>
+          </span>` : ''}
           <button class="btn-mini btn-remove-account" type="button">削除</button>
+          <span class="relogin-state"></span>
           <span class="oauth-status" data-status=""></span>
         `;
+        setRowRelogin(row, acc.reloginReason);
 
         row.querySelector('.btn-remove-account').addEventListener('click', async () => {
           const removed = accountFromRow(row);
@@ -162,6 +278,7 @@
                 row.dataset.hasToken = '1';
                 const tokenInput = row.querySelector('.account-token');
                 if (tokenInput) { tokenInput.value = savedTokenMask; row.dataset.tokenMasked = '1'; }
+                setRowRelogin(row, null);
                 queuePersistSetup();
               } else {
                 statusEl.textContent = exchangeResult.message || 'ログイン失敗';
@@ -173,6 +290,7 @@
               row.dataset.hasToken = '1';
               const tokenInput = row.querySelector('.account-token');
               if (tokenInput) { tokenInput.value = savedTokenMask; row.dataset.tokenMasked = '1'; }
+              setRowRelogin(row, null);
               queuePersistSetup();
             } else if (result.pending) {
               // Codex async flow: backend is waiting for localhost callback.
@@ -190,6 +308,7 @@
                   row.dataset.hasToken = '1';
                   const tokenInput = row.querySelector('.account-token');
                   if (tokenInput) { tokenInput.value = savedTokenMask; row.dataset.tokenMasked = '1'; }
+                  setRowRelogin(row, null);
                   queuePersistSetup();
                   break;
                 }
@@ -219,18 +338,21 @@
             const acc = accountFromRow(row);
             const statusEl = row.querySelector('.oauth-status');
             const loginBtn = row.querySelector('.btn-oauth-login');
-            statusEl.textContent = 'Claude CLI 認証情報を取り込み中...';
+            const cliLabel = service === 'codex' ? 'Codex CLI' : 'Claude CLI';
+            statusEl.textContent = `${cliLabel} 認証情報を取り込み中...`;
             statusEl.dataset.status = 'pending';
             loginBtn.disabled = true;
             importBtn.disabled = true;
             try {
-              const result = await importClaudeCliCredentials({ service, id: acc.id });
+              const modeSelect = row.querySelector('.cli-import-mode');
+              const result = await importCliCredentials({ service, id: acc.id, mode: modeSelect ? modeSelect.value : 'takeOver' });
               if (result.success) {
                 statusEl.textContent = result.message || '取り込み成功';
                 statusEl.dataset.status = 'ok';
                 row.dataset.hasToken = '1';
                 const tokenInput = row.querySelector('.account-token');
                 if (tokenInput) { tokenInput.value = savedTokenMask; row.dataset.tokenMasked = '1'; }
+                setRowRelogin(row, null);
                 queuePersistSetup();
               } else {
                 statusEl.textContent = result.message || '取り込み失敗';
@@ -248,6 +370,84 @@
           });
         }
 
+        const linkToggle = row.querySelector('.cli-link-toggle');
+        if (linkToggle) {
+          renderCliLinkState(row);
+          linkToggle.addEventListener('change', async () => {
+            const acc = accountFromRow(row);
+            const statusEl = row.querySelector('.oauth-status');
+            const linked = linkToggle.checked;
+            linkToggle.disabled = true;
+            try {
+              // The backend only links accounts it already knows about.
+              if (linked) await saveAccount({ service, id: acc.id, name: acc.name });
+              const entry = await setAccountCliLink({ service, id: acc.id, linked });
+              if (entry.cliLinked) {
+                // Linking one account unlinks the others of the service.
+                for (const other of row.parentElement?.querySelectorAll('.account-row') || []) {
+                  if (other !== row && other.dataset.cliLinked === '1') setRowCliLinked(other, false, null);
+                }
+              }
+              setRowCliLinked(row, entry.cliLinked, entry.cliSyncedAt);
+              if (entry.hasToken) {
+                row.dataset.hasToken = '1';
+                const tokenInput = row.querySelector('.account-token');
+                if (tokenInput) { tokenInput.value = savedTokenMask; row.dataset.tokenMasked = '1'; }
+              }
+              statusEl.textContent = linked ? 'CLI と連携しました' : 'CLI 連携を解除しました';
+              statusEl.dataset.status = 'ok';
+            } catch (e) {
+              const msg = e && typeof e === 'string' ? e : (e?.message || String(e));
+              linkToggle.checked = !linked;
+              statusEl.textContent = msg;
+              statusEl.dataset.status = 'error';
+              log(`CLI 連携エラー (${serviceMeta[service].label}): ${msg}`, 'warn');
+            } finally {
+              linkToggle.disabled = false;
+              renderCliLinkState(row);
+            }
+          });
+        }
+
+        const activateBtn = row.querySelector('.btn-cli-activate');
+        if (activateBtn) {
+          activateBtn.addEventListener('click', async () => {
+            const acc = accountFromRow(row);
+            const statusEl = row.querySelector('.oauth-status');
+            activateBtn.disabled = true;
+            statusEl.textContent = 'Claude Code CLI を切り替え中...';
+            statusEl.dataset.status = 'pending';
+            try {
+              const result = await activateCliAccount({ service, id: acc.id });
+              applyCliSwitch(result);
+              statusEl.textContent = `Claude Code CLI をこのアカウントに切り替えました (${result.path})`;
+              statusEl.dataset.status = 'ok';
+            } catch (e) {
+              const msg = e && typeof e === 'string' ? e : (e?.message || String(e));
+              statusEl.textContent = msg;
+              statusEl.dataset.status = 'error';
+              log(`CLI 切り替えエラー (${serviceMeta[service].label}): ${msg}`, 'warn');
+            } finally {
+              activateBtn.disabled = false;
+            }
+          });
+        }
+
+        const persistPreference = async () => {
+          const acc = accountFromRow(row);
+          const priority = Math.max(-10, Math.min(10, Math.trunc(acc.priority)));
+          row.querySelector('.account-priority').value = String(priority);
+          try {
+            await saveAccount({ service, id: acc.id, name: acc.name });
+            await setAccountPreference({ service, id: acc.id, priority, reserve: acc.reserve });
+          } catch (e) {
+            const msg = e && typeof e === 'string' ? e : (e?.message || String(e));
+            log(`優先度の保存に失敗しました (${serviceMeta[service].label}): ${msg}`, 'warn');
+          }
+        };
+        row.querySelector('.account-priority')?.addEventListener('change', persistPreference);
+        row.querySelector('.account-reserve')?.addEventListener('change', persistPreference);
+
         row.querySelector('.account-name').addEventListener('input', queuePersistSetup);
 
         const tokenInput = row.querySelector('.account-token');
@@ -282,6 +482,12 @@
           name: acc.name || `${serviceMeta[service].label} ${idx + 1}`,
           token: acc.token || '',
           hasToken: Boolean(acc.hasToken),
+          mutedUntil: acc.mutedUntil,
+          cliLinked: acc.cliLinked,
+          cliSyncedAt: acc.cliSyncedAt,
+          priority: acc.priority,
+          reserve: acc.reserve,
+          reloginReason: acc.reloginReason,
         }));
       }
       return collected;
@@ -296,6 +502,10 @@
       addAccountRow,
       collectAccounts,
       upsertDomTokenState,
+      applyCliSync,
+      applyCliSwitch,
+      setMutedUntil,
+      setReloginReason,
     };
   }
 
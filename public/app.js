@@ -4,7 +4,7 @@
 const state = {
   polling: false,
   pollInFlight: false,
-  timer: null,
+  pendingServices: null,
   pollStartedAt: null,
   pollInterval: 600,
   ringTimer: null,
@@ -12,20 +12,32 @@ const state = {
   hasSavedMinimalBounds: false,
   accounts: { claude: [], codex: [] }, // [{ id, name, token }]
   services: {},   // { id: { label, windows: [{name, utilization, resetsAt}], status, lastRaw } }
+  recommendations: [], // ranked per service by the backend
   logs: [],
   rawResponses: {},
   history: {},    // { 'serviceKey:windowName': [util1, util2, ...] }
-  notifySettings: { critical: true, recovery: true, warning: false, thresholdWarning: 75, thresholdCritical: 90 },
+  notifySettings: {
+    critical: true, recovery: true, warning: false, projected: true, thresholdWarning: 75, thresholdCritical: 90,
+    hysteresis: 2, cooldownMinutes: 15, renotifyMinutes: 0,
+  },
+  quietHours: [], // [{ enabled, channels, days, start, end, timezone, allowExhausted }]
   externalNotify: {
-    discord: { enabled: false, webhookUrl: '', critical: true, recovery: true, warning: false },
-    pushover: { enabled: false, apiToken: '', userKey: '', critical: true, recovery: true, warning: false },
+    // Secrets live in the OS keychain; these hold the masked values from the backend.
+    discord: { enabled: false, webhookUrl: '', hasSecret: false, critical: true, recovery: true, warning: false, projected: false },
+    slack: { enabled: false, webhookUrl: '', hasSecret: false, critical: true, recovery: true, warning: false, projected: false },
+    teams: { enabled: false, webhookUrl: '', hasSecret: false, critical: true, recovery: true, warning: false, projected: false },
+    pushover: { enabled: false, apiToken: '', userKey: '', hasSecret: false, critical: true, recovery: true, warning: false, projected: false },
+    ntfy: { enabled: false, serverUrl: 'https://ntfy.sh', topic: '', accessToken: '', hasSecret: false, critical: true, recovery: true, warning: false, projected: false },
+    gotify: { enabled: false, serverUrl: '', appToken: '', hasSecret: false, critical: true, recovery: true, warning: false, projected: false },
+    webhooks: [],
   },
   usageExport: { enabled: false, path: '' },
-  rateLimitUntil: {}, // { serviceKey: epochMs } — skip polling until this time
-  rateLimitBackoff: {}, // { serviceKey: number } — consecutive 429 count (no retry-after)
+  metrics: { enabled: false, bindAddress: '127.0.0.1', port: 9464 },
+  mcp: { enabled: false, port: 9465 },
+  cliSwitch: { autoSwitch: false },
+  tokenRefresh: { leadMinutes: 5 },
 };
 
-const THRESHOLDS_EXHAUSTED = 100;
 const POLL_RING_TICK_MS = 1000;
 const RATE_LIMIT_RETRY_AFTER_RE = /\[retry-after:(\d+)\]/;
 const SETUP_PERSIST_DEBOUNCE_MS = 200;
@@ -40,6 +52,9 @@ const NOTIFY_THRESHOLD_MIN = 1;
 const NOTIFY_THRESHOLD_MAX = 99;
 const NOTIFY_THRESHOLD_WARNING_DEFAULT = 75;
 const NOTIFY_THRESHOLD_CRITICAL_DEFAULT = 90;
+const NOTIFY_HYSTERESIS_MAX = 20;
+const NOTIFY_MINUTES_MAX = 1440;
+const NOTIFY_RENOTIFY_MIN = 5;
 const MINIMAL_FALLBACK_CARD_HEIGHT = 240;
 const MINIMAL_MIN_HEIGHT_FLOOR = 220;
 const MINIMAL_WINDOW_MAX_SIZE = 2000;
@@ -59,10 +74,10 @@ if (!window.AccountUi) {
   throw new Error('AccountUi helpers are required');
 }
 const {
-  deriveServiceStatus,
-  buildTransitionEffects,
   calcElapsedPct: calcElapsedPctValue,
   computePollingState,
+  parseHeaderLines,
+  formatHeaderLines,
 } = window.UiLogic;
 // Minimal-mode sizing — single source of truth: card width (must match CSS --minimal-card-width)
 const MINIMAL_CARD_WIDTH = 290;
@@ -119,24 +134,6 @@ const toErrorMessage = (error) => {
   return String(error ?? 'unknown error');
 };
 
-function classifyUtilization(pct) {
-  return window.UiLogic.classifyUtilization(pct, state.notifySettings, THRESHOLDS_EXHAUSTED);
-}
-
-function classifyWindows(windows) {
-  return window.UiLogic.classifyWindows(windows, state.notifySettings, THRESHOLDS_EXHAUSTED);
-}
-
-function reclassifyAllServices() {
-  for (const svc of Object.values(state.services)) {
-    if (!svc.windows || svc.windows.length === 0) continue;
-    const prev = svc.status;
-    classifyWindows(svc.windows);
-    svc.status = deriveServiceStatus(svc.windows);
-    checkStatusTransition(prev, svc.status, svc.label, svc.windows);
-  }
-}
-
 function formatReset(epoch) {
   if (!epoch) return '';
   const d = typeof epoch === 'number' ? new Date(epoch * 1000) : new Date(epoch);
@@ -153,48 +150,65 @@ function formatReset(epoch) {
   return `${timeStr} (あと${m}分)`;
 }
 
-async function notifyDesktop(title, body) {
-  await window.quotaApi.sendNotification({ title, body });
-  return true;
+const MUTE_CHOICES_MIN = [60, 240, 480, 1440];
+
+// "★ おすすめ" on the top-ranked usable account of each service; the reasons go in the tooltip.
+function recommendHtml(serviceKey) {
+  const [service, id] = serviceKey.split(':');
+  const rec = state.recommendations.find((r) => r.service === service && r.id === id);
+  if (!rec || rec.rank !== 1 || !rec.usable) return '';
+  const title = ['おすすめのアカウント', ...(rec.reasons || [])].join('\n');
+  return `<span class="card-recommend" title="${escHtml(title)}">★ おすすめ</span>`;
 }
 
-function sendExternalChannelNotifications(prev, next, label, windows) {
-  const en = state.externalNotify;
-  const channels = [
-    { key: 'discord', settings: en.discord },
-    { key: 'pushover', settings: en.pushover },
-  ];
-  for (const { key, settings } of channels) {
-    if (!settings.enabled) continue;
-    const channelEffects = buildTransitionEffects(prev, next, label, windows, {
-      critical: settings.critical,
-      recovery: settings.recovery,
-      warning: settings.warning,
-    });
-    for (const item of channelEffects.notifications) {
-      window.quotaApi.sendExternalNotification({
-        title: item.title, body: item.body, level: next, channel: key,
-      }).catch((e) => {
-        log(`外部通知送信エラー (${key}): ${toErrorMessage(e)}`, 'warn');
-      });
-    }
+async function refreshRecommendations() {
+  try {
+    const ranked = await window.quotaApi.getRecommendations();
+    state.recommendations = Array.isArray(ranked) ? ranked : [];
+  } catch {
+    state.recommendations = [];
   }
+  render();
 }
 
-function checkStatusTransition(prev, next, label, windows) {
-  // Desktop notifications
-  const desktopEffects = buildTransitionEffects(prev, next, label, windows, state.notifySettings);
-  for (const item of desktopEffects.notifications) {
-    notifyDesktop(item.title, item.body).catch((e) => {
-      log(`通知送信エラー: ${toErrorMessage(e)}`, 'warn');
-    });
-  }
-  // External channel notifications (each with its own trigger settings)
-  sendExternalChannelNotifications(prev, next, label, windows);
-  // Logs (always based on desktop effects)
-  for (const item of desktopEffects.logs) {
-    log(item.message, item.level);
-  }
+// Set by the backend when the refresh token was rejected; cleared by a new login.
+function reloginHtml(serviceKey) {
+  const [service, id] = serviceKey.split(':');
+  const acc = (state.accounts[service] || []).find((a) => a.id === id);
+  if (!acc?.reloginReason) return '';
+  const title = `再ログインが必要です\n${acc.reloginReason}`;
+  return `<span class="card-relogin" title="${escHtml(title)}">🔑 要再ログイン</span>`;
+}
+
+// Per-account mute selector in the card header; `mutedUntil` comes from the account snapshot.
+function muteHtml(serviceKey) {
+  const [service, id] = serviceKey.split(':');
+  const acc = (state.accounts[service] || []).find((a) => a.id === id);
+  if (!acc) return '';
+  const muted = Number(acc.mutedUntil) > Date.now();
+  const current = muted
+    ? `🔕 ${new Date(acc.mutedUntil).toLocaleTimeString('ja-JP', { hour: '2-digit', minute: '2-digit' })} まで`
+    : '🔔';
+  const options = MUTE_CHOICES_MIN.map((min) =>
+    `<option value="${min}">${min >= 60 ? `${min / 60}時間` : `${min}分`}ミュート</option>`
+  ).join('');
+  return `<select class="card-mute" data-service="${escHtml(service)}" data-id="${escHtml(id)}" title="このアカウントの通知をミュート">
+    <option value="" selected>${current}</option>
+    ${muted ? '<option value="0">ミュート解除</option>' : ''}
+    ${options}
+  </select>`;
+}
+
+// Burn-rate projection from the backend; shown only when it runs out before the reset.
+function forecastHtml(forecast) {
+  if (!forecast?.beforeReset || !forecast.exhaustedAt) return '';
+  const at = formatReset(forecast.exhaustedAt / 1000);
+  return `<div class="forecast-info">上限到達見込み: ${at} (${forecast.ratePerHour.toFixed(1)}%/時)</div>`;
+}
+
+async function notifyDesktop(title, body) {
+  await window.quotaApi.sendNotification({ title, body });
+  return true;
 }
 
 async function resolveAppVersion() {
@@ -229,7 +243,14 @@ const accountUi = window.AccountUi.createAccountUi({
   queuePersistSetup,
   deleteAccount: (payload) => window.quotaApi.deleteAccount(payload),
   oauthLogin: (payload) => window.quotaApi.oauthLogin(payload),
-  importClaudeCliCredentials: (payload) => window.quotaApi.importClaudeCliCredentials(payload),
+  importCliCredentials: (payload) => window.quotaApi.importCliCredentials(payload),
+  saveAccount: (payload) => window.quotaApi.saveAccount(payload),
+  setAccountCliLink: (payload) => window.quotaApi.setAccountCliLink(payload),
+  activateCliAccount: (payload) => window.quotaApi.activateCliAccount(payload),
+  setAccountPreference: (payload) => window.quotaApi.setAccountPreference(payload).then((saved) => {
+    refreshRecommendations();
+    return saved;
+  }),
   oauthExchangeCode: (payload) => window.quotaApi.oauthExchangeCode(payload),
   log,
 });
@@ -239,6 +260,10 @@ const {
   addAccountRow,
   collectAccounts,
   upsertDomTokenState,
+  applyCliSync,
+  applyCliSwitch,
+  setMutedUntil,
+  setReloginReason,
 } = accountUi;
 
 async function persistSetup() {
@@ -303,121 +328,63 @@ function queuePersistSetup() {
 // ═══════════════════════════════════════
 // Poll orchestrator
 // ═══════════════════════════════════════
-async function tryAutoRefresh(service, id) {
-  try {
-    const status = await window.quotaApi.getTokenStatus({ service, id });
-    if (status && status.needsRefresh && status.hasRefreshToken) {
-      log(`トークンを自動更新中: ${SERVICE_META[service].label}`, '');
-      const result = await window.quotaApi.refreshToken({ service, id });
-      if (result.success) {
-        log(`トークンを自動更新しました: ${SERVICE_META[service].label}`, 'ok');
-        return true;
-      } else {
-        log(`トークン自動更新に失敗: ${result.message}`, 'warn');
-      }
-    }
-  } catch (e) {
-    log(`トークン更新チェックエラー: ${toErrorMessage(e)}`, 'warn');
+function applyUsageUpdate(update, targetServices) {
+  const meta = SERVICE_META[update.service];
+  if (!meta) return;
+  const serviceKey = `${update.service}:${update.id}`;
+  const label = `${meta.label}: ${update.name}`;
+  // Window and account statuses are classified by the backend.
+  const windows = Array.isArray(update.windows) ? update.windows : [];
+
+  if (update.error) {
+    const entry = {
+      label,
+      windows,
+      status: update.status || 'error',
+      error: update.error,
+    };
+    if (update.rateLimitUntil) entry.rateLimitUntil = update.rateLimitUntil;
+    targetServices[serviceKey] = entry;
+    if (!update.skipped) log(`${label} エラー: ${update.error}`, 'warn');
+    return;
   }
-  return false;
-}
-
-async function pollServiceAccounts(service, accounts, nextServices, worstOf) {
-  const meta = SERVICE_META[service];
-  let anySuccess = false;
-
-  for (const acc of accounts) {
-    if (!hasUsableToken(acc)) continue;
-    const serviceKey = `${service}:${acc.id}`;
-    const label = `${meta.label}: ${acc.name}`;
-
-    // Skip if currently rate-limited
-    const rateLimitUntil = state.rateLimitUntil[serviceKey] || 0;
-    if (rateLimitUntil > Date.now()) {
-      const prev = state.services[serviceKey];
-      nextServices[serviceKey] = {
-        label,
-        windows: prev?.windows || [],
-        status: prev?.status || 'error',
-        error: prev?.error || 'レート制限中',
-        rateLimitUntil,
-      };
-      continue;
-    }
-    // Clear expired rate-limit entry
-    if (rateLimitUntil) delete state.rateLimitUntil[serviceKey];
 
-    // Auto-refresh token if close to expiry
-    await tryAutoRefresh(service, acc.id);
+  state.rawResponses[serviceKey] = update.raw;
+  for (const w of windows) {
+    recordHistory(`${serviceKey}:${w.name}`, w.utilization);
+  }
 
-    try {
-      const result = await window.quotaApi.fetchUsage({
-        service,
-        id: acc.id,
-        name: acc.name,
-        token: acc.token || undefined,
-      });
-      const data = result.raw;
-      const windows = result.windows;
-      classifyWindows(windows);
-      const worstStatus = worstOf(windows);
-      state.rawResponses[serviceKey] = data;
-      for (const w of windows) {
-        recordHistory(`${serviceKey}:${w.name}`, w.utilization);
-      }
+  targetServices[serviceKey] = { label, windows, status: update.status || 'ok' };
+  log(`${label} 取得成功: ${windows.map(w => `${w.name}=${w.utilization}%`).join(', ')}`);
+  upsertDomTokenState(update.service, update.id, true);
+}
 
-      const prev = state.services[serviceKey]?.status;
-      nextServices[serviceKey] = { label, windows, status: worstStatus };
-      checkStatusTransition(prev, worstStatus, label, windows);
-      anySuccess = true;
-      delete state.rateLimitBackoff[serviceKey]; // reset backoff on success
-      log(`${label} 取得成功: ${windows.map(w => `${w.name}=${w.utilization}%`).join(', ')}`);
-      upsertDomTokenState(service, acc.id, true);
-    } catch (e) {
-      const errorMessage = toErrorMessage(e);
-      const retryMatch = errorMessage.match(RATE_LIMIT_RETRY_AFTER_RE);
-      const is429 = errorMessage.includes('(HTTP 429)');
-      const rateLimitEntry = { label, windows: [], status: 'error', error: errorMessage };
-      if (is429) {
-        const retryDelaySecs = retryMatch ? parseInt(retryMatch[1], 10) : 0;
-        let until;
-        if (retryDelaySecs > 0) {
-          // Explicit positive retry-after: use it and reset backoff counter
-          until = Date.now() + retryDelaySecs * 1000;
-          delete state.rateLimitBackoff[serviceKey];
-        } else {
-          // No retry-after or retry-after: 0 → exponential backoff
-          const backoffCount = state.rateLimitBackoff[serviceKey] || 0;
-          state.rateLimitBackoff[serviceKey] = backoffCount + 1;
-          const baseInterval = state.pollInterval || POLL_INTERVAL_DEFAULT_SEC;
-          const backoffSecs = Math.min(Math.max(baseInterval * Math.pow(2, backoffCount + 1), POLL_INTERVAL_MIN_SEC), 3600);
-          until = Date.now() + backoffSecs * 1000;
-        }
-        state.rateLimitUntil[serviceKey] = until;
-        rateLimitEntry.rateLimitUntil = until;
-      }
-      nextServices[serviceKey] = rateLimitEntry;
-      log(`${label} エラー: ${errorMessage}`, 'warn');
-    }
+// The backend scheduler owns the poll loop; these handlers mirror its events.
+function handlePollCycleStarted(payload) {
+  state.pollInFlight = true;
+  if (state.polling) {
+    state.pollStartedAt = payload.startedAt || Date.now();
+    if (payload.interval) state.pollInterval = payload.interval;
   }
+  state.pendingServices = {};
+  state.rawResponses = {};
+  updatePollRing();
+  updateCountdown();
+  updatePollStatus('取得中...');
+}
 
-  return anySuccess;
+function handleUsageUpdated(update) {
+  applyUsageUpdate(update, state.pendingServices || state.services);
+  if (!state.pendingServices) render();
 }
 
-async function pollAll() {
+function handlePollCycleFinished() {
+  if (state.pendingServices) {
+    state.services = state.pendingServices;
+    state.pendingServices = null;
+  }
+  state.pollInFlight = false;
   state.accounts = collectAccounts();
-  queuePersistSetup();
-  let anySuccess = false;
-  const nextServices = {};
-  state.rawResponses = {};
-  const statusOrder = ['unknown', 'ok', 'warning', 'critical', 'exhausted'];
-  const worstOf = (windows) => windows.reduce((a, w) => (
-    statusOrder.indexOf(w.status) > statusOrder.indexOf(a) ? w.status : a
-  ), 'ok');
-  anySuccess = (await pollServiceAccounts('claude', state.accounts.claude, nextServices, worstOf)) || anySuccess;
-  anySuccess = (await pollServiceAccounts('codex', state.accounts.codex, nextServices, worstOf)) || anySuccess;
-
-  state.services = nextServices;
 
   const allAccounts = [...state.accounts.claude, ...state.accounts.codex];
   const withToken = allAccounts.filter((a) => hasUsableToken(a));
@@ -427,9 +394,111 @@ async function pollAll() {
   }
 
   render();
+  refreshRecommendations();
   persistLastData();
   maybeWriteUsageSnapshot().catch(() => {});
-  return anySuccess;
+  if (state.polling) {
+    updateCountdown();
+  } else {
+    updatePollStatus('取得完了');
+  }
+}
+
+// Sent after notify thresholds change; only statuses differ from the last fetch.
+function handleUsageReclassified(updates) {
+  for (const update of Array.isArray(updates) ? updates : []) {
+    const svc = state.services[`${update.service}:${update.id}`];
+    if (!svc) continue;
+    svc.windows = Array.isArray(update.windows) ? update.windows : svc.windows;
+    svc.status = update.status || svc.status;
+  }
+  render();
+}
+
+function handleStatusChanged(payload) {
+  for (const item of payload?.logs || []) {
+    log(item.message, item.level);
+  }
+}
+
+function handleNotificationFailed(payload) {
+  const message = toErrorMessage(payload?.message);
+  if (payload?.channel === 'desktop') {
+    log(`通知送信エラー: ${message}`, 'warn');
+  } else {
+    log(`外部通知送信エラー: ${message}`, 'warn');
+  }
+}
+
+function handleCliCredentialsSynced(payload) {
+  const service = payload?.service;
+  if (!SERVICE_META[service]) return;
+  applyCliSync(service, payload.id, payload);
+  if (payload.error) {
+    log(`CLI 認証情報の同期に失敗しました (${SERVICE_META[service].label} ${payload.id}): ${payload.error}`, 'warn');
+  }
+}
+
+function handleAccountReloginChanged(payload) {
+  const service = payload?.service;
+  if (!SERVICE_META[service]) return;
+  const acc = (state.accounts[service] || []).find((a) => a.id === payload.id);
+  if (acc) acc.reloginReason = payload.reason || null;
+  setReloginReason(service, payload.id, payload.reason || null);
+  // Newly marked accounts are already logged through `status-changed`.
+  if (!payload.reason) log(`${acc?.name || payload.id} のトークンを再び更新できるようになりました`, 'ok');
+  render();
+}
+
+// Sent by the background refresh timer.
+function handleTokenRefreshed(payload) {
+  const label = SERVICE_META[payload?.service]?.label || payload?.service;
+  const name = payload?.name || payload?.id;
+  if (payload?.error) {
+    log(`トークンの自動更新に失敗しました (${label} ${name}): ${payload.error}`, 'warn');
+    return;
+  }
+  const until = payload?.expiresAt
+    ? ` (${new Date(payload.expiresAt).toLocaleTimeString('ja-JP', { hour: '2-digit', minute: '2-digit' })} まで有効)`
+    : '';
+  log(`トークンを自動更新しました (${label} ${name})${until}`, 'ok');
+}
+
+function handleCliAccountSwitched(payload) {
+  applyCliSwitch(payload);
+  const how = payload?.auto ? '自動で' : '';
+  log(`Claude Code CLI を${how}「${payload?.name || payload?.id}」に切り替えました`, payload?.auto ? 'warn' : 'ok');
+}
+
+async function subscribeSchedulerEvents() {
+  await window.quotaApi.onPollCycleStarted(handlePollCycleStarted);
+  await window.quotaApi.onUsageUpdated(handleUsageUpdated);
+  await window.quotaApi.onPollCycleFinished(handlePollCycleFinished);
+  await window.quotaApi.onUsageReclassified(handleUsageReclassified);
+  await window.quotaApi.onStatusChanged(handleStatusChanged);
+  await window.quotaApi.onNotificationFailed(handleNotificationFailed);
+  await window.quotaApi.onCliCredentialsSynced(handleCliCredentialsSynced);
+  await window.quotaApi.onCliAccountSwitched(handleCliAccountSwitched);
+  await window.quotaApi.onAccountReloginChanged(handleAccountReloginChanged);
+  await window.quotaApi.onTokenRefreshed(handleTokenRefreshed);
+}
+
+function reportStoreLoadIssue(issue) {
+  if (!issue) return;
+  if (issue.kind === 'newerSchema') {
+    log(`accounts.json は新しいバージョンのアプリで保存されているため、読み取り専用で起動しました (${issue.message})。設定の変更は保存されません`, 'crit');
+    return;
+  }
+  log(`accounts.json を読み込めなかったため初期設定で起動しました (${issue.message})。元のファイルは ${issue.backupPath} に退避しています`, 'crit');
+}
+
+async function restoreLatestUsage() {
+  const updates = await window.quotaApi.getLatestUsage();
+  if (!Array.isArray(updates) || updates.length === 0) return;
+  for (const update of updates) {
+    applyUsageUpdate(update, state.services);
+  }
+  render();
 }
 
 async function maybeWriteUsageSnapshot() {
@@ -555,7 +624,7 @@ function render() {
       return `<div class="card card-${svc.status}">
         <div class="card-header">
           <span class="card-header-left">${logoHtml}<span class="card-label">${svc.label}</span></span>
-          <span class="card-status error">エラー</span>
+          <span class="card-header-right">${muteHtml(id)}<span class="card-status error">エラー</span></span>
         </div>
         <div class="card-error-message">${escHtml(displayError)}</div>
         ${rateLimitHtml}
@@ -579,13 +648,14 @@ function render() {
         </div>
         ${elBar}
         ${w.resetsAt ? `<div class="reset-info">リセット: ${formatReset(w.resetsAt)}</div>` : ''}
+        ${forecastHtml(w.forecast)}
       </div>`;
     }).join('');
 
     return `<div class="card card-${svc.status}">
       <div class="card-header">
         <span class="card-header-left">${logoHtml}<span class="card-label">${svc.label}</span></span>
-        <span class="card-status ${svc.status}">${svc.status}</span>
+        <span class="card-header-right">${reloginHtml(id)}${recommendHtml(id)}${muteHtml(id)}<span class="card-status ${svc.status}">${svc.status}</span></span>
       </div>
       ${windowsHtml}
     </div>`;
@@ -909,45 +979,26 @@ function setupAppContextMenu() {
 // ═══════════════════════════════════════
 // Controls
 // ═══════════════════════════════════════
-async function runPollCycle() {
-  if (!state.polling || state.pollInFlight) return;
-  state.pollInFlight = true;
-  state.pollStartedAt = Date.now();
-  persistPollingState();
-  updatePollRing();
-  updateCountdown();
-  updatePollStatus('取得中...');
-
-  try {
-    await pollAll();
-    updatePollStatus('取得完了');
-  } finally {
-    state.pollInFlight = false;
-  }
+async function requestPollNow() {
+  await persistSetup();
+  await window.quotaApi.pollNow();
 }
 
-function startPolling() {
-  stopPolling(false);
+async function startPolling() {
   const intervalSec = Math.max(POLL_INTERVAL_MIN_SEC, parseInt($('#poll-interval').value, 10) || POLL_INTERVAL_DEFAULT_SEC);
   state.polling = true;
   state.pollInterval = intervalSec;
   state.pollStartedAt = Date.now();
-  state.pollInFlight = false;
-  persistPollingState();
   $('#btn-start').textContent = '⏹ 停止';
   $('#btn-start').classList.add('active');
-  state.timer = setInterval(() => {
-    runPollCycle();
-  }, intervalSec * 1000);
   startRingTimer();
-  runPollCycle();
-  queuePersistSetup();
+  // Tokens typed into the form must reach the keyring before the backend polls.
+  await persistSetup();
+  persistPollingState();
 }
 
 function stopPolling(persistState = true) {
   state.polling = false;
-  state.pollInFlight = false;
-  if (state.timer) { clearInterval(state.timer); state.timer = null; }
   stopRingTimer();
   state.pollStartedAt = null;
   if (persistState) persistPollingState();
@@ -1055,32 +1106,93 @@ document.addEventListener('DOMContentLoaded', async () => {
       if (typeof ns.critical === 'boolean') state.notifySettings.critical = ns.critical;
       if (typeof ns.recovery === 'boolean') state.notifySettings.recovery = ns.recovery;
       if (typeof ns.warning === 'boolean') state.notifySettings.warning = ns.warning;
+      if (typeof ns.projected === 'boolean') state.notifySettings.projected = ns.projected;
       if (typeof ns.thresholdWarning === 'number') state.notifySettings.thresholdWarning = ns.thresholdWarning;
       if (typeof ns.thresholdCritical === 'number') state.notifySettings.thresholdCritical = ns.thresholdCritical;
+      if (typeof ns.hysteresis === 'number') state.notifySettings.hysteresis = ns.hysteresis;
+      if (typeof ns.cooldownMinutes === 'number') state.notifySettings.cooldownMinutes = ns.cooldownMinutes;
+      if (typeof ns.renotifyMinutes === 'number') state.notifySettings.renotifyMinutes = ns.renotifyMinutes;
+      if (Array.isArray(ns.quietHours)) state.quietHours = ns.quietHours;
     }
     if (settings?.externalNotify) {
       const en = settings.externalNotify;
       if (en.discord) {
         if (typeof en.discord.enabled === 'boolean') state.externalNotify.discord.enabled = en.discord.enabled;
         if (typeof en.discord.webhookUrl === 'string') state.externalNotify.discord.webhookUrl = en.discord.webhookUrl;
+        if (typeof en.discord.hasSecret === 'boolean') state.externalNotify.discord.hasSecret = en.discord.hasSecret;
         if (typeof en.discord.critical === 'boolean') state.externalNotify.discord.critical = en.discord.critical;
         if (typeof en.discord.recovery === 'boolean') state.externalNotify.discord.recovery = en.discord.recovery;
         if (typeof en.discord.warning === 'boolean') state.externalNotify.discord.warning = en.discord.warning;
+        if (typeof en.discord.projected === 'boolean') state.externalNotify.discord.projected = en.discord.projected;
+      }
+      if (en.slack) {
+        if (typeof en.slack.enabled === 'boolean') state.externalNotify.slack.enabled = en.slack.enabled;
+        if (typeof en.slack.webhookUrl === 'string') state.externalNotify.slack.webhookUrl = en.slack.webhookUrl;
+        if (typeof en.slack.hasSecret === 'boolean') state.externalNotify.slack.hasSecret = en.slack.hasSecret;
+        if (typeof en.slack.critical === 'boolean') state.externalNotify.slack.critical = en.slack.critical;
+        if (typeof en.slack.recovery === 'boolean') state.externalNotify.slack.recovery = en.slack.recovery;
+        if (typeof en.slack.warning === 'boolean') state.externalNotify.slack.warning = en.slack.warning;
+        if (typeof en.slack.projected === 'boolean') state.externalNotify.slack.projected = en.slack.projected;
+      }
+      if (en.teams) {
+        if (typeof en.teams.enabled === 'boolean') state.externalNotify.teams.enabled = en.teams.enabled;
+        if (typeof en.teams.webhookUrl === 'string') state.externalNotify.teams.webhookUrl = en.teams.webhookUrl;
+        if (typeof en.teams.hasSecret === 'boolean') state.externalNotify.teams.hasSecret = en.teams.hasSecret;
+        if (typeof en.teams.critical === 'boolean') state.externalNotify.teams.critical = en.teams.critical;
+        if (typeof en.teams.recovery === 'boolean') state.externalNotify.teams.recovery = en.teams.recovery;
+        if (typeof en.teams.warning === 'boolean') state.externalNotify.teams.warning = en.teams.warning;
+        if (typeof en.teams.projected === 'boolean') state.externalNotify.teams.projected = en.teams.projected;
       }
       if (en.pushover) {
         if (typeof en.pushover.enabled === 'boolean') state.externalNotify.pushover.enabled = en.pushover.enabled;
         if (typeof en.pushover.apiToken === 'string') state.externalNotify.pushover.apiToken = en.pushover.apiToken;
         if (typeof en.pushover.userKey === 'string') state.externalNotify.pushover.userKey = en.pushover.userKey;
+        if (typeof en.pushover.hasSecret === 'boolean') state.externalNotify.pushover.hasSecret = en.pushover.hasSecret;
         if (typeof en.pushover.critical === 'boolean') state.externalNotify.pushover.critical = en.pushover.critical;
         if (typeof en.pushover.recovery === 'boolean') state.externalNotify.pushover.recovery = en.pushover.recovery;
         if (typeof en.pushover.warning === 'boolean') state.externalNotify.pushover.warning = en.pushover.warning;
+        if (typeof en.pushover.projected === 'boolean') state.externalNotify.pushover.projected = en.pushover.projected;
       }
+      if (en.ntfy) {
+        if (typeof en.ntfy.enabled === 'boolean') state.externalNotify.ntfy.enabled = en.ntfy.enabled;
+        if (typeof en.ntfy.serverUrl === 'string') state.externalNotify.ntfy.serverUrl = en.ntfy.serverUrl;
+        if (typeof en.ntfy.topic === 'string') state.externalNotify.ntfy.topic = en.ntfy.topic;
+        if (typeof en.ntfy.accessToken === 'string') state.externalNotify.ntfy.accessToken = en.ntfy.accessToken;
+        if (typeof en.ntfy.hasSecret === 'boolean') state.externalNotify.ntfy.hasSecret = en.ntfy.hasSecret;
+        if (typeof en.ntfy.critical === 'boolean') state.externalNotify.ntfy.critical = en.ntfy.critical;
+        if (typeof en.ntfy.recovery === 'boolean') state.externalNotify.ntfy.recovery = en.ntfy.recovery;
+        if (typeof en.ntfy.warning === 'boolean') state.externalNotify.ntfy.warning = en.ntfy.warning;
+        if (typeof en.ntfy.projected === 'boolean') state.externalNotify.ntfy.projected = en.ntfy.projected;
+      }
+      if (en.gotify) {
+        if (typeof en.gotify.enabled === 'boolean') state.externalNotify.gotify.enabled = en.gotify.enabled;
+        if (typeof en.gotify.serverUrl === 'string') state.externalNotify.gotify.serverUrl = en.gotify.serverUrl;
+        if (typeof en.gotify.appToken === 'string') state.externalNotify.gotify.appToken = en.gotify.appToken;
+        if (typeof en.gotify.hasSecret === 'boolean') state.externalNotify.gotify.hasSecret = en.gotify.hasSecret;
+        if (typeof en.gotify.critical === 'boolean') state.externalNotify.gotify.critical = en.gotify.critical;
+        if (typeof en.gotify.recovery === 'boolean') state.externalNotify.gotify.recovery = en.gotify.recovery;
+        if (typeof en.gotify.warning === 'boolean') state.externalNotify.gotify.warning = en.gotify.warning;
+        if (typeof en.gotify.projected === 'boolean') state.externalNotify.gotify.projected = en.gotify.projected;
+      }
+      if (Array.isArray(en.webhooks)) state.externalNotify.webhooks = en.webhooks;
     }
     if (settings?.usageExport) {
       const us = settings.usageExport;
       if (typeof us.enabled === 'boolean') state.usageExport.enabled = us.enabled;
       if (typeof us.path === 'string') state.usageExport.path = us.path;
     }
+    if (settings?.metrics) {
+      const ms = settings.metrics;
+      if (typeof ms.enabled === 'boolean') state.metrics.enabled = ms.enabled;
+      if (typeof ms.bindAddress === 'string') state.metrics.bindAddress = ms.bindAddress;
+      if (typeof ms.port === 'number') state.metrics.port = ms.port;
+    }
+    if (settings?.mcp) {
+      if (typeof settings.mcp.enabled === 'boolean') state.mcp.enabled = settings.mcp.enabled;
+      if (typeof settings.mcp.port === 'number') state.mcp.port = settings.mcp.port;
+    }
+    if (typeof settings?.cliSwitch?.autoSwitch === 'boolean') state.cliSwitch.autoSwitch = settings.cliSwitch.autoSwitch;
+    if (typeof settings?.tokenRefresh?.leadMinutes === 'number') state.tokenRefresh.leadMinutes = settings.tokenRefresh.leadMinutes;
     restoredPollState = await window.quotaApi.getPollingState();
     const windowState = await window.quotaApi.getWindowState();
     state.windowMode = windowState?.mode === 'minimal' ? 'minimal' : 'normal';
@@ -1109,12 +1221,30 @@ document.addEventListener('DOMContentLoaded', async () => {
       state.rawResponses = {};
       const histRaw = sessionStorage.getItem(SESSION_KEYS.history);
       if (histRaw) state.history = JSON.parse(histRaw);
-      reclassifyAllServices();
       render();
       if (fetchedAt) log(`前回取得: ${new Date(fetchedAt).toLocaleString()}`);
     }
   } catch {}
 
+  try {
+    reportStoreLoadIssue(await window.quotaApi.getStoreLoadIssue());
+    await window.quotaApi.onStoreLoadFailed(reportStoreLoadIssue);
+  } catch {}
+
+  try {
+    for (const error of await window.quotaApi.getListenerErrors()) {
+      log(`待ち受けを開始できませんでした: ${error}`, 'warn');
+    }
+  } catch {}
+
+  try {
+    await subscribeSchedulerEvents();
+    await restoreLatestUsage();
+    await refreshRecommendations();
+  } catch (e) {
+    log(`バックエンドイベント購読エラー: ${toErrorMessage(e)}`, 'warn');
+  }
+
   $('#btn-start').addEventListener('click', () => {
     if (state.polling) { stopPolling(); return; }
     const accounts = collectAccounts();
@@ -1137,28 +1267,31 @@ document.addEventListener('DOMContentLoaded', async () => {
       ensureSetupOpenIfMissingToken(accounts);
       return;
     }
-    if (state.polling) {
-      runPollCycle();
-      return;
-    }
-    state.pollInFlight = true;
     updatePollStatus('取得中...');
-    pollAll()
-      .then(() => updatePollStatus('取得完了'))
-      .finally(() => {
-        state.pollInFlight = false;
-      });
+    requestPollNow().catch((e) => {
+      log(`取得要求エラー: ${toErrorMessage(e)}`, 'warn');
+    });
   });
 
   $('#notify-critical').checked = state.notifySettings.critical;
   $('#notify-recovery').checked = state.notifySettings.recovery;
   $('#notify-warning').checked = state.notifySettings.warning;
+  $('#notify-projected').checked = state.notifySettings.projected;
   $('#threshold-warning').value = String(state.notifySettings.thresholdWarning);
   $('#threshold-critical').value = String(state.notifySettings.thresholdCritical);
+  $('#threshold-hysteresis').value = String(state.notifySettings.hysteresis);
+  $('#notify-cooldown').value = String(state.notifySettings.cooldownMinutes);
+  $('#notify-renotify').value = String(state.notifySettings.renotifyMinutes);
+  // Unlike the thresholds, 0 is a valid value here (off).
+  const readIntInput = (selector, max, fallback) => {
+    const n = parseInt($(selector).value, 10);
+    return Number.isFinite(n) ? clamp(n, 0, max) : fallback;
+  };
   const persistNotifySettings = () => {
     state.notifySettings.critical = $('#notify-critical').checked;
     state.notifySettings.recovery = $('#notify-recovery').checked;
     state.notifySettings.warning = $('#notify-warning').checked;
+    state.notifySettings.projected = $('#notify-projected').checked;
     state.notifySettings.thresholdWarning = Math.max(
       NOTIFY_THRESHOLD_MIN,
       Math.min(
@@ -1173,15 +1306,23 @@ document.addEventListener('DOMContentLoaded', async () => {
         parseInt($('#threshold-critical').value, 10) || NOTIFY_THRESHOLD_CRITICAL_DEFAULT
       )
     );
+    state.notifySettings.hysteresis = readIntInput('#threshold-hysteresis', NOTIFY_HYSTERESIS_MAX, state.notifySettings.hysteresis);
+    state.notifySettings.cooldownMinutes = readIntInput('#notify-cooldown', NOTIFY_MINUTES_MAX, state.notifySettings.cooldownMinutes);
+    const renotify = readIntInput('#notify-renotify', NOTIFY_MINUTES_MAX, state.notifySettings.renotifyMinutes);
+    state.notifySettings.renotifyMinutes = renotify > 0 ? Math.max(NOTIFY_RENOTIFY_MIN, renotify) : 0;
+    $('#notify-renotify').value = String(state.notifySettings.renotifyMinutes);
+    // The backend re-classifies the latest results and emits usage-reclassified.
     window.quotaApi.setSettings({ notifySettings: state.notifySettings }).catch(() => {});
-    reclassifyAllServices();
-    render();
   };
   $('#notify-critical').addEventListener('change', persistNotifySettings);
   $('#notify-recovery').addEventListener('change', persistNotifySettings);
   $('#notify-warning').addEventListener('change', persistNotifySettings);
+  $('#notify-projected').addEventListener('change', persistNotifySettings);
   $('#threshold-warning').addEventListener('change', persistNotifySettings);
   $('#threshold-critical').addEventListener('change', persistNotifySettings);
+  $('#threshold-hysteresis').addEventListener('change', persistNotifySettings);
+  $('#notify-cooldown').addEventListener('change', persistNotifySettings);
+  $('#notify-renotify').addEventListener('change', persistNotifySettings);
   $('#btn-notify-test').addEventListener('click', async () => {
     try {
       await notifyDesktop('テスト', 'AI Quota Monitor の通知が有効です');
@@ -1191,62 +1332,147 @@ document.addEventListener('DOMContentLoaded', async () => {
     }
   });
 
+  // Quiet hours
+  const quietHoursListEl = $('#quiet-hours-list');
+  const QUIET_DAYS = [['mon', '月'], ['tue', '火'], ['wed', '水'], ['thu', '木'], ['fri', '金'], ['sat', '土'], ['sun', '日']];
+  const renderQuietHours = () => {
+    if (!quietHoursListEl) return;
+    quietHoursListEl.innerHTML = state.quietHours.map((rule, i) => `
+      <div class="quiet-hours-item" data-index="${i}">
+        <label class="check-label"><input type="checkbox" data-field="enabled"${rule.enabled ? ' checked' : ''}> 有効</label>
+        <div class="row ext-notify-row">
+          <input type="time" data-field="start" value="${escHtml(rule.start || '22:00')}">
+          <span>〜</span>
+          <input type="time" data-field="end" value="${escHtml(rule.end || '07:00')}">
+          <input type="text" data-field="timezone" placeholder="タイムゾーン (例: Asia/Tokyo、空欄でシステム)" value="${escHtml(rule.timezone || '')}">
+        </div>
+        <div class="ext-notify-triggers">
+          ${QUIET_DAYS.map(([day, label]) =>
+            `<label class="check-label"><input type="checkbox" data-day="${day}"${(rule.days || []).includes(day) ? ' checked' : ''}> ${label}</label>`
+          ).join('')}
+        </div>
+        <div class="row ext-notify-row">
+          <input type="text" data-field="channels" placeholder="チャンネル (空欄ですべて)" value="${escHtml((rule.channels || []).join(', '))}">
+        </div>
+        <label class="check-label"><input type="checkbox" data-field="allowExhausted"${rule.allowExhausted ? ' checked' : ''}> exhausted は静音時間中も通知する</label>
+        <div class="webhook-actions">
+          <button class="btn-mini" data-action="delete" type="button">削除</button>
+        </div>
+      </div>`).join('');
+  };
+  const collectQuietHours = () => {
+    if (!quietHoursListEl) return state.quietHours;
+    return [...quietHoursListEl.querySelectorAll('.quiet-hours-item')].map((row) => {
+      const field = (name) => row.querySelector(`[data-field="${name}"]`);
+      return {
+        enabled: Boolean(field('enabled').checked),
+        start: String(field('start').value || '22:00'),
+        end: String(field('end').value || '07:00'),
+        timezone: String(field('timezone').value || '').trim(),
+        days: QUIET_DAYS.map(([day]) => day).filter((day) => row.querySelector(`[data-day="${day}"]`).checked),
+        channels: String(field('channels').value || '').split(',').map((c) => c.trim()).filter(Boolean),
+        allowExhausted: Boolean(field('allowExhausted').checked),
+      };
+    });
+  };
+  const persistQuietHours = (quietHours) => window.quotaApi.setSettings({
+    notifySettings: { quietHours },
+  }).then((saved) => {
+    state.quietHours = Array.isArray(saved?.notifySettings?.quietHours) ? saved.notifySettings.quietHours : quietHours;
+    renderQuietHours();
+  }).catch((e) => {
+    log(`静音時間の保存エラー: ${toErrorMessage(e)}`, 'warn');
+  });
+  renderQuietHours();
+  quietHoursListEl?.addEventListener('change', () => {
+    persistQuietHours(collectQuietHours());
+  });
+  quietHoursListEl?.addEventListener('click', (e) => {
+    const button = e.target.closest('button[data-action="delete"]');
+    if (!button) return;
+    const quietHours = collectQuietHours();
+    quietHours.splice(Number(button.closest('.quiet-hours-item').dataset.index), 1);
+    persistQuietHours(quietHours);
+  });
+  $('#btn-quiet-hours-add')?.addEventListener('click', () => {
+    persistQuietHours([
+      ...collectQuietHours(),
+      { enabled: true, start: '22:00', end: '07:00', timezone: '', days: [], channels: [], allowExhausted: false },
+    ]);
+  });
+
+  // Per-account mute from the card header
+  $('#dashboard').addEventListener('change', async (e) => {
+    const select = e.target.closest('select.card-mute');
+    if (!select || select.value === '') return;
+    const { service, id } = select.dataset;
+    const minutes = Number(select.value);
+    const mutedUntil = minutes > 0 ? Date.now() + minutes * 60 * 1000 : null;
+    try {
+      const saved = await window.quotaApi.setAccountMute({ service, id, mutedUntil });
+      const acc = (state.accounts[service] || []).find((a) => a.id === id);
+      if (acc) acc.mutedUntil = saved?.mutedUntil ?? null;
+      setMutedUntil(service, id, saved?.mutedUntil ?? null);
+      log(saved?.mutedUntil ? `${acc?.name || id} の通知をミュートしました` : `${acc?.name || id} のミュートを解除しました`, 'ok');
+    } catch (err) {
+      log(`ミュート設定に失敗しました: ${toErrorMessage(err)}`, 'warn');
+    }
+    render();
+  });
+
   // External notification channels
-  const discordEnabledEl = $('#discord-enabled');
-  const discordUrlEl = $('#discord-webhook-url');
-  const pushoverEnabledEl = $('#pushover-enabled');
-  const pushoverTokenEl = $('#pushover-api-token');
-  const pushoverKeyEl = $('#pushover-user-key');
-  const discordCriticalEl = $('#discord-critical');
-  const discordRecoveryEl = $('#discord-recovery');
-  const discordWarningEl = $('#discord-warning');
-  const pushoverCriticalEl = $('#pushover-critical');
-  const pushoverRecoveryEl = $('#pushover-recovery');
-  const pushoverWarningEl = $('#pushover-warning');
-  if (discordEnabledEl) discordEnabledEl.checked = state.externalNotify.discord.enabled;
-  if (discordUrlEl) discordUrlEl.value = state.externalNotify.discord.webhookUrl;
-  if (discordCriticalEl) discordCriticalEl.checked = state.externalNotify.discord.critical;
-  if (discordRecoveryEl) discordRecoveryEl.checked = state.externalNotify.discord.recovery;
-  if (discordWarningEl) discordWarningEl.checked = state.externalNotify.discord.warning;
-  if (pushoverEnabledEl) pushoverEnabledEl.checked = state.externalNotify.pushover.enabled;
-  if (pushoverTokenEl) pushoverTokenEl.value = state.externalNotify.pushover.apiToken;
-  if (pushoverKeyEl) pushoverKeyEl.value = state.externalNotify.pushover.userKey;
-  if (pushoverCriticalEl) pushoverCriticalEl.checked = state.externalNotify.pushover.critical;
-  if (pushoverRecoveryEl) pushoverRecoveryEl.checked = state.externalNotify.pushover.recovery;
-  if (pushoverWarningEl) pushoverWarningEl.checked = state.externalNotify.pushover.warning;
+  // Each channel is a set of text fields (secrets come back masked) plus the
+  // shared trigger checkboxes; element ids are `#<key>-<field-id>`.
+  const NOTIFY_CHANNELS = [
+    { key: 'discord', label: 'Discord', fields: [['webhook-url', 'webhookUrl']] },
+    { key: 'slack', label: 'Slack', fields: [['webhook-url', 'webhookUrl']] },
+    { key: 'teams', label: 'Teams', fields: [['webhook-url', 'webhookUrl']] },
+    { key: 'pushover', label: 'Pushover', fields: [['api-token', 'apiToken'], ['user-key', 'userKey']] },
+    { key: 'ntfy', label: 'ntfy', fields: [['server-url', 'serverUrl'], ['topic', 'topic'], ['access-token', 'accessToken']] },
+    { key: 'gotify', label: 'Gotify', fields: [['server-url', 'serverUrl'], ['app-token', 'appToken']] },
+  ];
+  const NOTIFY_TRIGGER_KEYS = ['critical', 'recovery', 'warning', 'projected'];
+  for (const { key, fields } of NOTIFY_CHANNELS) {
+    const enabledEl = $(`#${key}-enabled`);
+    if (enabledEl) enabledEl.checked = state.externalNotify[key].enabled;
+    for (const [id, prop] of fields) {
+      const el = $(`#${key}-${id}`);
+      if (el) el.value = state.externalNotify[key][prop];
+    }
+    for (const trigger of NOTIFY_TRIGGER_KEYS) {
+      const el = $(`#${key}-${trigger}`);
+      if (el) el.checked = state.externalNotify[key][trigger];
+    }
+  }
 
   let didLogExtNotifyPersistError = false;
   const persistExternalNotifySettings = () => {
-    state.externalNotify.discord.enabled = Boolean($('#discord-enabled')?.checked);
-    state.externalNotify.discord.webhookUrl = String($('#discord-webhook-url')?.value || '').trim();
-    state.externalNotify.discord.critical = Boolean($('#discord-critical')?.checked);
-    state.externalNotify.discord.recovery = Boolean($('#discord-recovery')?.checked);
-    state.externalNotify.discord.warning = Boolean($('#discord-warning')?.checked);
-    state.externalNotify.pushover.enabled = Boolean($('#pushover-enabled')?.checked);
-    state.externalNotify.pushover.apiToken = String($('#pushover-api-token')?.value || '').trim();
-    state.externalNotify.pushover.userKey = String($('#pushover-user-key')?.value || '').trim();
-    state.externalNotify.pushover.critical = Boolean($('#pushover-critical')?.checked);
-    state.externalNotify.pushover.recovery = Boolean($('#pushover-recovery')?.checked);
-    state.externalNotify.pushover.warning = Boolean($('#pushover-warning')?.checked);
-    return window.quotaApi.setSettings({
-      externalNotify: {
-        discord: {
-          enabled: state.externalNotify.discord.enabled,
-          webhookUrl: state.externalNotify.discord.webhookUrl,
-          critical: state.externalNotify.discord.critical,
-          recovery: state.externalNotify.discord.recovery,
-          warning: state.externalNotify.discord.warning,
-        },
-        pushover: {
-          enabled: state.externalNotify.pushover.enabled,
-          apiToken: state.externalNotify.pushover.apiToken,
-          userKey: state.externalNotify.pushover.userKey,
-          critical: state.externalNotify.pushover.critical,
-          recovery: state.externalNotify.pushover.recovery,
-          warning: state.externalNotify.pushover.warning,
-        },
-      },
-    }).then(() => {
+    const patch = {};
+    for (const { key, fields } of NOTIFY_CHANNELS) {
+      const current = state.externalNotify[key];
+      current.enabled = Boolean($(`#${key}-enabled`)?.checked);
+      for (const [id, prop] of fields) {
+        current[prop] = String($(`#${key}-${id}`)?.value || '').trim();
+      }
+      for (const trigger of NOTIFY_TRIGGER_KEYS) {
+        current[trigger] = Boolean($(`#${key}-${trigger}`)?.checked);
+      }
+      patch[key] = { enabled: current.enabled };
+      for (const [, prop] of fields) patch[key][prop] = current[prop];
+      for (const trigger of NOTIFY_TRIGGER_KEYS) patch[key][trigger] = current[trigger];
+    }
+    return window.quotaApi.setSettings({ externalNotify: patch }).then((saved) => {
+      // Replace what was typed with the masked values now stored in the keychain.
+      const en = saved?.externalNotify;
+      for (const { key, fields } of NOTIFY_CHANNELS) {
+        if (!en?.[key]) continue;
+        for (const [id, prop] of fields) {
+          state.externalNotify[key][prop] = en[key][prop];
+          const el = $(`#${key}-${id}`);
+          if (el) el.value = en[key][prop];
+        }
+        state.externalNotify[key].hasSecret = Boolean(en[key].hasSecret);
+      }
       didLogExtNotifyPersistError = false;
     }).catch((e) => {
       if (!didLogExtNotifyPersistError) {
@@ -1255,51 +1481,146 @@ document.addEventListener('DOMContentLoaded', async () => {
       }
     });
   };
-  const extNotifyInputs = [
-    discordEnabledEl, discordUrlEl, discordCriticalEl, discordRecoveryEl, discordWarningEl,
-    pushoverEnabledEl, pushoverTokenEl, pushoverKeyEl, pushoverCriticalEl, pushoverRecoveryEl, pushoverWarningEl,
-  ];
+  const extNotifyInputs = NOTIFY_CHANNELS.flatMap(({ key, fields }) => [
+    $(`#${key}-enabled`),
+    ...fields.map(([id]) => $(`#${key}-${id}`)),
+    ...NOTIFY_TRIGGER_KEYS.map((trigger) => $(`#${key}-${trigger}`)),
+  ]);
   for (const el of extNotifyInputs) {
     if (el) el.addEventListener('change', persistExternalNotifySettings);
   }
 
-  $('#btn-discord-test')?.addEventListener('click', async () => {
-    try {
-      await persistExternalNotifySettings();
-      const result = await window.quotaApi.sendExternalNotification({
-        title: 'テスト',
-        body: 'AI Quota Monitor の Discord 通知が有効です',
-        level: 'ok',
-        channel: 'discord',
-      });
-      if (result.ok) {
-        log('Discord テスト通知を送信しました', 'ok');
-      } else {
-        log(`Discord テスト通知エラー: ${result.errors.join(', ')}`, 'warn');
+  for (const { key, label } of NOTIFY_CHANNELS) {
+    $(`#btn-${key}-test`)?.addEventListener('click', async () => {
+      try {
+        await persistExternalNotifySettings();
+        const result = await window.quotaApi.sendExternalNotification({
+          title: 'テスト',
+          body: `AI Quota Monitor の ${label} 通知が有効です`,
+          level: 'ok',
+          channel: key,
+        });
+        if (result.ok) {
+          log(`${label} テスト通知を送信しました`, 'ok');
+        } else {
+          log(`${label} テスト通知エラー: ${result.errors.join(', ')}`, 'warn');
+        }
+      } catch (e) {
+        log(`${label} テスト通知に失敗しました: ${toErrorMessage(e)}`, 'warn');
       }
-    } catch (e) {
-      log(`Discord テスト通知に失敗しました: ${toErrorMessage(e)}`, 'warn');
+    });
+  }
+
+  // User-defined webhooks
+  const webhookListEl = $('#webhook-list');
+  const WEBHOOK_TRIGGERS = [
+    ['warning', 'warning'],
+    ['projected', '上限到達の見込み'],
+    ['critical', 'critical / exhausted'],
+    ['recovery', '回復'],
+  ];
+  const renderWebhooks = () => {
+    if (!webhookListEl) return;
+    webhookListEl.innerHTML = state.externalNotify.webhooks.map((wh, i) => `
+      <div class="webhook-item" data-index="${i}">
+        <label class="check-label"><input type="checkbox" data-field="enabled"${wh.enabled ? ' checked' : ''}> 有効</label>
+        <div class="row ext-notify-row">
+          <input type="text" data-field="name" placeholder="名前" value="${escHtml(wh.name || '')}">
+          <select data-field="method">
+            ${['POST', 'PUT', 'PATCH'].map((m) => `<option${(wh.method || 'POST') === m ? ' selected' : ''}>${m}</option>`).join('')}
+          </select>
+        </div>
+        <div class="row ext-notify-row">
+          <input type="text" data-field="url" placeholder="https://example.com/hook" value="${escHtml(wh.url || '')}">
+        </div>
+        <textarea data-field="headers" rows="2" placeholder="Authorization: Bearer ...">${escHtml(formatHeaderLines(wh.headers))}</textarea>
+        <textarea data-field="bodyTemplate" rows="5" placeholder="本文テンプレート">${escHtml(wh.bodyTemplate || '')}</textarea>
+        <div class="ext-notify-triggers">
+          ${WEBHOOK_TRIGGERS.map(([key, label]) =>
+            `<label class="check-label"><input type="checkbox" data-field="${key}"${wh[key] ? ' checked' : ''}> ${label}</label>`
+          ).join('')}
+        </div>
+        <div class="webhook-actions">
+          <button class="btn-mini" data-action="test" type="button">テスト送信</button>
+          <button class="btn-mini" data-action="delete" type="button">削除</button>
+        </div>
+      </div>`).join('');
+  };
+  const collectWebhooks = () => {
+    if (!webhookListEl) return state.externalNotify.webhooks;
+    return [...webhookListEl.querySelectorAll('.webhook-item')].map((row) => {
+      const prev = state.externalNotify.webhooks[Number(row.dataset.index)] || {};
+      const field = (name) => row.querySelector(`[data-field="${name}"]`);
+      const webhook = {
+        name: String(field('name').value || '').trim(),
+        enabled: Boolean(field('enabled').checked),
+        url: String(field('url').value || '').trim(),
+        method: field('method').value,
+        headers: parseHeaderLines(field('headers').value),
+        bodyTemplate: String(field('bodyTemplate').value || ''),
+      };
+      for (const [key] of WEBHOOK_TRIGGERS) webhook[key] = Boolean(field(key).checked);
+      if (prev.id) webhook.id = prev.id;
+      return webhook;
+    });
+  };
+  let didLogWebhookPersistError = false;
+  const persistWebhooks = (webhooks) => window.quotaApi.setSettings({
+    externalNotify: { webhooks },
+  }).then((saved) => {
+    // Saved entries carry their generated ids and masked URL/header values.
+    state.externalNotify.webhooks = Array.isArray(saved?.externalNotify?.webhooks)
+      ? saved.externalNotify.webhooks
+      : webhooks;
+    renderWebhooks();
+    didLogWebhookPersistError = false;
+  }).catch((e) => {
+    if (!didLogWebhookPersistError) {
+      log(`Webhook 設定保存エラー: ${toErrorMessage(e)}`, 'warn');
+      didLogWebhookPersistError = true;
     }
   });
-
-  $('#btn-pushover-test')?.addEventListener('click', async () => {
+  renderWebhooks();
+  webhookListEl?.addEventListener('change', () => {
+    persistWebhooks(collectWebhooks());
+  });
+  webhookListEl?.addEventListener('click', async (e) => {
+    const button = e.target.closest('button[data-action]');
+    if (!button) return;
+    const index = Number(button.closest('.webhook-item').dataset.index);
+    if (button.dataset.action === 'delete') {
+      const webhooks = collectWebhooks();
+      webhooks.splice(index, 1);
+      await persistWebhooks(webhooks);
+      return;
+    }
     try {
-      await persistExternalNotifySettings();
+      await persistWebhooks(collectWebhooks());
+      const webhook = state.externalNotify.webhooks[index];
+      if (!webhook?.id) return;
+      const label = webhook.name || webhook.id;
       const result = await window.quotaApi.sendExternalNotification({
         title: 'テスト',
-        body: 'AI Quota Monitor の Pushover 通知が有効です',
+        body: 'AI Quota Monitor の Webhook 通知が有効です',
         level: 'ok',
-        channel: 'pushover',
+        channel: `webhook:${webhook.id}`,
       });
       if (result.ok) {
-        log('Pushover テスト通知を送信しました', 'ok');
+        log(`Webhook (${label}) にテスト通知を送信しました`, 'ok');
       } else {
-        log(`Pushover テスト通知エラー: ${result.errors.join(', ')}`, 'warn');
+        log(`Webhook (${label}) テスト通知エラー: ${result.errors.join(', ')}`, 'warn');
       }
-    } catch (e) {
-      log(`Pushover テスト通知に失敗しました: ${toErrorMessage(e)}`, 'warn');
+    } catch (err) {
+      log(`Webhook テスト通知に失敗しました: ${toErrorMessage(err)}`, 'warn');
     }
   });
+  $('#btn-webhook-add')?.addEventListener('click', () => {
+    state.externalNotify.webhooks = [
+      ...collectWebhooks(),
+      { name: '', enabled: true, url: '', method: 'POST', headers: [], bodyTemplate: '', critical: true, recovery: true, warning: false, projected: false },
+    ];
+    renderWebhooks();
+  });
 
   // Usage snapshot export
   const exportEnabledEl = $('#export-enabled');
@@ -1321,6 +1642,75 @@ document.addEventListener('DOMContentLoaded', async () => {
   $('#export-enabled').addEventListener('change', persistExportSettings);
   $('#export-path').addEventListener('change', persistExportSettings);
 
+  // Metrics exporter
+  $('#metrics-enabled').checked = Boolean(state.metrics.enabled);
+  $('#metrics-bind-address').value = String(state.metrics.bindAddress || '');
+  $('#metrics-port').value = String(state.metrics.port || '');
+  const persistMetricsSettings = async () => {
+    const patch = {
+      enabled: Boolean($('#metrics-enabled').checked),
+      bindAddress: String($('#metrics-bind-address').value || '').trim() || '127.0.0.1',
+      port: parseInt($('#metrics-port').value, 10) || 9464,
+    };
+    try {
+      const saved = await window.quotaApi.setSettings({ metrics: patch });
+      if (saved?.metrics) state.metrics = saved.metrics;
+      if (state.metrics.enabled) {
+        log(`メトリクスを公開中: http://${state.metrics.bindAddress}:${state.metrics.port}/metrics`);
+      }
+    } catch (e) {
+      log(`メトリクス設定エラー: ${toErrorMessage(e)}`, 'warn');
+    }
+  };
+  $('#metrics-enabled').addEventListener('change', persistMetricsSettings);
+  $('#metrics-bind-address').addEventListener('change', persistMetricsSettings);
+  $('#metrics-port').addEventListener('change', persistMetricsSettings);
+
+  // MCP endpoint
+  $('#mcp-enabled').checked = Boolean(state.mcp.enabled);
+  $('#mcp-port').value = String(state.mcp.port || '');
+  const persistMcpSettings = async () => {
+    const patch = {
+      enabled: Boolean($('#mcp-enabled').checked),
+      port: parseInt($('#mcp-port').value, 10) || 9465,
+    };
+    try {
+      const saved = await window.quotaApi.setSettings({ mcp: patch });
+      if (saved?.mcp) state.mcp = saved.mcp;
+      if (state.mcp.enabled) {
+        log(`MCP サーバーを公開中: http://127.0.0.1:${state.mcp.port}/mcp`);
+      }
+    } catch (e) {
+      log(`MCP 設定エラー: ${toErrorMessage(e)}`, 'warn');
+    }
+  };
+  $('#mcp-enabled').addEventListener('change', persistMcpSettings);
+  $('#mcp-port').addEventListener('change', persistMcpSettings);
+
+  // Claude Code CLI auto-switch
+  $('#cli-auto-switch').checked = Boolean(state.cliSwitch.autoSwitch);
+  $('#cli-auto-switch').addEventListener('change', async () => {
+    try {
+      const saved = await window.quotaApi.setSettings({ cliSwitch: { autoSwitch: $('#cli-auto-switch').checked } });
+      if (saved?.cliSwitch) state.cliSwitch = saved.cliSwitch;
+    } catch (e) {
+      log(`CLI 自動切り替え設定エラー: ${toErrorMessage(e)}`, 'warn');
+    }
+  });
+
+  // Background token refresh lead time
+  $('#token-refresh-lead').value = String(state.tokenRefresh.leadMinutes);
+  $('#token-refresh-lead').addEventListener('change', async () => {
+    const leadMinutes = Math.max(1, Math.min(120, parseInt($('#token-refresh-lead').value, 10) || 5));
+    $('#token-refresh-lead').value = String(leadMinutes);
+    try {
+      const saved = await window.quotaApi.setSettings({ tokenRefresh: { leadMinutes } });
+      if (saved?.tokenRefresh) state.tokenRefresh = saved.tokenRefresh;
+    } catch (e) {
+      log(`トークン自動更新の設定エラー: ${toErrorMessage(e)}`, 'warn');
+    }
+  });
+
   $(SERVICE_META.claude.addBtnId).addEventListener('click', () => addAccountRow('claude'));
   $(SERVICE_META.codex.addBtnId).addEventListener('click', () => addAccountRow('codex'));
   $('#poll-interval').addEventListener('change', queuePersistSetup);
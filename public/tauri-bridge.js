@@ -7,18 +7,45 @@
   }
 
   const call = (command, args = {}) => invoke(command, args);
+  const listen = tauri?.event?.listen;
+  const subscribe = (event, handler) => {
+    if (typeof listen !== 'function') {
+      return Promise.reject(new Error('Tauri event API is unavailable'));
+    }
+    return listen(event, (e) => handler(e.payload));
+  };
 
   root.quotaApi = {
     platform: 'tauri',
     listAccounts: () => call('list_accounts'),
     saveAccount: (payload) => call('save_account', { payload }),
     deleteAccount: (payload) => call('delete_account', { payload }),
+    setAccountMute: (payload) => call('set_account_mute', { payload }),
+    setAccountCliLink: (payload) => call('set_account_cli_link', { payload }),
+    setAccountPreference: (payload) => call('set_account_preference', { payload }),
+    getRecommendations: () => call('get_recommendations'),
     getSettings: () => call('get_settings'),
     setSettings: (payload) => call('set_settings', { payload }),
     writeUsageSnapshot: (payload) => call('write_usage_snapshot', { payload }),
     getPollingState: () => call('get_polling_state'),
     setPollingState: (payload) => call('set_polling_state', { payload }),
     fetchUsage: (payload) => call('fetch_usage', { payload }),
+    pollNow: () => call('poll_now'),
+    getLatestUsage: () => call('get_latest_usage'),
+    getStoreLoadIssue: () => call('get_store_load_issue'),
+    getListenerErrors: () => call('get_listener_errors'),
+    onStoreLoadFailed: (handler) => subscribe('store-load-failed', handler),
+    queryUsageHistory: (query) => call('query_usage_history', { payload: query || {} }),
+    onPollCycleStarted: (handler) => subscribe('poll-cycle-started', handler),
+    onUsageUpdated: (handler) => subscribe('usage-updated', handler),
+    onPollCycleFinished: (handler) => subscribe('poll-cycle-finished', handler),
+    onUsageReclassified: (handler) => subscribe('usage-reclassified', handler),
+    onStatusChanged: (handler) => subscribe('status-changed', handler),
+    onNotificationFailed: (handler) => subscribe('notification-failed', handler),
+    onCliCredentialsSynced: (handler) => subscribe('cli-credentials-synced', handler),
+    onCliAccountSwitched: (handler) => subscribe('cli-account-switched', handler),
+    onAccountReloginChanged: (handler) => subscribe('account-relogin-changed', handler),
+    onTokenRefreshed: (handler) => subscribe('token-refreshed', handler),
     getWindowState: () => call('get_window_state'),
     setWindowMode: (payload) => call('set_window_mode', { payload }),
     setWindowPosition: (payload) => call('set_window_position', { payload }),
@@ -30,7 +57,8 @@
     quitApp: () => call('quit_app'),
     oauthLogin: (payload) => call('oauth_login', { payload }),
     cancelOauthLogin: () => call('cancel_oauth_login'),
-    importClaudeCliCredentials: (payload) => call('import_claude_cli_credentials', { payload }),
+    importCliCredentials: (payload) => call('import_cli_credentials', { payload }),
+    activateCliAccount: (payload) => call('activate_cli_account', { payload }),
     refreshToken: (payload) => call('refresh_token', { payload }),
     getTokenStatus: (payload) => call('get_token_status', { payload }),
     oauthExchangeCode: (payload) => call('oauth_exchange_code', { payload }),
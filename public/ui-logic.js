@@ -7,75 +7,6 @@
     root.UiLogic = api;
   }
 }(typeof globalThis !== 'undefined' ? globalThis : this, function factory() {
-  const STATUS_ORDER = ['unknown', 'ok', 'warning', 'critical', 'exhausted'];
-
-  function classifyUtilization(pct, notifySettings, exhaustedThreshold = 100) {
-    const value = Number(pct);
-    if (!Number.isFinite(value)) return 'ok';
-    if (value >= exhaustedThreshold) return 'exhausted';
-    if (value >= notifySettings.thresholdCritical) return 'critical';
-    if (value >= notifySettings.thresholdWarning) return 'warning';
-    return 'ok';
-  }
-
-  function classifyWindows(windows, notifySettings, exhaustedThreshold = 100) {
-    for (const windowInfo of windows) {
-      if (windowInfo.forceExhausted) {
-        windowInfo.status = 'exhausted';
-        continue;
-      }
-      windowInfo.status = classifyUtilization(windowInfo.utilization, notifySettings, exhaustedThreshold);
-    }
-    return windows;
-  }
-
-  function deriveServiceStatus(windows) {
-    return windows.reduce((current, windowInfo) => {
-      return STATUS_ORDER.indexOf(windowInfo.status) > STATUS_ORDER.indexOf(current)
-        ? windowInfo.status
-        : current;
-    }, 'ok');
-  }
-
-  function buildTransitionEffects(prev, next, label, windows, notifySettings) {
-    if (!prev || prev === next) return { notifications: [], logs: [] };
-
-    const notifications = [];
-    const logs = [];
-    const detail = windows.map((windowInfo) => `${windowInfo.name}: ${windowInfo.utilization}%`).join(', ');
-
-    if ((next === 'critical' || next === 'exhausted') && notifySettings.critical) {
-      notifications.push({
-        title: `${label} ⚠️`,
-        body: `ステータス: ${next} — ${detail}`,
-      });
-    }
-
-    if (next === 'warning' && prev !== 'critical' && prev !== 'exhausted' && notifySettings.warning) {
-      notifications.push({
-        title: `${label} ⚠`,
-        body: `ステータス: ${next} — ${detail}`,
-      });
-    }
-
-    if (next === 'ok' && (prev === 'critical' || prev === 'exhausted') && notifySettings.recovery) {
-      notifications.push({
-        title: `${label} ✅`,
-        body: 'クォータが回復しました',
-      });
-    }
-
-    if (next === 'critical' || next === 'exhausted') {
-      logs.push({ level: 'crit', message: `${label} → ${next}` });
-    } else if (next === 'warning' && prev !== 'critical' && prev !== 'exhausted') {
-      logs.push({ level: 'warn', message: `${label} → ${next}` });
-    } else if (next === 'ok' && (prev === 'critical' || prev === 'exhausted')) {
-      logs.push({ level: 'ok', message: `${label} → ok (回復)` });
-    }
-
-    return { notifications, logs };
-  }
-
   function deriveTokenInputValue({ hasToken, token, savedTokenMask }) {
     const tokenMasked = Boolean(hasToken && !token);
     return {
@@ -136,15 +67,30 @@
     };
   }
 
+  function parseHeaderLines(text) {
+    const headers = [];
+    for (const line of String(text || '').split(/\r?\n/)) {
+      const idx = line.indexOf(':');
+      if (idx <= 0) continue;
+      const name = line.slice(0, idx).trim();
+      if (!name) continue;
+      headers.push({ name, value: line.slice(idx + 1).trim() });
+    }
+    return headers;
+  }
+
+  function formatHeaderLines(headers) {
+    return (Array.isArray(headers) ? headers : [])
+      .map((h) => `${h.name}: ${h.value}`)
+      .join('\n');
+  }
+
   return {
-    STATUS_ORDER,
-    classifyUtilization,
-    classifyWindows,
-    deriveServiceStatus,
-    buildTransitionEffects,
     deriveTokenInputValue,
     normalizeAccountToken,
     calcElapsedPct,
     computePollingState,
+    parseHeaderLines,
+    formatHeaderLines,
   };
 }));
@@ -1,10 +1,26 @@
-use crate::store_repo::{read_store, write_store};
+use crate::cli_sync;
+use crate::notify_dispatch;
+use crate::store_repo::{read_store, update_store};
+use crate::token_refresh;
 use crate::token_store::{delete_token, ensure_service, get_token, set_token};
+use crate::usage_state::{self, UsageUpdate};
 use crate::error::{AppError, AppResult};
 use crate::validation::{validate_account_id, validate_account_name, validate_token};
-use tauri::AppHandle;
+use serde::Serialize;
+use tauri::{AppHandle, Emitter};
 use zeroize::Zeroize;
 
+pub const EVENT_ACCOUNT_RELOGIN_CHANGED: &str = "account-relogin-changed";
+
+#[derive(Debug, Clone, Serialize)]
+#[serde(rename_all = "camelCase")]
+struct ReloginChanged {
+    service: String,
+    id: String,
+    /// Set while the account needs a new login.
+    reason: Option<String>,
+}
+
 pub fn list_accounts(app: AppHandle) -> AppResult<crate::AccountsSnapshot> {
     let store = read_store(&app)?;
 
@@ -26,19 +42,25 @@ pub fn list_accounts(app: AppHandle) -> AppResult<crate::AccountsSnapshot> {
                 } else {
                     fallback
                 };
-                let has_token = get_token(service, &id).is_some();
-                Some(crate::AccountSnapshotEntry {
-                    id,
-                    name,
-                    has_token,
-                })
+                Some(snapshot_entry(service, &crate::AccountEntry { id, name, ..entry.clone() }))
             })
             .collect::<Vec<_>>()
     };
 
+    let services = crate::providers::all()
+        .iter()
+        .map(|provider| {
+            let accounts = store
+                .services
+                .get(provider.id())
+                .map(Vec::as_slice)
+                .unwrap_or_default();
+            (provider.id().to_string(), map_accounts(provider.id(), accounts))
+        })
+        .collect();
+
     Ok(crate::AccountsSnapshot {
-        claude: map_accounts("claude", &store.services.claude),
-        codex: map_accounts("codex", &store.services.codex),
+        services,
         settings: store.settings,
     })
 }
@@ -57,43 +79,245 @@ pub fn save_account(
     let name = crate::sanitize_string(payload.name.as_deref(), &fallback_name);
     validate_account_name(&name)?;
 
-    let mut store = read_store(&app)?;
-    let list = match service.as_str() {
-        "claude" => &mut store.services.claude,
-        "codex" => &mut store.services.codex,
-        _ => return Err(AppError::UnsupportedService),
+    let entry = update_store(&app, |store| {
+        let list = store.services.entry(service.clone()).or_default();
+
+        let pasted = payload.token.as_deref().is_some_and(|t| !t.trim().is_empty());
+        let entry = if let Some(existing) = list.iter_mut().find(|x| x.id == id) {
+            existing.name = name.clone();
+            if pasted {
+                existing.relogin_reason = None;
+            }
+            existing.clone()
+        } else {
+            let entry = crate::AccountEntry {
+                id: id.clone(),
+                name: name.clone(),
+                muted_until: None,
+                cli_linked: false,
+                cli_synced_at: None,
+                priority: 0,
+                reserve: false,
+                relogin_reason: None,
+            };
+            list.push(entry.clone());
+            entry
+        };
+
+        if let Some(mut token_input) = payload.token.take() {
+            let mut trimmed = token_input.trim().to_string();
+            if !trimmed.is_empty() {
+                validate_token(&trimmed)?;
+                set_token(&service, &id, &trimmed)?;
+            }
+            trimmed.zeroize();
+            token_input.zeroize();
+        }
+
+        if payload.clear_token.unwrap_or(false) {
+            delete_token(&service, &id)?;
+        }
+        Ok(entry)
+    })?;
+
+    Ok(snapshot_entry(&service, &entry))
+}
+
+fn snapshot_entry(service: &str, entry: &crate::AccountEntry) -> crate::AccountSnapshotEntry {
+    crate::AccountSnapshotEntry {
+        id: entry.id.clone(),
+        name: entry.name.clone(),
+        has_token: get_token(service, &entry.id).is_some(),
+        muted_until: entry.muted_until,
+        cli_linked: entry.cli_linked,
+        cli_synced_at: entry.cli_synced_at,
+        priority: entry.priority,
+        reserve: entry.reserve,
+        relogin_reason: entry.relogin_reason.clone(),
+    }
+}
+
+/// Run `f` on a stored account; unknown accounts are an input error.
+fn update_account<T>(
+    app: &AppHandle,
+    service: &str,
+    id: &str,
+    f: impl FnOnce(&mut crate::AccountEntry) -> T,
+) -> AppResult<T> {
+    update_store(app, |store| {
+        let entry = store
+            .services
+            .get_mut(service)
+            .and_then(|list| list.iter_mut().find(|x| x.id == id))
+            .ok_or_else(|| AppError::InvalidInput(format!("Unknown account: {service}:{id}")))?;
+        Ok(f(entry))
+    })
+}
+
+/// Forward "needs re-login" changes recorded by `token_refresh` to the UI,
+/// and send the re-login notice when an account is newly marked.
+pub fn start_relogin_watch(app: AppHandle) {
+    let Some(mut rx) = token_refresh::subscribe_relogin() else {
+        return;
     };
+    tauri::async_runtime::spawn(async move {
+        while let Some(change) = rx.recv().await {
+            let _ = app.emit(
+                EVENT_ACCOUNT_RELOGIN_CHANGED,
+                ReloginChanged {
+                    service: change.service.clone(),
+                    id: change.id.clone(),
+                    reason: change.reason.clone(),
+                },
+            );
+            let Some(reason) = change.reason.as_deref().filter(|_| change.newly_marked) else {
+                continue;
+            };
+            let update = usage_state::get(&change.service, &change.id)
+                .unwrap_or_else(|| unfetched_update(&app, &change.service, &change.id, reason));
+            notify_dispatch::dispatch_relogin(&app, &update, reason);
+        }
+    });
+}
 
-    if let Some(existing) = list.iter_mut().find(|x| x.id == id) {
-        existing.name = name.clone();
-    } else {
-        list.push(crate::AccountEntry {
-            id: id.clone(),
-            name: name.clone(),
-        });
+/// Stand-in for an account that has not been fetched yet, for the re-login notice.
+fn unfetched_update(app: &AppHandle, service: &str, id: &str, reason: &str) -> UsageUpdate {
+    let name = read_store(app)
+        .ok()
+        .and_then(|store| {
+            store
+                .services
+                .get(service)
+                .and_then(|list| list.iter().find(|a| a.id == id))
+                .map(|a| a.name.clone())
+        })
+        .unwrap_or_else(|| id.to_string());
+    UsageUpdate {
+        service: service.to_string(),
+        id: id.to_string(),
+        name,
+        fetched_at: chrono::Utc::now().timestamp_millis(),
+        windows: Vec::new(),
+        status: "error".to_string(),
+        raw: None,
+        error: Some(reason.to_string()),
+        rate_limit_until: None,
+        skipped: false,
     }
+}
+
+/// Mute or unmute notifications for one account. A time in the past unmutes.
+pub fn set_account_mute(
+    app: AppHandle,
+    payload: crate::SetAccountMutePayload,
+) -> AppResult<crate::AccountSnapshotEntry> {
+    let service = crate::sanitize_string(payload.service.as_deref(), "");
+    ensure_service(&service)?;
+
+    let id = crate::sanitize_string(payload.id.as_deref(), "");
+    validate_account_id(&id)?;
+
+    let now = chrono::Utc::now().timestamp_millis();
+    let muted_until = payload.muted_until.filter(|&until| until > now);
+    let entry = update_account(&app, &service, &id, |entry| {
+        entry.muted_until = muted_until;
+        entry.clone()
+    })?;
+    Ok(snapshot_entry(&service, &entry))
+}
+
+/// Set the recommendation priority and reserve flag of one account.
+pub fn set_account_preference(
+    app: AppHandle,
+    payload: crate::SetAccountPreferencePayload,
+) -> AppResult<crate::AccountSnapshotEntry> {
+    let service = crate::sanitize_string(payload.service.as_deref(), "");
+    ensure_service(&service)?;
 
-    if let Some(mut token_input) = payload.token.take() {
-        let mut trimmed = token_input.trim().to_string();
-        if !trimmed.is_empty() {
-            validate_token(&trimmed)?;
-            set_token(&service, &id, &trimmed)?;
+    let id = crate::sanitize_string(payload.id.as_deref(), "");
+    validate_account_id(&id)?;
+
+    if let Some(priority) = payload.priority {
+        if !(-crate::ACCOUNT_PRIORITY_MAX..=crate::ACCOUNT_PRIORITY_MAX).contains(&priority) {
+            return Err(AppError::InvalidInput(format!(
+                "priority must be between -{max} and {max}",
+                max = crate::ACCOUNT_PRIORITY_MAX
+            )));
         }
-        trimmed.zeroize();
-        token_input.zeroize();
     }
+    let entry = update_account(&app, &service, &id, |entry| {
+        if let Some(priority) = payload.priority {
+            entry.priority = priority;
+        }
+        if let Some(reserve) = payload.reserve {
+            entry.reserve = reserve;
+        }
+        entry.clone()
+    })?;
+    Ok(snapshot_entry(&service, &entry))
+}
 
-    if payload.clear_token.unwrap_or(false) {
-        delete_token(&service, &id)?;
+/// Link an account to the CLI's login or unlink it. Linking copies the CLI's
+/// access token right away; from then on `cli_sync` follows the file.
+pub fn set_account_cli_link(
+    app: AppHandle,
+    payload: crate::SetAccountCliLinkPayload,
+) -> AppResult<crate::AccountSnapshotEntry> {
+    let service = crate::sanitize_string(payload.service.as_deref(), "");
+    ensure_service(&service)?;
+
+    let id = crate::sanitize_string(payload.id.as_deref(), "");
+    validate_account_id(&id)?;
+
+    let linked = payload.linked.unwrap_or(false);
+    if linked && !cli_sync::supports(&service) {
+        return Err(AppError::InvalidInput(format!("No CLI to link for {service}")));
+    }
+    let _guard = cli_sync::exclusive();
+    let previous = update_store(&app, |store| {
+        let accounts = store.services.get_mut(&service).map(Vec::as_mut_slice).unwrap_or_default();
+        set_cli_link(accounts, &id, linked)
+    })?;
+    if linked {
+        if let Err(e) = cli_sync::sync_account(&app, &service, &id) {
+            // Put the previous link back.
+            let _ = update_store(&app, |store| {
+                for entry in store.services.get_mut(&service).into_iter().flatten() {
+                    entry.cli_linked = previous.contains(&entry.id);
+                }
+                Ok(())
+            });
+            return Err(e);
+        }
     }
 
-    write_store(&app, &store)?;
+    let store = read_store(&app)?;
+    let entry = store
+        .services
+        .get(&service)
+        .and_then(|list| list.iter().find(|x| x.id == id))
+        .ok_or_else(|| AppError::InvalidInput(format!("Unknown account: {service}:{id}")))?;
+    Ok(snapshot_entry(&service, entry))
+}
 
-    Ok(crate::AccountSnapshotEntry {
-        id: id.clone(),
-        name,
-        has_token: get_token(&service, &id).is_some(),
-    })
+/// Link account `id` to its CLI, or unlink it. Only one account per service
+/// can follow the CLI's credentials file, so linking unlinks the others, like
+/// `cli_switch::activate`. Returns the ids that were linked before.
+fn set_cli_link(accounts: &mut [crate::AccountEntry], id: &str, linked: bool) -> AppResult<Vec<String>> {
+    if !accounts.iter().any(|a| a.id == id) {
+        return Err(AppError::InvalidInput(format!("Unknown account: {id}")));
+    }
+    let previous = accounts.iter().filter(|a| a.cli_linked).map(|a| a.id.clone()).collect();
+    for entry in accounts.iter_mut() {
+        if entry.id == id {
+            entry.cli_linked = linked;
+            entry.cli_synced_at = None;
+        } else if linked && entry.cli_linked {
+            entry.cli_linked = false;
+            entry.cli_synced_at = None;
+        }
+    }
+    Ok(previous)
 }
 
 pub fn delete_account(app: AppHandle, payload: crate::DeleteAccountPayload) -> AppResult<crate::ApiOk> {
@@ -103,16 +327,49 @@ pub fn delete_account(app: AppHandle, payload: crate::DeleteAccountPayload) -> A
     let id = crate::sanitize_string(payload.id.as_deref(), "");
     validate_account_id(&id)?;
 
-    let mut store = read_store(&app)?;
-    let list = match service.as_str() {
-        "claude" => &mut store.services.claude,
-        "codex" => &mut store.services.codex,
-        _ => return Err(AppError::UnsupportedService),
-    };
-
-    list.retain(|x| x.id != id);
-    write_store(&app, &store)?;
+    update_store(&app, |store| {
+        if let Some(list) = store.services.get_mut(&service) {
+            list.retain(|x| x.id != id);
+        }
+        Ok(())
+    })?;
     delete_token(&service, &id)?;
 
     Ok(crate::ApiOk { ok: true })
 }
+
+#[cfg(test)]
+mod tests {
+    use super::set_cli_link;
+
+    fn account(id: &str, cli_linked: bool) -> crate::AccountEntry {
+        crate::AccountEntry {
+            id: id.to_string(),
+            name: id.to_string(),
+            muted_until: None,
+            cli_linked,
+            cli_synced_at: cli_linked.then_some(1),
+            priority: 0,
+            reserve: false,
+            relogin_reason: None,
+        }
+    }
+
+    fn linked(accounts: &[crate::AccountEntry]) -> Vec<&str> {
+        accounts.iter().filter(|a| a.cli_linked).map(|a| a.id.as_str()).collect()
+    }
+
+    #[test]
+    fn linking_an_account_unlinks_the_others() {
+        let mut accounts = vec![account("a1", true), account("a2", false), account("a3", false)];
+        assert_eq!(set_cli_link(&mut accounts, "a2", true).unwrap(), vec!["a1"]);
+        assert_eq!(linked(&accounts), ["a2"]);
+        assert_eq!(accounts[0].cli_synced_at, None);
+
+        set_cli_link(&mut accounts, "a3", false).unwrap();
+        assert_eq!(linked(&accounts), ["a2"]);
+        set_cli_link(&mut accounts, "a2", false).unwrap();
+        assert!(linked(&accounts).is_empty());
+        assert!(set_cli_link(&mut accounts, "missing", true).is_err());
+    }
+}
@@ -1,13 +1,11 @@
-use crate::usage_parser::{parse_claude_usage, parse_codex_usage, UsageWindow};
+use crate::usage_parser::UsageWindow;
 use crate::validation::{validate_token, validate_upstream_url};
-use reqwest::header::{ACCEPT, AUTHORIZATION, CONTENT_TYPE, HeaderMap, HeaderValue};
+use reqwest::header::{CONTENT_TYPE, HeaderMap};
 use serde::Serialize;
 use serde_json::Value;
 use std::time::{Duration, SystemTime};
 use thiserror::Error;
 
-const CLAUDE_USAGE_URL: &str = "https://api.anthropic.com/api/oauth/usage";
-const CODEX_USAGE_URL: &str = "https://chatgpt.com/backend-api/wham/usage";
 const HTTP_REQUEST_TIMEOUT_SECS: u64 = 30;
 
 #[derive(Debug, Clone, Serialize)]
@@ -58,6 +56,14 @@ pub enum ApiError {
     NonJson,
     #[error("{0}")]
     Upstream(String),
+    /// HTTP 401: the access token was rejected (expired or revoked).
+    #[error("{0}")]
+    Unauthorized(String),
+    #[error("{message}")]
+    RateLimited {
+        message: String,
+        retry_after_secs: Option<u64>,
+    },
 }
 
 pub(crate) fn build_error_message(status: u16, content_type: &str, retry_after_secs: Option<u64>) -> String {
@@ -118,66 +124,29 @@ async fn fetch_usage_raw(url: &str, headers: HeaderMap) -> Result<RawUpstream, A
     })
 }
 
-async fn fetch_claude_usage_raw(token: &str, anthropic_oauth_beta: &str) -> Result<RawUpstream, ApiError> {
-    let token = token.trim();
-    validate_token(token).map_err(|e| ApiError::Validation(e.to_string()))?;
-
-    let mut headers = HeaderMap::new();
-    headers.insert(
-        AUTHORIZATION,
-        HeaderValue::from_str(&format!("Bearer {token}"))
-            .map_err(|e| ApiError::Validation(format!("Invalid authorization header: {e}")))?,
-    );
-    headers.insert(ACCEPT, HeaderValue::from_static("application/json"));
-    headers.insert(
-        "anthropic-beta",
-        HeaderValue::from_str(anthropic_oauth_beta)
-            .map_err(|e| ApiError::Validation(format!("Invalid anthropic-beta header: {e}")))?,
-    );
-
-    fetch_usage_raw(CLAUDE_USAGE_URL, headers).await
-}
-
-async fn fetch_codex_usage_raw(token: &str) -> Result<RawUpstream, ApiError> {
+pub async fn fetch_normalized_usage(service: &str, token: &str) -> Result<FetchUsageResponse, ApiError> {
+    let provider = crate::providers::get(service).ok_or(ApiError::UnsupportedService)?;
     let token = token.trim();
     validate_token(token).map_err(|e| ApiError::Validation(e.to_string()))?;
-
-    let mut headers = HeaderMap::new();
-    headers.insert(
-        AUTHORIZATION,
-        HeaderValue::from_str(&format!("Bearer {token}"))
-            .map_err(|e| ApiError::Validation(format!("Invalid authorization header: {e}")))?,
-    );
-    headers.insert(ACCEPT, HeaderValue::from_static("application/json"));
-
-    fetch_usage_raw(CODEX_USAGE_URL, headers).await
-}
-
-pub async fn fetch_normalized_usage(
-    service: &str,
-    token: &str,
-    anthropic_oauth_beta: &str,
-) -> Result<FetchUsageResponse, ApiError> {
-    let raw = match service {
-        "claude" => fetch_claude_usage_raw(token, anthropic_oauth_beta).await?,
-        "codex" => fetch_codex_usage_raw(token).await?,
-        _ => return Err(ApiError::UnsupportedService),
-    };
+    let headers = provider
+        .usage_headers(token)
+        .map_err(|e| ApiError::Validation(e.to_string()))?;
+    let raw = fetch_usage_raw(provider.usage_url(), headers).await?;
 
     if !raw.ok {
-        return Err(ApiError::Upstream(build_error_message(
-            raw.status,
-            &raw.content_type,
-            raw.retry_after_secs,
-        )));
+        let message = build_error_message(raw.status, &raw.content_type, raw.retry_after_secs);
+        return Err(match raw.status {
+            401 => ApiError::Unauthorized(message),
+            429 => ApiError::RateLimited {
+                message,
+                retry_after_secs: raw.retry_after_secs,
+            },
+            _ => ApiError::Upstream(message),
+        });
     }
 
     let parsed: Value = serde_json::from_str(&raw.body).map_err(|_| ApiError::NonJson)?;
-    let windows = match service {
-        "claude" => parse_claude_usage(&parsed),
-        "codex" => parse_codex_usage(&parsed),
-        _ => Vec::new(),
-    };
+    let windows = provider.parse_usage(&parsed);
 
     Ok(FetchUsageResponse {
         raw: parsed,
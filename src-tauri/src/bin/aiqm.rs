@@ -0,0 +1,3 @@
+fn main() {
+    std::process::exit(ai_quota_monitor_lib::cli::main());
+}
@@ -0,0 +1,516 @@
+//! `aiqm`: headless access to the same accounts, keyring tokens and settings
+//! as the desktop app.
+
+mod output;
+mod statusline;
+
+use crate::error::{AppError, AppResult};
+use crate::mcp;
+use crate::status::{self, Status};
+use crate::store_repo::{default_data_dir, load_store_file};
+use crate::token_refresh;
+use crate::token_store::{ensure_service, get_token};
+use crate::usage_commands::fetch_stored_usage;
+use crate::validation::validate_account_id;
+use output::Format;
+use std::io::{IsTerminal, Read, Write};
+use std::path::PathBuf;
+use std::time::Duration;
+
+pub const EXIT_OK: i32 = 0;
+pub const EXIT_WARNING: i32 = 1;
+pub const EXIT_CRITICAL: i32 = 2;
+pub const EXIT_EXHAUSTED: i32 = 3;
+pub const EXIT_ERROR: i32 = 4;
+
+const WATCH_INTERVAL_MIN_SECS: u64 = 30;
+
+const USAGE: &str = "\
+Usage: aiqm [--data-dir DIR] <command> [options]
+
+Commands:
+  status          Fetch usage once and print it
+  watch           Fetch usage repeatedly until interrupted
+  refresh-token   Refresh OAuth access tokens
+  export          Write a usage snapshot JSON file
+  statusline      Print one line from the app's usage cache (no network);
+                  reads Claude Code's statusline JSON from stdin
+  mcp             Serve quota tools over MCP (JSON-RPC on stdin/stdout)
+  help            Show this help
+
+Options:
+  -s, --service <ID>        Only this service (claude, codex)
+  -a, --account <ID>        Only this account id
+  -f, --format <FORMAT>     table (default), json or line
+  -i, --interval <SECS>     watch: poll interval (default: app setting, min 30)
+  -o, --output <PATH>       export: output file, '-' for stdout
+                            (default: the app's usage export path)
+  -t, --template <TEXT>     statusline: placeholders {service} {account}
+                            {status} {windows} {max} {reset} {model}
+                            (default: \"{account} {windows}\")
+      --color               statusline: color windows by status (ANSI)
+      --snapshot <PATH>     statusline: read this snapshot file instead of
+                            the usage cache
+      --data-dir <DIR>      Directory containing accounts.json
+
+Exit codes (status, refresh-token, export):
+  0 ok, 1 warning, 2 critical, 3 exhausted, 4 error
+";
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+enum Command {
+    Status,
+    Watch,
+    RefreshToken,
+    Export,
+    Statusline,
+    Mcp,
+    Help,
+    Version,
+}
+
+#[derive(Debug, Clone)]
+struct Options {
+    command: Command,
+    data_dir: Option<PathBuf>,
+    service: Option<String>,
+    account: Option<String>,
+    format: Format,
+    interval: Option<u64>,
+    output: Option<String>,
+    template: Option<String>,
+    color: bool,
+    snapshot: Option<PathBuf>,
+}
+
+/// Entry point for the `aiqm` binary. Returns the process exit code.
+pub fn main() -> i32 {
+    let args: Vec<String> = std::env::args().skip(1).collect();
+    let opts = match parse_args(&args) {
+        Ok(opts) => opts,
+        Err(e) => {
+            eprintln!("aiqm: {e}\n\n{USAGE}");
+            return EXIT_ERROR;
+        }
+    };
+
+    match opts.command {
+        Command::Help => {
+            print!("{USAGE}");
+            return EXIT_OK;
+        }
+        Command::Version => {
+            println!("aiqm {}", env!("CARGO_PKG_VERSION"));
+            return EXIT_OK;
+        }
+        _ => {}
+    }
+
+    let runtime = match tokio::runtime::Builder::new_current_thread().enable_all().build() {
+        Ok(runtime) => runtime,
+        Err(e) => {
+            eprintln!("aiqm: failed to start async runtime: {e}");
+            return EXIT_ERROR;
+        }
+    };
+    match runtime.block_on(run(&opts)) {
+        Ok(code) => code,
+        Err(e) => {
+            eprintln!("aiqm: {e}");
+            EXIT_ERROR
+        }
+    }
+}
+
+fn parse_args(args: &[String]) -> Result<Options, String> {
+    let mut opts = Options {
+        command: Command::Help,
+        data_dir: None,
+        service: None,
+        account: None,
+        format: Format::Table,
+        interval: None,
+        output: None,
+        template: None,
+        color: false,
+        snapshot: None,
+    };
+    let mut command = None;
+    let mut iter = args.iter();
+
+    while let Some(arg) = iter.next() {
+        let (flag, inline) = match arg.split_once('=') {
+            Some((flag, value)) if flag.starts_with("--") => (flag, Some(value.to_string())),
+            _ => (arg.as_str(), None),
+        };
+        let mut value = |name: &str| -> Result<String, String> {
+            inline
+                .clone()
+                .or_else(|| iter.next().cloned())
+                .ok_or_else(|| format!("{name} requires a value"))
+        };
+        match flag {
+            "-h" | "--help" => command = Some(Command::Help),
+            "-V" | "--version" => command = Some(Command::Version),
+            "--data-dir" => opts.data_dir = Some(PathBuf::from(value(flag)?)),
+            "-s" | "--service" => opts.service = Some(value(flag)?),
+            "-a" | "--account" => opts.account = Some(value(flag)?),
+            "-f" | "--format" => opts.format = Format::parse(&value(flag)?)?,
+            "-i" | "--interval" => {
+                let raw = value(flag)?;
+                let secs = raw
+                    .parse::<u64>()
+                    .map_err(|_| format!("invalid interval: {raw}"))?;
+                opts.interval = Some(secs);
+            }
+            "-o" | "--output" => opts.output = Some(value(flag)?),
+            "-t" | "--template" => opts.template = Some(value(flag)?),
+            "--color" => opts.color = true,
+            "--snapshot" => opts.snapshot = Some(PathBuf::from(value(flag)?)),
+            _ if flag.starts_with('-') => return Err(format!("unknown option: {flag}")),
+            _ if command.is_none() => {
+                command = Some(match flag {
+                    "status" => Command::Status,
+                    "watch" => Command::Watch,
+                    "refresh-token" => Command::RefreshToken,
+                    "export" => Command::Export,
+                    "statusline" => Command::Statusline,
+                    "mcp" => Command::Mcp,
+                    "help" => Command::Help,
+                    other => return Err(format!("unknown command: {other}")),
+                });
+            }
+            other => return Err(format!("unexpected argument: {other}")),
+        }
+    }
+
+    if let Some(service) = opts.service.as_deref() {
+        ensure_service(service).map_err(|_| format!("unsupported service: {service}"))?;
+    }
+    if let Some(account) = opts.account.as_deref() {
+        validate_account_id(account).map_err(|e| e.to_string())?;
+    }
+    opts.command = command.unwrap_or(Command::Help);
+    Ok(opts)
+}
+
+async fn run(opts: &Options) -> AppResult<i32> {
+    let data_dir = match opts.data_dir.clone() {
+        Some(dir) => dir,
+        None => default_data_dir()?,
+    };
+    crate::store_repo::use_store_file(data_dir.join(crate::STORE_FILE));
+    let store = load_store_file(&data_dir.join(crate::STORE_FILE));
+
+    match opts.command {
+        Command::Status => {
+            let entries = fetch_entries(&store, opts).await;
+            print_entries(&entries, opts.format);
+            Ok(exit_code(&entries))
+        }
+        Command::Watch => {
+            let interval = opts
+                .interval
+                .unwrap_or(u64::try_from(store.settings.poll_interval).unwrap_or(0))
+                .max(WATCH_INTERVAL_MIN_SECS);
+            loop {
+                // Re-read so accounts added in the app are picked up.
+                let store = load_store_file(&data_dir.join(crate::STORE_FILE));
+                let entries = fetch_entries(&store, opts).await;
+                print_entries(&entries, opts.format);
+                tokio::time::sleep(Duration::from_secs(interval)).await;
+            }
+        }
+        Command::RefreshToken => refresh_tokens(&store, opts).await,
+        Command::Export => {
+            let entries = fetch_entries(&store, opts).await;
+            let fetched_at = Some(chrono::Utc::now().to_rfc3339());
+            match opts.output.as_deref() {
+                Some("-") => {
+                    let json = crate::export_commands::snapshot_json(
+                        env!("CARGO_PKG_VERSION"),
+                        fetched_at,
+                        entries.clone(),
+                        &store.services,
+                    )?;
+                    let mut stdout = std::io::stdout();
+                    let _ = stdout.write_all(&json);
+                    let _ = writeln!(stdout);
+                }
+                Some(path) => {
+                    let path = crate::export_commands::resolve_export_path_in(&data_dir, path)?;
+                    crate::export_commands::write_snapshot_file(&path, fetched_at, entries.clone(), &store.services)?;
+                }
+                None => {
+                    let configured = store.settings.usage_export.path.as_deref().ok_or_else(|| {
+                        AppError::InvalidInput(
+                            "No export path configured; pass --output".to_string(),
+                        )
+                    })?;
+                    let path = crate::export_commands::resolve_export_path_in(&data_dir, configured)?;
+                    crate::export_commands::write_snapshot_file(&path, fetched_at, entries.clone(), &store.services)?;
+                }
+            }
+            Ok(exit_code(&entries))
+        }
+        Command::Statusline => print_statusline(&data_dir, &store, opts),
+        Command::Mcp => serve_mcp(&data_dir, opts).await,
+        Command::Help | Command::Version => Ok(EXIT_OK),
+    }
+}
+
+/// Always exits 0 once a line is printed so the statusline keeps showing it.
+fn print_statusline(data_dir: &std::path::Path, store: &crate::Store, opts: &Options) -> AppResult<i32> {
+    let mut stdin = std::io::stdin();
+    let input = if stdin.is_terminal() {
+        None
+    } else {
+        let mut text = String::new();
+        let _ = stdin.read_to_string(&mut text);
+        statusline::StatuslineInput::parse(&text)
+    };
+    // Statusline JSON on stdin means we were started by Claude Code.
+    let service = opts.service.as_deref().or(input.as_ref().map(|_| "claude"));
+
+    let path = opts
+        .snapshot
+        .clone()
+        .unwrap_or_else(|| data_dir.join(crate::USAGE_CACHE_FILE));
+    let snapshot = crate::export_commands::read_snapshot_file(&path)?;
+    let linked: Vec<(&str, &str)> = store
+        .services
+        .iter()
+        .flat_map(|(service, accounts)| {
+            accounts
+                .iter()
+                .filter(|a| a.cli_linked)
+                .map(move |a| (service.as_str(), a.id.as_str()))
+        })
+        .collect();
+    let entry = statusline::pick_entry(&snapshot.entries, service, opts.account.as_deref(), &linked)
+        .ok_or_else(|| {
+            AppError::InvalidInput("No cached usage for the selected account".to_string())
+        })?;
+
+    let now_ms = now_millis();
+    let ctx = statusline::Context {
+        model: input.as_ref().map_or("", |i| i.model_name()),
+        color: opts.color,
+        now_ms,
+        age_ms: snapshot_age_ms(&snapshot, now_ms),
+        stale_after_ms: statusline::stale_after_ms(store.settings.poll_interval),
+    };
+    let template = opts.template.as_deref().unwrap_or(statusline::DEFAULT_TEMPLATE);
+    println!("{}", statusline::render(entry, template, &ctx));
+    Ok(EXIT_OK)
+}
+
+fn snapshot_age_ms(snapshot: &crate::export_commands::UsageSnapshotFile, now_ms: i64) -> i64 {
+    chrono::DateTime::parse_from_rfc3339(&snapshot.generated_at)
+        .map_or(i64::MAX, |at| now_ms - at.timestamp_millis())
+}
+
+/// MCP over stdio: one JSON-RPC message per line until stdin closes.
+async fn serve_mcp(data_dir: &std::path::Path, opts: &Options) -> AppResult<i32> {
+    let stdin = std::io::stdin();
+    let mut stdout = std::io::stdout();
+    let mut line = String::new();
+    loop {
+        line.clear();
+        let n = stdin
+            .read_line(&mut line)
+            .map_err(|e| AppError::Message(format!("Failed to read stdin: {e}")))?;
+        if n == 0 {
+            return Ok(EXIT_OK);
+        }
+        if line.trim().is_empty() {
+            continue;
+        }
+        let reply = match mcp::parse_message(&line) {
+            Ok(message) => {
+                let entries = if mcp::is_tool_call(&message) {
+                    mcp_entries(data_dir, opts).await
+                } else {
+                    Vec::new()
+                };
+                mcp::handle_message(&message, &entries, now_millis())
+            }
+            Err(response) => Some(response),
+        };
+        if let Some(reply) = reply {
+            let _ = writeln!(stdout, "{reply}");
+            let _ = stdout.flush();
+        }
+    }
+}
+
+/// The app's usage cache while it is fresh, otherwise a live fetch.
+async fn mcp_entries(data_dir: &std::path::Path, opts: &Options) -> Vec<crate::UsageSnapshotEntry> {
+    let store = load_store_file(&data_dir.join(crate::STORE_FILE));
+    let cache = crate::export_commands::read_snapshot_file(&data_dir.join(crate::USAGE_CACHE_FILE));
+    if let Ok(snapshot) = cache {
+        if snapshot_age_ms(&snapshot, now_millis()) <= statusline::stale_after_ms(store.settings.poll_interval) {
+            return snapshot.entries;
+        }
+    }
+    fetch_entries(&store, opts).await
+}
+
+/// Accounts selected by `--service` / `--account`, as `(service, entry)`.
+fn selected_accounts<'a>(
+    store: &'a crate::Store,
+    opts: &'a Options,
+) -> impl Iterator<Item = (&'static str, &'a crate::AccountEntry)> + 'a {
+    crate::providers::all()
+        .iter()
+        .map(|p| p.id())
+        .filter(|service| opts.service.as_deref().is_none_or(|s| s == *service))
+        .flat_map(move |service| {
+            store
+                .services
+                .get(service)
+                .into_iter()
+                .flatten()
+                .filter(|account| opts.account.as_deref().is_none_or(|a| a == account.id))
+                .map(move |account| (service, account))
+        })
+}
+
+async fn fetch_entries(store: &crate::Store, opts: &Options) -> Vec<crate::UsageSnapshotEntry> {
+    let mut entries = Vec::new();
+    for (service, account) in selected_accounts(store, opts) {
+        let mut entry = crate::UsageSnapshotEntry {
+            service: service.to_string(),
+            id: account.id.clone(),
+            name: account.name.clone(),
+            has_token: get_token(service, &account.id).is_some(),
+            label: Some(account.name.clone()),
+            status: None,
+            windows: Vec::new(),
+            error: None,
+        };
+        if entry.has_token {
+            // Errors here surface through the fetch below (expired token -> HTTP 401).
+            let _ = token_refresh::refresh_if_expiring(service, &account.id).await;
+            match fetch_stored_usage(service, &account.id).await {
+                Ok(response) => {
+                    entry.windows = response.windows;
+                    status::classify_windows(&mut entry.windows, &store.settings.notify_settings);
+                    entry.status = Some(status::account_status(&entry.windows, None));
+                }
+                Err(e) => {
+                    entry.status = Some("error".to_string());
+                    entry.error = Some(e.to_string());
+                }
+            }
+        }
+        entries.push(entry);
+    }
+    entries
+}
+
+fn print_entries(entries: &[crate::UsageSnapshotEntry], format: Format) {
+    let mut stdout = std::io::stdout();
+    let _ = stdout.write_all(output::render(entries, format, now_millis()).as_bytes());
+    let _ = stdout.flush();
+}
+
+/// Worst status across accounts with a token; any fetch error wins.
+fn exit_code(entries: &[crate::UsageSnapshotEntry]) -> i32 {
+    let fetched: Vec<_> = entries.iter().filter(|e| e.has_token).collect();
+    if fetched.is_empty() || fetched.iter().any(|e| e.error.is_some()) {
+        return EXIT_ERROR;
+    }
+    let worst = fetched
+        .iter()
+        .filter_map(|e| e.status.as_deref().and_then(Status::parse))
+        .fold(Status::Ok, Status::max);
+    match worst {
+        Status::Unknown | Status::Ok => EXIT_OK,
+        Status::Warning | Status::Projected => EXIT_WARNING,
+        Status::Critical => EXIT_CRITICAL,
+        Status::Exhausted => EXIT_EXHAUSTED,
+    }
+}
+
+async fn refresh_tokens(store: &crate::Store, opts: &Options) -> AppResult<i32> {
+    let mut code = EXIT_OK;
+    let mut any = false;
+    for (service, account) in selected_accounts(store, opts) {
+        if crate::token_store::get_refresh_token(service, &account.id).is_none() {
+            continue;
+        }
+        any = true;
+        match token_refresh::do_refresh(service, &account.id).await {
+            Ok(_) => println!("{service}/{}: refreshed", account.id),
+            Err(e) => {
+                eprintln!("{service}/{}: {e}", account.id);
+                code = EXIT_ERROR;
+            }
+        }
+    }
+    if !any {
+        return Err(AppError::InvalidInput(
+            "No selected account has a refresh token".to_string(),
+        ));
+    }
+    Ok(code)
+}
+
+fn now_millis() -> i64 {
+    std::time::SystemTime::now()
+        .duration_since(std::time::UNIX_EPOCH)
+        .unwrap_or_default()
+        .as_millis() as i64
+}
+
+#[cfg(test)]
+mod tests {
+    use super::{exit_code, parse_args, Command, EXIT_CRITICAL, EXIT_ERROR, EXIT_OK};
+    use super::output::Format;
+
+    fn args(list: &[&str]) -> Vec<String> {
+        list.iter().map(|s| s.to_string()).collect()
+    }
+
+    fn entry(status: &str, error: Option<&str>) -> crate::UsageSnapshotEntry {
+        crate::UsageSnapshotEntry {
+            service: "claude".to_string(),
+            id: "main".to_string(),
+            name: "Main".to_string(),
+            has_token: true,
+            label: None,
+            status: Some(status.to_string()),
+            windows: Vec::new(),
+            error: error.map(str::to_string),
+        }
+    }
+
+    #[test]
+    fn parse_args_reads_command_and_options() {
+        let opts = parse_args(&args(&["status", "-s", "codex", "--format=json", "--account", "work"]))
+            .expect("valid args");
+        assert_eq!(opts.command, Command::Status);
+        assert_eq!(opts.service.as_deref(), Some("codex"));
+        assert_eq!(opts.account.as_deref(), Some("work"));
+        assert_eq!(opts.format, Format::Json);
+
+        let opts = parse_args(&args(&["statusline", "--color", "-t", "{max}"])).expect("valid args");
+        assert_eq!(opts.command, Command::Statusline);
+        assert!(opts.color);
+        assert_eq!(opts.template.as_deref(), Some("{max}"));
+
+        assert_eq!(parse_args(&[]).expect("empty").command, Command::Help);
+        assert!(parse_args(&args(&["status", "--service", "gemini"])).is_err());
+        assert!(parse_args(&args(&["bogus"])).is_err());
+        assert!(parse_args(&args(&["status", "--format"])).is_err());
+    }
+
+    #[test]
+    fn exit_code_reflects_worst_status() {
+        assert_eq!(exit_code(&[entry("ok", None)]), EXIT_OK);
+        assert_eq!(exit_code(&[entry("ok", None), entry("critical", None)]), EXIT_CRITICAL);
+        assert_eq!(exit_code(&[entry("ok", None), entry("error", Some("HTTP 401"))]), EXIT_ERROR);
+        assert_eq!(exit_code(&[]), EXIT_ERROR);
+    }
+}
@@ -0,0 +1,169 @@
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Format {
+    /// Aligned columns, one row per window.
+    Table,
+    /// Pretty-printed JSON array of snapshot entries.
+    Json,
+    /// Everything on a single line, for prompts and log lines.
+    Line,
+}
+
+impl Format {
+    pub fn parse(value: &str) -> Result<Self, String> {
+        match value {
+            "table" => Ok(Format::Table),
+            "json" => Ok(Format::Json),
+            "line" => Ok(Format::Line),
+            other => Err(format!("unknown format: {other} (expected table, json or line)")),
+        }
+    }
+}
+
+pub fn render(entries: &[crate::UsageSnapshotEntry], format: Format, now_ms: i64) -> String {
+    match format {
+        Format::Table => render_table(entries, now_ms),
+        Format::Json => {
+            let mut out = serde_json::to_string_pretty(entries).unwrap_or_else(|_| "[]".to_string());
+            out.push('\n');
+            out
+        }
+        Format::Line => render_line(entries),
+    }
+}
+
+fn render_table(entries: &[crate::UsageSnapshotEntry], now_ms: i64) -> String {
+    let mut rows: Vec<[String; 6]> = vec![[
+        "SERVICE".to_string(),
+        "ACCOUNT".to_string(),
+        "WINDOW".to_string(),
+        "USED".to_string(),
+        "RESETS IN".to_string(),
+        "STATUS".to_string(),
+    ]];
+
+    for entry in entries {
+        let account = entry.name.clone();
+        if !entry.has_token {
+            rows.push([entry.service.clone(), account, "-".into(), "-".into(), "-".into(), "no token".into()]);
+            continue;
+        }
+        if let Some(error) = entry.error.as_deref() {
+            rows.push([entry.service.clone(), account, "-".into(), "-".into(), "-".into(), format!("error: {error}")]);
+            continue;
+        }
+        for window in &entry.windows {
+            let resets = window
+                .resets_at_millis()
+                .map(|at| format_remaining(at - now_ms))
+                .unwrap_or_else(|| "-".to_string());
+            rows.push([
+                entry.service.clone(),
+                account.clone(),
+                window.name.clone(),
+                format!("{:.0}%", window.utilization),
+                resets,
+                window.status.clone().unwrap_or_default(),
+            ]);
+        }
+    }
+
+    let mut widths = [0usize; 6];
+    for row in &rows {
+        for (width, cell) in widths.iter_mut().zip(row.iter()) {
+            *width = (*width).max(cell.chars().count());
+        }
+    }
+
+    let mut out = String::new();
+    for row in &rows {
+        let line = row
+            .iter()
+            .zip(widths.iter())
+            .map(|(cell, width)| format!("{cell:<width$}"))
+            .collect::<Vec<_>>()
+            .join("  ");
+        out.push_str(line.trim_end());
+        out.push('\n');
+    }
+    out
+}
+
+fn render_line(entries: &[crate::UsageSnapshotEntry]) -> String {
+    let parts: Vec<String> = entries
+        .iter()
+        .filter(|e| e.has_token)
+        .map(|entry| {
+            let detail = match entry.error.as_deref() {
+                Some(_) => "error".to_string(),
+                None => entry
+                    .windows
+                    .iter()
+                    .map(|w| format!("{} {:.0}%", w.name, w.utilization))
+                    .collect::<Vec<_>>()
+                    .join(" "),
+            };
+            format!("{}/{}: {}", entry.service, entry.name, detail)
+        })
+        .collect();
+    format!("{}\n", parts.join(" | "))
+}
+
+/// `2d3h`, `4h12m`, `9m`; `now` once the reset time has passed.
+pub fn format_remaining(ms: i64) -> String {
+    if ms <= 0 {
+        return "now".to_string();
+    }
+    let minutes = ms / 60_000;
+    let (d, h, m) = (minutes / 1440, (minutes % 1440) / 60, minutes % 60);
+    if d > 0 {
+        format!("{d}d{h}h")
+    } else if h > 0 {
+        format!("{h}h{m}m")
+    } else {
+        format!("{m}m")
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::{format_remaining, render, Format};
+    use crate::usage_parser::UsageWindow;
+
+    fn entry() -> crate::UsageSnapshotEntry {
+        crate::UsageSnapshotEntry {
+            service: "claude".to_string(),
+            id: "main".to_string(),
+            name: "Main".to_string(),
+            has_token: true,
+            label: None,
+            status: Some("warning".to_string()),
+            windows: vec![
+                UsageWindow::new("5h".to_string(), 42.4, None, None, false, Some("ok".to_string())),
+                UsageWindow::new("7d".to_string(), 80.0, None, None, false, Some("warning".to_string())),
+            ],
+            error: None,
+        }
+    }
+
+    #[test]
+    fn line_format_is_single_line() {
+        assert_eq!(render(&[entry()], Format::Line, 0), "claude/Main: 5h 42% 7d 80%\n");
+    }
+
+    #[test]
+    fn table_has_header_and_one_row_per_window() {
+        let table = render(&[entry()], Format::Table, 0);
+        let lines: Vec<_> = table.lines().collect();
+        assert_eq!(lines.len(), 3);
+        assert!(lines[0].starts_with("SERVICE"));
+        assert!(lines[2].ends_with("warning"));
+    }
+
+    #[test]
+    fn remaining_time_is_compact() {
+        assert_eq!(format_remaining(-1), "now");
+        assert_eq!(format_remaining(9 * 60_000), "9m");
+        assert_eq!(format_remaining((4 * 60 + 12) * 60_000), "4h12m");
+        assert_eq!(format_remaining((2 * 1440 + 3 * 60) * 60_000), "2d3h");
+    }
+}
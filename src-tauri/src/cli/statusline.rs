@@ -0,0 +1,224 @@
+//! `aiqm statusline`: one line for Claude Code's custom statusline command,
+//! rendered from the app's usage cache without touching the network.
+
+use super::output::format_remaining;
+use crate::usage_parser::UsageWindow;
+use serde::Deserialize;
+
+pub const DEFAULT_TEMPLATE: &str = "{account} {windows}";
+/// The cache counts as stale after this long or three poll intervals, whichever is longer.
+const STALE_AFTER_MIN_MS: i64 = 10 * 60 * 1000;
+
+/// The parts of Claude Code's statusline JSON (stdin) that we use.
+#[derive(Debug, Clone, Default, Deserialize)]
+pub struct StatuslineInput {
+    #[serde(default)]
+    pub model: Option<StatuslineModel>,
+}
+
+#[derive(Debug, Clone, Default, Deserialize)]
+pub struct StatuslineModel {
+    #[serde(default)]
+    pub display_name: Option<String>,
+}
+
+impl StatuslineInput {
+    /// Lenient parse: anything unreadable yields None and the line is still printed.
+    pub fn parse(text: &str) -> Option<Self> {
+        serde_json::from_str(text.trim()).ok()
+    }
+
+    pub fn model_name(&self) -> &str {
+        self.model
+            .as_ref()
+            .and_then(|m| m.display_name.as_deref())
+            .unwrap_or_default()
+    }
+}
+
+/// `--account` wins; otherwise the account linked to the service's CLI (the one
+/// the session runs on), then the entry with the highest window utilization,
+/// since that is the quota most likely to stop the session.
+/// `linked` lists the `(service, id)` pairs with `cli_linked` set.
+pub fn pick_entry<'a>(
+    entries: &'a [crate::UsageSnapshotEntry],
+    service: Option<&str>,
+    account: Option<&str>,
+    linked: &[(&str, &str)],
+) -> Option<&'a crate::UsageSnapshotEntry> {
+    let candidates = entries.iter().filter(|e| {
+        e.has_token
+            && service.is_none_or(|s| s == e.service)
+            && account.is_none_or(|a| a == e.id)
+    });
+    let most_used = |a: &&crate::UsageSnapshotEntry, b: &&crate::UsageSnapshotEntry| {
+        max_utilization(a).total_cmp(&max_utilization(b))
+    };
+    if account.is_none() {
+        let linked_entry = candidates
+            .clone()
+            .filter(|e| linked.contains(&(e.service.as_str(), e.id.as_str())))
+            .max_by(most_used);
+        if linked_entry.is_some() {
+            return linked_entry;
+        }
+    }
+    candidates.max_by(most_used)
+}
+
+fn max_utilization(entry: &crate::UsageSnapshotEntry) -> f64 {
+    entry
+        .windows
+        .iter()
+        .map(|w| w.utilization)
+        .fold(f64::NEG_INFINITY, f64::max)
+}
+
+pub fn stale_after_ms(poll_interval_secs: i32) -> i64 {
+    (i64::from(poll_interval_secs.max(0)) * 3000).max(STALE_AFTER_MIN_MS)
+}
+
+pub struct Context<'a> {
+    pub model: &'a str,
+    pub color: bool,
+    pub now_ms: i64,
+    /// How old the cache is; a ` (stale 25m)` suffix is added past `stale_after_ms`.
+    pub age_ms: i64,
+    pub stale_after_ms: i64,
+}
+
+/// Expand `{service}`, `{account}`, `{status}`, `{windows}`, `{max}`, `{reset}`
+/// and `{model}` in `template`.
+pub fn render(entry: &crate::UsageSnapshotEntry, template: &str, ctx: &Context) -> String {
+    let status = entry.status.as_deref().unwrap_or("unknown");
+    let windows = match entry.error.as_deref() {
+        Some(_) if entry.windows.is_empty() => paint("error", "error", ctx.color),
+        _ => entry
+            .windows
+            .iter()
+            .map(|w| render_window(w, ctx))
+            .collect::<Vec<_>>()
+            .join(" "),
+    };
+    let worst = entry
+        .windows
+        .iter()
+        .max_by(|a, b| a.utilization.total_cmp(&b.utilization));
+    let max = worst.map(|w| format!("{:.0}%", w.utilization)).unwrap_or_default();
+    let reset = worst
+        .and_then(UsageWindow::resets_at_millis)
+        .map(|at| format_remaining(at - ctx.now_ms))
+        .unwrap_or_default();
+
+    let mut line = template
+        .replace("{service}", &entry.service)
+        .replace("{account}", &entry.name)
+        .replace("{status}", &paint(status, status, ctx.color))
+        .replace("{windows}", &windows)
+        .replace("{max}", &max)
+        .replace("{reset}", &reset)
+        .replace("{model}", ctx.model);
+    if ctx.age_ms > ctx.stale_after_ms {
+        line.push_str(&format!(" (stale {})", format_remaining(ctx.age_ms)));
+    }
+    line.trim().to_string()
+}
+
+/// `5h 62%`, plus `⏳1h20m` when the burn rate runs out before the reset.
+fn render_window(window: &UsageWindow, ctx: &Context) -> String {
+    let mut text = format!("{} {:.0}%", window.name, window.utilization);
+    if let Some(at) = window
+        .forecast
+        .as_ref()
+        .filter(|f| f.before_reset)
+        .and_then(|f| f.exhausted_at)
+    {
+        text.push_str(&format!("⏳{}", format_remaining(at - ctx.now_ms)));
+    }
+    paint(&text, window.status.as_deref().unwrap_or("unknown"), ctx.color)
+}
+
+fn paint(text: &str, status: &str, color: bool) -> String {
+    if !color {
+        return text.to_string();
+    }
+    let code = match status {
+        "ok" => "32",
+        "warning" | "projected" => "33",
+        "critical" | "exhausted" => "31",
+        _ => "90",
+    };
+    format!("\x1b[{code}m{text}\x1b[0m")
+}
+
+#[cfg(test)]
+mod tests {
+    use super::{pick_entry, render, Context, StatuslineInput, DEFAULT_TEMPLATE};
+    use crate::usage_parser::UsageWindow;
+
+    const MIN: i64 = 60 * 1000;
+
+    fn entry(id: &str, utilizations: &[f64]) -> crate::UsageSnapshotEntry {
+        crate::UsageSnapshotEntry {
+            service: "claude".to_string(),
+            id: id.to_string(),
+            name: id.to_uppercase(),
+            has_token: true,
+            label: None,
+            status: Some("ok".to_string()),
+            windows: utilizations
+                .iter()
+                .zip(["5h", "7d"])
+                .map(|(u, name)| UsageWindow::new(name.to_string(), *u, None, None, false, Some("ok".to_string())))
+                .collect(),
+            error: None,
+        }
+    }
+
+    fn ctx(color: bool, age_ms: i64) -> Context<'static> {
+        Context {
+            model: "Opus",
+            color,
+            now_ms: 0,
+            age_ms,
+            stale_after_ms: 10 * MIN,
+        }
+    }
+
+    #[test]
+    fn reads_model_from_statusline_json() {
+        let input = StatuslineInput::parse(r#"{"session_id":"x","model":{"id":"m","display_name":"Opus"}}"#)
+            .expect("valid json");
+        assert_eq!(input.model_name(), "Opus");
+        assert!(StatuslineInput::parse("not json").is_none());
+    }
+
+    #[test]
+    fn picks_requested_or_most_used_account() {
+        let entries = vec![entry("a", &[10.0, 20.0]), entry("b", &[70.0, 5.0])];
+        assert_eq!(pick_entry(&entries, Some("claude"), None, &[]).map(|e| e.id.as_str()), Some("b"));
+        assert_eq!(pick_entry(&entries, None, Some("a"), &[]).map(|e| e.id.as_str()), Some("a"));
+        assert!(pick_entry(&entries, Some("codex"), None, &[]).is_none());
+    }
+
+    #[test]
+    fn prefers_cli_linked_account_over_most_used() {
+        let entries = vec![entry("a", &[10.0, 20.0]), entry("b", &[70.0, 5.0])];
+        let linked = [("claude", "a")];
+        assert_eq!(pick_entry(&entries, Some("claude"), None, &linked).map(|e| e.id.as_str()), Some("a"));
+        // --account still wins over the linked account.
+        assert_eq!(pick_entry(&entries, Some("claude"), Some("b"), &linked).map(|e| e.id.as_str()), Some("b"));
+        // A linked account without cached usage falls back to the most used one.
+        assert_eq!(pick_entry(&entries, Some("claude"), None, &[("claude", "c")]).map(|e| e.id.as_str()), Some("b"));
+        assert_eq!(pick_entry(&entries, Some("claude"), None, &[("codex", "a")]).map(|e| e.id.as_str()), Some("b"));
+    }
+
+    #[test]
+    fn renders_template_and_marks_stale_cache() {
+        let e = entry("a", &[42.4, 80.0]);
+        assert_eq!(render(&e, DEFAULT_TEMPLATE, &ctx(false, MIN)), "A 5h 42% 7d 80%");
+        assert_eq!(render(&e, "{model} | {max}", &ctx(false, MIN)), "Opus | 80%");
+        assert_eq!(render(&e, "{max}", &ctx(false, 25 * MIN)), "80% (stale 25m)");
+        assert_eq!(render(&e, "{status}", &ctx(true, 0)), "\x1b[32mok\x1b[0m");
+    }
+}
@@ -0,0 +1,211 @@
+//! Switching the account the Claude Code CLI is logged in as. The chosen
+//! account's tokens are written to the CLI's credentials file and the account
+//! becomes CLI-linked: the CLI owns its refresh token from then on, and the
+//! previously linked account gets the file's current tokens back.
+
+use crate::cli_sync;
+use crate::error::{AppError, AppResult};
+use crate::oauth::OAuthTokens;
+use crate::oauth_commands;
+use crate::recommend::{self, Recommendation};
+use crate::status::Status;
+use crate::store_repo::{read_store, update_store};
+use crate::token_refresh;
+use crate::token_store;
+use crate::usage_state::{self, UsageUpdate};
+use serde::Serialize;
+use tauri::{AppHandle, Emitter};
+use zeroize::Zeroize;
+
+pub const EVENT_CLI_ACCOUNT_SWITCHED: &str = "cli-account-switched";
+
+/// Only the Claude Code CLI keeps a refresh token we can hand over.
+const SWITCH_SERVICE: &str = "claude";
+
+#[derive(Debug, Clone, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct CliSwitchResult {
+    service: String,
+    id: String,
+    name: String,
+    /// Accounts that were linked before and got their tokens back.
+    previous: Vec<String>,
+    path: String,
+    /// Chosen by the auto-switch policy rather than the user.
+    auto: bool,
+}
+
+/// Write the stored tokens of `service:id` to the CLI credentials file and
+/// link the account to it.
+pub async fn activate(app: &AppHandle, service: &str, id: &str, auto: bool) -> AppResult<CliSwitchResult> {
+    if service != SWITCH_SERVICE {
+        return Err(AppError::InvalidInput(format!("CLI への切り替えは Claude のみ対応しています ({service})")));
+    }
+    // No refresh may rotate the tokens while they are being handed over.
+    let _refresh = token_refresh::hold_account(service, id)
+        .await
+        .map_err(|e| AppError::Message(e.to_string()))?;
+    let _sync = cli_sync::exclusive();
+
+    let store = read_store(app)?;
+    let accounts = store.services.get(service).cloned().unwrap_or_default();
+    let account = accounts
+        .iter()
+        .find(|a| a.id == id)
+        .ok_or_else(|| AppError::InvalidInput(format!("Unknown account: {service}:{id}")))?;
+    let mut tokens = stored_tokens(service, id)?;
+    let previous_ids: Vec<String> = accounts
+        .iter()
+        .filter(|a| a.cli_linked && a.id != id)
+        .map(|a| a.id.clone())
+        .collect();
+    // The CLI may have rotated the previous login since it was last synced.
+    let mut handed_back = if previous_ids.is_empty() {
+        None
+    } else {
+        oauth_commands::read_claude_cli_tokens().ok()
+    };
+
+    let written = oauth_commands::write_claude_cli_tokens(&tokens);
+    tokens.access_token.zeroize();
+    if let Some(mut refresh) = tokens.refresh_token.take() {
+        refresh.zeroize();
+    }
+    let path = written?;
+
+    for previous in &previous_ids {
+        if let Some(old) = handed_back.as_ref() {
+            let mut copy = old.clone();
+            oauth_commands::store_tokens(service, previous, &mut copy)?;
+        }
+    }
+    if let Some(mut old) = handed_back.take() {
+        old.access_token.zeroize();
+        if let Some(mut refresh) = old.refresh_token.take() {
+            refresh.zeroize();
+        }
+    }
+    update_store(app, |store| {
+        for entry in store.services.get_mut(service).into_iter().flatten() {
+            entry.cli_linked = entry.id == id;
+            if !entry.cli_linked {
+                entry.cli_synced_at = None;
+            }
+        }
+        Ok(())
+    })?;
+    // Drops the app's copy of the refresh token and records the sync.
+    cli_sync::sync_account(app, service, id)?;
+
+    let result = CliSwitchResult {
+        service: service.to_string(),
+        id: id.to_string(),
+        name: account.name.clone(),
+        previous: previous_ids,
+        path: path.display().to_string(),
+        auto,
+    };
+    let _ = app.emit(EVENT_CLI_ACCOUNT_SWITCHED, &result);
+    Ok(result)
+}
+
+fn stored_tokens(service: &str, id: &str) -> AppResult<OAuthTokens> {
+    let access_token = token_store::get_token(service, id)
+        .ok_or_else(|| AppError::Message("トークンが保存されていません".into()))?;
+    let Some(refresh_token) = token_store::get_refresh_token(service, id) else {
+        let mut access_token = access_token;
+        access_token.zeroize();
+        return Err(AppError::Message(
+            "refresh_token が保存されていません。OAuth ログインしたアカウントを選んでください".into(),
+        ));
+    };
+    Ok(OAuthTokens {
+        access_token,
+        refresh_token: Some(refresh_token),
+        expires_at: token_store::get_expires_at(service, id),
+    })
+}
+
+/// With auto-switch on, move the CLI off an exhausted account to the best
+/// recommended one. Called after each poll.
+pub async fn auto_switch(app: &AppHandle) {
+    let Ok(store) = read_store(app) else {
+        return;
+    };
+    if !store.settings.cli_switch.auto_switch {
+        return;
+    }
+    let Some(active) = store
+        .services
+        .get(SWITCH_SERVICE)
+        .and_then(|list| list.iter().find(|a| a.cli_linked))
+    else {
+        return;
+    };
+    let entries: Vec<crate::UsageSnapshotEntry> = usage_state::latest()
+        .iter()
+        .filter(|u| u.service == SWITCH_SERVICE)
+        .map(UsageUpdate::to_snapshot_entry)
+        .collect();
+    let exhausted = entries
+        .iter()
+        .find(|e| e.id == active.id)
+        .is_some_and(|e| e.status.as_deref().and_then(Status::parse) == Some(Status::Exhausted));
+    if !exhausted {
+        return;
+    }
+    let ranked = recommend::rank(&entries, &store.services, chrono::Utc::now().timestamp_millis());
+    let accounts = store.services.get(SWITCH_SERVICE).map(Vec::as_slice).unwrap_or_default();
+    let candidate = best_candidate(&ranked, &active.id, |id| {
+        let needs_relogin = accounts.iter().any(|a| a.id == id && a.relogin_reason.is_some());
+        !needs_relogin && token_store::get_refresh_token(SWITCH_SERVICE, id).is_some()
+    });
+    if let Some(candidate) = candidate {
+        let id = candidate.id.clone();
+        let _ = activate(app, SWITCH_SERVICE, &id, true).await;
+    }
+}
+
+/// The best-ranked usable account other than `active` that `eligible` accepts.
+fn best_candidate<'a>(
+    ranked: &'a [Recommendation],
+    active: &str,
+    eligible: impl Fn(&str) -> bool,
+) -> Option<&'a Recommendation> {
+    ranked
+        .iter()
+        .filter(|r| r.service == SWITCH_SERVICE && r.id != active && r.usable)
+        .find(|r| eligible(&r.id))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::best_candidate;
+    use crate::recommend::Recommendation;
+
+    fn ranked(id: &str, rank: usize, usable: bool) -> Recommendation {
+        Recommendation {
+            service: "claude".to_string(),
+            id: id.to_string(),
+            name: id.to_string(),
+            rank,
+            score: usable.then_some(50.0),
+            usable,
+            reserve: false,
+            reasons: Vec::new(),
+        }
+    }
+
+    #[test]
+    fn picks_the_best_ranked_eligible_account() {
+        let list = vec![
+            ranked("b", 1, true),
+            ranked("active", 2, true),
+            ranked("a", 3, true),
+            ranked("d", 4, false),
+        ];
+        assert_eq!(best_candidate(&list, "active", |_| true).unwrap().id, "b");
+        assert_eq!(best_candidate(&list, "active", |id| id != "b").unwrap().id, "a");
+        assert!(best_candidate(&list, "active", |id| id == "d").is_none());
+    }
+}
@@ -0,0 +1,126 @@
+//! Accounts linked to a CLI login follow the CLI's credentials file. When the
+//! CLI rotates its tokens, the new access token is copied into the keychain.
+//! Linked accounts never get a refresh token here, so the app cannot refresh
+//! (and thereby invalidate) the CLI's session.
+
+use crate::error::{AppError, AppResult};
+use crate::fs_util::{self, Fingerprint};
+use crate::oauth_commands;
+use crate::store_repo::{read_store, update_store};
+use crate::token_store;
+use serde::Serialize;
+use std::collections::HashMap;
+use std::sync::{Mutex, MutexGuard};
+use std::time::Duration;
+use tauri::{AppHandle, Emitter};
+use zeroize::Zeroize;
+
+pub const EVENT_CLI_CREDENTIALS_SYNCED: &str = "cli-credentials-synced";
+
+/// How often the credentials files are checked for changes.
+const CLI_SYNC_INTERVAL: Duration = Duration::from_secs(5);
+
+#[derive(Debug, Clone, Serialize)]
+#[serde(rename_all = "camelCase")]
+struct CliCredentialsSynced {
+    service: String,
+    id: String,
+    /// Set on success.
+    synced_at: Option<i64>,
+    /// Set when the file could not be read.
+    error: Option<String>,
+}
+
+/// Held while syncing, so a CLI account switch cannot interleave with it.
+static SYNC_LOCK: Mutex<()> = Mutex::new(());
+
+pub fn supports(service: &str) -> bool {
+    crate::providers::get(service).is_some_and(|p| p.has_cli())
+}
+
+/// Keep the watcher out while the links or the credentials files change.
+pub fn exclusive() -> MutexGuard<'static, ()> {
+    SYNC_LOCK.lock().unwrap_or_else(|poisoned| poisoned.into_inner())
+}
+
+/// Spawn the watcher. Files are compared by mtime and size on a timer, like
+/// the store's own change detection.
+pub fn start(app: AppHandle) {
+    tauri::async_runtime::spawn(async move {
+        let mut seen: HashMap<&'static str, Option<Fingerprint>> = HashMap::new();
+        loop {
+            sync_changed(&app, &mut seen);
+            tokio::time::sleep(CLI_SYNC_INTERVAL).await;
+        }
+    });
+}
+
+/// Re-import for linked accounts of every CLI whose file changed since the
+/// last check (or on the first check after startup).
+fn sync_changed(app: &AppHandle, seen: &mut HashMap<&'static str, Option<Fingerprint>>) {
+    let _guard = exclusive();
+    let Ok(store) = read_store(app) else {
+        return;
+    };
+    for provider in crate::providers::with_cli() {
+        let service = provider.id();
+        let linked: Vec<&str> = store
+            .services
+            .get(service)
+            .into_iter()
+            .flatten()
+            .filter(|a| a.cli_linked)
+            .map(|a| a.id.as_str())
+            .collect();
+        let stamp = provider.cli_credentials_path().and_then(|p| fs_util::fingerprint(&p));
+        let previous = seen.insert(service, stamp);
+        if linked.is_empty() || stamp.is_none() || previous == Some(stamp) {
+            continue;
+        }
+        for id in linked {
+            let _ = sync_account(app, service, id);
+        }
+    }
+}
+
+/// Copy the CLI's current access token into the keychain for one account and
+/// record the sync time. Emits `cli-credentials-synced` either way.
+pub fn sync_account(app: &AppHandle, service: &str, id: &str) -> AppResult<i64> {
+    let result = copy_cli_token(service, id).and_then(|()| {
+        let now = chrono::Utc::now().timestamp_millis();
+        update_store(app, |store| {
+            let entry = store
+                .services
+                .get_mut(service)
+                .and_then(|list| list.iter_mut().find(|a| a.id == id))
+                .ok_or_else(|| AppError::InvalidInput(format!("Unknown account: {service}:{id}")))?;
+            entry.cli_synced_at = Some(now);
+            Ok(now)
+        })
+    });
+    let _ = app.emit(
+        EVENT_CLI_CREDENTIALS_SYNCED,
+        CliCredentialsSynced {
+            service: service.to_string(),
+            id: id.to_string(),
+            synced_at: result.as_ref().ok().copied(),
+            error: result.as_ref().err().map(ToString::to_string),
+        },
+    );
+    result
+}
+
+fn copy_cli_token(service: &str, id: &str) -> AppResult<()> {
+    let mut tokens = oauth_commands::read_cli_access_token(service)?;
+    // A refresh token from an earlier import would let the app rotate the
+    // CLI's session behind its back.
+    token_store::delete_refresh_token(service, id)?;
+    let current = token_store::get_token(service, id);
+    if current.as_deref() == Some(tokens.access_token.as_str())
+        && token_store::get_expires_at(service, id) == tokens.expires_at
+    {
+        tokens.access_token.zeroize();
+        return Ok(());
+    }
+    oauth_commands::store_tokens(service, id, &mut tokens)
+}
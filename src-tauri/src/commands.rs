@@ -1,19 +1,24 @@
 use crate::account_commands::{
     delete_account as delete_account_impl, list_accounts as list_accounts_impl,
-    save_account as save_account_impl,
+    save_account as save_account_impl, set_account_cli_link as set_account_cli_link_impl,
+    set_account_mute as set_account_mute_impl, set_account_preference as set_account_preference_impl,
 };
 use crate::api_client::FetchUsageResponse;
+use crate::cli_switch;
 use crate::external_notify::send_external_notification as send_external_notification_impl;
 use crate::notification_commands::send_notification as send_notification_impl;
 use crate::oauth_commands::{
     self, OAuthLoginResult, TokenStatus,
 };
 use crate::export_commands::write_usage_snapshot as write_usage_snapshot_impl;
+use crate::history_commands::query_usage_history as query_usage_history_impl;
+use crate::history_store::HistorySeries;
 use crate::settings_commands::{
     get_polling_state as get_polling_state_impl, get_settings as get_settings_impl,
     set_polling_state as set_polling_state_impl, set_settings as set_settings_impl,
 };
 use crate::usage_commands::fetch_usage as fetch_usage_impl;
+use crate::usage_state::UsageUpdate;
 use crate::window_commands::{
     get_window_state as get_window_state_impl, set_window_mode as set_window_mode_impl,
     resize_window_keep_top_left as resize_window_keep_top_left_impl,
@@ -39,6 +44,30 @@ pub fn delete_account(app: AppHandle, payload: crate::DeleteAccountPayload) -> R
     delete_account_impl(app, payload).map_err(|e| e.to_string())
 }
 
+#[tauri::command]
+pub fn set_account_mute(
+    app: AppHandle,
+    payload: crate::SetAccountMutePayload,
+) -> Result<crate::AccountSnapshotEntry, String> {
+    set_account_mute_impl(app, payload).map_err(|e| e.to_string())
+}
+
+#[tauri::command]
+pub fn set_account_preference(
+    app: AppHandle,
+    payload: crate::SetAccountPreferencePayload,
+) -> Result<crate::AccountSnapshotEntry, String> {
+    set_account_preference_impl(app, payload).map_err(|e| e.to_string())
+}
+
+#[tauri::command]
+pub fn set_account_cli_link(
+    app: AppHandle,
+    payload: crate::SetAccountCliLinkPayload,
+) -> Result<crate::AccountSnapshotEntry, String> {
+    set_account_cli_link_impl(app, payload).map_err(|e| e.to_string())
+}
+
 #[tauri::command]
 pub fn get_settings(app: AppHandle) -> Result<crate::Settings, String> {
     get_settings_impl(app).map_err(|e| e.to_string())
@@ -75,6 +104,40 @@ pub async fn fetch_usage(
     fetch_usage_impl(app, payload).await.map_err(|e| e.to_string())
 }
 
+#[tauri::command]
+pub fn poll_now() -> Result<crate::ApiOk, String> {
+    crate::poll_scheduler::poll_now();
+    Ok(crate::ApiOk { ok: true })
+}
+
+#[tauri::command]
+pub fn get_latest_usage() -> Result<Vec<UsageUpdate>, String> {
+    Ok(crate::usage_state::latest())
+}
+
+#[tauri::command]
+pub fn get_recommendations(app: AppHandle) -> Result<Vec<crate::recommend::Recommendation>, String> {
+    crate::recommend::latest(&app).map_err(|e| e.to_string())
+}
+
+#[tauri::command]
+pub fn get_store_load_issue() -> Result<Option<crate::StoreLoadIssue>, String> {
+    Ok(crate::store_repo::load_issue())
+}
+
+#[tauri::command]
+pub fn get_listener_errors() -> Result<Vec<String>, String> {
+    Ok(crate::settings_commands::startup_listener_errors())
+}
+
+#[tauri::command]
+pub fn query_usage_history(
+    app: AppHandle,
+    payload: crate::QueryUsageHistoryPayload,
+) -> Result<Vec<HistorySeries>, String> {
+    query_usage_history_impl(app, payload).map_err(|e| e.to_string())
+}
+
 #[tauri::command]
 pub fn get_window_state(app: AppHandle) -> Result<crate::WindowState, String> {
     get_window_state_impl(app).map_err(|e| e.to_string())
@@ -156,10 +219,24 @@ pub fn cancel_oauth_login() -> Result<crate::ApiOk, String> {
 }
 
 #[tauri::command]
-pub fn import_claude_cli_credentials(payload: crate::OAuthLoginPayload) -> Result<OAuthLoginResult, String> {
+pub fn import_cli_credentials(payload: crate::ImportCliPayload) -> Result<OAuthLoginResult, String> {
+    let service = crate::sanitize_string(payload.service.as_deref(), "");
+    let id = crate::sanitize_string(payload.id.as_deref(), "");
+    let mode = crate::sanitize_string(payload.mode.as_deref(), "");
+    oauth_commands::CliImportMode::parse(&mode)
+        .and_then(|mode| oauth_commands::import_cli_credentials(&service, &id, mode))
+        .map_err(|e| e.to_string())
+}
+
+#[tauri::command]
+pub async fn activate_cli_account(
+    app: AppHandle,
+    payload: crate::OAuthLoginPayload,
+) -> Result<cli_switch::CliSwitchResult, String> {
     let service = crate::sanitize_string(payload.service.as_deref(), "");
     let id = crate::sanitize_string(payload.id.as_deref(), "");
-    oauth_commands::import_claude_cli_credentials(&service, &id)
+    cli_switch::activate(&app, &service, &id, false)
+        .await
         .map_err(|e| e.to_string())
 }
 
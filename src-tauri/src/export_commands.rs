@@ -1,24 +1,30 @@
 use crate::error::{AppError, AppResult};
+use crate::fs_util;
+use crate::recommend::{self, Recommendation};
 use crate::store_repo::read_store;
 use crate::validation::validate_export_path;
-use serde::Serialize;
+use crate::usage_state::UsageUpdate;
+use serde::{Deserialize, Serialize};
 use std::fs;
 use std::path::{Path, PathBuf};
 use tauri::AppHandle;
 use tauri::Manager;
 
 fn resolve_export_path(app: &AppHandle, configured: &str) -> AppResult<PathBuf> {
+    let base = app
+        .path()
+        .app_data_dir()
+        .map_err(|e| AppError::Store(format!("Failed to resolve app data directory: {e}")))?;
+    resolve_export_path_in(&base, configured)
+}
+
+/// Relative paths are saved under the app data dir for consistency across platforms.
+pub fn resolve_export_path_in(base: &Path, configured: &str) -> AppResult<PathBuf> {
     validate_export_path(configured)?;
     let p = PathBuf::from(configured);
     if p.is_absolute() {
         return Ok(p);
     }
-
-    // Relative paths are saved under app data dir for consistency across platforms.
-    let base = app
-        .path()
-        .app_data_dir()
-        .map_err(|e| AppError::Store(format!("Failed to resolve app data directory: {e}")))?;
     Ok(base.join(p))
 }
 
@@ -37,39 +43,26 @@ fn ensure_parent_dir(path: &Path) -> AppResult<()> {
 
 fn atomic_write(path: &Path, bytes: &[u8]) -> AppResult<()> {
     ensure_parent_dir(path)?;
-
-    let tmp_path = path.with_extension("tmp");
-    fs::write(&tmp_path, bytes).map_err(|e| {
-        AppError::Store(format!(
-            "Failed to write export temp file '{}': {e}",
-            tmp_path.display()
-        ))
-    })?;
-
-    // Best-effort atomic swap (Windows rename fails if destination exists).
-    if path.exists() {
-        let _ = fs::remove_file(path);
-    }
-    fs::rename(&tmp_path, path).map_err(|e| {
-        let _ = fs::remove_file(&tmp_path);
+    fs_util::atomic_write(path, bytes).map_err(|e| {
         AppError::Store(format!(
-            "Failed to move export file into place '{}': {e}",
+            "Failed to write export file '{}': {e}",
             path.display()
         ))
-    })?;
-
-    Ok(())
+    })
 }
 
-#[derive(Debug, Clone, Serialize)]
+#[derive(Debug, Clone, Serialize, Deserialize)]
 #[serde(rename_all = "camelCase")]
-struct UsageSnapshotFile {
+pub struct UsageSnapshotFile {
     schema_version: i32,
     app_name: String,
     app_version: String,
-    generated_at: String,
-    fetched_at: Option<String>,
-    entries: Vec<crate::UsageSnapshotEntry>,
+    pub generated_at: String,
+    pub fetched_at: Option<String>,
+    pub entries: Vec<crate::UsageSnapshotEntry>,
+    /// Accounts ranked by `recommend::rank`, per service.
+    #[serde(default)]
+    pub recommendations: Vec<Recommendation>,
 }
 
 pub fn write_usage_snapshot(app: AppHandle, payload: crate::WriteUsageSnapshotPayload) -> AppResult<crate::ApiOk> {
@@ -85,21 +78,76 @@ pub fn write_usage_snapshot(app: AppHandle, payload: crate::WriteUsageSnapshotPa
     };
 
     let out_path = resolve_export_path(&app, configured_path)?;
-    let now = chrono::Utc::now().to_rfc3339();
     let version = app.package_info().version.to_string();
+    let json = snapshot_json(
+        &version,
+        payload.fetched_at,
+        payload.entries.unwrap_or_default(),
+        &store.services,
+    )?;
+    atomic_write(&out_path, &json)?;
+    Ok(crate::ApiOk { ok: true })
+}
 
-    let entries = payload.entries.unwrap_or_default();
+/// Serialize entries into the snapshot file format (schema version 1), with
+/// recommendations using the account flags of `services`.
+pub fn snapshot_json(
+    app_version: &str,
+    fetched_at: Option<String>,
+    entries: Vec<crate::UsageSnapshotEntry>,
+    services: &crate::Services,
+) -> AppResult<Vec<u8>> {
+    let recommendations = recommend::rank(&entries, services, chrono::Utc::now().timestamp_millis());
     let file = UsageSnapshotFile {
         schema_version: 1,
         app_name: crate::APP_NAME.to_string(),
-        app_version: version,
-        generated_at: now,
-        fetched_at: payload.fetched_at,
+        app_version: app_version.to_string(),
+        generated_at: chrono::Utc::now().to_rfc3339(),
+        fetched_at,
         entries,
+        recommendations,
     };
+    serde_json::to_vec_pretty(&file)
+        .map_err(|e| AppError::Message(format!("Failed to serialize snapshot JSON: {e}")))
+}
 
-    let json = serde_json::to_vec_pretty(&file)
-        .map_err(|e| AppError::Message(format!("Failed to serialize snapshot JSON: {e}")))?;
-    atomic_write(&out_path, &json)?;
-    Ok(crate::ApiOk { ok: true })
+/// Write a snapshot to an explicit path (CLI `export`).
+pub fn write_snapshot_file(
+    path: &Path,
+    fetched_at: Option<String>,
+    entries: Vec<crate::UsageSnapshotEntry>,
+    services: &crate::Services,
+) -> AppResult<()> {
+    let json = snapshot_json(env!("CARGO_PKG_VERSION"), fetched_at, entries, services)?;
+    atomic_write(path, &json)
+}
+
+/// Read a snapshot written by `write_usage_snapshot`, `aiqm export` or the usage cache.
+pub fn read_snapshot_file(path: &Path) -> AppResult<UsageSnapshotFile> {
+    let bytes = fs::read(path).map_err(|e| {
+        AppError::Store(format!("Failed to read snapshot '{}': {e}", path.display()))
+    })?;
+    serde_json::from_slice(&bytes).map_err(|e| {
+        AppError::Store(format!("Failed to parse snapshot '{}': {e}", path.display()))
+    })
+}
+
+/// Write the latest poll results to `usage_cache.json` in the app data dir,
+/// so `aiqm statusline` can show them without fetching.
+pub fn write_usage_cache(app: &AppHandle, updates: &[UsageUpdate]) -> AppResult<()> {
+    let dir = app
+        .path()
+        .app_data_dir()
+        .map_err(|e| AppError::Store(format!("Failed to resolve app data directory: {e}")))?;
+    let entries = updates.iter().map(UsageUpdate::to_snapshot_entry).collect();
+    let fetched_at = updates
+        .iter()
+        .map(|u| u.fetched_at)
+        .max()
+        .and_then(chrono::DateTime::from_timestamp_millis)
+        .map(|at| at.to_rfc3339());
+    let version = app.package_info().version.to_string();
+    let services = read_store(app).map(|store| store.services).unwrap_or_default();
+    let json = snapshot_json(&version, fetched_at, entries, &services)?;
+    atomic_write(&dir.join(crate::USAGE_CACHE_FILE), &json)
 }
@@ -1,5 +1,10 @@
 use crate::error::{AppError, AppResult};
+use crate::notify_schedule::{self, Gate, HeldNotification};
+use crate::status::Triggers;
 use crate::store_repo::read_store;
+use crate::token_store;
+use crate::usage_parser::UsageWindow;
+use serde_json::Value;
 use std::time::Duration;
 use tauri::AppHandle;
 
@@ -10,15 +15,60 @@ const DISCORD_COLOR_WARNING: u32 = 0xFEE75C;
 const DISCORD_COLOR_CRITICAL: u32 = 0xED4245;
 const DISCORD_COLOR_DEFAULT: u32 = 0x5865F2;
 
+const SLACK_COLOR_OK: &str = "#2EB67D";
+const SLACK_COLOR_WARNING: &str = "#ECB22E";
+const SLACK_COLOR_CRITICAL: &str = "#E01E5A";
+const SLACK_COLOR_DEFAULT: &str = "#36C5F0";
+/// Block Kit limits: header text 150 chars, section text 3000 chars.
+const SLACK_HEADER_MAX_CHARS: usize = 150;
+const SLACK_SECTION_MAX_CHARS: usize = 3000;
+
+pub const DEFAULT_WEBHOOK_TEMPLATE: &str = r#"{
+  "title": "{{title}}",
+  "body": "{{body}}",
+  "level": "{{level}}",
+  "service": "{{service}}",
+  "account": "{{account}}",
+  "windows": {{windows_json}}
+}"#;
+const WEBHOOK_CHANNEL_PREFIX: &str = "webhook:";
+
+/// Account the notification is about, for templated payloads. Empty for test sends.
+#[derive(Debug, Clone, Default)]
+pub struct NotifyContext {
+    pub service: String,
+    pub account: String,
+    pub windows: Vec<UsageWindow>,
+}
+
 fn discord_embed_color(level: &str) -> u32 {
     match level {
         "ok" => DISCORD_COLOR_OK,
-        "warning" => DISCORD_COLOR_WARNING,
+        "warning" | "projected" => DISCORD_COLOR_WARNING,
         "critical" | "exhausted" => DISCORD_COLOR_CRITICAL,
         _ => DISCORD_COLOR_DEFAULT,
     }
 }
 
+fn slack_attachment_color(level: &str) -> &'static str {
+    match level {
+        "ok" => SLACK_COLOR_OK,
+        "warning" | "projected" => SLACK_COLOR_WARNING,
+        "critical" | "exhausted" => SLACK_COLOR_CRITICAL,
+        _ => SLACK_COLOR_DEFAULT,
+    }
+}
+
+/// Adaptive Card container style (also used as the title's text color).
+fn teams_card_style(level: &str) -> &'static str {
+    match level {
+        "ok" => "good",
+        "warning" | "projected" => "warning",
+        "critical" | "exhausted" => "attention",
+        _ => "default",
+    }
+}
+
 fn pushover_priority(level: &str) -> i32 {
     match level {
         "critical" | "exhausted" => 1,
@@ -38,21 +88,120 @@ async fn send_discord(webhook_url: &str, title: &str, body: &str, level: &str) -
         }]
     });
 
+    post_json(webhook_url, &payload, "Discord").await
+}
+
+/// Escape `&`, `<` and `>` for Slack mrkdwn / plain_text.
+fn slack_escape(text: &str) -> String {
+    text.replace('&', "&amp;").replace('<', "&lt;").replace('>', "&gt;")
+}
+
+fn truncate_chars(text: &str, max: usize) -> String {
+    if text.chars().count() <= max {
+        return text.to_string();
+    }
+    let mut out: String = text.chars().take(max - 1).collect();
+    out.push('…');
+    out
+}
+
+/// Incoming-webhook payload: Block Kit blocks inside an attachment so the
+/// level shows as the attachment's side color. `text` is the notification fallback.
+fn slack_payload(title: &str, body: &str, level: &str, ts: i64) -> Value {
+    let mut blocks = vec![serde_json::json!({
+        "type": "header",
+        "text": { "type": "plain_text", "text": truncate_chars(title, SLACK_HEADER_MAX_CHARS), "emoji": true }
+    })];
+    if !body.is_empty() {
+        blocks.push(serde_json::json!({
+            "type": "section",
+            "text": { "type": "mrkdwn", "text": truncate_chars(&slack_escape(body), SLACK_SECTION_MAX_CHARS) }
+        }));
+    }
+    blocks.push(serde_json::json!({
+        "type": "context",
+        "elements": [{
+            "type": "mrkdwn",
+            "text": format!("{} · <!date^{ts}^{{date_short_pretty}} {{time}}|{ts}>", crate::APP_NAME)
+        }]
+    }));
+    serde_json::json!({
+        "text": slack_escape(title),
+        "attachments": [{
+            "color": slack_attachment_color(level),
+            "blocks": blocks
+        }]
+    })
+}
+
+async fn send_slack(webhook_url: &str, title: &str, body: &str, level: &str) -> AppResult<()> {
+    let payload = slack_payload(title, body, level, chrono::Utc::now().timestamp());
+    post_json(webhook_url, &payload, "Slack").await
+}
+
+/// Adaptive Card message, accepted by both Workflows and legacy connector URLs.
+fn teams_payload(title: &str, body: &str, level: &str, timestamp: &str) -> Value {
+    let style = teams_card_style(level);
+    let mut items = vec![serde_json::json!({
+        "type": "TextBlock",
+        "text": title,
+        "weight": "Bolder",
+        "size": "Medium",
+        "color": style,
+        "wrap": true
+    })];
+    if !body.is_empty() {
+        items.push(serde_json::json!({ "type": "TextBlock", "text": body, "wrap": true }));
+    }
+    items.push(serde_json::json!({
+        "type": "TextBlock",
+        "text": format!("{} · {timestamp}", crate::APP_NAME),
+        "isSubtle": true,
+        "size": "Small",
+        "wrap": true
+    }));
+    serde_json::json!({
+        "type": "message",
+        "attachments": [{
+            "contentType": "application/vnd.microsoft.card.adaptive",
+            "contentUrl": null,
+            "content": {
+                "$schema": "http://adaptivecards.io/schemas/adaptive-card.json",
+                "type": "AdaptiveCard",
+                "version": "1.4",
+                "msteams": { "width": "Full" },
+                "body": [{
+                    "type": "Container",
+                    "style": style,
+                    "bleed": true,
+                    "items": items
+                }]
+            }
+        }]
+    })
+}
+
+async fn send_teams(webhook_url: &str, title: &str, body: &str, level: &str) -> AppResult<()> {
+    let payload = teams_payload(title, body, level, &chrono::Utc::now().to_rfc3339());
+    post_json(webhook_url, &payload, "Teams").await
+}
+
+async fn post_json(url: &str, payload: &Value, label: &str) -> AppResult<()> {
     let client = reqwest::Client::builder()
         .timeout(Duration::from_secs(EXTERNAL_NOTIFY_TIMEOUT_SECS))
         .build()
         .map_err(|e| AppError::Message(format!("Failed to build HTTP client: {e}")))?;
 
     let resp = client
-        .post(webhook_url)
-        .json(&payload)
+        .post(url)
+        .json(payload)
         .send()
         .await
-        .map_err(|e| AppError::Message(format!("Discord request failed: {e}")))?;
+        .map_err(|e| AppError::Message(format!("{label} request failed: {e}")))?;
 
     if !resp.status().is_success() {
         return Err(AppError::Message(format!(
-            "Discord returned HTTP {}",
+            "{label} returned HTTP {}",
             resp.status()
         )));
     }
@@ -60,6 +209,110 @@ async fn send_discord(webhook_url: &str, title: &str, body: &str, level: &str) -
     Ok(())
 }
 
+/// ntfy priority (1-5): high for critical, default for warnings, low otherwise.
+fn ntfy_priority(level: &str) -> i32 {
+    match level {
+        "critical" | "exhausted" => 4,
+        "warning" | "projected" => 3,
+        _ => 2,
+    }
+}
+
+/// Gotify priority (0-10); 8+ is shown as high priority by the Android client.
+fn gotify_priority(level: &str) -> i32 {
+    match level {
+        "critical" | "exhausted" => 8,
+        "warning" | "projected" => 5,
+        _ => 2,
+    }
+}
+
+/// ntfy tags: an emoji shortcode (shown before the title) plus the level itself.
+fn ntfy_tags(level: &str) -> Vec<&str> {
+    let emoji = match level {
+        "ok" => "white_check_mark",
+        "warning" => "warning",
+        "projected" => "hourglass_flowing_sand",
+        "critical" => "rotating_light",
+        "exhausted" => "no_entry",
+        _ => "bell",
+    };
+    if level.is_empty() {
+        vec![emoji]
+    } else {
+        vec![emoji, level]
+    }
+}
+
+fn ntfy_payload(topic: &str, title: &str, body: &str, level: &str) -> Value {
+    serde_json::json!({
+        "topic": topic,
+        "title": title,
+        "message": if body.is_empty() { title } else { body },
+        "priority": ntfy_priority(level),
+        "tags": ntfy_tags(level)
+    })
+}
+
+/// JSON publish to the server root, so non-ASCII titles need no header encoding.
+async fn send_ntfy(
+    ntfy: &crate::NtfySettings,
+    access_token: Option<&str>,
+    title: &str,
+    body: &str,
+    level: &str,
+) -> AppResult<()> {
+    let client = reqwest::Client::builder()
+        .timeout(Duration::from_secs(EXTERNAL_NOTIFY_TIMEOUT_SECS))
+        .build()
+        .map_err(|e| AppError::Message(format!("Failed to build HTTP client: {e}")))?;
+    let mut request = client
+        .post(&ntfy.server_url)
+        .json(&ntfy_payload(&ntfy.topic, title, body, level));
+    if let Some(token) = access_token {
+        request = request.bearer_auth(token);
+    }
+
+    let resp = request
+        .send()
+        .await
+        .map_err(|e| AppError::Message(format!("ntfy request failed: {e}")))?;
+    if !resp.status().is_success() {
+        return Err(AppError::Message(format!(
+            "ntfy returned HTTP {}",
+            resp.status()
+        )));
+    }
+    Ok(())
+}
+
+async fn send_gotify(server_url: &str, app_token: &str, title: &str, body: &str, level: &str) -> AppResult<()> {
+    let payload = serde_json::json!({
+        "title": title,
+        "message": if body.is_empty() { title } else { body },
+        "priority": gotify_priority(level)
+    });
+    let client = reqwest::Client::builder()
+        .timeout(Duration::from_secs(EXTERNAL_NOTIFY_TIMEOUT_SECS))
+        .build()
+        .map_err(|e| AppError::Message(format!("Failed to build HTTP client: {e}")))?;
+
+    let resp = client
+        .post(format!("{server_url}/message"))
+        .header("X-Gotify-Key", app_token)
+        .json(&payload)
+        .send()
+        .await
+        .map_err(|e| AppError::Message(format!("Gotify request failed: {e}")))?;
+    if !resp.status().is_success() {
+        return Err(AppError::Message(format!(
+            "Gotify returned HTTP {}",
+            resp.status()
+        )));
+    }
+    Ok(())
+}
+
 async fn send_pushover(
     api_token: &str,
     user_key: &str,
@@ -102,6 +355,349 @@ async fn send_pushover(
     Ok(())
 }
 
+/// Text for one placeholder, or None when the key is unknown (left as is).
+fn placeholder_value(key: &str, title: &str, body: &str, level: &str, ctx: &NotifyContext) -> Option<Placeholder> {
+    let text = |s: &str| Some(Placeholder::Text(s.to_string()));
+    match key {
+        "title" => text(title),
+        "body" => text(body),
+        "level" => text(level),
+        "service" => text(&ctx.service),
+        "account" => text(&ctx.account),
+        "timestamp" => text(&chrono::Utc::now().to_rfc3339()),
+        "windows" => text(
+            &ctx.windows
+                .iter()
+                .map(|w| format!("{}: {}%", w.name, w.utilization))
+                .collect::<Vec<_>>()
+                .join(", "),
+        ),
+        "windows_json" => Some(Placeholder::Json(Value::Array(
+            ctx.windows.iter().map(window_json).collect(),
+        ))),
+        "max_utilization" => Some(Placeholder::Json(
+            ctx.windows
+                .iter()
+                .map(|w| w.utilization)
+                .reduce(f64::max)
+                .map_or(Value::Null, Value::from),
+        )),
+        _ => {
+            // window.<name>.<field>
+            let (name, field) = key.strip_prefix("window.")?.rsplit_once('.')?;
+            let window = ctx.windows.iter().find(|w| w.name == name)?;
+            let value = window_json(window).get(field)?.clone();
+            Some(match value {
+                Value::String(s) => Placeholder::Text(s),
+                other => Placeholder::Json(other),
+            })
+        }
+    }
+}
+
+enum Placeholder {
+    /// Escaped for a JSON string context when the body is JSON.
+    Text(String),
+    /// Inserted as JSON (numbers, arrays, null).
+    Json(Value),
+}
+
+fn window_json(w: &UsageWindow) -> Value {
+    serde_json::json!({
+        "name": w.name,
+        "utilization": w.utilization,
+        "status": w.status,
+        "resets_at": w
+            .resets_at_millis()
+            .and_then(chrono::DateTime::from_timestamp_millis)
+            .map(|at| at.to_rfc3339()),
+    })
+}
+
+/// Expand `{{key}}` placeholders. With `json`, text is escaped for use inside
+/// a JSON string and the result must parse as JSON.
+pub fn render_webhook_body(
+    template: &str,
+    json: bool,
+    title: &str,
+    body: &str,
+    level: &str,
+    ctx: &NotifyContext,
+) -> Result<String, String> {
+    let mut out = String::with_capacity(template.len());
+    let mut rest = template;
+    while let Some(start) = rest.find("{{") {
+        out.push_str(&rest[..start]);
+        let after = &rest[start + 2..];
+        let Some(end) = after.find("}}") else {
+            out.push_str(&rest[start..]);
+            rest = "";
+            break;
+        };
+        let key = after[..end].trim();
+        match placeholder_value(key, title, body, level, ctx) {
+            Some(Placeholder::Text(text)) if json => {
+                let quoted = Value::String(text).to_string();
+                out.push_str(&quoted[1..quoted.len() - 1]);
+            }
+            Some(Placeholder::Text(text)) => out.push_str(&text),
+            Some(Placeholder::Json(value)) => out.push_str(&value.to_string()),
+            None => out.push_str(&rest[start..start + 2 + end + 2]),
+        }
+        rest = &after[end + 2..];
+    }
+    out.push_str(rest);
+
+    if json {
+        serde_json::from_str::<Value>(&out).map_err(|e| format!("Rendered body is not valid JSON: {e}"))?;
+    }
+    Ok(out)
+}
+
+async fn send_webhook(
+    hook: &crate::WebhookSettings,
+    secret: &token_store::WebhookSecret,
+    title: &str,
+    body: &str,
+    level: &str,
+    ctx: &NotifyContext,
+) -> AppResult<()> {
+    let content_type = secret
+        .headers
+        .iter()
+        .find(|(name, _)| name.eq_ignore_ascii_case("content-type"))
+        .map_or("application/json", |(_, value)| value.as_str());
+    let json = content_type.to_ascii_lowercase().contains("json");
+    let payload = render_webhook_body(&hook.body_template, json, title, body, level, ctx)
+        .map_err(AppError::Message)?;
+
+    let method = reqwest::Method::from_bytes(hook.method.as_bytes())
+        .map_err(|_| AppError::InvalidInput(format!("Invalid method: {}", hook.method)))?;
+    let client = reqwest::Client::builder()
+        .timeout(Duration::from_secs(EXTERNAL_NOTIFY_TIMEOUT_SECS))
+        .build()
+        .map_err(|e| AppError::Message(format!("Failed to build HTTP client: {e}")))?;
+    let mut request = client
+        .request(method, &secret.url)
+        .header(reqwest::header::CONTENT_TYPE, content_type);
+    for (name, value) in secret.headers.iter().filter(|(n, _)| !n.eq_ignore_ascii_case("content-type")) {
+        request = request.header(name.as_str(), value.as_str());
+    }
+
+    let resp = request
+        .body(payload)
+        .send()
+        .await
+        .map_err(|e| AppError::Message(format!("Webhook request failed: {e}")))?;
+    if !resp.status().is_success() {
+        return Err(AppError::Message(format!(
+            "Webhook returned HTTP {}",
+            resp.status()
+        )));
+    }
+    Ok(())
+}
+
+fn webhook_channel(id: &str) -> String {
+    format!("{WEBHOOK_CHANNEL_PREFIX}{id}")
+}
+
+fn find_webhook<'a>(settings: &'a crate::ExternalNotifySettings, channel: &str) -> Option<&'a crate::WebhookSettings> {
+    let id = channel.strip_prefix(WEBHOOK_CHANNEL_PREFIX)?;
+    settings.webhooks.iter().find(|w| w.id == id)
+}
+
+fn discord_configured(settings: &crate::ExternalNotifySettings) -> bool {
+    settings.discord.has_secret
+}
+
+fn slack_configured(settings: &crate::ExternalNotifySettings) -> bool {
+    settings.slack.has_secret
+}
+
+fn teams_configured(settings: &crate::ExternalNotifySettings) -> bool {
+    settings.teams.has_secret
+}
+
+fn pushover_configured(settings: &crate::ExternalNotifySettings) -> bool {
+    settings.pushover.has_secret
+}
+
+fn ntfy_configured(settings: &crate::ExternalNotifySettings) -> bool {
+    !settings.ntfy.server_url.is_empty() && !settings.ntfy.topic.is_empty()
+}
+
+fn gotify_configured(settings: &crate::ExternalNotifySettings) -> bool {
+    !settings.gotify.server_url.is_empty() && settings.gotify.has_secret
+}
+
+fn notify_secret(name: &str, label: &str) -> AppResult<String> {
+    token_store::get_notify_secret(name)
+        .ok_or_else(|| AppError::Message(format!("{label} is not stored in the keyring")))
+}
+
+/// Channels that are enabled and configured, with their own transition triggers.
+/// Webhooks are keyed `webhook:<id>`.
+pub fn enabled_channels(settings: &crate::ExternalNotifySettings) -> Vec<(String, Triggers)> {
+    let mut channels = Vec::new();
+    if settings.discord.enabled && discord_configured(settings) {
+        let d = &settings.discord;
+        channels.push((
+            "discord".to_string(),
+            Triggers {
+                critical: d.critical,
+                recovery: d.recovery,
+                warning: d.warning,
+                projected: d.projected,
+            },
+        ));
+    }
+    if settings.slack.enabled && slack_configured(settings) {
+        let s = &settings.slack;
+        channels.push((
+            "slack".to_string(),
+            Triggers {
+                critical: s.critical,
+                recovery: s.recovery,
+                warning: s.warning,
+                projected: s.projected,
+            },
+        ));
+    }
+    if settings.teams.enabled && teams_configured(settings) {
+        let t = &settings.teams;
+        channels.push((
+            "teams".to_string(),
+            Triggers {
+                critical: t.critical,
+                recovery: t.recovery,
+                warning: t.warning,
+                projected: t.projected,
+            },
+        ));
+    }
+    if settings.pushover.enabled && pushover_configured(settings) {
+        let p = &settings.pushover;
+        channels.push((
+            "pushover".to_string(),
+            Triggers {
+                critical: p.critical,
+                recovery: p.recovery,
+                warning: p.warning,
+                projected: p.projected,
+            },
+        ));
+    }
+    if settings.ntfy.enabled && ntfy_configured(settings) {
+        let n = &settings.ntfy;
+        channels.push((
+            "ntfy".to_string(),
+            Triggers {
+                critical: n.critical,
+                recovery: n.recovery,
+                warning: n.warning,
+                projected: n.projected,
+            },
+        ));
+    }
+    if settings.gotify.enabled && gotify_configured(settings) {
+        let g = &settings.gotify;
+        channels.push((
+            "gotify".to_string(),
+            Triggers {
+                critical: g.critical,
+                recovery: g.recovery,
+                warning: g.warning,
+                projected: g.projected,
+            },
+        ));
+    }
+    for hook in settings.webhooks.iter().filter(|w| w.enabled && w.has_secret) {
+        channels.push((
+            webhook_channel(&hook.id),
+            Triggers {
+                critical: hook.critical,
+                recovery: hook.recovery,
+                warning: hook.warning,
+                projected: hook.projected,
+            },
+        ));
+    }
+    channels
+}
+
+/// Send to one channel. The error message is prefixed with the channel name.
+pub async fn send_to_channel(
+    settings: &crate::ExternalNotifySettings,
+    channel: &str,
+    title: &str,
+    body: &str,
+    level: &str,
+    ctx: &NotifyContext,
+) -> Result<(), String> {
+    match channel {
+        "discord" => {
+            let send = async {
+                let webhook_url = notify_secret(token_store::DISCORD_WEBHOOK_URL_SECRET, "Webhook URL")?;
+                send_discord(&webhook_url, title, body, level).await
+            };
+            send.await.map_err(|e| format!("Discord: {e}"))
+        }
+        "slack" => {
+            let send = async {
+                let webhook_url = notify_secret(token_store::SLACK_WEBHOOK_URL_SECRET, "Webhook URL")?;
+                send_slack(&webhook_url, title, body, level).await
+            };
+            send.await.map_err(|e| format!("Slack: {e}"))
+        }
+        "teams" => {
+            let send = async {
+                let webhook_url = notify_secret(token_store::TEAMS_WEBHOOK_URL_SECRET, "Webhook URL")?;
+                send_teams(&webhook_url, title, body, level).await
+            };
+            send.await.map_err(|e| format!("Teams: {e}"))
+        }
+        "pushover" => {
+            let send = async {
+                let api_token = notify_secret(token_store::PUSHOVER_API_TOKEN_SECRET, "API Token")?;
+                let user_key = notify_secret(token_store::PUSHOVER_USER_KEY_SECRET, "User Key")?;
+                send_pushover(&api_token, &user_key, title, body, level).await
+            };
+            send.await.map_err(|e| format!("Pushover: {e}"))
+        }
+        "ntfy" => {
+            let send = async {
+                let access_token = if settings.ntfy.has_secret {
+                    Some(notify_secret(token_store::NTFY_ACCESS_TOKEN_SECRET, "Access token")?)
+                } else {
+                    None
+                };
+                send_ntfy(&settings.ntfy, access_token.as_deref(), title, body, level).await
+            };
+            send.await.map_err(|e| format!("ntfy: {e}"))
+        }
+        "gotify" => {
+            let send = async {
+                let app_token = notify_secret(token_store::GOTIFY_APP_TOKEN_SECRET, "App token")?;
+                send_gotify(&settings.gotify.server_url, &app_token, title, body, level).await
+            };
+            send.await.map_err(|e| format!("Gotify: {e}"))
+        }
+        other => {
+            let Some(hook) = find_webhook(settings, other) else {
+                return Err(format!("Unknown channel: {other}"));
+            };
+            let send = async {
+                let secret = token_store::get_webhook_secret(&hook.id)
+                    .filter(|s| !s.url.is_empty())
+                    .ok_or_else(|| AppError::Message("URL is not stored in the keyring".to_string()))?;
+                send_webhook(hook, &secret, title, body, level, ctx).await
+            };
+            send.await.map_err(|e| format!("Webhook {}: {e}", hook.name))
+        }
+    }
+}
+
 pub async fn send_external_notification(
     app: AppHandle,
     payload: crate::SendExternalNotificationPayload,
@@ -117,42 +713,50 @@ pub async fn send_external_notification(
         return Err(AppError::InvalidInput("title is required".to_string()));
     }
 
-    let mut errors = Vec::new();
-    let send_all = channel.is_empty();
-
-    // Discord
-    let should_send_discord = if send_all {
-        settings.discord.enabled && !settings.discord.webhook_url.is_empty()
+    // No channel: every enabled channel outside quiet hours (held ones are
+    // summarized later). Explicit channel (test button): send if configured.
+    let targets: Vec<String> = if channel.is_empty() {
+        let rules = &store.settings.notify_settings.quiet_hours;
+        let now = chrono::Utc::now();
+        enabled_channels(settings)
+            .into_iter()
+            .map(|(key, _)| key)
+            .filter(|key| match notify_schedule::gate(rules, key, &level, None, now) {
+                Gate::Send => true,
+                Gate::Hold => {
+                    let item = HeldNotification {
+                        at: now.timestamp_millis(),
+                        title: title.clone(),
+                        body: body.clone(),
+                        level: level.clone(),
+                    };
+                    notify_schedule::hold(key, item);
+                    false
+                }
+                Gate::Drop => false,
+            })
+            .collect()
     } else {
-        channel == "discord" && !settings.discord.webhook_url.is_empty()
-    };
-    if should_send_discord {
-        if let Err(e) = send_discord(&settings.discord.webhook_url, &title, &body, &level).await {
-            errors.push(format!("Discord: {e}"));
+        match channel.as_str() {
+            "discord" if discord_configured(settings) => vec!["discord".to_string()],
+            "slack" if slack_configured(settings) => vec!["slack".to_string()],
+            "teams" if teams_configured(settings) => vec!["teams".to_string()],
+            "pushover" if pushover_configured(settings) => vec!["pushover".to_string()],
+            "ntfy" if ntfy_configured(settings) => vec!["ntfy".to_string()],
+            "gotify" if gotify_configured(settings) => vec!["gotify".to_string()],
+            other => find_webhook(settings, other)
+                .filter(|w| w.has_secret)
+                .map(|w| webhook_channel(&w.id))
+                .into_iter()
+                .collect(),
         }
-    }
-
-    // Pushover
-    let should_send_pushover = if send_all {
-        settings.pushover.enabled
-            && !settings.pushover.api_token.is_empty()
-            && !settings.pushover.user_key.is_empty()
-    } else {
-        channel == "pushover"
-            && !settings.pushover.api_token.is_empty()
-            && !settings.pushover.user_key.is_empty()
     };
-    if should_send_pushover {
-        if let Err(e) = send_pushover(
-            &settings.pushover.api_token,
-            &settings.pushover.user_key,
-            &title,
-            &body,
-            &level,
-        )
-        .await
-        {
-            errors.push(format!("Pushover: {e}"));
+
+    let mut errors = Vec::new();
+    let ctx = NotifyContext::default();
+    for target in targets {
+        if let Err(e) = send_to_channel(settings, &target, &title, &body, &level, &ctx).await {
+            errors.push(e);
         }
     }
 
@@ -161,3 +765,105 @@ pub async fn send_external_notification(
         errors,
     })
 }
+
+#[cfg(test)]
+mod tests {
+    use super::{
+        gotify_priority, ntfy_payload, render_webhook_body, slack_payload, teams_payload, NotifyContext,
+        DEFAULT_WEBHOOK_TEMPLATE,
+    };
+    use crate::usage_parser::UsageWindow;
+    use serde_json::Value;
+
+    fn ctx() -> NotifyContext {
+        NotifyContext {
+            service: "claude".to_string(),
+            account: "Main".to_string(),
+            windows: vec![UsageWindow::new(
+                "5時間".to_string(),
+                91.0,
+                Some(serde_json::json!(1_700_003_600)),
+                None,
+                false,
+                Some("critical".to_string()),
+            )],
+        }
+    }
+
+    #[test]
+    fn default_template_renders_valid_json_with_escaping() {
+        let out = render_webhook_body(DEFAULT_WEBHOOK_TEMPLATE, true, "Claude Code: \"Main\" ⚠️", "a\nb", "critical", &ctx())
+            .expect("valid json");
+        let value: Value = serde_json::from_str(&out).unwrap();
+        assert_eq!(value["title"], "Claude Code: \"Main\" ⚠️");
+        assert_eq!(value["body"], "a\nb");
+        assert_eq!(value["windows"][0]["utilization"], 91.0);
+        assert_eq!(value["windows"][0]["resets_at"], "2023-11-14T23:13:20+00:00");
+    }
+
+    #[test]
+    fn per_window_and_unknown_placeholders() {
+        let out = render_webhook_body(
+            "{{account}} {{window.5時間.utilization}}% {{window.5時間.status}} {{max_utilization}} {{nope}}",
+            false,
+            "",
+            "",
+            "",
+            &ctx(),
+        )
+        .unwrap();
+        assert_eq!(out, "Main 91.0% critical 91.0 {{nope}}");
+    }
+
+    #[test]
+    fn invalid_json_after_rendering_is_rejected() {
+        assert!(render_webhook_body("{\"text\": {{title}}}", true, "x", "", "", &ctx()).is_err());
+    }
+
+    #[test]
+    fn slack_payload_colors_attachment_and_escapes_text() {
+        let payload = slack_payload("Claude Code: Main ⚠️", "5時間 <92%> & rising", "critical", 1_700_000_000);
+        let attachment = &payload["attachments"][0];
+        assert_eq!(attachment["color"], "#E01E5A");
+        assert_eq!(attachment["blocks"][0]["type"], "header");
+        assert_eq!(attachment["blocks"][1]["text"]["text"], "5時間 &lt;92%&gt; &amp; rising");
+        assert!(attachment["blocks"][2]["elements"][0]["text"]
+            .as_str()
+            .unwrap()
+            .contains("<!date^1700000000^"));
+
+        let long_title = "x".repeat(200);
+        let payload = slack_payload(&long_title, "", "ok", 0);
+        let header = payload["attachments"][0]["blocks"][0]["text"]["text"].as_str().unwrap();
+        assert_eq!(header.chars().count(), 150);
+        assert_eq!(payload["attachments"][0]["blocks"].as_array().unwrap().len(), 2);
+    }
+
+    #[test]
+    fn teams_payload_is_an_adaptive_card_styled_by_level() {
+        let payload = teams_payload("Codex: Main", "週次 80%", "projected", "2026-01-01T00:00:00+00:00");
+        assert_eq!(payload["type"], "message");
+        let attachment = &payload["attachments"][0];
+        assert_eq!(attachment["contentType"], "application/vnd.microsoft.card.adaptive");
+        let container = &attachment["content"]["body"][0];
+        assert_eq!(container["style"], "warning");
+        assert_eq!(container["items"][0]["text"], "Codex: Main");
+        assert_eq!(container["items"][1]["text"], "週次 80%");
+    }
+
+    #[test]
+    fn ntfy_payload_maps_level_to_priority_and_tags() {
+        let payload = ntfy_payload("aiqm", "Claude Code: A ⚠️", "5時間 92%", "critical");
+        assert_eq!(payload["topic"], "aiqm");
+        assert_eq!(payload["priority"], 4);
+        assert_eq!(payload["tags"], serde_json::json!(["rotating_light", "critical"]));
+
+        let test_send = ntfy_payload("aiqm", "テスト", "", "");
+        assert_eq!(test_send["message"], "テスト");
+        assert_eq!(test_send["priority"], 2);
+        assert_eq!(test_send["tags"], serde_json::json!(["bell"]));
+
+        assert_eq!(gotify_priority("exhausted"), 8);
+        assert_eq!(gotify_priority("projected"), 5);
+    }
+}
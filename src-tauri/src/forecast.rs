@@ -0,0 +1,184 @@
+use crate::history_store::{self, HistoryFilter, HistorySample};
+use crate::status::EXHAUSTED_THRESHOLD;
+use crate::usage_parser::UsageWindow;
+use serde::{Deserialize, Serialize};
+use tauri::AppHandle;
+
+/// Only samples this recent feed the burn-rate estimate.
+const LOOKBACK_MS: i64 = 2 * 60 * 60 * 1000;
+/// Fewer samples (current point included) than this give no forecast.
+const MIN_SAMPLES: usize = 3;
+/// Samples must span at least this long; a burst of polls says little about the rate.
+const MIN_SPAN_MS: i64 = 10 * 60 * 1000;
+/// A drop larger than this (in points) between samples is taken as a window reset.
+const RESET_DROP_PCT: f64 = 5.0;
+const HOUR_MS: f64 = 60.0 * 60.0 * 1000.0;
+
+/// Projected exhaustion of one window from its recent burn rate.
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct Forecast {
+    /// Utilization points consumed per hour (least-squares slope).
+    pub rate_per_hour: f64,
+    /// Epoch milliseconds when utilization reaches 100% at this rate; None when not rising.
+    pub exhausted_at: Option<i64>,
+    /// True when `exhausted_at` comes before the window's `resets_at`.
+    pub before_reset: bool,
+    /// Number of samples used for the estimate.
+    pub samples: usize,
+}
+
+/// Estimate the burn rate from `(ts, utilization)` points sorted by time, the
+/// last being the current value. Only points since the last reset and within
+/// the lookback are used.
+pub fn project(points: &[(i64, f64)], resets_at: Option<i64>) -> Option<Forecast> {
+    let &(now, current) = points.last()?;
+    let start = points
+        .windows(2)
+        .rposition(|pair| pair[1].1 < pair[0].1 - RESET_DROP_PCT)
+        .map_or(0, |i| i + 1);
+    let recent: Vec<(i64, f64)> = points[start..]
+        .iter()
+        .copied()
+        .filter(|(ts, u)| *ts >= now - LOOKBACK_MS && u.is_finite())
+        .collect();
+    if recent.len() < MIN_SAMPLES || now - recent[0].0 < MIN_SPAN_MS {
+        return None;
+    }
+
+    // Least squares on hours relative to the first sample keeps the sums small.
+    let t0 = recent[0].0;
+    let n = recent.len() as f64;
+    let xs: Vec<f64> = recent.iter().map(|(ts, _)| (ts - t0) as f64 / HOUR_MS).collect();
+    let mean_x = xs.iter().sum::<f64>() / n;
+    let mean_y = recent.iter().map(|(_, u)| u).sum::<f64>() / n;
+    let (mut sxy, mut sxx) = (0.0, 0.0);
+    for (x, (_, y)) in xs.iter().zip(&recent) {
+        sxy += (x - mean_x) * (y - mean_y);
+        sxx += (x - mean_x) * (x - mean_x);
+    }
+    if sxx <= 0.0 {
+        return None;
+    }
+    let rate_per_hour = sxy / sxx;
+
+    let exhausted_at = if current >= EXHAUSTED_THRESHOLD {
+        Some(now)
+    } else if rate_per_hour > 0.0 {
+        let hours = (EXHAUSTED_THRESHOLD - current) / rate_per_hour;
+        Some(now + (hours * HOUR_MS) as i64)
+    } else {
+        None
+    };
+    let before_reset = match (exhausted_at, resets_at) {
+        (Some(at), Some(reset)) => at < reset,
+        _ => false,
+    };
+
+    Some(Forecast {
+        rate_per_hour,
+        exhausted_at,
+        before_reset,
+        samples: recent.len(),
+    })
+}
+
+/// Set `forecast` on each window from the recorded history plus the current value.
+pub fn annotate(app: &AppHandle, service: &str, id: &str, windows: &mut [UsageWindow], now: i64) {
+    let filter = HistoryFilter {
+        service: Some(service.to_string()),
+        id: Some(id.to_string()),
+        from: Some(now - LOOKBACK_MS),
+        ..Default::default()
+    };
+    let samples = history_store::read_samples(app, &filter).unwrap_or_default();
+    annotate_from_samples(&samples, windows, now);
+}
+
+fn annotate_from_samples(samples: &[HistorySample], windows: &mut [UsageWindow], now: i64) {
+    for window in windows.iter_mut() {
+        let mut points: Vec<(i64, f64)> = samples
+            .iter()
+            .filter(|s| s.window == window.name && s.ts < now)
+            .map(|s| (s.ts, s.utilization))
+            .collect();
+        points.push((now, window.utilization));
+        window.forecast = project(&points, window.resets_at_millis());
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::{annotate_from_samples, project};
+    use crate::history_store::HistorySample;
+    use crate::usage_parser::UsageWindow;
+
+    const MIN: i64 = 60 * 1000;
+
+    #[test]
+    fn steady_burn_projects_exhaustion_before_reset() {
+        // 10 points per 30 minutes: 20%/h, 60% now -> 100% in 2h.
+        let points = [(0, 40.0), (30 * MIN, 50.0), (60 * MIN, 60.0)];
+        let forecast = project(&points, Some(180 * MIN)).unwrap();
+        assert!((forecast.rate_per_hour - 20.0).abs() < 1e-9);
+        assert_eq!(forecast.exhausted_at, Some(180 * MIN));
+        assert!(!forecast.before_reset);
+        assert_eq!(forecast.samples, 3);
+
+        let forecast = project(&points, Some(181 * MIN)).unwrap();
+        assert!(forecast.before_reset);
+    }
+
+    #[test]
+    fn flat_or_falling_usage_never_exhausts() {
+        let points = [(0, 30.0), (20 * MIN, 30.0), (40 * MIN, 29.0)];
+        let forecast = project(&points, Some(300 * MIN)).unwrap();
+        assert_eq!(forecast.exhausted_at, None);
+        assert!(!forecast.before_reset);
+    }
+
+    #[test]
+    fn too_few_or_too_close_samples_give_no_forecast() {
+        assert!(project(&[(0, 10.0), (30 * MIN, 20.0)], None).is_none());
+        assert!(project(&[(0, 10.0), (MIN, 20.0), (2 * MIN, 30.0)], None).is_none());
+        assert!(project(&[], None).is_none());
+    }
+
+    #[test]
+    fn samples_before_a_reset_are_ignored() {
+        let points = [
+            (0, 80.0),
+            (20 * MIN, 95.0),
+            (40 * MIN, 2.0),
+            (50 * MIN, 4.0),
+            (60 * MIN, 6.0),
+        ];
+        let forecast = project(&points, None).unwrap();
+        assert_eq!(forecast.samples, 3);
+        assert!((forecast.rate_per_hour - 12.0).abs() < 1e-9);
+    }
+
+    #[test]
+    fn annotate_matches_samples_by_window_name() {
+        let sample = |ts: i64, window: &str, utilization: f64| HistorySample {
+            ts,
+            service: "claude".to_string(),
+            id: "a".to_string(),
+            window: window.to_string(),
+            utilization,
+            resets_at: None,
+        };
+        let samples = vec![
+            sample(0, "5h", 10.0),
+            sample(0, "7d", 50.0),
+            sample(30 * MIN, "5h", 20.0),
+        ];
+        let mut windows = vec![
+            UsageWindow::new("5h".to_string(), 30.0, None, None, false, None),
+            UsageWindow::new("7d".to_string(), 50.0, None, None, false, None),
+        ];
+        annotate_from_samples(&samples, &mut windows, 60 * MIN);
+        assert_eq!(windows[0].forecast.as_ref().map(|f| f.samples), Some(3));
+        assert!(windows[1].forecast.is_none());
+    }
+}
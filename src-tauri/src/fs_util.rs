@@ -0,0 +1,113 @@
+use std::fs::{self, File, OpenOptions};
+use std::io::{self, Write};
+use std::path::{Path, PathBuf};
+use std::time::SystemTime;
+
+/// Write `bytes` to a temp file next to `path`, fsync it and rename it over `path`.
+/// Readers see either the old or the new content, never a truncated file.
+pub fn atomic_write(path: &Path, bytes: &[u8]) -> io::Result<()> {
+    write_replace(path, bytes, OpenOptions::new().write(true).create(true).truncate(true))
+}
+
+/// [`atomic_write`] for credentials: on Unix the file is readable by the
+/// owner only.
+pub fn atomic_write_private(path: &Path, bytes: &[u8]) -> io::Result<()> {
+    let mut options = OpenOptions::new();
+    options.write(true).create(true).truncate(true);
+    #[cfg(unix)]
+    std::os::unix::fs::OpenOptionsExt::mode(&mut options, 0o600);
+    write_replace(path, bytes, &options)
+}
+
+fn write_replace(path: &Path, bytes: &[u8], options: &OpenOptions) -> io::Result<()> {
+    let tmp_path = temp_path(path);
+    let result = (|| {
+        let mut file = options.open(&tmp_path)?;
+        file.write_all(bytes)?;
+        file.sync_all()?;
+        drop(file);
+        // Replaces an existing destination on both Unix and Windows.
+        fs::rename(&tmp_path, path)
+    })();
+    if result.is_err() {
+        let _ = fs::remove_file(&tmp_path);
+    }
+    result
+}
+
+fn temp_path(path: &Path) -> PathBuf {
+    let name = path
+        .file_name()
+        .map(|n| n.to_string_lossy().into_owned())
+        .unwrap_or_default();
+    path.with_file_name(format!(".{name}.{}.tmp", std::process::id()))
+}
+
+/// Advisory exclusive lock on `path` (created if missing), held until the
+/// returned file is dropped. Blocks while another process holds it.
+pub fn lock_exclusive(path: &Path) -> io::Result<File> {
+    let file = OpenOptions::new()
+        .read(true)
+        .write(true)
+        .create(true)
+        .truncate(false)
+        .open(path)?;
+    file.lock()?;
+    Ok(file)
+}
+
+/// Cheap change detector for small files rewritten by `atomic_write`.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct Fingerprint {
+    modified: Option<SystemTime>,
+    len: u64,
+}
+
+/// `None` when the file does not exist or cannot be inspected.
+pub fn fingerprint(path: &Path) -> Option<Fingerprint> {
+    let meta = fs::metadata(path).ok()?;
+    Some(Fingerprint {
+        modified: meta.modified().ok(),
+        len: meta.len(),
+    })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::{atomic_write, atomic_write_private, fingerprint, lock_exclusive};
+
+    #[test]
+    fn atomic_write_replaces_content_and_cleans_up() {
+        let dir = std::env::temp_dir().join(format!("aiqm-fs-util-test-{}", std::process::id()));
+        std::fs::create_dir_all(&dir).expect("temp dir");
+        let path = dir.join("data.json");
+
+        atomic_write(&path, b"first").expect("first write");
+        let before = fingerprint(&path);
+        atomic_write(&path, b"second, longer").expect("second write");
+        assert_eq!(std::fs::read(&path).expect("read"), b"second, longer");
+        assert_ne!(fingerprint(&path), before);
+        assert_eq!(std::fs::read_dir(&dir).expect("list").count(), 1, "temp file left behind");
+
+        let lock_path = dir.join("data.lock");
+        let held = lock_exclusive(&lock_path).expect("lock");
+        let other = std::fs::File::open(&lock_path).expect("open lock file");
+        assert!(other.try_lock().is_err());
+        drop(held);
+        assert!(other.try_lock().is_ok());
+        let _ = std::fs::remove_dir_all(&dir);
+    }
+
+    #[cfg(unix)]
+    #[test]
+    fn private_write_is_owner_only() {
+        use std::os::unix::fs::PermissionsExt;
+        let dir = std::env::temp_dir().join(format!("aiqm-fs-util-private-{}", std::process::id()));
+        std::fs::create_dir_all(&dir).expect("temp dir");
+        let path = dir.join("credentials.json");
+        atomic_write_private(&path, b"{}").expect("write");
+        let mode = std::fs::metadata(&path).expect("metadata").permissions().mode();
+        assert_eq!(mode & 0o777, 0o600);
+        let _ = std::fs::remove_dir_all(&dir);
+    }
+}
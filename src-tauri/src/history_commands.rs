@@ -0,0 +1,44 @@
+use crate::error::{AppError, AppResult};
+use crate::history_store::{self, HistoryFilter, HistorySeries};
+use crate::token_store::ensure_service;
+use crate::validation::validate_account_id;
+use tauri::AppHandle;
+
+pub fn query_usage_history(
+    app: AppHandle,
+    payload: crate::QueryUsageHistoryPayload,
+) -> AppResult<Vec<HistorySeries>> {
+    let service = payload
+        .service
+        .map(|s| s.trim().to_string())
+        .filter(|s| !s.is_empty());
+    if let Some(service) = service.as_deref() {
+        ensure_service(service)?;
+    }
+
+    let id = payload
+        .id
+        .map(|s| s.trim().to_string())
+        .filter(|s| !s.is_empty());
+    if let Some(id) = id.as_deref() {
+        validate_account_id(id)?;
+    }
+
+    if let (Some(from), Some(to)) = (payload.from, payload.to) {
+        if from > to {
+            return Err(AppError::InvalidInput("from must not be after to".to_string()));
+        }
+    }
+
+    let filter = HistoryFilter {
+        service,
+        id,
+        window: payload
+            .window
+            .map(|s| s.trim().to_string())
+            .filter(|s| !s.is_empty()),
+        from: payload.from,
+        to: payload.to,
+    };
+    history_store::query(&app, &filter, payload.bucket_secs, payload.max_points)
+}
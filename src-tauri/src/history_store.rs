@@ -0,0 +1,357 @@
+use crate::error::{AppError, AppResult};
+use crate::fs_util;
+use crate::usage_parser::UsageWindow;
+use serde::{Deserialize, Serialize};
+use serde_json::Value;
+use std::collections::BTreeMap;
+use std::fs::{self, OpenOptions};
+use std::io::{BufRead, BufReader, Write};
+use std::path::PathBuf;
+use std::sync::{Mutex, OnceLock};
+use std::time::{Duration, Instant};
+use tauri::{AppHandle, Manager};
+
+const HISTORY_FILE: &str = "usage_history.jsonl";
+const PRUNE_INTERVAL_SECS: u64 = 3600;
+const DEFAULT_MAX_POINTS: usize = 500;
+const MAX_POINTS_LIMIT: usize = 10_000;
+const DAY_MS: i64 = 24 * 60 * 60 * 1000;
+
+/// Serializes appends and rewrites of the history file within this process.
+static HISTORY_LOCK: OnceLock<Mutex<Option<Instant>>> = OnceLock::new();
+
+/// One utilization sample, stored as a single JSON line.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct HistorySample {
+    /// Epoch milliseconds.
+    pub ts: i64,
+    pub service: String,
+    pub id: String,
+    pub window: String,
+    pub utilization: f64,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub resets_at: Option<Value>,
+}
+
+#[derive(Debug, Clone, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct HistoryPoint {
+    /// Bucket start (epoch milliseconds).
+    pub ts: i64,
+    pub utilization: f64,
+    pub min: f64,
+    pub max: f64,
+    pub samples: usize,
+}
+
+#[derive(Debug, Clone, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct HistorySeries {
+    pub service: String,
+    pub id: String,
+    pub window: String,
+    pub points: Vec<HistoryPoint>,
+}
+
+#[derive(Debug, Clone, Default)]
+pub struct HistoryFilter {
+    pub service: Option<String>,
+    pub id: Option<String>,
+    pub window: Option<String>,
+    pub from: Option<i64>,
+    pub to: Option<i64>,
+}
+
+impl HistoryFilter {
+    fn matches(&self, sample: &HistorySample) -> bool {
+        self.service.as_deref().is_none_or(|s| s == sample.service)
+            && self.id.as_deref().is_none_or(|s| s == sample.id)
+            && self.window.as_deref().is_none_or(|s| s == sample.window)
+            && self.from.is_none_or(|from| sample.ts >= from)
+            && self.to.is_none_or(|to| sample.ts <= to)
+    }
+}
+
+fn history_lock() -> &'static Mutex<Option<Instant>> {
+    HISTORY_LOCK.get_or_init(|| Mutex::new(None))
+}
+
+fn history_path(app: &AppHandle) -> AppResult<PathBuf> {
+    let dir = app
+        .path()
+        .app_data_dir()
+        .map_err(|e| AppError::Store(format!("Failed to resolve app data directory: {e}")))?;
+    fs::create_dir_all(&dir)
+        .map_err(|e| AppError::Store(format!("Failed to create app data directory: {e}")))?;
+    Ok(dir.join(HISTORY_FILE))
+}
+
+/// Append one sample per window. Unknown-format placeholders are skipped.
+pub fn record_usage(
+    app: &AppHandle,
+    service: &str,
+    id: &str,
+    windows: &[UsageWindow],
+    fetched_at: i64,
+) -> AppResult<()> {
+    let settings = crate::store_repo::read_store(app)?.settings.history;
+    if !settings.enabled {
+        return Ok(());
+    }
+
+    let mut body = String::new();
+    for window in windows {
+        if window.status.as_deref() == Some("unknown") {
+            continue;
+        }
+        let sample = HistorySample {
+            ts: fetched_at,
+            service: service.to_string(),
+            id: id.to_string(),
+            window: window.name.clone(),
+            utilization: window.utilization,
+            resets_at: window.resets_at.clone(),
+        };
+        let line = serde_json::to_string(&sample)
+            .map_err(|e| AppError::Store(format!("Failed to serialize history sample: {e}")))?;
+        body.push_str(&line);
+        body.push('\n');
+    }
+    if body.is_empty() {
+        return Ok(());
+    }
+
+    let path = history_path(app)?;
+    let mut last_prune = history_lock()
+        .lock()
+        .map_err(|_| AppError::Store("History lock is poisoned".to_string()))?;
+    let mut file = OpenOptions::new()
+        .create(true)
+        .append(true)
+        .open(&path)
+        .map_err(|e| AppError::Store(format!("Failed to open history file: {e}")))?;
+    file.write_all(body.as_bytes())
+        .map_err(|e| AppError::Store(format!("Failed to append history: {e}")))?;
+
+    let due = last_prune.is_none_or(|t| t.elapsed() >= Duration::from_secs(PRUNE_INTERVAL_SECS));
+    if due {
+        *last_prune = Some(Instant::now());
+        prune_locked(&path, settings.retention_days)?;
+    }
+    Ok(())
+}
+
+/// Drop samples older than the configured retention period.
+pub fn prune(app: &AppHandle) -> AppResult<()> {
+    let retention_days = crate::store_repo::read_store(app)?.settings.history.retention_days;
+    let path = history_path(app)?;
+    let mut last_prune = history_lock()
+        .lock()
+        .map_err(|_| AppError::Store("History lock is poisoned".to_string()))?;
+    *last_prune = Some(Instant::now());
+    prune_locked(&path, retention_days)
+}
+
+fn prune_locked(path: &std::path::Path, retention_days: i32) -> AppResult<()> {
+    if !path.exists() {
+        return Ok(());
+    }
+    let cutoff = now_millis() - i64::from(retention_days.max(1)) * DAY_MS;
+    let raw = fs::read_to_string(path)
+        .map_err(|e| AppError::Store(format!("Failed to read history file: {e}")))?;
+
+    let mut kept = String::with_capacity(raw.len());
+    let mut dropped = false;
+    for line in raw.lines() {
+        match serde_json::from_str::<HistorySample>(line) {
+            Ok(sample) if sample.ts >= cutoff => {
+                kept.push_str(line);
+                kept.push('\n');
+            }
+            _ => dropped = true,
+        }
+    }
+    if !dropped {
+        return Ok(());
+    }
+
+    fs_util::atomic_write(path, kept.as_bytes())
+        .map_err(|e| AppError::Store(format!("Failed to replace history file: {e}")))
+}
+
+/// Read raw samples matching `filter`, oldest first.
+pub fn read_samples(app: &AppHandle, filter: &HistoryFilter) -> AppResult<Vec<HistorySample>> {
+    let path = history_path(app)?;
+    let _guard = history_lock()
+        .lock()
+        .map_err(|_| AppError::Store("History lock is poisoned".to_string()))?;
+    let file = match fs::File::open(&path) {
+        Ok(file) => file,
+        Err(e) if e.kind() == std::io::ErrorKind::NotFound => return Ok(Vec::new()),
+        Err(e) => return Err(AppError::Store(format!("Failed to open history file: {e}"))),
+    };
+
+    let mut out = Vec::new();
+    for line in BufReader::new(file).lines() {
+        let Ok(line) = line else {
+            continue;
+        };
+        // Tolerate a torn last line from an interrupted append.
+        let Ok(sample) = serde_json::from_str::<HistorySample>(&line) else {
+            continue;
+        };
+        if filter.matches(&sample) {
+            out.push(sample);
+        }
+    }
+    out.sort_by_key(|s| s.ts);
+    Ok(out)
+}
+
+/// Query a time range and downsample each series into fixed-width buckets.
+/// When `bucket_secs` is not given, the bucket width is derived from `max_points`.
+pub fn query(
+    app: &AppHandle,
+    filter: &HistoryFilter,
+    bucket_secs: Option<i64>,
+    max_points: Option<usize>,
+) -> AppResult<Vec<HistorySeries>> {
+    let samples = read_samples(app, filter)?;
+    Ok(downsample(samples, filter, bucket_secs, max_points))
+}
+
+fn downsample(
+    samples: Vec<HistorySample>,
+    filter: &HistoryFilter,
+    bucket_secs: Option<i64>,
+    max_points: Option<usize>,
+) -> Vec<HistorySeries> {
+    let max_points = max_points.unwrap_or(DEFAULT_MAX_POINTS).clamp(1, MAX_POINTS_LIMIT);
+    let first = samples.first().map(|s| s.ts).unwrap_or(0);
+    let last = samples.last().map(|s| s.ts).unwrap_or(0);
+    let from = filter.from.unwrap_or(first);
+    let to = filter.to.unwrap_or(last);
+    // `bucket_secs` and the range come from IPC; a huge value means one bucket.
+    let bucket_ms = match bucket_secs.filter(|b| *b > 0) {
+        Some(secs) => secs.saturating_mul(1000),
+        None => (to.saturating_sub(from) / max_points as i64).max(1),
+    };
+
+    let mut grouped: BTreeMap<(String, String, String), Vec<HistorySample>> = BTreeMap::new();
+    for sample in samples {
+        grouped
+            .entry((sample.service.clone(), sample.id.clone(), sample.window.clone()))
+            .or_default()
+            .push(sample);
+    }
+
+    grouped
+        .into_iter()
+        .map(|((service, id, window), series)| {
+            let mut points: Vec<HistoryPoint> = Vec::new();
+            let mut sum = 0.0;
+            for sample in series {
+                let bucket = from.saturating_add((sample.ts.saturating_sub(from).max(0) / bucket_ms) * bucket_ms);
+                match points.last_mut() {
+                    Some(point) if point.ts == bucket => {
+                        sum += sample.utilization;
+                        point.samples += 1;
+                        point.min = point.min.min(sample.utilization);
+                        point.max = point.max.max(sample.utilization);
+                        point.utilization = sum / point.samples as f64;
+                    }
+                    _ => {
+                        sum = sample.utilization;
+                        points.push(HistoryPoint {
+                            ts: bucket,
+                            utilization: sample.utilization,
+                            min: sample.utilization,
+                            max: sample.utilization,
+                            samples: 1,
+                        });
+                    }
+                }
+            }
+            HistorySeries {
+                service,
+                id,
+                window,
+                points,
+            }
+        })
+        .collect()
+}
+
+fn now_millis() -> i64 {
+    std::time::SystemTime::now()
+        .duration_since(std::time::UNIX_EPOCH)
+        .unwrap_or_default()
+        .as_millis() as i64
+}
+
+#[cfg(test)]
+mod tests {
+    use super::{downsample, HistoryFilter, HistorySample};
+
+    fn sample(ts: i64, window: &str, utilization: f64) -> HistorySample {
+        HistorySample {
+            ts,
+            service: "claude".to_string(),
+            id: "a1".to_string(),
+            window: window.to_string(),
+            utilization,
+            resets_at: None,
+        }
+    }
+
+    #[test]
+    fn downsample_averages_within_bucket() {
+        let samples = vec![
+            sample(0, "5h", 10.0),
+            sample(30_000, "5h", 20.0),
+            sample(60_000, "5h", 40.0),
+        ];
+        let series = downsample(samples, &HistoryFilter::default(), Some(60), None);
+        assert_eq!(series.len(), 1);
+        let points = &series[0].points;
+        assert_eq!(points.len(), 2);
+        assert_eq!(points[0].ts, 0);
+        assert_eq!(points[0].utilization, 15.0);
+        assert_eq!(points[0].min, 10.0);
+        assert_eq!(points[0].max, 20.0);
+        assert_eq!(points[1].ts, 60_000);
+        assert_eq!(points[1].samples, 1);
+    }
+
+    #[test]
+    fn huge_bucket_collapses_into_one_point() {
+        let samples = vec![sample(0, "5h", 10.0), sample(60_000, "5h", 30.0)];
+        let series = downsample(samples, &HistoryFilter::default(), Some(i64::MAX), None);
+        let points = &series[0].points;
+        assert_eq!(points.len(), 1);
+        assert_eq!(points[0].ts, 0);
+        assert_eq!(points[0].utilization, 20.0);
+    }
+
+    #[test]
+    fn downsample_splits_series_per_window() {
+        let samples = vec![sample(0, "5h", 10.0), sample(0, "7d", 50.0)];
+        let series = downsample(samples, &HistoryFilter::default(), None, Some(10));
+        assert_eq!(series.len(), 2);
+        assert_eq!(series[0].window, "5h");
+        assert_eq!(series[1].window, "7d");
+    }
+
+    #[test]
+    fn filter_respects_time_range() {
+        let filter = HistoryFilter {
+            from: Some(100),
+            to: Some(200),
+            ..HistoryFilter::default()
+        };
+        assert!(filter.matches(&sample(150, "5h", 1.0)));
+        assert!(!filter.matches(&sample(50, "5h", 1.0)));
+        assert!(!filter.matches(&sample(250, "5h", 1.0)));
+    }
+}
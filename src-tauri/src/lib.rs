@@ -0,0 +1,1153 @@
+use serde::{Deserialize, Serialize};
+use std::collections::BTreeMap;
+use store_repo::{migrate_notify_secrets, read_store, update_store};
+use tauri::Manager;
+use window_ops::apply_window_mode;
+mod api_client;
+mod account_commands;
+pub mod cli;
+mod cli_switch;
+mod cli_sync;
+mod commands;
+mod error;
+mod export_commands;
+mod external_notify;
+mod forecast;
+mod fs_util;
+mod history_commands;
+mod history_store;
+mod mcp;
+mod mcp_server;
+mod metrics_server;
+mod notification_commands;
+mod notify_dispatch;
+mod notify_schedule;
+mod notify_throttle;
+mod oauth;
+mod oauth_commands;
+mod poll_scheduler;
+mod providers;
+mod recommend;
+mod reset_watch;
+mod settings_commands;
+mod status;
+mod store_repo;
+mod token_refresh;
+mod token_scheduler;
+mod token_store;
+mod usage_commands;
+mod usage_parser;
+mod usage_state;
+mod validation;
+mod window_commands;
+mod window_ops;
+
+pub const APP_NAME: &str = "AI Quota Monitor";
+/// Must match `identifier` in tauri.conf.json.
+const APP_IDENTIFIER: &str = "com.suzuki.takahiro.quota-monitor";
+const STORE_FILE: &str = "accounts.json";
+/// Latest poll results written by the app each cycle, read by `aiqm statusline`.
+const USAGE_CACHE_FILE: &str = "usage_cache.json";
+const ANTHROPIC_OAUTH_BETA: &str = "oauth-2025-04-20";
+const HISTORY_RETENTION_DAYS_DEFAULT: i32 = 30;
+const HISTORY_RETENTION_DAYS_MAX: i32 = 365;
+const METRICS_BIND_ADDRESS_DEFAULT: &str = "127.0.0.1";
+const METRICS_PORT_DEFAULT: i32 = 9464;
+const MCP_PORT_DEFAULT: i32 = 9465;
+const MAX_WEBHOOKS: usize = 10;
+const MAX_QUIET_HOURS_RULES: usize = 10;
+const HYSTERESIS_DEFAULT: i32 = 2;
+const HYSTERESIS_MAX: i32 = 20;
+const COOLDOWN_MINUTES_DEFAULT: i32 = 15;
+const RENOTIFY_MINUTES_MIN: i32 = 5;
+const NOTIFY_MINUTES_MAX: i32 = 1440;
+const ACCOUNT_PRIORITY_MAX: i32 = 10;
+const TOKEN_REFRESH_LEAD_MINUTES_DEFAULT: i32 = 5;
+const TOKEN_REFRESH_LEAD_MINUTES_MAX: i32 = 120;
+const NTFY_SERVER_DEFAULT: &str = "https://ntfy.sh";
+
+const NORMAL_WINDOW_DEFAULT_W: i32 = 1100;
+const NORMAL_WINDOW_DEFAULT_H: i32 = 840;
+const NORMAL_WINDOW_MIN_W: i32 = 980;
+const NORMAL_WINDOW_MIN_H: i32 = 700;
+
+const MINIMAL_CARD_WIDTH: i32 = 290;
+const MINIMAL_PAD: i32 = 16;
+const MINIMAL_MIN_W_DEFAULT: i32 = MINIMAL_CARD_WIDTH + MINIMAL_PAD;
+const MINIMAL_WINDOW_DEFAULT_W: i32 = MINIMAL_MIN_W_DEFAULT + 74;
+const MINIMAL_WINDOW_DEFAULT_H: i32 = 420;
+const MINIMAL_WINDOW_MIN_H_DEFAULT: i32 = 240;
+const MINIMAL_FLOOR_W: i32 = MINIMAL_CARD_WIDTH - 40;
+const MINIMAL_FLOOR_H: i32 = 220;
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+struct AccountEntry {
+    id: String,
+    name: String,
+    /// Epoch milliseconds until which this account's notifications are muted.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    muted_until: Option<i64>,
+    /// Follow the CLI's credentials file instead of refreshing tokens here.
+    #[serde(default)]
+    cli_linked: bool,
+    /// Epoch milliseconds of the last token copy from the CLI.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    cli_synced_at: Option<i64>,
+    /// Added to the recommendation score (-10..=10).
+    #[serde(default)]
+    priority: i32,
+    /// Recommended only when no other account of the service is usable.
+    #[serde(default)]
+    reserve: bool,
+    /// Set when the refresh token was rejected (e.g. `invalid_grant`); cleared
+    /// by a successful refresh or new tokens.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    relogin_reason: Option<String>,
+}
+
+/// Accounts keyed by provider id (see `providers::all`).
+type Services = BTreeMap<String, Vec<AccountEntry>>;
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+struct PollingState {
+    active: bool,
+    started_at: Option<i64>,
+    interval: i32,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+struct Bounds {
+    width: i32,
+    height: i32,
+    x: Option<i32>,
+    y: Option<i32>,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+struct WindowState {
+    mode: String,
+    normal_bounds: Bounds,
+    minimal_bounds: Option<Bounds>,
+    minimal_min_width: i32,
+    minimal_min_height: i32,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+struct NotifySettings {
+    critical: bool,
+    recovery: bool,
+    warning: bool,
+    /// Notify when the burn rate projects exhaustion before the window resets.
+    projected: bool,
+    threshold_warning: i32,
+    threshold_critical: i32,
+    /// Times when notifications are held back and summarized afterwards.
+    #[serde(default)]
+    quiet_hours: Vec<QuietHoursRule>,
+    /// Percentage points utilization must fall below a threshold before the
+    /// status steps down again.
+    #[serde(default)]
+    hysteresis: i32,
+    /// Minimum minutes between alerts of the same or lower severity per
+    /// account and channel. 0 disables the cooldown.
+    #[serde(default)]
+    cooldown_minutes: i32,
+    /// Repeat critical/exhausted alerts every N minutes until the account
+    /// recovers. 0 disables the reminders.
+    #[serde(default)]
+    renotify_minutes: i32,
+}
+
+/// One quiet period. `start` > `end` spans midnight; the range then starts on
+/// the listed day. `start` == `end` covers the whole day.
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+struct QuietHoursRule {
+    enabled: bool,
+    /// `desktop`, `discord`, ... or `webhook:<id>`; empty for every channel.
+    channels: Vec<String>,
+    /// `mon` .. `sun`; empty for every day.
+    days: Vec<String>,
+    /// `HH:MM`.
+    start: String,
+    end: String,
+    /// IANA name such as `Asia/Tokyo`; empty for the system time zone.
+    timezone: String,
+    /// Still send transitions to `exhausted` during the period.
+    allow_exhausted: bool,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+struct UsageExportSettings {
+    enabled: bool,
+    path: Option<String>,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+struct DiscordSettings {
+    enabled: bool,
+    /// Masked; the URL itself is kept in the keyring.
+    webhook_url: String,
+    #[serde(default)]
+    has_secret: bool,
+    critical: bool,
+    recovery: bool,
+    warning: bool,
+    projected: bool,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+struct SlackSettings {
+    enabled: bool,
+    /// Masked; the URL itself is kept in the keyring.
+    webhook_url: String,
+    #[serde(default)]
+    has_secret: bool,
+    critical: bool,
+    recovery: bool,
+    warning: bool,
+    projected: bool,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+struct TeamsSettings {
+    enabled: bool,
+    /// Masked; the URL itself is kept in the keyring.
+    webhook_url: String,
+    #[serde(default)]
+    has_secret: bool,
+    critical: bool,
+    recovery: bool,
+    warning: bool,
+    projected: bool,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+struct PushoverSettings {
+    enabled: bool,
+    /// Masked; both keys are kept in the keyring.
+    api_token: String,
+    user_key: String,
+    /// True when both the API token and the user key are stored.
+    #[serde(default)]
+    has_secret: bool,
+    critical: bool,
+    recovery: bool,
+    warning: bool,
+    projected: bool,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+struct NtfySettings {
+    enabled: bool,
+    /// Base URL of the ntfy server, e.g. `https://ntfy.sh`.
+    server_url: String,
+    topic: String,
+    /// Masked optional access token; the token itself is kept in the keyring.
+    access_token: String,
+    /// True when an access token is stored.
+    #[serde(default)]
+    has_secret: bool,
+    critical: bool,
+    recovery: bool,
+    warning: bool,
+    projected: bool,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+struct GotifySettings {
+    enabled: bool,
+    /// Base URL of the Gotify server.
+    server_url: String,
+    /// Masked; the application token is kept in the keyring.
+    app_token: String,
+    #[serde(default)]
+    has_secret: bool,
+    critical: bool,
+    recovery: bool,
+    warning: bool,
+    projected: bool,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+struct ExternalNotifySettings {
+    discord: DiscordSettings,
+    slack: SlackSettings,
+    teams: TeamsSettings,
+    pushover: PushoverSettings,
+    ntfy: NtfySettings,
+    gotify: GotifySettings,
+    #[serde(default)]
+    webhooks: Vec<WebhookSettings>,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+struct WebhookHeader {
+    name: String,
+    /// Masked; the value is kept in the keyring.
+    value: String,
+}
+
+/// User-defined webhook. The URL and header values live in the keyring
+/// (`token_store::WebhookSecret`); only their masks are stored here.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+struct WebhookSettings {
+    id: String,
+    name: String,
+    enabled: bool,
+    url: String,
+    #[serde(default)]
+    has_secret: bool,
+    method: String,
+    headers: Vec<WebhookHeader>,
+    /// Body with `{{title}}`-style placeholders; see `external_notify::render_webhook_body`.
+    body_template: String,
+    critical: bool,
+    recovery: bool,
+    warning: bool,
+    projected: bool,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+struct HistorySettings {
+    enabled: bool,
+    retention_days: i32,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+struct MetricsSettings {
+    enabled: bool,
+    bind_address: String,
+    port: i32,
+}
+
+/// Local HTTP MCP endpoint. Always bound to 127.0.0.1.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+struct McpSettings {
+    enabled: bool,
+    port: i32,
+}
+
+/// Switching the account the Claude Code CLI is logged in as.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+struct CliSwitchSettings {
+    /// Move the CLI to the account with the most headroom when the active
+    /// one is exhausted.
+    auto_switch: bool,
+}
+
+/// Background refresh of OAuth tokens, independent of polling.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+struct TokenRefreshSettings {
+    /// Refresh this many minutes before the access token expires.
+    lead_minutes: i32,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+struct Settings {
+    poll_interval: i32,
+    polling_state: PollingState,
+    window_state: WindowState,
+    notify_settings: NotifySettings,
+    usage_export: UsageExportSettings,
+    external_notify: ExternalNotifySettings,
+    history: HistorySettings,
+    metrics: MetricsSettings,
+    mcp: McpSettings,
+    cli_switch: CliSwitchSettings,
+    token_refresh: TokenRefreshSettings,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+struct Store {
+    schema_version: u32,
+    services: Services,
+    settings: Settings,
+}
+
+#[derive(Debug, Clone, Deserialize)]
+#[serde(rename_all = "camelCase")]
+struct AccountEntryRaw {
+    id: Option<String>,
+    name: Option<String>,
+    muted_until: Option<i64>,
+    cli_linked: Option<bool>,
+    cli_synced_at: Option<i64>,
+    priority: Option<i32>,
+    reserve: Option<bool>,
+    relogin_reason: Option<String>,
+}
+
+type ServicesRaw = BTreeMap<String, Option<Vec<AccountEntryRaw>>>;
+
+#[derive(Debug, Clone, Deserialize)]
+#[serde(rename_all = "camelCase")]
+struct PollingStateRaw {
+    active: Option<bool>,
+    started_at: Option<i64>,
+    interval: Option<i32>,
+}
+
+#[derive(Debug, Clone, Deserialize)]
+#[serde(rename_all = "camelCase")]
+struct BoundsRaw {
+    width: Option<i32>,
+    height: Option<i32>,
+}
+
+#[derive(Debug, Clone, Deserialize)]
+#[serde(rename_all = "camelCase")]
+struct WindowStateRaw {
+    mode: Option<String>,
+    normal_bounds: Option<BoundsRaw>,
+    minimal_bounds: Option<BoundsRaw>,
+    minimal_min_width: Option<i32>,
+    minimal_min_height: Option<i32>,
+}
+
+#[derive(Debug, Clone, Deserialize)]
+#[serde(rename_all = "camelCase")]
+struct NotifySettingsRaw {
+    critical: Option<bool>,
+    recovery: Option<bool>,
+    warning: Option<bool>,
+    projected: Option<bool>,
+    threshold_warning: Option<i32>,
+    threshold_critical: Option<i32>,
+    quiet_hours: Option<Vec<QuietHoursRuleRaw>>,
+    hysteresis: Option<i32>,
+    cooldown_minutes: Option<i32>,
+    renotify_minutes: Option<i32>,
+}
+
+#[derive(Debug, Clone, Deserialize)]
+#[serde(rename_all = "camelCase")]
+struct QuietHoursRuleRaw {
+    enabled: Option<bool>,
+    channels: Option<Vec<String>>,
+    days: Option<Vec<String>>,
+    start: Option<String>,
+    end: Option<String>,
+    timezone: Option<String>,
+    allow_exhausted: Option<bool>,
+}
+
+#[derive(Debug, Clone, Deserialize)]
+#[serde(rename_all = "camelCase")]
+struct UsageExportSettingsRaw {
+    enabled: Option<bool>,
+    path: Option<String>,
+}
+
+#[derive(Debug, Clone, Deserialize)]
+#[serde(rename_all = "camelCase")]
+struct DiscordSettingsRaw {
+    enabled: Option<bool>,
+    webhook_url: Option<String>,
+    critical: Option<bool>,
+    recovery: Option<bool>,
+    warning: Option<bool>,
+    projected: Option<bool>,
+}
+
+#[derive(Debug, Clone, Deserialize)]
+#[serde(rename_all = "camelCase")]
+struct SlackSettingsRaw {
+    enabled: Option<bool>,
+    webhook_url: Option<String>,
+    critical: Option<bool>,
+    recovery: Option<bool>,
+    warning: Option<bool>,
+    projected: Option<bool>,
+}
+
+#[derive(Debug, Clone, Deserialize)]
+#[serde(rename_all = "camelCase")]
+struct TeamsSettingsRaw {
+    enabled: Option<bool>,
+    webhook_url: Option<String>,
+    critical: Option<bool>,
+    recovery: Option<bool>,
+    warning: Option<bool>,
+    projected: Option<bool>,
+}
+
+#[derive(Debug, Clone, Deserialize)]
+#[serde(rename_all = "camelCase")]
+struct PushoverSettingsRaw {
+    enabled: Option<bool>,
+    api_token: Option<String>,
+    user_key: Option<String>,
+    critical: Option<bool>,
+    recovery: Option<bool>,
+    warning: Option<bool>,
+    projected: Option<bool>,
+}
+
+#[derive(Debug, Clone, Deserialize)]
+#[serde(rename_all = "camelCase")]
+struct NtfySettingsRaw {
+    enabled: Option<bool>,
+    server_url: Option<String>,
+    topic: Option<String>,
+    access_token: Option<String>,
+    critical: Option<bool>,
+    recovery: Option<bool>,
+    warning: Option<bool>,
+    projected: Option<bool>,
+}
+
+#[derive(Debug, Clone, Deserialize)]
+#[serde(rename_all = "camelCase")]
+struct GotifySettingsRaw {
+    enabled: Option<bool>,
+    server_url: Option<String>,
+    app_token: Option<String>,
+    critical: Option<bool>,
+    recovery: Option<bool>,
+    warning: Option<bool>,
+    projected: Option<bool>,
+}
+
+#[derive(Debug, Clone, Deserialize)]
+#[serde(rename_all = "camelCase")]
+struct ExternalNotifySettingsRaw {
+    discord: Option<DiscordSettingsRaw>,
+    slack: Option<SlackSettingsRaw>,
+    teams: Option<TeamsSettingsRaw>,
+    pushover: Option<PushoverSettingsRaw>,
+    ntfy: Option<NtfySettingsRaw>,
+    gotify: Option<GotifySettingsRaw>,
+    webhooks: Option<Vec<WebhookSettingsRaw>>,
+}
+
+#[derive(Debug, Clone, Deserialize)]
+#[serde(rename_all = "camelCase")]
+struct WebhookHeaderRaw {
+    name: Option<String>,
+    value: Option<String>,
+}
+
+#[derive(Debug, Clone, Deserialize)]
+#[serde(rename_all = "camelCase")]
+struct WebhookSettingsRaw {
+    id: Option<String>,
+    name: Option<String>,
+    enabled: Option<bool>,
+    url: Option<String>,
+    method: Option<String>,
+    headers: Option<Vec<WebhookHeaderRaw>>,
+    body_template: Option<String>,
+    critical: Option<bool>,
+    recovery: Option<bool>,
+    warning: Option<bool>,
+    projected: Option<bool>,
+}
+
+#[derive(Debug, Clone, Deserialize)]
+#[serde(rename_all = "camelCase")]
+struct HistorySettingsRaw {
+    enabled: Option<bool>,
+    retention_days: Option<i32>,
+}
+
+#[derive(Debug, Clone, Deserialize)]
+#[serde(rename_all = "camelCase")]
+struct MetricsSettingsRaw {
+    enabled: Option<bool>,
+    bind_address: Option<String>,
+    port: Option<i32>,
+}
+
+#[derive(Debug, Clone, Deserialize)]
+#[serde(rename_all = "camelCase")]
+struct McpSettingsRaw {
+    enabled: Option<bool>,
+    port: Option<i32>,
+}
+
+#[derive(Debug, Clone, Deserialize)]
+#[serde(rename_all = "camelCase")]
+struct CliSwitchSettingsRaw {
+    auto_switch: Option<bool>,
+}
+
+#[derive(Debug, Clone, Deserialize)]
+#[serde(rename_all = "camelCase")]
+struct TokenRefreshSettingsRaw {
+    lead_minutes: Option<i32>,
+}
+
+#[derive(Debug, Clone, Deserialize)]
+#[serde(rename_all = "camelCase")]
+struct SettingsRaw {
+    poll_interval: Option<i32>,
+    polling_state: Option<PollingStateRaw>,
+    window_state: Option<WindowStateRaw>,
+    notify_settings: Option<NotifySettingsRaw>,
+    usage_export: Option<UsageExportSettingsRaw>,
+    external_notify: Option<ExternalNotifySettingsRaw>,
+    history: Option<HistorySettingsRaw>,
+    metrics: Option<MetricsSettingsRaw>,
+    mcp: Option<McpSettingsRaw>,
+    cli_switch: Option<CliSwitchSettingsRaw>,
+    token_refresh: Option<TokenRefreshSettingsRaw>,
+}
+
+#[derive(Debug, Clone, Deserialize)]
+struct StoreRaw {
+    services: Option<ServicesRaw>,
+    settings: Option<SettingsRaw>,
+}
+
+#[derive(Debug, Clone, Serialize)]
+#[serde(rename_all = "camelCase")]
+struct AccountSnapshotEntry {
+    id: String,
+    name: String,
+    has_token: bool,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    muted_until: Option<i64>,
+    cli_linked: bool,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    cli_synced_at: Option<i64>,
+    priority: i32,
+    reserve: bool,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    relogin_reason: Option<String>,
+}
+
+#[derive(Debug, Clone, Serialize)]
+struct AccountsSnapshot {
+    #[serde(flatten)]
+    services: BTreeMap<String, Vec<AccountSnapshotEntry>>,
+    settings: Settings,
+}
+
+#[derive(Debug, Clone, Serialize)]
+#[serde(rename_all = "camelCase")]
+struct ApiOk {
+    ok: bool,
+}
+
+#[derive(Debug, Clone, Deserialize)]
+#[serde(rename_all = "camelCase")]
+struct SaveAccountPayload {
+    service: Option<String>,
+    id: Option<String>,
+    name: Option<String>,
+    token: Option<String>,
+    clear_token: Option<bool>,
+}
+
+#[derive(Debug, Clone, Deserialize)]
+#[serde(rename_all = "camelCase")]
+struct DeleteAccountPayload {
+    service: Option<String>,
+    id: Option<String>,
+}
+
+#[derive(Debug, Clone, Deserialize)]
+#[serde(rename_all = "camelCase")]
+struct SetAccountMutePayload {
+    service: Option<String>,
+    id: Option<String>,
+    /// Epoch milliseconds; None or a past time unmutes.
+    muted_until: Option<i64>,
+}
+
+#[derive(Debug, Clone, Deserialize)]
+#[serde(rename_all = "camelCase")]
+struct SetAccountPreferencePayload {
+    service: Option<String>,
+    id: Option<String>,
+    priority: Option<i32>,
+    reserve: Option<bool>,
+}
+
+#[derive(Debug, Clone, Deserialize)]
+#[serde(rename_all = "camelCase")]
+struct SetAccountCliLinkPayload {
+    service: Option<String>,
+    id: Option<String>,
+    linked: Option<bool>,
+}
+
+#[derive(Debug, Clone, Deserialize)]
+#[serde(rename_all = "camelCase")]
+struct NotifySettingsPatch {
+    critical: Option<bool>,
+    recovery: Option<bool>,
+    warning: Option<bool>,
+    projected: Option<bool>,
+    threshold_warning: Option<i32>,
+    threshold_critical: Option<i32>,
+    /// Replaces the whole list; rules are validated like stored ones.
+    quiet_hours: Option<Vec<QuietHoursRuleRaw>>,
+    hysteresis: Option<i32>,
+    cooldown_minutes: Option<i32>,
+    renotify_minutes: Option<i32>,
+}
+
+#[derive(Debug, Clone, Deserialize)]
+#[serde(rename_all = "camelCase")]
+struct UsageExportSettingsPatch {
+    enabled: Option<bool>,
+    path: Option<String>,
+}
+
+#[derive(Debug, Clone, Deserialize)]
+#[serde(rename_all = "camelCase")]
+struct DiscordSettingsPatch {
+    enabled: Option<bool>,
+    webhook_url: Option<String>,
+    critical: Option<bool>,
+    recovery: Option<bool>,
+    warning: Option<bool>,
+    projected: Option<bool>,
+}
+
+#[derive(Debug, Clone, Deserialize)]
+#[serde(rename_all = "camelCase")]
+struct SlackSettingsPatch {
+    enabled: Option<bool>,
+    webhook_url: Option<String>,
+    critical: Option<bool>,
+    recovery: Option<bool>,
+    warning: Option<bool>,
+    projected: Option<bool>,
+}
+
+#[derive(Debug, Clone, Deserialize)]
+#[serde(rename_all = "camelCase")]
+struct TeamsSettingsPatch {
+    enabled: Option<bool>,
+    webhook_url: Option<String>,
+    critical: Option<bool>,
+    recovery: Option<bool>,
+    warning: Option<bool>,
+    projected: Option<bool>,
+}
+
+#[derive(Debug, Clone, Deserialize)]
+#[serde(rename_all = "camelCase")]
+struct PushoverSettingsPatch {
+    enabled: Option<bool>,
+    api_token: Option<String>,
+    user_key: Option<String>,
+    critical: Option<bool>,
+    recovery: Option<bool>,
+    warning: Option<bool>,
+    projected: Option<bool>,
+}
+
+#[derive(Debug, Clone, Deserialize)]
+#[serde(rename_all = "camelCase")]
+struct NtfySettingsPatch {
+    enabled: Option<bool>,
+    server_url: Option<String>,
+    topic: Option<String>,
+    /// Plaintext to replace, masked to keep, empty to clear.
+    access_token: Option<String>,
+    critical: Option<bool>,
+    recovery: Option<bool>,
+    warning: Option<bool>,
+    projected: Option<bool>,
+}
+
+#[derive(Debug, Clone, Deserialize)]
+#[serde(rename_all = "camelCase")]
+struct GotifySettingsPatch {
+    enabled: Option<bool>,
+    server_url: Option<String>,
+    /// Plaintext to replace, masked to keep, empty to clear.
+    app_token: Option<String>,
+    critical: Option<bool>,
+    recovery: Option<bool>,
+    warning: Option<bool>,
+    projected: Option<bool>,
+}
+
+#[derive(Debug, Clone, Deserialize)]
+#[serde(rename_all = "camelCase")]
+struct ExternalNotifySettingsPatch {
+    discord: Option<DiscordSettingsPatch>,
+    slack: Option<SlackSettingsPatch>,
+    teams: Option<TeamsSettingsPatch>,
+    pushover: Option<PushoverSettingsPatch>,
+    ntfy: Option<NtfySettingsPatch>,
+    gotify: Option<GotifySettingsPatch>,
+    /// Replaces the whole list; webhooks left out are deleted.
+    webhooks: Option<Vec<WebhookSettingsPatch>>,
+}
+
+#[derive(Debug, Clone, Deserialize)]
+#[serde(rename_all = "camelCase")]
+struct WebhookHeaderPatch {
+    name: String,
+    /// Plaintext to replace, masked to keep the stored value.
+    value: String,
+}
+
+#[derive(Debug, Clone, Deserialize)]
+#[serde(rename_all = "camelCase")]
+struct WebhookSettingsPatch {
+    /// Missing for a new webhook; one is generated.
+    id: Option<String>,
+    name: Option<String>,
+    enabled: Option<bool>,
+    /// Plaintext to replace, masked to keep, empty to clear.
+    url: Option<String>,
+    method: Option<String>,
+    headers: Option<Vec<WebhookHeaderPatch>>,
+    body_template: Option<String>,
+    critical: Option<bool>,
+    recovery: Option<bool>,
+    warning: Option<bool>,
+    projected: Option<bool>,
+}
+
+#[derive(Debug, Clone, Deserialize)]
+#[serde(rename_all = "camelCase")]
+struct HistorySettingsPatch {
+    enabled: Option<bool>,
+    retention_days: Option<i32>,
+}
+
+#[derive(Debug, Clone, Deserialize)]
+#[serde(rename_all = "camelCase")]
+struct MetricsSettingsPatch {
+    enabled: Option<bool>,
+    bind_address: Option<String>,
+    port: Option<i32>,
+}
+
+#[derive(Debug, Clone, Deserialize)]
+#[serde(rename_all = "camelCase")]
+struct McpSettingsPatch {
+    enabled: Option<bool>,
+    port: Option<i32>,
+}
+
+#[derive(Debug, Clone, Deserialize)]
+#[serde(rename_all = "camelCase")]
+struct CliSwitchSettingsPatch {
+    auto_switch: Option<bool>,
+}
+
+#[derive(Debug, Clone, Deserialize)]
+#[serde(rename_all = "camelCase")]
+struct TokenRefreshSettingsPatch {
+    lead_minutes: Option<i32>,
+}
+
+#[derive(Debug, Clone, Deserialize)]
+#[serde(rename_all = "camelCase")]
+struct SetSettingsPayload {
+    poll_interval: Option<i32>,
+    notify_settings: Option<NotifySettingsPatch>,
+    usage_export: Option<UsageExportSettingsPatch>,
+    external_notify: Option<ExternalNotifySettingsPatch>,
+    history: Option<HistorySettingsPatch>,
+    metrics: Option<MetricsSettingsPatch>,
+    mcp: Option<McpSettingsPatch>,
+    cli_switch: Option<CliSwitchSettingsPatch>,
+    token_refresh: Option<TokenRefreshSettingsPatch>,
+}
+
+#[derive(Debug, Clone, Deserialize)]
+#[serde(rename_all = "camelCase")]
+struct SetPollingStatePayload {
+    active: Option<bool>,
+    started_at: Option<i64>,
+    interval: Option<i32>,
+}
+
+#[derive(Debug, Clone, Deserialize)]
+#[serde(rename_all = "camelCase")]
+struct FetchUsagePayload {
+    service: Option<String>,
+    id: Option<String>,
+    name: Option<String>,
+    token: Option<String>,
+}
+
+#[derive(Debug, Clone, Deserialize)]
+#[serde(rename_all = "camelCase")]
+struct QueryUsageHistoryPayload {
+    service: Option<String>,
+    id: Option<String>,
+    window: Option<String>,
+    from: Option<i64>,
+    to: Option<i64>,
+    bucket_secs: Option<i64>,
+    max_points: Option<usize>,
+}
+
+#[derive(Debug, Clone, Deserialize)]
+#[serde(rename_all = "camelCase")]
+struct SetWindowModePayload {
+    mode: Option<String>,
+    min_width: Option<i32>,
+    min_height: Option<i32>,
+    preferred_width: Option<i32>,
+    preferred_height: Option<i32>,
+}
+
+#[derive(Debug, Clone, Deserialize)]
+#[serde(rename_all = "camelCase")]
+struct SetWindowPositionPayload {
+    x: Option<i32>,
+    y: Option<i32>,
+    width: Option<i32>,
+    height: Option<i32>,
+}
+
+#[derive(Debug, Clone, Deserialize)]
+#[serde(rename_all = "camelCase")]
+struct ResizeWindowPayload {
+    width: Option<i32>,
+    height: Option<i32>,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+struct UsageSnapshotEntry {
+    service: String,
+    id: String,
+    name: String,
+    has_token: bool,
+    label: Option<String>,
+    status: Option<String>,
+    windows: Vec<usage_parser::UsageWindow>,
+    error: Option<String>,
+}
+
+#[derive(Debug, Clone, Deserialize)]
+#[serde(rename_all = "camelCase")]
+struct WriteUsageSnapshotPayload {
+    fetched_at: Option<String>,
+    entries: Option<Vec<UsageSnapshotEntry>>,
+}
+
+#[derive(Debug, Clone, Deserialize)]
+#[serde(rename_all = "camelCase")]
+struct SendNotificationPayload {
+    title: Option<String>,
+    body: Option<String>,
+}
+
+#[derive(Debug, Clone, Deserialize)]
+#[serde(rename_all = "camelCase")]
+struct SendExternalNotificationPayload {
+    title: Option<String>,
+    body: Option<String>,
+    level: Option<String>,
+    channel: Option<String>,
+}
+
+#[derive(Debug, Clone, Copy, Serialize)]
+#[serde(rename_all = "camelCase")]
+enum StoreLoadIssueKind {
+    /// accounts.json was unreadable and moved aside; the app starts from defaults.
+    Quarantined,
+    /// accounts.json was saved by a newer version; it is kept and not written.
+    NewerSchema,
+}
+
+#[derive(Debug, Clone, Serialize)]
+#[serde(rename_all = "camelCase")]
+struct StoreLoadIssue {
+    kind: StoreLoadIssueKind,
+    message: String,
+    /// Where the unreadable accounts.json was moved to.
+    backup_path: Option<String>,
+    detected_at: i64,
+}
+
+#[derive(Debug, Clone, Serialize)]
+#[serde(rename_all = "camelCase")]
+struct ExternalNotifyResult {
+    ok: bool,
+    errors: Vec<String>,
+}
+
+#[derive(Debug, Clone, Deserialize)]
+#[serde(rename_all = "camelCase")]
+struct OAuthLoginPayload {
+    service: Option<String>,
+    id: Option<String>,
+}
+
+#[derive(Debug, Clone, Deserialize)]
+#[serde(rename_all = "camelCase")]
+struct ImportCliPayload {
+    service: Option<String>,
+    id: Option<String>,
+    /// `accessOnly` or `takeOver`; there is no default on purpose.
+    mode: Option<String>,
+}
+
+#[derive(Debug, Clone, Deserialize)]
+#[serde(rename_all = "camelCase")]
+struct OAuthExchangeCodePayload {
+    service: Option<String>,
+    id: Option<String>,
+    code: Option<String>,
+}
+
+fn sanitize_string(input: Option<&str>, fallback: &str) -> String {
+    let v = input.unwrap_or("").trim();
+    if v.is_empty() {
+        fallback.to_string()
+    } else {
+        v.to_string()
+    }
+}
+
+pub fn run() {
+    tauri::Builder::default()
+        .plugin(tauri_plugin_notification::init())
+        .plugin(tauri_plugin_shell::init())
+        .setup(|app| {
+            let handle = app.handle().clone();
+            let store = match update_store(&handle, |store| {
+                // Keyring failures leave the plaintext values for the next start.
+                let _ = migrate_notify_secrets(store);
+                Ok(store.clone())
+            }) {
+                Ok(store) => store,
+                // A store from a newer version is read-only; start without migrating.
+                Err(_) => read_store(&handle)?,
+            };
+
+            if let Some(webview_window) = app.get_webview_window("main") {
+                apply_window_mode(&webview_window, &store.settings.window_state)?;
+            }
+
+            let _ = history_store::prune(&handle);
+            settings_commands::start_listeners(&handle);
+            account_commands::start_relogin_watch(handle.clone());
+            cli_sync::start(handle.clone());
+            token_scheduler::start(handle.clone());
+            poll_scheduler::start(handle);
+
+            Ok(())
+        })
+        .invoke_handler(tauri::generate_handler![
+            commands::list_accounts,
+            commands::save_account,
+            commands::delete_account,
+            commands::set_account_mute,
+            commands::set_account_cli_link,
+            commands::set_account_preference,
+            commands::get_recommendations,
+            commands::get_settings,
+            commands::set_settings,
+            commands::write_usage_snapshot,
+            commands::get_polling_state,
+            commands::set_polling_state,
+            commands::fetch_usage,
+            commands::poll_now,
+            commands::get_latest_usage,
+            commands::query_usage_history,
+            commands::get_store_load_issue,
+            commands::get_listener_errors,
+            commands::get_window_state,
+            commands::set_window_mode,
+            commands::set_window_position,
+            commands::start_window_drag,
+            commands::resize_window_keep_top_left,
+            commands::get_version,
+            commands::quit_app,
+            commands::send_notification,
+            commands::send_external_notification,
+            commands::oauth_login,
+            commands::cancel_oauth_login,
+            commands::import_cli_credentials,
+            commands::activate_cli_account,
+            commands::refresh_token,
+            commands::get_token_status,
+            commands::oauth_exchange_code,
+        ])
+        .run(tauri::generate_context!())
+        .expect("error while running tauri application");
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::time::{SystemTime, UNIX_EPOCH};
+
+    #[test]
+    fn validate_token_rejects_control_chars() {
+        assert!(validation::validate_token("abc\ndef").is_err());
+    }
+
+    #[test]
+    fn validate_token_accepts_common_jwt_format() {
+        assert!(validation::validate_token("eyJhbGciOiJIUzI1NiIsInR5cCI6IkpXVCJ9.abc_xyz-123/456").is_ok());
+    }
+
+    #[test]
+    fn validate_token_rejects_excessive_length() {
+        let too_long = "a".repeat(16385);
+        assert!(validation::validate_token(&too_long).is_err());
+    }
+
+    #[test]
+    fn validate_account_id_rejects_unsupported_characters() {
+        assert!(validation::validate_account_id("abc:def").is_err());
+    }
+
+    #[test]
+    fn validate_upstream_url_requires_https_and_allowlisted_host() {
+        assert!(validation::validate_upstream_url("https://api.anthropic.com/api/oauth/usage").is_ok());
+        assert!(validation::validate_upstream_url("https://chatgpt.com/backend-api/wham/usage").is_ok());
+        assert!(validation::validate_upstream_url("http://api.anthropic.com/api/oauth/usage").is_err());
+        assert!(validation::validate_upstream_url("https://example.com").is_err());
+    }
+
+    #[test]
+    fn build_error_returns_sanitized_message() {
+        assert_eq!(
+            api_client::build_error_message(401, "application/json", None),
+            "Authentication failed (HTTP 401)"
+        );
+    }
+
+    #[test]
+    fn rate_limit_blocks_immediate_repeat() {
+        let unique_token = format!(
+            "t{}",
+            SystemTime::now()
+                .duration_since(UNIX_EPOCH)
+                .expect("clock drift")
+                .as_nanos()
+        );
+        assert!(validation::enforce_fetch_usage_rate_limit("claude", &unique_token).is_ok());
+        assert!(validation::enforce_fetch_usage_rate_limit("claude", &unique_token).is_err());
+    }
+}
@@ -0,0 +1,272 @@
+//! Model Context Protocol (JSON-RPC 2.0) handling shared by the stdio front end
+//! (`aiqm mcp`) and the app's local HTTP endpoint (`mcp_server.rs`).
+
+use crate::usage_parser::UsageWindow;
+use serde_json::{json, Value};
+
+pub const PROTOCOL_VERSION: &str = "2025-06-18";
+const SUPPORTED_VERSIONS: [&str; 3] = ["2025-06-18", "2025-03-26", "2024-11-05"];
+
+const PARSE_ERROR: i64 = -32700;
+const INVALID_REQUEST: i64 = -32600;
+const METHOD_NOT_FOUND: i64 = -32601;
+const INVALID_PARAMS: i64 = -32602;
+
+/// True for `tools/call`, the only method that needs usage data. Front ends
+/// load entries (cache or live fetch) only then.
+pub fn is_tool_call(message: &Value) -> bool {
+    message.get("method").and_then(Value::as_str) == Some("tools/call")
+}
+
+/// Parse one JSON-RPC message; a parse error is returned as the response to send.
+pub fn parse_message(text: &str) -> Result<Value, Value> {
+    serde_json::from_str(text).map_err(|e| error_response(Value::Null, PARSE_ERROR, &format!("Parse error: {e}")))
+}
+
+/// Handle one JSON-RPC message. Returns None for notifications and responses,
+/// which get no reply.
+pub fn handle_message(message: &Value, entries: &[crate::UsageSnapshotEntry], now_ms: i64) -> Option<Value> {
+    let Some(method) = message.get("method").and_then(Value::as_str) else {
+        if message.get("id").is_some() && (message.get("result").is_some() || message.get("error").is_some()) {
+            return None;
+        }
+        return Some(error_response(
+            message.get("id").cloned().unwrap_or(Value::Null),
+            INVALID_REQUEST,
+            "Invalid request",
+        ));
+    };
+    let id = message.get("id").cloned()?;
+    let params = message.get("params").cloned().unwrap_or(Value::Null);
+
+    let result = match method {
+        "initialize" => Ok(initialize(&params)),
+        "ping" => Ok(json!({})),
+        "tools/list" => Ok(json!({ "tools": tool_definitions() })),
+        "tools/call" => call_tool(&params, entries, now_ms),
+        _ => Err((METHOD_NOT_FOUND, format!("Method not found: {method}"))),
+    };
+    Some(match result {
+        Ok(result) => json!({ "jsonrpc": "2.0", "id": id, "result": result }),
+        Err((code, message)) => error_response(id, code, &message),
+    })
+}
+
+fn error_response(id: Value, code: i64, message: &str) -> Value {
+    json!({ "jsonrpc": "2.0", "id": id, "error": { "code": code, "message": message } })
+}
+
+fn initialize(params: &Value) -> Value {
+    let requested = params.get("protocolVersion").and_then(Value::as_str);
+    let version = requested
+        .filter(|v| SUPPORTED_VERSIONS.contains(v))
+        .unwrap_or(PROTOCOL_VERSION);
+    json!({
+        "protocolVersion": version,
+        "capabilities": { "tools": {} },
+        "serverInfo": { "name": "aiqm", "title": crate::APP_NAME, "version": env!("CARGO_PKG_VERSION") },
+        "instructions": "Quota usage of the Claude Code / Codex accounts registered in AI Quota Monitor. \
+Check it before starting long tasks, or to pick the least-used account."
+    })
+}
+
+fn tool_definitions() -> Value {
+    let services: Vec<&str> = crate::providers::all().iter().map(|p| p.id()).collect();
+    let filter = json!({
+        "type": "object",
+        "properties": {
+            "service": { "type": "string", "enum": services, "description": "Only this service" },
+            "account": { "type": "string", "description": "Only this account id" }
+        }
+    });
+    let mut with_window = filter.clone();
+    with_window["properties"]["window"] = json!({ "type": "string", "description": "Only this window, e.g. 5時間" });
+    json!([
+        {
+            "name": "list_accounts",
+            "description": "List monitored accounts with their overall quota status.",
+            "inputSchema": filter,
+            "annotations": { "readOnlyHint": true }
+        },
+        {
+            "name": "get_quota_status",
+            "description": "Utilization (%), status, reset time and projected exhaustion of each quota window.",
+            "inputSchema": filter,
+            "annotations": { "readOnlyHint": true }
+        },
+        {
+            "name": "time_until_reset",
+            "description": "Seconds until each quota window resets.",
+            "inputSchema": with_window,
+            "annotations": { "readOnlyHint": true }
+        }
+    ])
+}
+
+fn call_tool(params: &Value, entries: &[crate::UsageSnapshotEntry], now_ms: i64) -> Result<Value, (i64, String)> {
+    let name = params
+        .get("name")
+        .and_then(Value::as_str)
+        .ok_or((INVALID_PARAMS, "Missing tool name".to_string()))?;
+    let args = params.get("arguments").cloned().unwrap_or_else(|| json!({}));
+    let arg = |key: &str| args.get(key).and_then(Value::as_str).map(str::to_string);
+    let selected: Vec<_> = entries
+        .iter()
+        .filter(|e| arg("service").is_none_or(|s| s == e.service))
+        .filter(|e| arg("account").is_none_or(|a| a == e.id))
+        .collect();
+
+    let output = match name {
+        "list_accounts" => json!({
+            "accounts": selected.iter().map(|e| json!({
+                "service": e.service,
+                "id": e.id,
+                "name": e.name,
+                "status": e.status,
+                "error": e.error,
+            })).collect::<Vec<_>>()
+        }),
+        "get_quota_status" => json!({
+            "accounts": selected.iter().map(|e| json!({
+                "service": e.service,
+                "id": e.id,
+                "name": e.name,
+                "status": e.status,
+                "error": e.error,
+                "windows": e.windows.iter().map(window_json).collect::<Vec<_>>(),
+            })).collect::<Vec<_>>()
+        }),
+        "time_until_reset" => {
+            let window = arg("window");
+            let mut resets = Vec::new();
+            for entry in &selected {
+                for w in entry.windows.iter().filter(|w| window.as_deref().is_none_or(|n| n == w.name)) {
+                    let Some(at) = w.resets_at_millis() else {
+                        continue;
+                    };
+                    resets.push(json!({
+                        "service": entry.service,
+                        "id": entry.id,
+                        "window": w.name,
+                        "resetsAt": iso(at),
+                        "secondsUntilReset": ((at - now_ms) / 1000).max(0),
+                    }));
+                }
+            }
+            json!({ "resets": resets })
+        }
+        other => return Err((INVALID_PARAMS, format!("Unknown tool: {other}"))),
+    };
+
+    let is_error = selected.is_empty();
+    let text = if is_error {
+        "No usage data for the selected accounts. Is AI Quota Monitor polling?".to_string()
+    } else {
+        serde_json::to_string_pretty(&output).unwrap_or_default()
+    };
+    Ok(json!({
+        "content": [{ "type": "text", "text": text }],
+        "structuredContent": output,
+        "isError": is_error,
+    }))
+}
+
+fn window_json(w: &UsageWindow) -> Value {
+    let forecast = w.forecast.as_ref();
+    json!({
+        "name": w.name,
+        "utilization": w.utilization,
+        "status": w.status,
+        "resetsAt": w.resets_at_millis().map(iso),
+        "projectedExhaustionAt": forecast.and_then(|f| f.exhausted_at).map(iso),
+        "exhaustsBeforeReset": forecast.is_some_and(|f| f.before_reset),
+    })
+}
+
+fn iso(ms: i64) -> Option<String> {
+    chrono::DateTime::from_timestamp_millis(ms).map(|at| at.to_rfc3339())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::{handle_message, is_tool_call, parse_message, PROTOCOL_VERSION};
+    use crate::usage_parser::UsageWindow;
+    use serde_json::json;
+
+    fn entries() -> Vec<crate::UsageSnapshotEntry> {
+        vec![crate::UsageSnapshotEntry {
+            service: "claude".to_string(),
+            id: "main".to_string(),
+            name: "Main".to_string(),
+            has_token: true,
+            label: None,
+            status: Some("warning".to_string()),
+            windows: vec![UsageWindow::new(
+                "5時間".to_string(),
+                80.0,
+                Some(json!(1_700_003_600)),
+                Some(18000.0),
+                false,
+                Some("warning".to_string()),
+            )],
+            error: None,
+        }]
+    }
+
+    fn call(name: &str, arguments: serde_json::Value) -> serde_json::Value {
+        let request = json!({
+            "jsonrpc": "2.0", "id": 7, "method": "tools/call",
+            "params": { "name": name, "arguments": arguments }
+        });
+        assert!(is_tool_call(&request));
+        handle_message(&request, &entries(), 1_700_000_000_000).expect("response")
+    }
+
+    #[test]
+    fn initialize_negotiates_version_and_lists_tools() {
+        let init = json!({ "jsonrpc": "2.0", "id": 1, "method": "initialize",
+            "params": { "protocolVersion": "2024-11-05", "capabilities": {} } });
+        let response = handle_message(&init, &[], 0).expect("response");
+        assert_eq!(response["result"]["protocolVersion"], "2024-11-05");
+        assert!(response["result"]["capabilities"]["tools"].is_object());
+
+        let future = json!({ "jsonrpc": "2.0", "id": 2, "method": "initialize",
+            "params": { "protocolVersion": "2099-01-01" } });
+        assert_eq!(handle_message(&future, &[], 0).unwrap()["result"]["protocolVersion"], PROTOCOL_VERSION);
+
+        let list = json!({ "jsonrpc": "2.0", "id": 3, "method": "tools/list" });
+        let tools = handle_message(&list, &[], 0).unwrap()["result"]["tools"].clone();
+        let names: Vec<_> = tools.as_array().unwrap().iter().map(|t| t["name"].clone()).collect();
+        assert_eq!(names, vec!["list_accounts", "get_quota_status", "time_until_reset"]);
+    }
+
+    #[test]
+    fn tools_report_usage_and_reset_time() {
+        let status = call("get_quota_status", json!({ "service": "claude" }));
+        let account = &status["result"]["structuredContent"]["accounts"][0];
+        assert_eq!(account["windows"][0]["utilization"], 80.0);
+        assert_eq!(account["windows"][0]["resetsAt"], "2023-11-14T23:13:20+00:00");
+        assert_eq!(status["result"]["isError"], false);
+
+        let reset = call("time_until_reset", json!({ "window": "5時間" }));
+        assert_eq!(reset["result"]["structuredContent"]["resets"][0]["secondsUntilReset"], 3600);
+
+        let none = call("list_accounts", json!({ "service": "codex" }));
+        assert_eq!(none["result"]["isError"], true);
+
+        let unknown = call("delete_everything", json!({}));
+        assert_eq!(unknown["error"]["code"], -32602);
+    }
+
+    #[test]
+    fn notifications_get_no_reply_and_errors_are_reported() {
+        let note = json!({ "jsonrpc": "2.0", "method": "notifications/initialized" });
+        assert!(handle_message(&note, &[], 0).is_none());
+
+        let unknown = json!({ "jsonrpc": "2.0", "id": "x", "method": "resources/list" });
+        assert_eq!(handle_message(&unknown, &[], 0).unwrap()["error"]["code"], -32601);
+
+        let parse_error = parse_message("{not json").unwrap_err();
+        assert_eq!(parse_error["error"]["code"], -32700);
+    }
+}
@@ -0,0 +1,261 @@
+use crate::error::{AppError, AppResult};
+use crate::mcp;
+use crate::store_repo::read_store;
+use crate::usage_state;
+use std::net::{IpAddr, Ipv4Addr, SocketAddr};
+use std::sync::{Mutex, OnceLock};
+use std::time::Duration;
+use tauri::async_runtime::JoinHandle;
+use tauri::AppHandle;
+use tokio::io::{AsyncReadExt, AsyncWriteExt};
+use tokio::net::{TcpListener, TcpStream};
+
+const ENDPOINT: &str = "/mcp";
+const MAX_REQUEST_BYTES: usize = 64 * 1024;
+const REQUEST_TIMEOUT_SECS: u64 = 5;
+/// Back-off after a failed accept (e.g. out of file descriptors).
+const ACCEPT_RETRY: Duration = Duration::from_millis(100);
+
+struct RunningServer {
+    port: u16,
+    task: JoinHandle<()>,
+}
+
+static SERVER: OnceLock<Mutex<Option<RunningServer>>> = OnceLock::new();
+
+fn server() -> &'static Mutex<Option<RunningServer>> {
+    SERVER.get_or_init(|| Mutex::new(None))
+}
+
+/// Start, stop or rebind the loopback MCP endpoint to match `settings.mcp`.
+pub fn apply(app: &AppHandle) -> AppResult<()> {
+    let settings = read_store(app)?.settings.mcp;
+    let wanted = if settings.enabled {
+        Some(
+            u16::try_from(settings.port)
+                .map_err(|_| AppError::InvalidInput("MCP port is out of range".to_string()))?,
+        )
+    } else {
+        None
+    };
+
+    let mut lock = server()
+        .lock()
+        .map_err(|_| AppError::Message("MCP server lock is poisoned".to_string()))?;
+    if lock.as_ref().map(|s| s.port) == wanted {
+        return Ok(());
+    }
+    if let Some(running) = lock.take() {
+        running.task.abort();
+    }
+    let Some(port) = wanted else {
+        return Ok(());
+    };
+
+    // Loopback only: the endpoint has no authentication.
+    let addr = SocketAddr::new(IpAddr::V4(Ipv4Addr::LOCALHOST), port);
+    let listener = std::net::TcpListener::bind(addr)
+        .map_err(|e| AppError::Message(format!("Failed to bind MCP listener on {addr}: {e}")))?;
+    listener
+        .set_nonblocking(true)
+        .map_err(|e| AppError::Message(format!("Failed to configure MCP listener: {e}")))?;
+    let task = tauri::async_runtime::spawn(serve(app.clone(), listener));
+    *lock = Some(RunningServer { port, task });
+    Ok(())
+}
+
+async fn serve(app: AppHandle, listener: std::net::TcpListener) {
+    let Ok(listener) = TcpListener::from_std(listener) else {
+        return;
+    };
+    loop {
+        let stream = match listener.accept().await {
+            Ok((stream, _)) => stream,
+            Err(e) => {
+                eprintln!("MCP listener: accept failed: {e}");
+                tokio::time::sleep(ACCEPT_RETRY).await;
+                continue;
+            }
+        };
+        let app = app.clone();
+        tauri::async_runtime::spawn(async move {
+            let _ = tokio::time::timeout(
+                Duration::from_secs(REQUEST_TIMEOUT_SECS),
+                handle_connection(&app, stream),
+            )
+            .await;
+        });
+    }
+}
+
+/// Configured accounts with their latest poll result, or no data yet.
+fn current_entries(app: &AppHandle) -> Vec<crate::UsageSnapshotEntry> {
+    let Ok(store) = read_store(app) else {
+        return Vec::new();
+    };
+    let mut entries = Vec::new();
+    for provider in crate::providers::all() {
+        let service = provider.id();
+        for account in store.services.get(service).into_iter().flatten() {
+            let entry = match usage_state::get(service, &account.id) {
+                Some(update) => update.to_snapshot_entry(),
+                None => crate::UsageSnapshotEntry {
+                    service: service.to_string(),
+                    id: account.id.clone(),
+                    name: account.name.clone(),
+                    has_token: crate::token_store::get_token(service, &account.id).is_some(),
+                    label: Some(account.name.clone()),
+                    status: None,
+                    windows: Vec::new(),
+                    error: None,
+                },
+            };
+            entries.push(entry);
+        }
+    }
+    entries
+}
+
+struct Request {
+    method: String,
+    path: String,
+    origin: Option<String>,
+    body: Vec<u8>,
+}
+
+async fn read_request(stream: &mut TcpStream) -> std::io::Result<Option<Request>> {
+    let mut buf = Vec::with_capacity(4096);
+    let mut chunk = [0u8; 4096];
+    let head_end = loop {
+        if let Some(pos) = buf.windows(4).position(|w| w == b"\r\n\r\n") {
+            break pos + 4;
+        }
+        if buf.len() >= MAX_REQUEST_BYTES {
+            return Ok(None);
+        }
+        let n = stream.read(&mut chunk).await?;
+        if n == 0 {
+            return Ok(None);
+        }
+        buf.extend_from_slice(&chunk[..n]);
+    };
+
+    let head = String::from_utf8_lossy(&buf[..head_end]).to_string();
+    let mut lines = head.lines();
+    let mut parts = lines.next().unwrap_or_default().split_whitespace();
+    let method = parts.next().unwrap_or_default().to_string();
+    let path = parts.next().unwrap_or_default().to_string();
+    let mut content_length = 0usize;
+    let mut origin = None;
+    for line in lines {
+        let Some((name, value)) = line.split_once(':') else {
+            continue;
+        };
+        match name.trim().to_ascii_lowercase().as_str() {
+            "content-length" => content_length = value.trim().parse().unwrap_or(0),
+            "origin" => origin = Some(value.trim().to_string()),
+            _ => {}
+        }
+    }
+    if head_end + content_length > MAX_REQUEST_BYTES {
+        return Ok(None);
+    }
+
+    let mut body = buf[head_end..].to_vec();
+    while body.len() < content_length {
+        let n = stream.read(&mut chunk).await?;
+        if n == 0 {
+            break;
+        }
+        body.extend_from_slice(&chunk[..n]);
+    }
+    body.truncate(content_length);
+    Ok(Some(Request {
+        method,
+        path,
+        origin,
+        body,
+    }))
+}
+
+/// Browsers send `Origin`; only pages served from this machine may call in
+/// (guards against DNS rebinding).
+fn origin_allowed(origin: Option<&str>) -> bool {
+    let Some(origin) = origin else {
+        return true;
+    };
+    let host = origin
+        .split_once("://")
+        .map_or(origin, |(_, rest)| rest)
+        .split('/')
+        .next()
+        .unwrap_or_default();
+    let host = match host.rsplit_once(':') {
+        Some((name, port)) if port.chars().all(|c| c.is_ascii_digit()) => name,
+        _ => host,
+    };
+    matches!(host, "localhost" | "127.0.0.1" | "[::1]")
+}
+
+async fn handle_connection(app: &AppHandle, mut stream: TcpStream) -> std::io::Result<()> {
+    let Some(request) = read_request(&mut stream).await? else {
+        return respond(&mut stream, "413 Payload Too Large", "").await;
+    };
+    if request.path != ENDPOINT {
+        return respond(&mut stream, "404 Not Found", "").await;
+    }
+    if !origin_allowed(request.origin.as_deref()) {
+        return respond(&mut stream, "403 Forbidden", "").await;
+    }
+    if request.method != "POST" {
+        // No server-initiated messages, so there is no SSE stream to GET.
+        return respond(&mut stream, "405 Method Not Allowed", "").await;
+    }
+
+    let text = String::from_utf8_lossy(&request.body);
+    let reply = match mcp::parse_message(&text) {
+        Ok(message) => {
+            let entries = if mcp::is_tool_call(&message) {
+                current_entries(app)
+            } else {
+                Vec::new()
+            };
+            mcp::handle_message(&message, &entries, chrono::Utc::now().timestamp_millis())
+        }
+        Err(response) => Some(response),
+    };
+    match reply {
+        Some(reply) => respond(&mut stream, "200 OK", &reply.to_string()).await,
+        None => respond(&mut stream, "202 Accepted", "").await,
+    }
+}
+
+async fn respond(stream: &mut TcpStream, status_line: &str, body: &str) -> std::io::Result<()> {
+    let content_type = if body.is_empty() {
+        String::new()
+    } else {
+        "Content-Type: application/json\r\n".to_string()
+    };
+    let head = format!(
+        "HTTP/1.1 {status_line}\r\n{content_type}Content-Length: {}\r\nConnection: close\r\n\r\n",
+        body.len()
+    );
+    stream.write_all(head.as_bytes()).await?;
+    stream.write_all(body.as_bytes()).await?;
+    stream.shutdown().await
+}
+
+#[cfg(test)]
+mod tests {
+    use super::origin_allowed;
+
+    #[test]
+    fn only_local_origins_are_allowed() {
+        assert!(origin_allowed(None));
+        assert!(origin_allowed(Some("http://localhost:3000")));
+        assert!(origin_allowed(Some("http://127.0.0.1")));
+        assert!(origin_allowed(Some("http://[::1]:8080")));
+        assert!(!origin_allowed(Some("https://evil.example")));
+        assert!(!origin_allowed(Some("http://localhost.evil.example")));
+    }
+}
@@ -0,0 +1,298 @@
+use crate::error::{AppError, AppResult};
+use crate::status::{self, Status};
+use crate::store_repo::read_store;
+use crate::usage_state::{self, FetchCounts, UsageUpdate};
+use crate::validation::validate_bind_address;
+use std::fmt::Write as _;
+use std::net::SocketAddr;
+use std::sync::{Mutex, OnceLock};
+use std::time::Duration;
+use tauri::async_runtime::JoinHandle;
+use tauri::AppHandle;
+use tokio::io::{AsyncReadExt, AsyncWriteExt};
+use tokio::net::{TcpListener, TcpStream};
+
+const CONTENT_TYPE: &str = "application/openmetrics-text; version=1.0.0; charset=utf-8";
+const MAX_REQUEST_BYTES: usize = 8192;
+const REQUEST_TIMEOUT_SECS: u64 = 5;
+/// Back-off after a failed accept (e.g. out of file descriptors).
+const ACCEPT_RETRY: Duration = Duration::from_millis(100);
+const STATES: [Status; 6] = [
+    Status::Unknown,
+    Status::Ok,
+    Status::Warning,
+    Status::Projected,
+    Status::Critical,
+    Status::Exhausted,
+];
+
+struct RunningServer {
+    addr: SocketAddr,
+    task: JoinHandle<()>,
+}
+
+static SERVER: OnceLock<Mutex<Option<RunningServer>>> = OnceLock::new();
+
+fn server() -> &'static Mutex<Option<RunningServer>> {
+    SERVER.get_or_init(|| Mutex::new(None))
+}
+
+/// Start, stop or rebind the `/metrics` listener to match `settings.metrics`.
+pub fn apply(app: &AppHandle) -> AppResult<()> {
+    let settings = read_store(app)?.settings.metrics;
+    let wanted = if settings.enabled {
+        let ip = validate_bind_address(&settings.bind_address)?;
+        let port = u16::try_from(settings.port)
+            .map_err(|_| AppError::InvalidInput("Metrics port is out of range".to_string()))?;
+        Some(SocketAddr::new(ip, port))
+    } else {
+        None
+    };
+
+    let mut lock = server()
+        .lock()
+        .map_err(|_| AppError::Message("Metrics server lock is poisoned".to_string()))?;
+    if lock.as_ref().map(|s| s.addr) == wanted {
+        return Ok(());
+    }
+    if let Some(running) = lock.take() {
+        running.task.abort();
+    }
+    let Some(addr) = wanted else {
+        return Ok(());
+    };
+
+    let listener = std::net::TcpListener::bind(addr)
+        .map_err(|e| AppError::Message(format!("Failed to bind metrics listener on {addr}: {e}")))?;
+    listener
+        .set_nonblocking(true)
+        .map_err(|e| AppError::Message(format!("Failed to configure metrics listener: {e}")))?;
+    let task = tauri::async_runtime::spawn(serve(app.clone(), listener));
+    *lock = Some(RunningServer { addr, task });
+    Ok(())
+}
+
+async fn serve(app: AppHandle, listener: std::net::TcpListener) {
+    let Ok(listener) = TcpListener::from_std(listener) else {
+        return;
+    };
+    loop {
+        let stream = match listener.accept().await {
+            Ok((stream, _)) => stream,
+            Err(e) => {
+                eprintln!("metrics listener: accept failed: {e}");
+                tokio::time::sleep(ACCEPT_RETRY).await;
+                continue;
+            }
+        };
+        let app = app.clone();
+        tauri::async_runtime::spawn(async move {
+            let _ = tokio::time::timeout(
+                Duration::from_secs(REQUEST_TIMEOUT_SECS),
+                handle_connection(&app, stream),
+            )
+            .await;
+        });
+    }
+}
+
+async fn handle_connection(app: &AppHandle, mut stream: TcpStream) -> std::io::Result<()> {
+    let mut buf = Vec::with_capacity(1024);
+    let mut chunk = [0u8; 1024];
+    while !buf.windows(4).any(|w| w == b"\r\n\r\n") && buf.len() < MAX_REQUEST_BYTES {
+        let n = stream.read(&mut chunk).await?;
+        if n == 0 {
+            break;
+        }
+        buf.extend_from_slice(&chunk[..n]);
+    }
+
+    let request = String::from_utf8_lossy(&buf);
+    let mut parts = request.lines().next().unwrap_or_default().split_whitespace();
+    let method = parts.next().unwrap_or_default();
+    let path = parts.next().unwrap_or_default();
+
+    let (status_line, content_type, body) = match (method, path) {
+        ("GET", "/metrics") => {
+            let notify = read_store(app)
+                .map(|s| s.settings.notify_settings)
+                .map_err(|e| std::io::Error::other(e.to_string()))?;
+            let samples: Vec<_> = usage_state::latest()
+                .into_iter()
+                .map(|u| {
+                    let counts = usage_state::fetch_counts(&u.service, &u.id);
+                    (u, counts)
+                })
+                .collect();
+            ("200 OK", CONTENT_TYPE, render(&samples, &notify))
+        }
+        ("GET", _) => ("404 Not Found", "text/plain; charset=utf-8", "Not Found\n".to_string()),
+        _ => (
+            "405 Method Not Allowed",
+            "text/plain; charset=utf-8",
+            "Method Not Allowed\n".to_string(),
+        ),
+    };
+
+    let head = format!(
+        "HTTP/1.1 {status_line}\r\nContent-Type: {content_type}\r\nContent-Length: {}\r\nConnection: close\r\n\r\n",
+        body.len()
+    );
+    stream.write_all(head.as_bytes()).await?;
+    stream.write_all(body.as_bytes()).await?;
+    stream.shutdown().await
+}
+
+fn escape_label(value: &str) -> String {
+    value
+        .replace('\\', "\\\\")
+        .replace('"', "\\\"")
+        .replace('\n', "\\n")
+}
+
+fn account_labels(update: &UsageUpdate) -> String {
+    format!(
+        "service=\"{}\",account=\"{}\",name=\"{}\"",
+        escape_label(&update.service),
+        escape_label(&update.id),
+        escape_label(&update.name)
+    )
+}
+
+/// Render the latest results in OpenMetrics text format.
+fn render(samples: &[(UsageUpdate, FetchCounts)], notify: &crate::NotifySettings) -> String {
+    let mut utilization = String::new();
+    let mut resets = String::new();
+    let mut window_secs = String::new();
+    let mut projected = String::new();
+    let mut states = String::new();
+    let mut fetches = String::new();
+    let mut errors = String::new();
+    let mut fetched_at = String::new();
+
+    for (update, counts) in samples {
+        let account = account_labels(update);
+        let _ = writeln!(fetches, "aiqm_fetches_total{{{account}}} {}", counts.total);
+        let _ = writeln!(errors, "aiqm_fetch_errors_total{{{account}}} {}", counts.errors);
+        let _ = writeln!(
+            fetched_at,
+            "aiqm_last_fetch_timestamp_seconds{{{account}}} {}",
+            update.fetched_at as f64 / 1000.0
+        );
+
+        let mut windows = update.windows.clone();
+        status::classify_windows(&mut windows, notify);
+        for window in &windows {
+            let labels = format!("{account},window=\"{}\"", escape_label(&window.name));
+            let _ = writeln!(utilization, "aiqm_utilization_percent{{{labels}}} {}", window.utilization);
+            if let Some(at) = window.resets_at_millis() {
+                let _ = writeln!(resets, "aiqm_reset_timestamp_seconds{{{labels}}} {}", at as f64 / 1000.0);
+            }
+            if let Some(secs) = window.window_seconds {
+                let _ = writeln!(window_secs, "aiqm_window_seconds{{{labels}}} {secs}");
+            }
+            if let Some(at) = window.forecast.as_ref().and_then(|f| f.exhausted_at) {
+                let _ = writeln!(
+                    projected,
+                    "aiqm_projected_exhaustion_timestamp_seconds{{{labels}}} {}",
+                    at as f64 / 1000.0
+                );
+            }
+            let current = window.status.as_deref().and_then(Status::parse);
+            for state in STATES {
+                let _ = writeln!(
+                    states,
+                    "aiqm_window_status{{{labels},aiqm_window_status=\"{}\"}} {}",
+                    state.as_str(),
+                    u8::from(current == Some(state))
+                );
+            }
+        }
+    }
+
+    let mut out = String::new();
+    let families = [
+        ("aiqm_utilization_percent", "gauge", "Window utilization in percent.", utilization),
+        ("aiqm_reset_timestamp_seconds", "gauge", "When the window resets, as a Unix timestamp.", resets),
+        ("aiqm_window_seconds", "gauge", "Length of the quota window.", window_secs),
+        (
+            "aiqm_projected_exhaustion_timestamp_seconds",
+            "gauge",
+            "When the window runs out at the recent burn rate, as a Unix timestamp.",
+            projected,
+        ),
+        ("aiqm_window_status", "stateset", "Window status from the notify thresholds.", states),
+        ("aiqm_fetches", "counter", "Usage fetch attempts since startup.", fetches),
+        ("aiqm_fetch_errors", "counter", "Failed usage fetches since startup.", errors),
+        ("aiqm_last_fetch_timestamp_seconds", "gauge", "When usage was last fetched, as a Unix timestamp.", fetched_at),
+    ];
+    for (name, kind, help, body) in families {
+        let _ = writeln!(out, "# TYPE {name} {kind}");
+        let _ = writeln!(out, "# HELP {name} {help}");
+        out.push_str(&body);
+    }
+    out.push_str("# EOF\n");
+    out
+}
+
+#[cfg(test)]
+mod tests {
+    use super::{escape_label, render};
+    use crate::usage_parser::UsageWindow;
+    use crate::usage_state::{FetchCounts, UsageUpdate};
+
+    fn notify_settings() -> crate::NotifySettings {
+        crate::NotifySettings {
+            critical: true,
+            recovery: true,
+            warning: false,
+            projected: true,
+            threshold_warning: 75,
+            threshold_critical: 90,
+            quiet_hours: Vec::new(),
+            hysteresis: 0,
+            cooldown_minutes: 0,
+            renotify_minutes: 0,
+        }
+    }
+
+    #[test]
+    fn render_emits_openmetrics_families() {
+        let update = UsageUpdate {
+            service: "claude".to_string(),
+            id: "a1".to_string(),
+            name: "Main".to_string(),
+            fetched_at: 1_700_000_000_000,
+            windows: vec![UsageWindow::new(
+                "5h".to_string(),
+                80.0,
+                Some(serde_json::json!(1_700_003_600)),
+                Some(18000.0),
+                false,
+                None,
+            )],
+            status: "warning".to_string(),
+            raw: None,
+            error: None,
+            rate_limit_until: None,
+            skipped: false,
+        };
+        let counts = FetchCounts { total: 3, errors: 1 };
+        let text = render(&[(update, counts)], &notify_settings());
+
+        let labels = "service=\"claude\",account=\"a1\",name=\"Main\",window=\"5h\"";
+        assert!(text.contains(&format!("aiqm_utilization_percent{{{labels}}} 80\n")));
+        assert!(text.contains(&format!("aiqm_reset_timestamp_seconds{{{labels}}} 1700003600\n")));
+        assert!(text.contains(&format!("aiqm_window_seconds{{{labels}}} 18000\n")));
+        assert!(text.contains(&format!("aiqm_window_status{{{labels},aiqm_window_status=\"warning\"}} 1\n")));
+        assert!(text.contains(&format!("aiqm_window_status{{{labels},aiqm_window_status=\"ok\"}} 0\n")));
+        assert!(text.contains("aiqm_fetch_errors_total{service=\"claude\",account=\"a1\",name=\"Main\"} 1\n"));
+        assert!(text.contains("# TYPE aiqm_fetches counter\n"));
+        assert!(text.ends_with("# EOF\n"));
+    }
+
+    #[test]
+    fn label_values_are_escaped() {
+        assert_eq!(escape_label("a\"b\\c\nd"), "a\\\"b\\\\c\\nd");
+    }
+}
@@ -13,12 +13,16 @@ pub fn send_notification(
         return Err(AppError::InvalidInput("title is required".to_string()));
     }
 
+    show_notification(&app, &title, &body)?;
+
+    Ok(crate::ApiOk { ok: true })
+}
+
+pub fn show_notification(app: &AppHandle, title: &str, body: &str) -> AppResult<()> {
     app.notification()
         .builder()
         .title(title)
         .body(body)
         .show()
-        .map_err(|e| AppError::Message(format!("Failed to send notification: {e}")))?;
-
-    Ok(crate::ApiOk { ok: true })
+        .map_err(|e| AppError::Message(format!("Failed to send notification: {e}")))
 }
@@ -0,0 +1,253 @@
+use crate::external_notify;
+use crate::notification_commands::show_notification;
+use crate::notify_schedule::{self, Gate, HeldNotification};
+use crate::notify_throttle::{self, Kind};
+use crate::poll_scheduler;
+use crate::reset_watch::RestoredWindow;
+use crate::status::{self, TransitionLog, Triggers};
+use crate::store_repo::read_store;
+use crate::usage_state::{self, UsageUpdate};
+use serde::Serialize;
+use tauri::{AppHandle, Emitter};
+
+pub const EVENT_STATUS_CHANGED: &str = "status-changed";
+pub const EVENT_NOTIFICATION_FAILED: &str = "notification-failed";
+
+#[derive(Debug, Clone, Serialize)]
+#[serde(rename_all = "camelCase")]
+struct StatusChanged {
+    service: String,
+    id: String,
+    label: String,
+    prev: String,
+    next: String,
+    logs: Vec<TransitionLog>,
+}
+
+#[derive(Debug, Clone, Serialize)]
+#[serde(rename_all = "camelCase")]
+struct NotificationFailed {
+    /// `desktop`, `discord`, `slack`, `teams`, `pushover`, `ntfy`, `gotify` or
+    /// `webhook:<id>`.
+    channel: String,
+    message: String,
+}
+
+/// `Claude Code: Main` style label used in notification titles and logs.
+pub fn account_label(service: &str, name: &str) -> String {
+    let provider = crate::providers::get(service).map_or(service, |p| p.label());
+    format!("{provider}: {name}")
+}
+
+/// Send desktop and external notifications for an account whose status moved
+/// from `prev` to `update.status`, or reminders while it stays critical or
+/// exhausted. External sends run in the background. The cooldown drops
+/// repeated alerts, quiet hours hold them back for a later summary and a
+/// muted account sends nothing.
+pub fn dispatch_transition(app: &AppHandle, prev: Option<&str>, update: &UsageUpdate) {
+    let Some(prev) = prev else {
+        return;
+    };
+    let Ok(store) = read_store(app) else {
+        return;
+    };
+    let label = account_label(&update.service, &update.name);
+    if prev == update.status {
+        if store.settings.notify_settings.renotify_minutes > 0 {
+            deliver(app, &store, update, Kind::Reminder, |triggers| {
+                status::build_reminder(&update.status, &label, &update.windows, triggers)
+                    .into_iter()
+                    .collect()
+            });
+        }
+        return;
+    }
+
+    let effects_for = |triggers: Triggers| {
+        status::build_transition_effects(Some(prev), &update.status, &label, &update.windows, triggers)
+    };
+    // Logs always follow the desktop triggers.
+    let desktop = effects_for(Triggers::from(&store.settings.notify_settings));
+    let _ = app.emit(
+        EVENT_STATUS_CHANGED,
+        StatusChanged {
+            service: update.service.clone(),
+            id: update.id.clone(),
+            label: label.clone(),
+            prev: prev.to_string(),
+            next: update.status.clone(),
+            logs: desktop.logs,
+        },
+    );
+    let kind = if update.status == "ok" { Kind::Recovery } else { Kind::Alert };
+    deliver(app, &store, update, kind, |triggers| effects_for(triggers).notifications);
+}
+
+/// Announce windows that came back after their reset time. Sent before the
+/// transition so that the throttle drops the plain recovery notice.
+pub fn dispatch_reset(app: &AppHandle, update: &UsageUpdate, restored: &[RestoredWindow]) {
+    let Ok(store) = read_store(app) else {
+        return;
+    };
+    let label = account_label(&update.service, &update.name);
+    let logs = restored
+        .iter()
+        .map(|w| TransitionLog {
+            level: "ok",
+            message: format!("{label}: {} がリセットされました ({}% → {}%)", w.name, w.before, w.after),
+        })
+        .collect();
+    let _ = app.emit(
+        EVENT_STATUS_CHANGED,
+        StatusChanged {
+            service: update.service.clone(),
+            id: update.id.clone(),
+            label: label.clone(),
+            prev: update.status.clone(),
+            next: update.status.clone(),
+            logs,
+        },
+    );
+    deliver(app, &store, update, Kind::Reset, |triggers| {
+        status::build_reset_notice(&label, restored, triggers).into_iter().collect()
+    });
+}
+
+/// Tell the user that an account was newly marked as needing a re-login.
+pub fn dispatch_relogin(app: &AppHandle, update: &UsageUpdate, reason: &str) {
+    let Ok(store) = read_store(app) else {
+        return;
+    };
+    let label = account_label(&update.service, &update.name);
+    let _ = app.emit(
+        EVENT_STATUS_CHANGED,
+        StatusChanged {
+            service: update.service.clone(),
+            id: update.id.clone(),
+            label: label.clone(),
+            prev: update.status.clone(),
+            next: update.status.clone(),
+            logs: vec![TransitionLog {
+                level: "crit",
+                message: format!("{label}: 再ログインが必要です ({reason})"),
+            }],
+        },
+    );
+    deliver(app, &store, update, Kind::Relogin, |triggers| {
+        status::build_relogin_notice(&label, reason, triggers).into_iter().collect()
+    });
+}
+
+/// Send `notifications_for(channel triggers)` on every enabled channel.
+fn deliver(
+    app: &AppHandle,
+    store: &crate::Store,
+    update: &UsageUpdate,
+    kind: Kind,
+    notifications_for: impl Fn(Triggers) -> Vec<status::Notification>,
+) {
+    let notify = &store.settings.notify_settings;
+    let account = usage_state::account_key(&update.service, &update.id);
+    let muted_until = store
+        .services
+        .get(&update.service)
+        .and_then(|list| list.iter().find(|a| a.id == update.id))
+        .and_then(|a| a.muted_until);
+    let now = chrono::Utc::now();
+    let level = update.status.as_str();
+    // True when the item should go out now; held items are queued.
+    let admit = |channel: &str, item: &status::Notification| {
+        let gate = notify_schedule::gate(&notify.quiet_hours, channel, level, muted_until, now);
+        if gate == Gate::Drop
+            || !notify_throttle::admit(&account, channel, kind, level, notify, now.timestamp_millis())
+        {
+            return false;
+        }
+        // Reminders are not worth a line in the summary.
+        if gate == Gate::Hold && kind != Kind::Reminder {
+            notify_schedule::hold(
+                channel,
+                HeldNotification {
+                    at: now.timestamp_millis(),
+                    title: item.title.clone(),
+                    body: item.body.clone(),
+                    level: level.to_string(),
+                },
+            );
+            poll_scheduler::summary_held();
+        }
+        gate == Gate::Send
+    };
+
+    for item in notifications_for(Triggers::from(notify)).iter().filter(|item| admit("desktop", item)) {
+        if let Err(e) = show_notification(app, &item.title, &item.body) {
+            notify_failed(app, "desktop", e.to_string());
+        }
+    }
+
+    let ctx = external_notify::NotifyContext {
+        service: update.service.clone(),
+        account: update.name.clone(),
+        windows: update.windows.clone(),
+    };
+    let settings = &store.settings.external_notify;
+    for (channel, triggers) in external_notify::enabled_channels(settings) {
+        for item in notifications_for(triggers) {
+            if !admit(&channel, &item) {
+                continue;
+            }
+            let app = app.clone();
+            let level = update.status.clone();
+            let (settings, channel, ctx) = (settings.clone(), channel.clone(), ctx.clone());
+            tauri::async_runtime::spawn(async move {
+                let sent = external_notify::send_to_channel(&settings, &channel, &item.title, &item.body, &level, &ctx);
+                if let Err(message) = sent.await {
+                    notify_failed(&app, &channel, message);
+                }
+            });
+        }
+    }
+}
+
+/// Epoch millis at which [`flush_quiet_summaries`] next has a summary to send.
+pub fn next_summary_flush(app: &AppHandle) -> Option<i64> {
+    let store = read_store(app).ok()?;
+    notify_schedule::next_release(&store.settings.notify_settings.quiet_hours, chrono::Utc::now())
+        .map(|at| at.timestamp_millis())
+}
+
+/// Send one summary per channel whose quiet period has ended.
+pub fn flush_quiet_summaries(app: &AppHandle) {
+    let Ok(store) = read_store(app) else {
+        return;
+    };
+    let due = notify_schedule::take_due(&store.settings.notify_settings.quiet_hours, chrono::Utc::now());
+    for (channel, items) in due {
+        let (title, body, level) = notify_schedule::summarize(&items);
+        if channel == "desktop" {
+            if let Err(e) = show_notification(app, &title, &body) {
+                notify_failed(app, "desktop", e.to_string());
+            }
+            continue;
+        }
+        let app = app.clone();
+        let settings = store.settings.external_notify.clone();
+        tauri::async_runtime::spawn(async move {
+            let ctx = external_notify::NotifyContext::default();
+            let sent = external_notify::send_to_channel(&settings, &channel, &title, &body, &level, &ctx);
+            if let Err(message) = sent.await {
+                notify_failed(&app, &channel, message);
+            }
+        });
+    }
+}
+
+fn notify_failed(app: &AppHandle, channel: &str, message: String) {
+    let _ = app.emit(
+        EVENT_NOTIFICATION_FAILED,
+        NotificationFailed {
+            channel: channel.to_string(),
+            message,
+        },
+    );
+}
@@ -0,0 +1,344 @@
+//! Quiet hours and per-account mutes, evaluated before any notification is
+//! sent. Alerts held back during quiet hours are queued per channel and sent
+//! as one summary once the channel's quiet period is over.
+
+use crate::error::AppResult;
+use crate::status::Status;
+use crate::validation::{validate_notify_channel, validate_time_of_day, validate_timezone, WEEKDAYS};
+use chrono::{DateTime, Datelike, NaiveTime, TimeZone, Timelike, Utc};
+use std::collections::BTreeMap;
+use std::sync::{Mutex, OnceLock};
+
+/// Lines listed in a summary; the rest are counted.
+const SUMMARY_MAX_LINES: usize = 10;
+/// How far ahead [`next_release`] looks; a week covers every weekday rule.
+const RELEASE_SEARCH_MINUTES: i64 = 8 * 24 * 60;
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Gate {
+    Send,
+    /// Quiet hours: queue for the summary.
+    Hold,
+    /// Account muted: drop.
+    Drop,
+}
+
+#[derive(Debug, Clone, PartialEq)]
+pub struct HeldNotification {
+    pub at: i64,
+    pub title: String,
+    pub body: String,
+    pub level: String,
+}
+
+static HELD: OnceLock<Mutex<BTreeMap<String, Vec<HeldNotification>>>> = OnceLock::new();
+
+fn held() -> &'static Mutex<BTreeMap<String, Vec<HeldNotification>>> {
+    HELD.get_or_init(|| Mutex::new(BTreeMap::new()))
+}
+
+/// Validate a rule from settings or a patch; names are trimmed and lowercased.
+pub fn rule_from_raw(raw: &crate::QuietHoursRuleRaw) -> AppResult<crate::QuietHoursRule> {
+    let start = raw.start.as_deref().unwrap_or("22:00").trim().to_string();
+    let end = raw.end.as_deref().unwrap_or("07:00").trim().to_string();
+    validate_time_of_day(&start)?;
+    validate_time_of_day(&end)?;
+    let timezone = raw.timezone.as_deref().unwrap_or_default().trim().to_string();
+    if !timezone.is_empty() {
+        validate_timezone(&timezone)?;
+    }
+    let mut days = Vec::new();
+    for day in raw.days.iter().flatten() {
+        let day = day.trim().to_ascii_lowercase();
+        if !WEEKDAYS.contains(&day.as_str()) {
+            return Err(crate::error::AppError::InvalidInput(format!("Unknown weekday: {day}")));
+        }
+        if !days.contains(&day) {
+            days.push(day);
+        }
+    }
+    let mut channels = Vec::new();
+    for channel in raw.channels.iter().flatten() {
+        let channel = channel.trim().to_string();
+        validate_notify_channel(&channel)?;
+        if !channels.contains(&channel) {
+            channels.push(channel);
+        }
+    }
+    Ok(crate::QuietHoursRule {
+        enabled: raw.enabled.unwrap_or(true),
+        channels,
+        days,
+        start,
+        end,
+        timezone,
+        allow_exhausted: raw.allow_exhausted.unwrap_or(false),
+    })
+}
+
+fn minutes(hhmm: &str) -> Option<u32> {
+    let time = NaiveTime::parse_from_str(hhmm, "%H:%M").ok()?;
+    Some(time.hour() * 60 + time.minute())
+}
+
+/// Weekday name and minute of day at `now` in the rule's time zone.
+fn local_day_and_minute(rule: &crate::QuietHoursRule, now: DateTime<Utc>) -> (usize, u32) {
+    let (weekday, time) = match rule.timezone.parse::<chrono_tz::Tz>() {
+        Ok(tz) => {
+            let local = tz.from_utc_datetime(&now.naive_utc());
+            (local.weekday(), local.time())
+        }
+        Err(_) => {
+            let local = now.with_timezone(&chrono::Local);
+            (local.weekday(), local.time())
+        }
+    };
+    (weekday.num_days_from_monday() as usize, time.hour() * 60 + time.minute())
+}
+
+fn applies_on(rule: &crate::QuietHoursRule, day: usize) -> bool {
+    rule.days.is_empty() || rule.days.iter().any(|d| d == WEEKDAYS[day % 7])
+}
+
+pub fn is_quiet(rule: &crate::QuietHoursRule, now: DateTime<Utc>) -> bool {
+    let (Some(start), Some(end)) = (minutes(&rule.start), minutes(&rule.end)) else {
+        return false;
+    };
+    if !rule.enabled {
+        return false;
+    }
+    let (day, minute) = local_day_and_minute(rule, now);
+    let yesterday = (day + 6) % 7;
+    if start == end {
+        applies_on(rule, day)
+    } else if start < end {
+        applies_on(rule, day) && (start..end).contains(&minute)
+    } else {
+        (applies_on(rule, day) && minute >= start) || (applies_on(rule, yesterday) && minute < end)
+    }
+}
+
+fn covers(rule: &crate::QuietHoursRule, channel: &str) -> bool {
+    rule.channels.is_empty() || rule.channels.iter().any(|c| c == channel)
+}
+
+/// Quiet rules for `channel` that are active at `now`.
+fn active_rules<'a>(
+    rules: &'a [crate::QuietHoursRule],
+    channel: &'a str,
+    now: DateTime<Utc>,
+) -> impl Iterator<Item = &'a crate::QuietHoursRule> {
+    rules.iter().filter(move |r| covers(r, channel) && is_quiet(r, now))
+}
+
+/// Whether a notification at `level` may go out on `channel` now.
+pub fn gate(
+    rules: &[crate::QuietHoursRule],
+    channel: &str,
+    level: &str,
+    muted_until: Option<i64>,
+    now: DateTime<Utc>,
+) -> Gate {
+    if muted_until.is_some_and(|until| now.timestamp_millis() < until) {
+        return Gate::Drop;
+    }
+    let mut active = active_rules(rules, channel, now).peekable();
+    if active.peek().is_none() {
+        return Gate::Send;
+    }
+    if level == "exhausted" && active.all(|r| r.allow_exhausted) {
+        return Gate::Send;
+    }
+    Gate::Hold
+}
+
+pub fn hold(channel: &str, item: HeldNotification) {
+    if let Ok(mut map) = held().lock() {
+        map.entry(channel.to_string()).or_default().push(item);
+    }
+}
+
+/// Take the held notifications of every channel whose quiet period is over.
+pub fn take_due(rules: &[crate::QuietHoursRule], now: DateTime<Utc>) -> Vec<(String, Vec<HeldNotification>)> {
+    let Ok(mut map) = held().lock() else {
+        return Vec::new();
+    };
+    let due: Vec<String> = map
+        .keys()
+        .filter(|channel| active_rules(rules, channel, now).next().is_none())
+        .cloned()
+        .collect();
+    due.into_iter()
+        .filter_map(|channel| map.remove(&channel).map(|items| (channel, items)))
+        .filter(|(_, items)| !items.is_empty())
+        .collect()
+}
+
+/// When the first channel with held notifications leaves quiet hours, or now
+/// if one already has. `None` when nothing is held, or when every held
+/// channel stays quiet for the next week.
+pub fn next_release(rules: &[crate::QuietHoursRule], now: DateTime<Utc>) -> Option<DateTime<Utc>> {
+    let channels: Vec<String> = held()
+        .lock()
+        .ok()?
+        .iter()
+        .filter(|(_, items)| !items.is_empty())
+        .map(|(channel, _)| channel.clone())
+        .collect();
+    release_at(rules, &channels, now)
+}
+
+/// Rules only change state on whole minutes, so after `now` the minute
+/// boundaries are checked in order.
+fn release_at(rules: &[crate::QuietHoursRule], channels: &[String], now: DateTime<Utc>) -> Option<DateTime<Utc>> {
+    if channels.is_empty() {
+        return None;
+    }
+    let next_minute = now.timestamp().div_euclid(60) * 60 + 60;
+    std::iter::once(now)
+        .chain((0..RELEASE_SEARCH_MINUTES).filter_map(|i| DateTime::from_timestamp(next_minute + i * 60, 0)))
+        .find(|at| channels.iter().any(|channel| active_rules(rules, channel, *at).next().is_none()))
+}
+
+/// Title, body and level (the most severe held one) of a summary.
+pub fn summarize(items: &[HeldNotification]) -> (String, String, String) {
+    let title = format!("静音時間中の通知 {}件", items.len());
+    let mut lines: Vec<String> = items
+        .iter()
+        .take(SUMMARY_MAX_LINES)
+        .map(|item| {
+            let at = DateTime::from_timestamp_millis(item.at)
+                .map(|at| at.with_timezone(&chrono::Local).format("%H:%M").to_string())
+                .unwrap_or_default();
+            let first_line = item.body.lines().next().unwrap_or_default();
+            if first_line.is_empty() {
+                format!("{at} {}", item.title)
+            } else {
+                format!("{at} {} — {first_line}", item.title)
+            }
+        })
+        .collect();
+    if items.len() > SUMMARY_MAX_LINES {
+        lines.push(format!("ほか {}件", items.len() - SUMMARY_MAX_LINES));
+    }
+    let level = items
+        .iter()
+        .filter_map(|item| Status::parse(&item.level))
+        .max()
+        .unwrap_or(Status::Unknown);
+    (title, lines.join("\n"), level.as_str().to_string())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::{gate, is_quiet, release_at, rule_from_raw, summarize, Gate, HeldNotification};
+    use chrono::{DateTime, Utc};
+
+    fn rule(start: &str, end: &str, days: &[&str]) -> crate::QuietHoursRule {
+        crate::QuietHoursRule {
+            enabled: true,
+            channels: Vec::new(),
+            days: days.iter().map(|d| d.to_string()).collect(),
+            start: start.to_string(),
+            end: end.to_string(),
+            timezone: "Asia/Tokyo".to_string(),
+            allow_exhausted: false,
+        }
+    }
+
+    fn at(rfc3339: &str) -> DateTime<Utc> {
+        DateTime::parse_from_rfc3339(rfc3339).unwrap().with_timezone(&Utc)
+    }
+
+    #[test]
+    fn overnight_range_follows_the_rule_time_zone() {
+        let night = rule("22:00", "07:00", &["fri"]);
+        // Fri 23:30 and Sat 06:59 JST are quiet; Sat 07:00 and Thu 23:30 are not.
+        assert!(is_quiet(&night, at("2026-10-16T23:30:00+09:00")));
+        assert!(is_quiet(&night, at("2026-10-17T06:59:00+09:00")));
+        assert!(!is_quiet(&night, at("2026-10-17T07:00:00+09:00")));
+        assert!(!is_quiet(&night, at("2026-10-15T23:30:00+09:00")));
+        // 14:30 UTC is 23:30 in Tokyo.
+        assert!(is_quiet(&night, at("2026-10-16T14:30:00Z")));
+
+        let lunch = rule("12:00", "13:00", &[]);
+        assert!(is_quiet(&lunch, at("2026-10-14T12:30:00+09:00")));
+        assert!(!is_quiet(&lunch, at("2026-10-14T13:00:00+09:00")));
+        let weekend = rule("00:00", "00:00", &["sat", "sun"]);
+        assert!(is_quiet(&weekend, at("2026-10-18T15:00:00+09:00")));
+        assert!(!is_quiet(&weekend, at("2026-10-19T15:00:00+09:00")));
+    }
+
+    #[test]
+    fn gate_respects_channels_exhausted_escalation_and_mute() {
+        let now = at("2026-10-16T23:30:00+09:00");
+        let mut night = rule("22:00", "07:00", &[]);
+        night.channels = vec!["desktop".to_string()];
+        let rules = vec![night.clone()];
+        assert_eq!(gate(&rules, "desktop", "critical", None, now), Gate::Hold);
+        assert_eq!(gate(&rules, "discord", "critical", None, now), Gate::Send);
+        assert_eq!(gate(&rules, "desktop", "exhausted", None, now), Gate::Hold);
+
+        night.allow_exhausted = true;
+        assert_eq!(gate(&[night], "desktop", "exhausted", None, now), Gate::Send);
+
+        let until = now.timestamp_millis() + 1;
+        assert_eq!(gate(&[], "discord", "critical", Some(until), now), Gate::Drop);
+        assert_eq!(gate(&[], "discord", "critical", Some(until - 1), now), Gate::Send);
+    }
+
+    #[test]
+    fn release_is_the_end_of_the_quiet_period() {
+        let now = at("2026-10-16T23:30:20+09:00");
+        let mut night = rule("22:00", "07:00", &[]);
+        night.channels = vec!["desktop".to_string()];
+        let desktop = vec!["desktop".to_string()];
+        assert_eq!(release_at(&[night.clone()], &desktop, now), Some(at("2026-10-17T07:00:00+09:00")));
+        assert_eq!(release_at(&[night.clone()], &["discord".to_string()], now), Some(now));
+        assert_eq!(release_at(&[night], &[], now), None);
+
+        let always = rule("00:00", "00:00", &[]);
+        assert_eq!(release_at(&[always], &desktop, now), None);
+    }
+
+    #[test]
+    fn raw_rules_are_validated() {
+        let raw: crate::QuietHoursRuleRaw = serde_json::from_str(
+            r#"{"days":["Sat","sun","sat"],"start":"23:00","end":"06:30","timezone":"Europe/Berlin","channels":["webhook:wh-1"]}"#,
+        )
+        .unwrap();
+        let parsed = rule_from_raw(&raw).unwrap();
+        assert_eq!(parsed.days, vec!["sat", "sun"]);
+        assert!(parsed.enabled);
+
+        for bad in [
+            r#"{"start":"25:00"}"#,
+            r#"{"timezone":"Mars/Olympus"}"#,
+            r#"{"days":["someday"]}"#,
+            r#"{"channels":["carrier-pigeon"]}"#,
+        ] {
+            let raw: crate::QuietHoursRuleRaw = serde_json::from_str(bad).unwrap();
+            assert!(rule_from_raw(&raw).is_err(), "{bad}");
+        }
+    }
+
+    #[test]
+    fn summary_lists_items_and_takes_the_worst_level() {
+        let item = |level: &str| HeldNotification {
+            at: 0,
+            title: "Claude Code: A".to_string(),
+            body: "5時間: 92%\n詳細".to_string(),
+            level: level.to_string(),
+        };
+        let mut items = vec![item("warning"), item("exhausted"), item("ok")];
+        let (title, body, level) = summarize(&items);
+        assert_eq!(title, "静音時間中の通知 3件");
+        assert_eq!(body.lines().count(), 3);
+        assert!(body.lines().all(|l| l.ends_with("Claude Code: A — 5時間: 92%")));
+        assert_eq!(level, "exhausted");
+
+        items.extend((0..10).map(|_| item("ok")));
+        let (_, body, _) = summarize(&items);
+        assert_eq!(body.lines().last(), Some("ほか 3件"));
+    }
+}
@@ -0,0 +1,172 @@
+//! Per-account, per-channel memory of sent alerts. A cooldown drops repeats
+//! of the same or lower severity, and reminders go out while an account stays
+//! critical or exhausted. Kept in the backend so UI reloads do not reset it.
+
+use crate::status::Status;
+use std::collections::HashMap;
+use std::sync::{Mutex, OnceLock};
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Kind {
+    /// Status got worse (warning, projected, critical, exhausted).
+    Alert,
+    /// Back to ok after critical/exhausted.
+    Recovery,
+    /// Still critical/exhausted after `renotify_minutes`.
+    Reminder,
+    /// A window came back after its reset time. Always goes out, and stands
+    /// in for the recovery notice when the account is no longer severe.
+    Reset,
+    /// The refresh token was rejected. Always goes out; the stored
+    /// "needs re-login" flag already keeps it to once per rejection.
+    Relogin,
+}
+
+#[derive(Debug, Clone, Default)]
+struct ChannelState {
+    /// Severity and time of the last alert or reminder that went out.
+    last_alert: Option<(Status, i64)>,
+    /// A recovery went out after that alert.
+    recovered: bool,
+}
+
+type Key = (String, String);
+
+static SENT: OnceLock<Mutex<HashMap<Key, ChannelState>>> = OnceLock::new();
+
+fn sent() -> &'static Mutex<HashMap<Key, ChannelState>> {
+    SENT.get_or_init(|| Mutex::new(HashMap::new()))
+}
+
+fn minutes_ms(minutes: i32) -> i64 {
+    i64::from(minutes.max(0)) * 60 * 1000
+}
+
+/// Whether a notification of `kind` for `account` (`service:id`) may go out on
+/// `channel` now; if so it is recorded as sent.
+pub fn admit(
+    account: &str,
+    channel: &str,
+    kind: Kind,
+    level: &str,
+    settings: &crate::NotifySettings,
+    now: i64,
+) -> bool {
+    let Ok(mut map) = sent().lock() else {
+        return true;
+    };
+    let state = map.entry((account.to_string(), channel.to_string())).or_default();
+    decide(
+        state,
+        kind,
+        Status::parse(level).unwrap_or(Status::Unknown),
+        minutes_ms(settings.cooldown_minutes),
+        minutes_ms(settings.renotify_minutes),
+        now,
+    )
+}
+
+fn decide(state: &mut ChannelState, kind: Kind, level: Status, cooldown: i64, renotify: i64, now: i64) -> bool {
+    match kind {
+        Kind::Alert => {
+            let repeat = state
+                .last_alert
+                .is_some_and(|(last, at)| now - at < cooldown && level <= last);
+            if repeat {
+                return false;
+            }
+            state.last_alert = Some((level, now));
+            state.recovered = false;
+            true
+        }
+        Kind::Recovery => {
+            if state.recovered {
+                return false;
+            }
+            state.recovered = true;
+            true
+        }
+        Kind::Reset => {
+            if level < Status::Critical {
+                state.recovered = true;
+            }
+            true
+        }
+        Kind::Relogin => true,
+        Kind::Reminder => {
+            if renotify <= 0 || state.recovered {
+                return false;
+            }
+            match state.last_alert {
+                // Already severe when first seen (e.g. at startup): start the clock.
+                None => {
+                    state.last_alert = Some((level, now));
+                    false
+                }
+                Some((last, at)) if now - at >= renotify => {
+                    state.last_alert = Some((last.max(level), now));
+                    true
+                }
+                Some(_) => false,
+            }
+        }
+    }
+}
+
+/// Forget accounts that are no longer configured (`service:id` keys).
+pub fn retain_accounts(accounts: &[String]) {
+    if let Ok(mut map) = sent().lock() {
+        map.retain(|(account, _), _| accounts.contains(account));
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::{decide, ChannelState, Kind};
+    use crate::status::Status;
+
+    const MIN: i64 = 60 * 1000;
+
+    #[test]
+    fn cooldown_drops_repeats_but_not_escalations() {
+        let mut state = ChannelState::default();
+        let cooldown = 15 * MIN;
+        assert!(decide(&mut state, Kind::Alert, Status::Critical, cooldown, 0, 0));
+        // Flapping critical -> ok -> critical within the cooldown.
+        assert!(decide(&mut state, Kind::Recovery, Status::Ok, cooldown, 0, MIN));
+        assert!(!decide(&mut state, Kind::Alert, Status::Critical, cooldown, 0, 2 * MIN));
+        assert!(!decide(&mut state, Kind::Recovery, Status::Ok, cooldown, 0, 3 * MIN));
+        assert!(!decide(&mut state, Kind::Alert, Status::Warning, cooldown, 0, 4 * MIN));
+        // More severe always goes out.
+        assert!(decide(&mut state, Kind::Alert, Status::Exhausted, cooldown, 0, 5 * MIN));
+        // After the cooldown the same level goes out again.
+        assert!(decide(&mut state, Kind::Alert, Status::Exhausted, cooldown, 0, 20 * MIN));
+
+        // A reset notice replaces the recovery notice.
+        assert!(decide(&mut state, Kind::Reset, Status::Ok, cooldown, 0, 30 * MIN));
+        assert!(!decide(&mut state, Kind::Recovery, Status::Ok, cooldown, 0, 30 * MIN));
+
+        let mut off = ChannelState::default();
+        assert!(decide(&mut off, Kind::Alert, Status::Critical, 0, 0, 0));
+        assert!(decide(&mut off, Kind::Alert, Status::Critical, 0, 0, 1));
+    }
+
+    #[test]
+    fn reminders_repeat_until_recovery() {
+        let renotify = 30 * MIN;
+        let mut state = ChannelState::default();
+        assert!(decide(&mut state, Kind::Alert, Status::Critical, 0, renotify, 0));
+        assert!(!decide(&mut state, Kind::Reminder, Status::Critical, 0, renotify, 29 * MIN));
+        assert!(decide(&mut state, Kind::Reminder, Status::Critical, 0, renotify, 30 * MIN));
+        assert!(!decide(&mut state, Kind::Reminder, Status::Critical, 0, renotify, 45 * MIN));
+        assert!(decide(&mut state, Kind::Reminder, Status::Critical, 0, renotify, 60 * MIN));
+        assert!(decide(&mut state, Kind::Recovery, Status::Ok, 0, renotify, 61 * MIN));
+        assert!(!decide(&mut state, Kind::Reminder, Status::Critical, 0, renotify, 120 * MIN));
+
+        // Severe since startup: the first reminder comes one interval later.
+        let mut fresh = ChannelState::default();
+        assert!(!decide(&mut fresh, Kind::Reminder, Status::Exhausted, 0, renotify, 0));
+        assert!(decide(&mut fresh, Kind::Reminder, Status::Exhausted, 0, renotify, renotify));
+        assert!(!decide(&mut ChannelState::default(), Kind::Reminder, Status::Critical, 0, 0, 0));
+    }
+}
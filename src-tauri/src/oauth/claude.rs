@@ -1,5 +1,5 @@
 use super::pkce;
-use super::OAuthTokens;
+use super::{send_refresh, OAuthTokens, RefreshError};
 use std::time::Duration;
 
 const CLIENT_ID: &str = "9d1c250a-e61b-44d9-88ed-5944d1962f5e";
@@ -77,7 +77,7 @@ pub async fn exchange_code(raw_code: &str, verifier: &str, expected_state: Optio
     parse_token_response(&body)
 }
 
-pub async fn refresh_token(refresh_tok: &str) -> Result<OAuthTokens, String> {
+pub async fn refresh_token(refresh_tok: &str) -> Result<OAuthTokens, RefreshError> {
     let params = serde_json::json!({
         "grant_type": "refresh_token",
         "refresh_token": refresh_tok,
@@ -89,26 +89,11 @@ pub async fn refresh_token(refresh_tok: &str) -> Result<OAuthTokens, String> {
     let client = reqwest::Client::builder()
         .timeout(Duration::from_secs(30))
         .build()
-        .map_err(|e| format!("HTTP client error: {e}"))?;
-
-    let resp = client
-        .post(TOKEN_URL)
-        .json(&params)
-        .send()
-        .await
-        .map_err(|e| format!("Token refresh request failed: {e}"))?;
+        .map_err(|e| RefreshError::Other(format!("HTTP client error: {e}")))?;
 
-    let status = resp.status();
-    let resp_body = resp
-        .text()
-        .await
-        .map_err(|e| format!("Failed to read refresh response: {e}"))?;
-
-    if !status.is_success() {
-        return Err(format!("Token refresh failed (HTTP {status}): {resp_body}"));
-    }
+    let resp_body = send_refresh(client.post(TOKEN_URL).json(&params)).await?;
 
-    parse_token_response(&resp_body)
+    parse_token_response(&resp_body).map_err(RefreshError::Other)
 }
 
 fn parse_token_response(body: &str) -> Result<OAuthTokens, String> {
@@ -1,6 +1,6 @@
 use super::callback_server::wait_for_callback;
 use super::pkce;
-use super::OAuthTokens;
+use super::{send_refresh, OAuthTokens, RefreshError};
 use std::collections::HashMap;
 use std::net::TcpListener;
 use std::time::Duration;
@@ -83,7 +83,7 @@ async fn exchange_code(code: &str, verifier: &str) -> Result<OAuthTokens, String
     parse_token_response(&body)
 }
 
-pub async fn refresh_token(refresh_token: &str) -> Result<OAuthTokens, String> {
+pub async fn refresh_token(refresh_token: &str) -> Result<OAuthTokens, RefreshError> {
     let mut params = HashMap::new();
     params.insert("grant_type", "refresh_token");
     params.insert("refresh_token", refresh_token);
@@ -92,26 +92,11 @@ pub async fn refresh_token(refresh_token: &str) -> Result<OAuthTokens, String> {
     let client = reqwest::Client::builder()
         .timeout(Duration::from_secs(30))
         .build()
-        .map_err(|e| format!("HTTP client error: {e}"))?;
+        .map_err(|e| RefreshError::Other(format!("HTTP client error: {e}")))?;
 
-    let resp = client
-        .post(TOKEN_URL)
-        .form(&params)
-        .send()
-        .await
-        .map_err(|e| format!("Token refresh request failed: {e}"))?;
+    let body = send_refresh(client.post(TOKEN_URL).form(&params)).await?;
 
-    let status = resp.status();
-    let body = resp
-        .text()
-        .await
-        .map_err(|e| format!("Failed to read refresh response: {e}"))?;
-
-    if !status.is_success() {
-        return Err(format!("Token refresh failed (HTTP {status}): {body}"));
-    }
-
-    parse_token_response(&body)
+    parse_token_response(&body).map_err(RefreshError::Other)
 }
 
 fn parse_token_response(body: &str) -> Result<OAuthTokens, String> {
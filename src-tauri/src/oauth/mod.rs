@@ -3,7 +3,10 @@ pub mod callback_server;
 pub mod codex;
 pub mod claude;
 
+use base64::engine::general_purpose::URL_SAFE_NO_PAD;
+use base64::Engine;
 use serde::{Deserialize, Serialize};
+use thiserror::Error;
 
 #[derive(Debug, Clone, Serialize, Deserialize)]
 #[serde(rename_all = "camelCase")]
@@ -13,3 +16,161 @@ pub struct OAuthTokens {
     /// Epoch milliseconds when the access token expires
     pub expires_at: Option<i64>,
 }
+
+/// `exp` claim of a JWT access token, as epoch milliseconds. The signature is
+/// not checked; this only fills in a missing expiry.
+pub fn jwt_expires_at(token: &str) -> Option<i64> {
+    let payload = token.split('.').nth(1)?;
+    let bytes = URL_SAFE_NO_PAD.decode(payload.trim_end_matches('=')).ok()?;
+    let claims: serde_json::Value = serde_json::from_slice(&bytes).ok()?;
+    claims["exp"].as_i64().map(|secs| secs * 1000)
+}
+
+/// Why a token refresh failed. Only `Unsent` may be retried with the same
+/// refresh token; `Revoked` means the user has to log in again.
+#[derive(Debug, Clone, PartialEq, Eq, Error)]
+pub enum RefreshError {
+    /// No refresh token is stored (access-token import or CLI-linked account).
+    #[error("refresh_token が保存されていません。再ログインしてください")]
+    MissingToken,
+    /// invalid_grant and friends: the refresh token expired, was revoked or was
+    /// already used.
+    #[error("refresh_token が無効になりました。再ログインが必要です ({0})")]
+    Revoked(String),
+    /// The request was never sent (connection refused, DNS failure), so the
+    /// refresh token is still unused.
+    #[error("トークン更新サーバーに接続できませんでした: {0}")]
+    Unsent(String),
+    /// The request may have reached the token endpoint but no usable response
+    /// came back (timeout, cut-off body). The refresh token may have been used.
+    #[error("トークン更新の通信に失敗しました: {0}")]
+    Network(String),
+    /// HTTP 5xx or 429 from the token endpoint.
+    #[error("トークン更新でサーバーエラーが発生しました (HTTP {status}): {body}")]
+    Server { status: u16, body: String },
+    #[error("{0}")]
+    Other(String),
+}
+
+impl RefreshError {
+    /// Whether the same refresh token may be sent again. Once a request may
+    /// have reached the endpoint, the token may already have been rotated and
+    /// resending it would be rejected as reused.
+    pub fn can_resend(&self) -> bool {
+        matches!(self, RefreshError::Unsent(_))
+    }
+}
+
+/// Send a refresh request and return the body of a successful response.
+pub async fn send_refresh(request: reqwest::RequestBuilder) -> Result<String, RefreshError> {
+    let resp = request.send().await.map_err(|e| {
+        if e.is_connect() {
+            RefreshError::Unsent(e.to_string())
+        } else {
+            RefreshError::Network(e.to_string())
+        }
+    })?;
+
+    let status = resp.status();
+    let body = resp
+        .text()
+        .await
+        .map_err(|e| RefreshError::Network(format!("Failed to read refresh response: {e}")))?;
+
+    if !status.is_success() {
+        return Err(classify_refresh_failure(status.as_u16(), &body));
+    }
+    Ok(body)
+}
+
+/// Classify a non-success response of a token endpoint. Both the RFC 6749
+/// shape (`{"error":"invalid_grant"}`) and OpenAI's nested
+/// `{"error":{"code":"refresh_token_expired"}}` are understood.
+pub fn classify_refresh_failure(status: u16, body: &str) -> RefreshError {
+    if status == 429 || status >= 500 {
+        return RefreshError::Server {
+            status,
+            body: body.to_string(),
+        };
+    }
+    let json: serde_json::Value = serde_json::from_str(body).unwrap_or_default();
+    let error = &json["error"];
+    let code = error
+        .as_str()
+        .or_else(|| error["code"].as_str())
+        .or_else(|| error["type"].as_str())
+        .unwrap_or_default();
+    let description = json["error_description"]
+        .as_str()
+        .or_else(|| error["message"].as_str())
+        .unwrap_or_default();
+    let revoked_code = matches!(code, "invalid_grant" | "invalid_token") || code.starts_with("refresh_token_");
+    if revoked_code || status == 401 || status == 403 {
+        let reason = match (code, description) {
+            ("", "") => format!("HTTP {status}"),
+            (code, "") => code.to_string(),
+            ("", description) => description.to_string(),
+            (code, description) => format!("{code}: {description}"),
+        };
+        return RefreshError::Revoked(reason);
+    }
+    RefreshError::Other(format!("Token refresh failed (HTTP {status}): {body}"))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::{classify_refresh_failure, send_refresh, RefreshError};
+    use std::io::{Read, Write};
+
+    fn block_on<F: std::future::Future>(future: F) -> F::Output {
+        tokio::runtime::Builder::new_current_thread()
+            .enable_all()
+            .build()
+            .expect("runtime")
+            .block_on(future)
+    }
+
+    #[test]
+    fn refresh_failures_are_classified() {
+        assert_eq!(
+            classify_refresh_failure(400, r#"{"error":"invalid_grant","error_description":"Refresh token revoked"}"#),
+            RefreshError::Revoked("invalid_grant: Refresh token revoked".into())
+        );
+        assert_eq!(
+            classify_refresh_failure(401, r#"{"error":{"message":"Expired","type":"invalid_request_error","code":"refresh_token_expired"}}"#),
+            RefreshError::Revoked("refresh_token_expired: Expired".into())
+        );
+        assert_eq!(classify_refresh_failure(401, "nope"), RefreshError::Revoked("HTTP 401".into()));
+        assert!(matches!(classify_refresh_failure(503, "busy"), RefreshError::Server { status: 503, .. }));
+        assert!(!classify_refresh_failure(429, "").can_resend());
+        let other = classify_refresh_failure(400, r#"{"error":"invalid_request"}"#);
+        assert!(matches!(other, RefreshError::Other(_)));
+    }
+
+    #[test]
+    fn cut_off_response_is_not_revoked() {
+        let listener = std::net::TcpListener::bind("127.0.0.1:0").expect("bind");
+        let addr = listener.local_addr().expect("addr");
+        let server = std::thread::spawn(move || {
+            let (mut stream, _) = listener.accept().expect("accept");
+            let mut buf = [0u8; 4096];
+            let _ = stream.read(&mut buf);
+            let _ = stream.write_all(b"HTTP/1.1 200 OK\r\nContent-Length: 100\r\n\r\n{\"access_token\"");
+        });
+        let result = block_on(send_refresh(reqwest::Client::new().post(format!("http://{addr}/token"))));
+        server.join().expect("server");
+        let err = result.expect_err("cut-off body");
+        assert!(matches!(err, RefreshError::Network(_)), "{err:?}");
+        assert!(!err.can_resend());
+    }
+
+    #[test]
+    fn refused_connection_may_be_resent() {
+        let addr = std::net::TcpListener::bind("127.0.0.1:0")
+            .and_then(|listener| listener.local_addr())
+            .expect("free port");
+        let result = block_on(send_refresh(reqwest::Client::new().post(format!("http://{addr}/token"))));
+        let err = result.expect_err("nothing listening");
+        assert!(err.can_resend(), "{err:?}");
+    }
+}
@@ -1,5 +1,6 @@
 use crate::error::{AppError, AppResult};
 use crate::oauth;
+use crate::providers::{self, AuthKind, UsageProvider};
 use crate::token_refresh;
 use crate::token_store;
 use serde::{Deserialize, Serialize};
@@ -8,20 +9,26 @@ use std::sync::Mutex;
 use tokio::sync::oneshot;
 use zeroize::Zeroize;
 
-/// Global cancel sender for in-progress Codex login.
+/// Global cancel sender for in-progress loopback login.
 static LOGIN_CANCEL: std::sync::OnceLock<Mutex<Option<oneshot::Sender<()>>>> =
     std::sync::OnceLock::new();
 
-/// Global PKCE verifier for in-progress Claude login (two-step flow).
-static CLAUDE_PENDING: std::sync::OnceLock<Mutex<Option<(String, String)>>> =
+/// Global PKCE verifier for in-progress paste-code login (two-step flow).
+static CODE_PENDING: std::sync::OnceLock<Mutex<Option<PendingCode>>> =
     std::sync::OnceLock::new();
 
+struct PendingCode {
+    service: &'static str,
+    verifier: String,
+    state: String,
+}
+
 fn cancel_store() -> &'static Mutex<Option<oneshot::Sender<()>>> {
     LOGIN_CANCEL.get_or_init(|| Mutex::new(None))
 }
 
-fn claude_pending_store() -> &'static Mutex<Option<(String, String)>> {
-    CLAUDE_PENDING.get_or_init(|| Mutex::new(None))
+fn code_pending_store() -> &'static Mutex<Option<PendingCode>> {
+    CODE_PENDING.get_or_init(|| Mutex::new(None))
 }
 
 #[derive(Debug, Clone, Serialize)]
@@ -34,10 +41,10 @@ pub struct OAuthLoginResult {
     /// Authorization URL to open in a browser (preferred: user copies into their browser of choice).
     #[serde(skip_serializing_if = "Option::is_none")]
     pub auth_url: Option<String>,
-    /// For Claude two-step flow: signals the frontend to show a code input dialog.
+    /// For the paste-code flow: signals the frontend to show a code input dialog.
     #[serde(skip_serializing_if = "Option::is_none")]
     pub needs_code: Option<bool>,
-    /// For async flows (Codex): login has started and we're waiting for callback.
+    /// For loopback flows: login has started and we're waiting for callback.
     #[serde(skip_serializing_if = "Option::is_none")]
     pub pending: Option<bool>,
 }
@@ -53,30 +60,31 @@ pub struct TokenStatus {
 }
 
 /// Start an OAuth login flow.
-/// - For Codex: opens browser, waits for local callback, returns completed result.
-/// - For Claude: opens browser and returns needs_code=true for the two-step flow.
+/// - Loopback: opens browser, waits for local callback, returns completed result.
+/// - Paste-code: opens browser and returns needs_code=true for the two-step flow.
 pub async fn oauth_login(service: &str, id: &str) -> AppResult<OAuthLoginResult> {
-    token_store::ensure_service(service)?;
+    let provider = providers::require(service)?;
     crate::validation::validate_account_id(id)?;
 
     cancel_login_impl();
 
-    match service {
-        "claude" => oauth_login_claude(),
-        "codex" => oauth_login_codex(id).await,
-        _ => Err(AppError::UnsupportedService),
+    match provider.auth_kind() {
+        AuthKind::OAuthPasteCode => oauth_login_paste_code(provider),
+        AuthKind::OAuthLoopback => oauth_login_loopback(provider, id).await,
     }
 }
 
-fn oauth_login_claude() -> AppResult<OAuthLoginResult> {
-    let (auth_url, verifier, state) = oauth::claude::build_auth_url();
+fn oauth_login_paste_code(provider: &'static dyn UsageProvider) -> AppResult<OAuthLoginResult> {
+    let (auth_url, verifier, state) = provider
+        .paste_code_auth_url()
+        .ok_or_else(|| AppError::Message(format!("{} does not support code login", provider.label())))?;
 
     // Store verifier + state for the second step
     {
-        let mut lock = claude_pending_store()
+        let mut lock = code_pending_store()
             .lock()
             .map_err(|_| AppError::Message("Lock poisoned".into()))?;
-        *lock = Some((verifier, state));
+        *lock = Some(PendingCode { service: provider.id(), verifier, state });
     }
 
     Ok(OAuthLoginResult {
@@ -90,16 +98,8 @@ fn oauth_login_claude() -> AppResult<OAuthLoginResult> {
     })
 }
 
-/// Import Claude CLI credentials from ~/.claude/.credentials.json.
-pub fn import_claude_cli_credentials(service: &str, id: &str) -> AppResult<OAuthLoginResult> {
-    token_store::ensure_service(service)?;
-    crate::validation::validate_account_id(id)?;
-    if service != "claude" {
-        return Err(AppError::Message(
-            "Claude CLI credentials import is only supported for Claude".into(),
-        ));
-    }
-
+/// Tokens from ~/.claude/.credentials.json.
+pub fn read_claude_cli_tokens() -> AppResult<oauth::OAuthTokens> {
     let path = find_existing_claude_credentials_path()
         .ok_or_else(|| AppError::Message("Claude CLI認証情報ファイルが見つかりませんでした".into()))?;
 
@@ -122,17 +122,135 @@ pub fn import_claude_cli_credentials(service: &str, id: &str) -> AppResult<OAuth
         )));
     }
 
-    let mut tokens = oauth::OAuthTokens {
+    Ok(oauth::OAuthTokens {
         access_token: oauth.access_token,
         refresh_token: oauth.refresh_token,
         expires_at: oauth.expires_at,
+    })
+}
+
+/// Log the Claude Code CLI in as `tokens` by rewriting `claudeAiOauth` in its
+/// credentials file. The previous file is kept as `.credentials.json.bak`;
+/// returns the path written.
+pub fn write_claude_cli_tokens(tokens: &oauth::OAuthTokens) -> AppResult<PathBuf> {
+    let path = find_existing_claude_credentials_path()
+        .or_else(|| claude_credentials_candidates().into_iter().next())
+        .ok_or_else(|| AppError::Message("Claude CLI認証情報ファイルの場所が分かりませんでした".into()))?;
+
+    let mut previous = match std::fs::read_to_string(&path) {
+        Ok(raw) => Some(raw),
+        Err(e) if e.kind() == std::io::ErrorKind::NotFound => None,
+        Err(e) => {
+            return Err(AppError::Message(format!(
+                "Claude credentials を読み取れませんでした ({}): {e}",
+                path.display()
+            )))
+        }
     };
+    let merged = merge_claude_credentials(previous.as_deref(), tokens);
+    let result = merged.and_then(|mut body| {
+        let written = (|| {
+            if let Some(raw) = previous.as_deref() {
+                crate::fs_util::atomic_write_private(&claude_backup_path(&path), raw.as_bytes())?;
+            } else if let Some(dir) = path.parent() {
+                std::fs::create_dir_all(dir)?;
+            }
+            crate::fs_util::atomic_write_private(&path, body.as_bytes())
+        })();
+        body.zeroize();
+        written.map_err(|e| {
+            AppError::Message(format!("Claude credentials を書き込めませんでした ({}): {e}", path.display()))
+        })
+    });
+    if let Some(raw) = previous.as_mut() {
+        raw.zeroize();
+    }
+    result.map(|()| path)
+}
+
+fn claude_backup_path(path: &std::path::Path) -> PathBuf {
+    let name = path
+        .file_name()
+        .map(|n| n.to_string_lossy().into_owned())
+        .unwrap_or_default();
+    path.with_file_name(format!("{name}.bak"))
+}
+
+/// Replace the tokens in a credentials file, keeping its other fields. The
+/// plan fields describe the previous login, so only `scopes` is carried over.
+fn merge_claude_credentials(previous: Option<&str>, tokens: &oauth::OAuthTokens) -> AppResult<String> {
+    let mut doc = match previous.filter(|raw| !raw.trim().is_empty()) {
+        Some(raw) => serde_json::from_str::<serde_json::Value>(raw)
+            .map_err(|e| AppError::Message(format!("Claude credentials の形式が不正です: {e}")))?,
+        None => serde_json::json!({}),
+    };
+    let root = doc
+        .as_object_mut()
+        .ok_or_else(|| AppError::Message("Claude credentials の形式が不正です".into()))?;
+
+    let mut oauth = serde_json::Map::new();
+    oauth.insert("accessToken".into(), tokens.access_token.clone().into());
+    oauth.insert("refreshToken".into(), tokens.refresh_token.clone().into());
+    oauth.insert("expiresAt".into(), tokens.expires_at.into());
+    if let Some(scopes) = root.get("claudeAiOauth").and_then(|o| o.get("scopes")) {
+        oauth.insert("scopes".into(), scopes.clone());
+    }
+    root.insert("claudeAiOauth".into(), serde_json::Value::Object(oauth));
+
+    serde_json::to_string(&doc).map_err(|e| AppError::Message(format!("Claude credentials を生成できませんでした: {e}")))
+}
+
+/// How much of a CLI login to take over. Codex rotates refresh tokens on
+/// every use, so only one program can keep refreshing a login.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum CliImportMode {
+    /// Copy the access token only; re-import once it expires.
+    AccessOnly,
+    /// Copy the refresh token too. The first refresh here may invalidate the
+    /// CLI's copy, so the CLI has to log in again afterwards.
+    TakeOver,
+}
+
+impl CliImportMode {
+    pub fn parse(value: &str) -> AppResult<Self> {
+        match value {
+            "accessOnly" => Ok(Self::AccessOnly),
+            "takeOver" => Ok(Self::TakeOver),
+            _ => Err(AppError::InvalidInput(
+                "Import mode must be `accessOnly` or `takeOver`".into(),
+            )),
+        }
+    }
+}
+
+/// Import the login of `service`'s CLI into account `id`.
+pub fn import_cli_credentials(service: &str, id: &str, mode: CliImportMode) -> AppResult<OAuthLoginResult> {
+    let provider = providers::require(service)?;
+    crate::validation::validate_account_id(id)?;
+    if !provider.has_cli() {
+        return Err(AppError::Message(format!(
+            "CLI credentials import is not supported for {}",
+            provider.label()
+        )));
+    }
+
+    let mut tokens = provider.read_cli_tokens(mode == CliImportMode::TakeOver)?;
     let expires_at = tokens.expires_at;
-    store_tokens("claude", id, &mut tokens)?;
+    // set_token clears any refresh token left from an earlier import or login.
+    store_tokens(provider.id(), id, &mut tokens)?;
 
+    let label = provider.label();
+    let message = match mode {
+        CliImportMode::AccessOnly => {
+            format!("{label} CLIのアクセストークンを取り込みました（期限切れ後は再取り込みが必要です）")
+        }
+        CliImportMode::TakeOver => {
+            format!("{label} CLIの認証情報を取り込みました（このアプリがトークンを更新した後は {label} CLI の再ログインが必要になる場合があります）")
+        }
+    };
     Ok(OAuthLoginResult {
         success: true,
-        message: "Claude CLIの認証情報を取り込みました".into(),
+        message,
         has_token: true,
         expires_at,
         auth_url: None,
@@ -141,8 +259,33 @@ pub fn import_claude_cli_credentials(service: &str, id: &str) -> AppResult<OAuth
     })
 }
 
-async fn oauth_login_codex(id: &str) -> AppResult<OAuthLoginResult> {
-    let (auth_url, cancel_tx, handle) = oauth::codex::start_login()
+/// Tokens from ~/.codex/auth.json (or $CODEX_HOME/auth.json).
+pub(crate) fn read_codex_cli_tokens(mode: CliImportMode) -> AppResult<oauth::OAuthTokens> {
+    let path = find_existing_codex_auth_path()
+        .ok_or_else(|| AppError::Message("Codex CLI認証情報ファイルが見つかりませんでした".into()))?;
+
+    let mut raw = std::fs::read_to_string(&path).map_err(|e| {
+        AppError::Message(format!("Codex auth.json を読み取れませんでした ({}): {e}", path.display()))
+    })?;
+    let parsed = parse_codex_auth(&raw, mode);
+    raw.zeroize();
+    parsed.map_err(|e| AppError::Message(format!("{e} ({})", path.display())))
+}
+
+/// The CLI's current access token for `service`, without its refresh token:
+/// accounts linked to a CLI must never refresh on their own.
+pub fn read_cli_access_token(service: &str) -> AppResult<oauth::OAuthTokens> {
+    let mut tokens = providers::require(service)?.read_cli_tokens(false)?;
+    if let Some(mut refresh) = tokens.refresh_token.take() {
+        refresh.zeroize();
+    }
+    Ok(tokens)
+}
+
+async fn oauth_login_loopback(provider: &'static dyn UsageProvider, id: &str) -> AppResult<OAuthLoginResult> {
+    let service = provider.id();
+    let (auth_url, cancel_tx, handle) = provider
+        .start_loopback_login()
         .await
         .map_err(AppError::from)?;
 
@@ -168,7 +311,7 @@ async fn oauth_login_codex(id: &str) -> AppResult<OAuthLoginResult> {
 
         match result {
             Ok(mut tokens) => {
-                let _ = store_tokens("codex", &id_for_task, &mut tokens);
+                let _ = store_tokens(service, &id_for_task, &mut tokens);
             }
             Err(_) => {
                 // Frontend polls token status; errors will show as timeout unless we add a status API.
@@ -179,38 +322,40 @@ async fn oauth_login_codex(id: &str) -> AppResult<OAuthLoginResult> {
     Ok(OAuthLoginResult {
         success: false,
         message: "ログインURLをブラウザで開いて認証してください（完了を待っています）".into(),
-        has_token: token_store::get_token("codex", &id_owned).is_some(),
-        expires_at: token_store::get_expires_at("codex", &id_owned),
+        has_token: token_store::get_token(service, &id_owned).is_some(),
+        expires_at: token_store::get_expires_at(service, &id_owned),
         auth_url: Some(auth_url),
         needs_code: None,
         pending: Some(true),
     })
 }
 
-/// Second step for Claude OAuth: exchange the authorization code.
+/// Second step of the paste-code flow: exchange the authorization code.
 pub async fn oauth_exchange_code(
     service: &str,
     id: &str,
     code: &str,
 ) -> AppResult<OAuthLoginResult> {
-    token_store::ensure_service(service)?;
+    let provider = providers::require(service)?;
     crate::validation::validate_account_id(id)?;
 
-    if service != "claude" {
-        return Err(AppError::Message(
-            "Code exchange is only supported for Claude".into(),
-        ));
+    if provider.auth_kind() != AuthKind::OAuthPasteCode {
+        return Err(AppError::Message(format!(
+            "Code exchange is not supported for {}",
+            provider.label()
+        )));
     }
 
-    let (verifier, state) = {
-        let mut lock = claude_pending_store()
+    let pending = {
+        let mut lock = code_pending_store()
             .lock()
             .map_err(|_| AppError::Message("Lock poisoned".into()))?;
         lock.take()
-            .ok_or_else(|| AppError::Message("No pending Claude login. Start login first.".into()))?
+            .filter(|pending| pending.service == provider.id())
+            .ok_or_else(|| AppError::Message(format!("No pending {} login. Start login first.", provider.label())))?
     };
 
-    let result = oauth::claude::exchange_code(code, &verifier, Some(&state)).await;
+    let result = provider.exchange_code(code, &pending.verifier, &pending.state).await;
 
     match result {
         Ok(mut tokens) => {
@@ -227,7 +372,7 @@ pub async fn oauth_exchange_code(
         }
         Err(e) => Ok(OAuthLoginResult {
             success: false,
-            message: e,
+            message: e.to_string(),
             has_token: token_store::get_token(service, id).is_some(),
             expires_at: token_store::get_expires_at(service, id),
             auth_url: None,
@@ -237,7 +382,7 @@ pub async fn oauth_exchange_code(
     }
 }
 
-fn store_tokens(
+pub fn store_tokens(
     service: &str,
     id: &str,
     tokens: &mut oauth::OAuthTokens,
@@ -256,6 +401,8 @@ fn store_tokens(
     }
 
     tokens.access_token.zeroize();
+    // Fresh tokens end any earlier rejection.
+    token_refresh::record_relogin(service, id, None);
     Ok(())
 }
 
@@ -271,8 +418,8 @@ fn cancel_login_impl() {
             let _ = tx.send(());
         }
     }
-    // Also clear pending Claude state
-    if let Ok(mut lock) = claude_pending_store().lock() {
+    // Also clear pending paste-code state
+    if let Ok(mut lock) = code_pending_store().lock() {
         *lock = None;
     }
 }
@@ -294,7 +441,7 @@ pub async fn refresh_account_token(service: &str, id: &str) -> AppResult<OAuthLo
         }),
         Err(e) => Ok(OAuthLoginResult {
             success: false,
-            message: e,
+            message: e.to_string(),
             has_token: token_store::get_token(service, id).is_some(),
             expires_at: token_store::get_expires_at(service, id),
             auth_url: None,
@@ -346,7 +493,92 @@ struct ClaudeCredentialOauth {
     expires_at: Option<i64>,
 }
 
-fn find_existing_claude_credentials_path() -> Option<PathBuf> {
+#[derive(Debug, Deserialize)]
+struct CodexAuthFile {
+    tokens: Option<CodexAuthTokens>,
+}
+
+#[derive(Debug, Deserialize)]
+struct CodexAuthTokens {
+    access_token: String,
+    refresh_token: Option<String>,
+    /// Epoch seconds or milliseconds, or RFC 3339. Missing in most files; the
+    /// access token's `exp` claim is used then.
+    expires_at: Option<serde_json::Value>,
+}
+
+fn parse_codex_auth(raw: &str, mode: CliImportMode) -> Result<oauth::OAuthTokens, String> {
+    let parsed: CodexAuthFile =
+        serde_json::from_str(raw).map_err(|e| format!("Codex auth.json の形式が不正です: {e}"))?;
+    let tokens = parsed
+        .tokens
+        .ok_or("tokens が見つかりません（API キーでログインしている場合は取り込めません）")?;
+    if tokens.access_token.trim().is_empty() {
+        return Err("access_token が空です".into());
+    }
+    let refresh_token = match mode {
+        CliImportMode::AccessOnly => None,
+        CliImportMode::TakeOver => Some(
+            tokens
+                .refresh_token
+                .filter(|t| !t.trim().is_empty())
+                .ok_or("refresh_token が見つかりません")?,
+        ),
+    };
+    let expires_at = tokens
+        .expires_at
+        .as_ref()
+        .and_then(parse_expires_at)
+        .or_else(|| oauth::jwt_expires_at(&tokens.access_token));
+    Ok(oauth::OAuthTokens {
+        access_token: tokens.access_token,
+        refresh_token,
+        expires_at,
+    })
+}
+
+/// Epoch milliseconds from seconds, milliseconds or an RFC 3339 string.
+fn parse_expires_at(value: &serde_json::Value) -> Option<i64> {
+    match value {
+        serde_json::Value::Number(n) => {
+            let n = n.as_i64()?;
+            // Seconds until the year 33658; anything larger is already millis.
+            Some(if n < 1_000_000_000_000 { n * 1000 } else { n })
+        }
+        serde_json::Value::String(s) => chrono::DateTime::parse_from_rfc3339(s.trim())
+            .ok()
+            .map(|dt| dt.timestamp_millis()),
+        _ => None,
+    }
+}
+
+pub(crate) fn find_existing_codex_auth_path() -> Option<PathBuf> {
+    codex_auth_candidates().into_iter().find(|candidate| candidate.is_file())
+}
+
+fn codex_auth_candidates() -> Vec<PathBuf> {
+    let mut out = Vec::new();
+
+    if let Ok(dir) = std::env::var("CODEX_HOME") {
+        let dir = dir.trim();
+        if !dir.is_empty() {
+            out.push(PathBuf::from(dir).join("auth.json"));
+        }
+    }
+
+    for key in ["HOME", "USERPROFILE"] {
+        if let Ok(home) = std::env::var(key) {
+            let home = home.trim();
+            if !home.is_empty() {
+                out.push(PathBuf::from(home).join(".codex").join("auth.json"));
+            }
+        }
+    }
+
+    out
+}
+
+pub(crate) fn find_existing_claude_credentials_path() -> Option<PathBuf> {
     for candidate in claude_credentials_candidates() {
         if candidate.is_file() {
             return Some(candidate);
@@ -376,3 +608,66 @@ fn claude_credentials_candidates() -> Vec<PathBuf> {
 
     out
 }
+
+#[cfg(test)]
+mod tests {
+    use super::{merge_claude_credentials, parse_codex_auth, CliImportMode};
+    use crate::oauth::OAuthTokens;
+    use base64::engine::general_purpose::URL_SAFE_NO_PAD;
+    use base64::Engine;
+
+    fn jwt(exp: i64) -> String {
+        let claims = URL_SAFE_NO_PAD.encode(format!(r#"{{"exp":{exp}}}"#));
+        format!("eyJhbGciOiJSUzI1NiJ9.{claims}.sig")
+    }
+
+    #[test]
+    fn codex_auth_import_respects_mode() {
+        let raw = format!(
+            r#"{{"OPENAI_API_KEY":null,"tokens":{{"id_token":"x","access_token":"{}","refresh_token":"rt-1","account_id":"acc"}},"last_refresh":"2026-10-01T00:00:00Z"}}"#,
+            jwt(1_800_000_000)
+        );
+        let access_only = parse_codex_auth(&raw, CliImportMode::AccessOnly).unwrap();
+        assert_eq!(access_only.refresh_token, None);
+        assert_eq!(access_only.expires_at, Some(1_800_000_000_000));
+
+        let take_over = parse_codex_auth(&raw, CliImportMode::TakeOver).unwrap();
+        assert_eq!(take_over.refresh_token.as_deref(), Some("rt-1"));
+
+        let explicit = r#"{"tokens":{"access_token":"opaque","expires_at":1700000000}}"#;
+        let parsed = parse_codex_auth(explicit, CliImportMode::AccessOnly).unwrap();
+        assert_eq!(parsed.expires_at, Some(1_700_000_000_000));
+        assert!(parse_codex_auth(explicit, CliImportMode::TakeOver).is_err());
+    }
+
+    #[test]
+    fn codex_auth_without_tokens_is_rejected() {
+        for raw in [r#"{"OPENAI_API_KEY":"sk-x"}"#, r#"{"tokens":{"access_token":" "}}"#, "not json"] {
+            assert!(parse_codex_auth(raw, CliImportMode::AccessOnly).is_err(), "{raw}");
+        }
+        assert!(CliImportMode::parse("both").is_err());
+    }
+
+    #[test]
+    fn claude_credentials_are_replaced_keeping_other_fields() {
+        let tokens = OAuthTokens {
+            access_token: "at-new".to_string(),
+            refresh_token: Some("rt-new".to_string()),
+            expires_at: Some(1_800_000_000_000),
+        };
+        let previous = r#"{"claudeAiOauth":{"accessToken":"at-old","refreshToken":"rt-old","expiresAt":1,"scopes":["user:inference"],"subscriptionType":"max"},"mcpOAuth":{"x":1}}"#;
+        let merged: serde_json::Value =
+            serde_json::from_str(&merge_claude_credentials(Some(previous), &tokens).unwrap()).unwrap();
+        let oauth = &merged["claudeAiOauth"];
+        assert_eq!(oauth["accessToken"], "at-new");
+        assert_eq!(oauth["refreshToken"], "rt-new");
+        assert_eq!(oauth["expiresAt"], 1_800_000_000_000_i64);
+        assert_eq!(oauth["scopes"][0], "user:inference");
+        assert!(oauth.get("subscriptionType").is_none());
+        assert_eq!(merged["mcpOAuth"]["x"], 1);
+
+        let fresh: serde_json::Value = serde_json::from_str(&merge_claude_credentials(None, &tokens).unwrap()).unwrap();
+        assert_eq!(fresh["claudeAiOauth"]["accessToken"], "at-new");
+        assert!(merge_claude_credentials(Some("[1]"), &tokens).is_err());
+    }
+}
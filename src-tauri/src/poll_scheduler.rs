@@ -0,0 +1,453 @@
+use crate::api_client::ApiError;
+use crate::cli_switch;
+use crate::error::AppError;
+use crate::export_commands;
+use crate::forecast;
+use crate::history_store;
+use crate::notify_dispatch;
+use crate::notify_throttle;
+use crate::reset_watch;
+use crate::status;
+use crate::store_repo::read_store;
+use crate::token_refresh;
+use crate::token_store::get_token;
+use crate::usage_commands::fetch_stored_usage;
+use crate::usage_state::{self, UsageUpdate};
+use serde::Serialize;
+use std::collections::HashMap;
+use std::sync::OnceLock;
+use std::time::Duration;
+use tauri::{AppHandle, Emitter};
+use tokio::sync::mpsc::{unbounded_channel, UnboundedReceiver, UnboundedSender};
+use tokio::time::{sleep_until, Instant};
+
+pub const EVENT_POLL_CYCLE_STARTED: &str = "poll-cycle-started";
+pub const EVENT_USAGE_UPDATED: &str = "usage-updated";
+pub const EVENT_POLL_CYCLE_FINISHED: &str = "poll-cycle-finished";
+pub const EVENT_USAGE_RECLASSIFIED: &str = "usage-reclassified";
+
+const POLL_INTERVAL_MIN_SECS: u64 = 30;
+const RATE_LIMIT_BACKOFF_MAX_SECS: u64 = 3600;
+const RATE_LIMITED_MESSAGE: &str = "レート制限中";
+
+enum SchedulerCommand {
+    /// Polling state or interval changed in the store.
+    Reload,
+    /// Run one cycle immediately (manual refresh).
+    PollNow,
+    /// Notify thresholds changed: re-derive statuses of the latest results.
+    Reclassify,
+    /// A notification was held for a quiet-hours summary: re-arm the flush timer.
+    SummaryHeld,
+}
+
+static SCHEDULER_TX: OnceLock<UnboundedSender<SchedulerCommand>> = OnceLock::new();
+
+#[derive(Debug, Clone, Serialize)]
+#[serde(rename_all = "camelCase")]
+struct PollCycleStarted {
+    started_at: i64,
+    interval: i32,
+    manual: bool,
+}
+
+#[derive(Debug, Clone, Serialize)]
+#[serde(rename_all = "camelCase")]
+struct PollCycleFinished {
+    started_at: i64,
+    finished_at: i64,
+    next_poll_at: Option<i64>,
+    any_success: bool,
+}
+
+#[derive(Debug, Clone, Copy, Default)]
+struct RateLimitState {
+    until: i64,
+    consecutive: u32,
+}
+
+/// Spawn the background poll loop. Safe to call once during app setup.
+pub fn start(app: AppHandle) {
+    let (tx, rx) = unbounded_channel();
+    if SCHEDULER_TX.set(tx).is_err() {
+        return;
+    }
+    tauri::async_runtime::spawn(run(app, rx));
+}
+
+/// Re-read `polling_state` from the store and reschedule accordingly.
+pub fn reload() {
+    send(SchedulerCommand::Reload);
+}
+
+/// Run one poll cycle now, regardless of whether polling is active.
+pub fn poll_now() {
+    send(SchedulerCommand::PollNow);
+}
+
+/// Re-classify the latest results with the current notify thresholds.
+pub fn reclassify() {
+    send(SchedulerCommand::Reclassify);
+}
+
+/// Schedule the quiet-hours summary for a newly held notification.
+pub fn summary_held() {
+    send(SchedulerCommand::SummaryHeld);
+}
+
+fn send(cmd: SchedulerCommand) {
+    if let Some(tx) = SCHEDULER_TX.get() {
+        let _ = tx.send(cmd);
+    }
+}
+
+/// Returns the poll interval when polling is active.
+fn active_interval(app: &AppHandle) -> Option<Duration> {
+    let store = read_store(app).ok()?;
+    polling_interval(&store.settings.polling_state)
+}
+
+/// The configured interval clamped to `POLL_INTERVAL_MIN_SECS`, or None when paused.
+fn polling_interval(polling: &crate::PollingState) -> Option<Duration> {
+    if !polling.active {
+        return None;
+    }
+    let secs = u64::try_from(polling.interval).unwrap_or(0).max(POLL_INTERVAL_MIN_SECS);
+    Some(Duration::from_secs(secs))
+}
+
+async fn run(app: AppHandle, mut rx: UnboundedReceiver<SchedulerCommand>) {
+    let mut rate_limits: HashMap<String, RateLimitState> = HashMap::new();
+    let mut last_started: Option<Instant> = None;
+    let mut next_due: Option<Instant> = active_interval(&app).map(|_| Instant::now());
+
+    loop {
+        let deadline = next_due.unwrap_or_else(Instant::now);
+        let reset_due = reset_watch::next_due().map(instant_at);
+        let flush_due = notify_dispatch::next_summary_flush(&app).map(instant_at);
+        tokio::select! {
+            cmd = rx.recv() => {
+                let Some(cmd) = cmd else {
+                    break;
+                };
+                match cmd {
+                    SchedulerCommand::Reload => {
+                        next_due = match (active_interval(&app), next_due) {
+                            (None, _) => None,
+                            // Just activated: poll right away.
+                            (Some(_), None) => Some(Instant::now()),
+                            (Some(interval), Some(_)) => {
+                                Some(last_started.map_or_else(Instant::now, |t| t + interval))
+                            }
+                        };
+                    }
+                    SchedulerCommand::PollNow => {
+                        let started = Instant::now();
+                        last_started = Some(started);
+                        next_due = run_cycle(&app, &mut rate_limits, started, true).await;
+                    }
+                    SchedulerCommand::Reclassify => reclassify_latest(&app),
+                    // The flush timer is recomputed at the top of the loop.
+                    SchedulerCommand::SummaryHeld => {}
+                }
+            }
+            _ = sleep_until(deadline), if next_due.is_some() => {
+                let started = Instant::now();
+                last_started = Some(started);
+                next_due = run_cycle(&app, &mut rate_limits, started, false).await;
+            }
+            _ = sleep_until(reset_due.unwrap_or_else(Instant::now)), if reset_due.is_some() => {
+                run_reset_checks(&app, &mut rate_limits).await;
+            }
+            _ = sleep_until(flush_due.unwrap_or_else(Instant::now)), if flush_due.is_some() => {
+                notify_dispatch::flush_quiet_summaries(&app);
+            }
+        }
+    }
+}
+
+/// `Instant` for an epoch-millis time; past times map to now.
+fn instant_at(at: i64) -> Instant {
+    Instant::now() + Duration::from_millis(u64::try_from(at - now_millis()).unwrap_or(0))
+}
+
+/// Poll every account once and return when the next cycle is due, so the
+/// scheduler sleeps until the same time that `next_poll_at` reports.
+async fn run_cycle(
+    app: &AppHandle,
+    rate_limits: &mut HashMap<String, RateLimitState>,
+    started: Instant,
+    manual: bool,
+) -> Option<Instant> {
+    let Ok(store) = read_store(app) else {
+        return None;
+    };
+    let polling = &store.settings.polling_state;
+    let interval = polling_interval(polling);
+    let started_at = now_millis();
+    let _ = app.emit(
+        EVENT_POLL_CYCLE_STARTED,
+        PollCycleStarted {
+            started_at,
+            interval: polling.interval,
+            manual,
+        },
+    );
+
+    let mut any_success = false;
+    let mut configured = Vec::new();
+    for provider in crate::providers::all() {
+        let service = provider.id();
+        for account in store.services.get(service).into_iter().flatten() {
+            configured.push(usage_state::account_key(service, &account.id));
+            if get_token(service, &account.id).is_none() {
+                continue;
+            }
+            let update = poll_and_dispatch(app, &store, service, account, rate_limits).await;
+            any_success |= update.error.is_none();
+        }
+    }
+    usage_state::retain_accounts(&configured);
+    notify_throttle::retain_accounts(&configured);
+    rate_limits.retain(|key, _| configured.contains(key));
+    finish_results(app).await;
+
+    let next_poll_at = interval.map(|i| started_at + i64::try_from(i.as_millis()).unwrap_or(i64::MAX));
+    let _ = app.emit(
+        EVENT_POLL_CYCLE_FINISHED,
+        PollCycleFinished {
+            started_at,
+            finished_at: now_millis(),
+            next_poll_at,
+            any_success,
+        },
+    );
+    interval.map(|i| started + i)
+}
+
+/// Fetch one account, classify and record the result, and send the
+/// notifications for its reset and status change.
+async fn poll_and_dispatch(
+    app: &AppHandle,
+    store: &crate::Store,
+    service: &str,
+    account: &crate::AccountEntry,
+    rate_limits: &mut HashMap<String, RateLimitState>,
+) -> UsageUpdate {
+    let interval = store.settings.polling_state.interval;
+    let mut update = poll_account(service, account, interval, rate_limits).await;
+    let fetched = update.error.is_none() && !update.skipped;
+    if fetched {
+        forecast::annotate(app, service, &account.id, &mut update.windows, update.fetched_at);
+    }
+    let previous = usage_state::get(service, &account.id);
+    classify(&mut update, &store.settings.notify_settings, previous.as_ref());
+    let restored = reset_watch::resolve(
+        service,
+        &account.id,
+        fetched.then_some(update.windows.as_slice()),
+        update.fetched_at,
+    );
+    if update.error.is_none() {
+        if !update.skipped {
+            let _ = history_store::record_usage(app, service, &account.id, &update.windows, update.fetched_at);
+        }
+        if !restored.is_empty() {
+            notify_dispatch::dispatch_reset(app, &update, &restored);
+        }
+        notify_dispatch::dispatch_transition(app, previous.map(|p| p.status).as_deref(), &update);
+    }
+    usage_state::record(&update);
+    let _ = app.emit(EVENT_USAGE_UPDATED, &update);
+    update
+}
+
+/// Refetch the accounts whose window reset timers are due.
+async fn run_reset_checks(app: &AppHandle, rate_limits: &mut HashMap<String, RateLimitState>) {
+    let now = now_millis();
+    let due = reset_watch::due_accounts(now);
+    if due.is_empty() {
+        return;
+    }
+    let Ok(store) = read_store(app) else {
+        // Count the attempt and push the timers back, or they stay due and
+        // the loop wakes up again immediately.
+        for (service, id) in due {
+            reset_watch::resolve(&service, &id, None, now);
+        }
+        return;
+    };
+    for (service, id) in due {
+        let account = store
+            .services
+            .get(&service)
+            .and_then(|list| list.iter().find(|a| a.id == id))
+            .filter(|_| get_token(&service, &id).is_some());
+        match account {
+            Some(account) => {
+                poll_and_dispatch(app, &store, &service, account, rate_limits).await;
+            }
+            // Gone or signed out: count it as a failed attempt so the timer expires.
+            None => {
+                reset_watch::resolve(&service, &id, None, now);
+            }
+        }
+    }
+    finish_results(app).await;
+}
+
+/// Shared tail of a poll: usage cache, quiet-hours summaries, reset timers
+/// and the CLI auto-switch.
+async fn finish_results(app: &AppHandle) {
+    let latest = usage_state::latest();
+    let _ = export_commands::write_usage_cache(app, &latest);
+    notify_dispatch::flush_quiet_summaries(app);
+    reset_watch::sync(&latest, now_millis());
+    cli_switch::auto_switch(app).await;
+}
+
+/// Classify against the thresholds; with a `previous` update, statuses step
+/// down only past the hysteresis margin.
+fn classify(update: &mut UsageUpdate, notify: &crate::NotifySettings, previous: Option<&UsageUpdate>) {
+    status::classify_windows(&mut update.windows, notify);
+    if let Some(previous) = previous {
+        status::apply_hysteresis(&mut update.windows, &previous.windows, notify);
+    }
+    update.status = status::account_status(&update.windows, update.error.as_deref());
+}
+
+fn reclassify_latest(app: &AppHandle) {
+    let Ok(store) = read_store(app) else {
+        return;
+    };
+    let mut updates = Vec::new();
+    for mut update in usage_state::latest() {
+        if update.windows.is_empty() {
+            continue;
+        }
+        let previous = update.status.clone();
+        classify(&mut update, &store.settings.notify_settings, None);
+        usage_state::set_latest(&update);
+        notify_dispatch::dispatch_transition(app, Some(&previous), &update);
+        updates.push(update);
+    }
+    reset_watch::sync(&usage_state::latest(), now_millis());
+    let _ = export_commands::write_usage_cache(app, &usage_state::latest());
+    let _ = app.emit(EVENT_USAGE_RECLASSIFIED, &updates);
+}
+
+async fn poll_account(
+    service: &str,
+    account: &crate::AccountEntry,
+    interval: i32,
+    rate_limits: &mut HashMap<String, RateLimitState>,
+) -> UsageUpdate {
+    let key = usage_state::account_key(service, &account.id);
+    let now = now_millis();
+    let mut update = UsageUpdate {
+        service: service.to_string(),
+        id: account.id.clone(),
+        name: account.name.clone(),
+        fetched_at: now,
+        windows: Vec::new(),
+        status: String::new(),
+        raw: None,
+        error: None,
+        rate_limit_until: None,
+        skipped: false,
+    };
+
+    // Still backing off after a 429: keep the previous windows and skip the request.
+    if let Some(limit) = rate_limits.get(&key).filter(|l| l.until > now) {
+        let previous = usage_state::get(service, &account.id);
+        update.windows = previous.as_ref().map(|p| p.windows.clone()).unwrap_or_default();
+        update.error = previous
+            .and_then(|p| p.error)
+            .or_else(|| Some(RATE_LIMITED_MESSAGE.to_string()));
+        update.rate_limit_until = Some(limit.until);
+        update.skipped = true;
+        return update;
+    }
+
+    // Errors here surface through the fetch below (expired token -> HTTP 401).
+    let _ = token_refresh::refresh_if_expiring(service, &account.id).await;
+
+    match fetch_stored_usage(service, &account.id).await {
+        Ok(response) => {
+            rate_limits.remove(&key);
+            update.windows = response.windows;
+            update.raw = Some(response.raw);
+        }
+        Err(e) => {
+            if let AppError::Api(ApiError::RateLimited { retry_after_secs, .. }) = &e {
+                let limit = next_rate_limit(rate_limits.get(&key).copied(), *retry_after_secs, interval, now);
+                update.rate_limit_until = Some(limit.until);
+                rate_limits.insert(key, limit);
+            }
+            update.error = Some(e.to_string());
+        }
+    }
+    update.fetched_at = now_millis();
+    update
+}
+
+fn next_rate_limit(
+    previous: Option<RateLimitState>,
+    retry_after_secs: Option<u64>,
+    interval: i32,
+    now: i64,
+) -> RateLimitState {
+    // An explicit positive retry-after wins and resets the backoff counter.
+    if let Some(secs) = retry_after_secs.filter(|s| *s > 0) {
+        return RateLimitState {
+            until: now + (secs as i64) * 1000,
+            consecutive: 0,
+        };
+    }
+    let consecutive = previous.map_or(0, |p| p.consecutive) + 1;
+    let secs = rate_limit_backoff_secs(interval, consecutive);
+    RateLimitState {
+        until: now + (secs as i64) * 1000,
+        consecutive,
+    }
+}
+
+/// Exponential backoff for 429 without retry-after: interval * 2^n, clamped to [30s, 1h].
+fn rate_limit_backoff_secs(interval: i32, consecutive: u32) -> u64 {
+    let base = u64::try_from(interval).unwrap_or(0);
+    let factor = 1u64 << consecutive.min(16);
+    base.saturating_mul(factor)
+        .clamp(POLL_INTERVAL_MIN_SECS, RATE_LIMIT_BACKOFF_MAX_SECS)
+}
+
+fn now_millis() -> i64 {
+    std::time::SystemTime::now()
+        .duration_since(std::time::UNIX_EPOCH)
+        .unwrap_or_default()
+        .as_millis() as i64
+}
+
+#[cfg(test)]
+mod tests {
+    use super::{next_rate_limit, rate_limit_backoff_secs, RateLimitState};
+
+    #[test]
+    fn rate_limit_backoff_doubles_and_clamps() {
+        assert_eq!(rate_limit_backoff_secs(60, 1), 120);
+        assert_eq!(rate_limit_backoff_secs(60, 2), 240);
+        assert_eq!(rate_limit_backoff_secs(600, 5), 3600);
+        assert_eq!(rate_limit_backoff_secs(0, 1), 30);
+    }
+
+    #[test]
+    fn retry_after_resets_backoff_counter() {
+        let previous = Some(RateLimitState { until: 0, consecutive: 3 });
+        let limit = next_rate_limit(previous, Some(90), 600, 1_000);
+        assert_eq!(limit.until, 91_000);
+        assert_eq!(limit.consecutive, 0);
+
+        let limit = next_rate_limit(previous, None, 60, 0);
+        assert_eq!(limit.consecutive, 4);
+        assert_eq!(limit.until, 960_000);
+    }
+}
@@ -0,0 +1,78 @@
+use super::{AuthKind, LoginFuture, RefreshFuture, UsageProvider};
+use crate::error::{AppError, AppResult};
+use crate::oauth::OAuthTokens;
+use crate::usage_parser::{parse_claude_usage, UsageWindow};
+use reqwest::header::{HeaderMap, HeaderValue};
+use serde_json::Value;
+use std::path::PathBuf;
+use zeroize::Zeroize;
+
+const USAGE_URL: &str = "https://api.anthropic.com/api/oauth/usage";
+
+pub struct ClaudeProvider;
+
+impl UsageProvider for ClaudeProvider {
+    fn id(&self) -> &'static str {
+        "claude"
+    }
+
+    fn label(&self) -> &'static str {
+        "Claude Code"
+    }
+
+    fn auth_kind(&self) -> AuthKind {
+        AuthKind::OAuthPasteCode
+    }
+
+    fn allowed_hosts(&self) -> &'static [&'static str] {
+        &["api.anthropic.com", "console.anthropic.com", "claude.ai"]
+    }
+
+    fn usage_url(&self) -> &'static str {
+        USAGE_URL
+    }
+
+    fn usage_headers(&self, token: &str) -> AppResult<HeaderMap> {
+        let mut headers = super::bearer_headers(token)?;
+        headers.insert(
+            "anthropic-beta",
+            HeaderValue::from_str(crate::ANTHROPIC_OAUTH_BETA)
+                .map_err(|e| AppError::InvalidInput(format!("Invalid anthropic-beta header: {e}")))?,
+        );
+        Ok(headers)
+    }
+
+    fn parse_usage(&self, data: &Value) -> Vec<UsageWindow> {
+        parse_claude_usage(data)
+    }
+
+    fn refresh_token<'a>(&'a self, refresh_token: &'a str) -> RefreshFuture<'a> {
+        Box::pin(crate::oauth::claude::refresh_token(refresh_token))
+    }
+
+    fn paste_code_auth_url(&self) -> Option<(String, String, String)> {
+        Some(crate::oauth::claude::build_auth_url())
+    }
+
+    fn exchange_code<'a>(&'a self, code: &'a str, verifier: &'a str, state: &'a str) -> LoginFuture<'a, OAuthTokens> {
+        Box::pin(crate::oauth::claude::exchange_code(code, verifier, Some(state)))
+    }
+
+    fn has_cli(&self) -> bool {
+        true
+    }
+
+    fn cli_credentials_path(&self) -> Option<PathBuf> {
+        crate::oauth_commands::find_existing_claude_credentials_path()
+    }
+
+    fn read_cli_tokens(&self, with_refresh: bool) -> AppResult<OAuthTokens> {
+        let mut tokens = crate::oauth_commands::read_claude_cli_tokens()?;
+        if !with_refresh {
+            if let Some(mut refresh) = tokens.refresh_token.take() {
+                refresh.zeroize();
+            }
+        }
+        Ok(tokens)
+    }
+}
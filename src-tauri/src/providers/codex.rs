@@ -0,0 +1,58 @@
+use super::{AuthKind, LoginFuture, LoopbackLogin, RefreshFuture, UsageProvider};
+use crate::error::AppResult;
+use crate::oauth::OAuthTokens;
+use crate::oauth_commands::CliImportMode;
+use crate::usage_parser::{parse_codex_usage, UsageWindow};
+use serde_json::Value;
+use std::path::PathBuf;
+
+const USAGE_URL: &str = "https://chatgpt.com/backend-api/wham/usage";
+
+pub struct CodexProvider;
+
+impl UsageProvider for CodexProvider {
+    fn id(&self) -> &'static str {
+        "codex"
+    }
+
+    fn label(&self) -> &'static str {
+        "Codex"
+    }
+
+    fn auth_kind(&self) -> AuthKind {
+        AuthKind::OAuthLoopback
+    }
+
+    fn allowed_hosts(&self) -> &'static [&'static str] {
+        &["chatgpt.com", "auth.openai.com"]
+    }
+
+    fn usage_url(&self) -> &'static str {
+        USAGE_URL
+    }
+
+    fn parse_usage(&self, data: &Value) -> Vec<UsageWindow> {
+        parse_codex_usage(data)
+    }
+
+    fn refresh_token<'a>(&'a self, refresh_token: &'a str) -> RefreshFuture<'a> {
+        Box::pin(crate::oauth::codex::refresh_token(refresh_token))
+    }
+
+    fn start_loopback_login(&self) -> LoginFuture<'static, LoopbackLogin> {
+        Box::pin(crate::oauth::codex::start_login())
+    }
+
+    fn has_cli(&self) -> bool {
+        true
+    }
+
+    fn cli_credentials_path(&self) -> Option<PathBuf> {
+        crate::oauth_commands::find_existing_codex_auth_path()
+    }
+
+    fn read_cli_tokens(&self, with_refresh: bool) -> AppResult<OAuthTokens> {
+        let mode = if with_refresh { CliImportMode::TakeOver } else { CliImportMode::AccessOnly };
+        crate::oauth_commands::read_codex_cli_tokens(mode)
+    }
+}
@@ -0,0 +1,149 @@
+pub mod claude;
+pub mod codex;
+
+use crate::error::{AppError, AppResult};
+use crate::oauth::{OAuthTokens, RefreshError};
+use crate::usage_parser::UsageWindow;
+use reqwest::header::{HeaderMap, HeaderValue, ACCEPT, AUTHORIZATION};
+use serde::Serialize;
+use serde_json::Value;
+use std::future::Future;
+use std::path::PathBuf;
+use std::pin::Pin;
+use tokio::sync::oneshot;
+
+pub type RefreshFuture<'a> = Pin<Box<dyn Future<Output = Result<OAuthTokens, RefreshError>> + Send + 'a>>;
+pub type LoginFuture<'a, T> = Pin<Box<dyn Future<Output = Result<T, String>> + Send + 'a>>;
+
+/// A loopback login waiting for its callback: the authorization URL, a sender
+/// that cancels the wait, and the task that resolves to the exchanged tokens.
+pub type LoopbackLogin = (
+    String,
+    oneshot::Sender<()>,
+    tokio::task::JoinHandle<Result<OAuthTokens, String>>,
+);
+
+/// How the user obtains a token for a provider.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub enum AuthKind {
+    /// OAuth where the user pastes `code#state` back into the app.
+    OAuthPasteCode,
+    /// OAuth completed through the local loopback callback server.
+    OAuthLoopback,
+}
+
+/// One AI vendor whose quota we can monitor.
+///
+/// Adding a vendor means implementing this trait in a new module and
+/// listing it in [`PROVIDERS`].
+pub trait UsageProvider: Sync {
+    /// Stable id used in the store, keyring keys and IPC payloads.
+    fn id(&self) -> &'static str;
+    /// Display name used in notifications and logs.
+    fn label(&self) -> &'static str;
+    fn auth_kind(&self) -> AuthKind;
+    /// Hosts the usage request may be sent to.
+    fn allowed_hosts(&self) -> &'static [&'static str];
+    fn usage_url(&self) -> &'static str;
+    /// Headers for the usage request. The default is a bearer token accepting JSON.
+    fn usage_headers(&self, token: &str) -> AppResult<HeaderMap> {
+        bearer_headers(token)
+    }
+    fn parse_usage(&self, data: &Value) -> Vec<UsageWindow>;
+    fn refresh_token<'a>(&'a self, refresh_token: &'a str) -> RefreshFuture<'a>;
+
+    /// [`AuthKind::OAuthPasteCode`]: authorization URL, PKCE verifier and state.
+    fn paste_code_auth_url(&self) -> Option<(String, String, String)> {
+        None
+    }
+    /// [`AuthKind::OAuthPasteCode`]: exchange the pasted `code#state` (or callback URL).
+    fn exchange_code<'a>(&'a self, _code: &'a str, _verifier: &'a str, _state: &'a str) -> LoginFuture<'a, OAuthTokens> {
+        let label = self.label();
+        Box::pin(async move { Err(format!("Code exchange is not supported for {label}")) })
+    }
+    /// [`AuthKind::OAuthLoopback`]: start the local callback listener.
+    fn start_loopback_login(&self) -> LoginFuture<'static, LoopbackLogin> {
+        let label = self.label();
+        Box::pin(async move { Err(format!("Loopback login is not supported for {label}")) })
+    }
+
+    /// Whether the vendor's CLI keeps a login that can be imported or followed.
+    fn has_cli(&self) -> bool {
+        false
+    }
+    /// The CLI's credentials file, if it exists.
+    fn cli_credentials_path(&self) -> Option<PathBuf> {
+        None
+    }
+    /// The CLI's current login. The refresh token is only read with `with_refresh`.
+    fn read_cli_tokens(&self, _with_refresh: bool) -> AppResult<OAuthTokens> {
+        Err(AppError::InvalidInput(format!("No CLI credentials for {}", self.id())))
+    }
+}
+
+/// Providers whose CLI login can be imported or followed.
+pub fn with_cli() -> impl Iterator<Item = &'static dyn UsageProvider> {
+    PROVIDERS.iter().copied().filter(|p| p.has_cli())
+}
+
+static PROVIDERS: &[&dyn UsageProvider] = &[&claude::ClaudeProvider, &codex::CodexProvider];
+
+pub fn all() -> &'static [&'static dyn UsageProvider] {
+    PROVIDERS
+}
+
+pub fn get(id: &str) -> Option<&'static dyn UsageProvider> {
+    PROVIDERS.iter().copied().find(|p| p.id() == id)
+}
+
+pub fn require(id: &str) -> AppResult<&'static dyn UsageProvider> {
+    get(id).ok_or(AppError::UnsupportedService)
+}
+
+pub fn is_allowed_host(host: &str) -> bool {
+    PROVIDERS
+        .iter()
+        .any(|p| p.allowed_hosts().contains(&host))
+}
+
+pub fn bearer_headers(token: &str) -> AppResult<HeaderMap> {
+    let mut headers = HeaderMap::new();
+    headers.insert(
+        AUTHORIZATION,
+        HeaderValue::from_str(&format!("Bearer {token}"))
+            .map_err(|e| AppError::InvalidInput(format!("Invalid authorization header: {e}")))?,
+    );
+    headers.insert(ACCEPT, HeaderValue::from_static("application/json"));
+    Ok(headers)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::{all, get, is_allowed_host, with_cli};
+
+    #[test]
+    fn provider_ids_are_unique() {
+        let ids: Vec<_> = all().iter().map(|p| p.id()).collect();
+        let mut deduped = ids.clone();
+        deduped.sort_unstable();
+        deduped.dedup();
+        assert_eq!(ids.len(), deduped.len());
+    }
+
+    #[test]
+    fn lookup_and_allowlist() {
+        assert!(get("claude").is_some());
+        assert!(get("codex").is_some());
+        assert!(get("gemini").is_none());
+        assert!(is_allowed_host("api.anthropic.com"));
+        assert!(is_allowed_host("chatgpt.com"));
+        assert!(!is_allowed_host("example.com"));
+    }
+
+    #[test]
+    fn cli_providers_know_their_credentials() {
+        let ids: Vec<_> = with_cli().map(|p| p.id()).collect();
+        assert_eq!(ids, ["claude", "codex"]);
+    }
+}
@@ -0,0 +1,258 @@
+//! Which account to use right now. Each account is scored by its tightest
+//! window: the headroom left, raised when the window resets soon and lowered
+//! when the burn rate reaches 100% before the reset. Accounts are ranked per
+//! service; reserve accounts come after every other usable one.
+
+use crate::error::AppResult;
+use crate::status::Status;
+use crate::store_repo::read_store;
+use crate::usage_parser::UsageWindow;
+use crate::usage_state::{self, UsageUpdate};
+use serde::{Deserialize, Serialize};
+use tauri::AppHandle;
+
+const HOUR_MS: f64 = 60.0 * 60.0 * 1000.0;
+/// Headroom of a window resetting within this many hours counts partly as refilled.
+const RESET_SOON_HOURS: f64 = 1.0;
+/// Headroom kept when the projection runs out before the reset.
+const BURN_PENALTY: f64 = 0.5;
+/// Score points per priority step.
+const PRIORITY_WEIGHT: f64 = 5.0;
+
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct Recommendation {
+    pub service: String,
+    pub id: String,
+    pub name: String,
+    /// 1-based rank within the service.
+    pub rank: usize,
+    /// Higher is better; `None` when the account is not usable.
+    pub score: Option<f64>,
+    pub usable: bool,
+    pub reserve: bool,
+    /// Human-readable factors behind the score.
+    pub reasons: Vec<String>,
+}
+
+/// Ranking of the latest poll results.
+pub fn latest(app: &AppHandle) -> AppResult<Vec<Recommendation>> {
+    let store = read_store(app)?;
+    let entries: Vec<crate::UsageSnapshotEntry> = usage_state::latest()
+        .iter()
+        .map(UsageUpdate::to_snapshot_entry)
+        .collect();
+    Ok(rank(&entries, &store.services, chrono::Utc::now().timestamp_millis()))
+}
+
+/// Rank the accounts of `entries` with the priority / reserve flags of `services`.
+pub fn rank(entries: &[crate::UsageSnapshotEntry], services: &crate::Services, now: i64) -> Vec<Recommendation> {
+    let mut out: Vec<Recommendation> = entries
+        .iter()
+        .map(|entry| {
+            let account = services
+                .get(&entry.service)
+                .and_then(|list| list.iter().find(|a| a.id == entry.id));
+            score_entry(
+                entry,
+                account.map_or(0, |a| a.priority),
+                account.is_some_and(|a| a.reserve),
+                now,
+            )
+        })
+        .collect();
+    out.sort_by(|a, b| {
+        a.service
+            .cmp(&b.service)
+            .then(tier(a).cmp(&tier(b)))
+            .then(b.score.unwrap_or(0.0).total_cmp(&a.score.unwrap_or(0.0)))
+            .then(a.name.cmp(&b.name))
+    });
+    let mut rank = 0;
+    for i in 0..out.len() {
+        rank = if i > 0 && out[i - 1].service == out[i].service { rank + 1 } else { 1 };
+        out[i].rank = rank;
+    }
+    out
+}
+
+fn tier(r: &Recommendation) -> u8 {
+    match (r.usable, r.reserve) {
+        (true, false) => 0,
+        (true, true) => 1,
+        (false, _) => 2,
+    }
+}
+
+fn score_entry(entry: &crate::UsageSnapshotEntry, priority: i32, reserve: bool, now: i64) -> Recommendation {
+    let mut reasons = Vec::new();
+    let exhausted = entry.status.as_deref().and_then(Status::parse) == Some(Status::Exhausted);
+    let unusable = if let Some(error) = entry.error.as_deref() {
+        Some(format!("取得エラー: {error}"))
+    } else if entry.windows.is_empty() {
+        Some("使用量データなし".to_string())
+    } else if exhausted {
+        Some("exhausted".to_string())
+    } else {
+        None
+    };
+
+    let score = match unusable {
+        Some(reason) => {
+            reasons.push(reason);
+            None
+        }
+        None => {
+            let (tightest, window_reasons) = entry
+                .windows
+                .iter()
+                .map(|w| window_score(w, now))
+                .min_by(|a, b| a.0.total_cmp(&b.0))
+                .unwrap_or((0.0, Vec::new()));
+            reasons.extend(window_reasons);
+            if priority != 0 {
+                reasons.push(format!("優先度 {priority:+}"));
+            }
+            Some(round1(tightest + f64::from(priority) * PRIORITY_WEIGHT))
+        }
+    };
+    if reserve {
+        reasons.push("予備アカウント".to_string());
+    }
+
+    Recommendation {
+        service: entry.service.clone(),
+        id: entry.id.clone(),
+        name: entry.name.clone(),
+        rank: 0,
+        score,
+        usable: score.is_some(),
+        reserve,
+        reasons,
+    }
+}
+
+/// Effective headroom of one window, with the reasons that shaped it.
+fn window_score(window: &UsageWindow, now: i64) -> (f64, Vec<String>) {
+    let headroom = (100.0 - window.utilization).clamp(0.0, 100.0);
+    let mut score = headroom;
+    let mut reasons = vec![format!("{}: 残り {}%", window.name, round1(headroom))];
+
+    if let Some(resets_at) = window.resets_at_millis().filter(|&at| at > now) {
+        let hours = (resets_at - now) as f64 / HOUR_MS;
+        if hours < RESET_SOON_HOURS {
+            score += (100.0 - headroom) * (1.0 - hours / RESET_SOON_HOURS);
+            reasons.push(format!("{}: {}分後にリセット", window.name, (hours * 60.0).ceil()));
+        }
+    }
+    if let Some(forecast) = window.forecast.as_ref().filter(|f| f.before_reset) {
+        score *= BURN_PENALTY;
+        let hours = forecast
+            .exhausted_at
+            .map(|at| ((at - now) as f64 / HOUR_MS).max(0.0))
+            .unwrap_or(0.0);
+        reasons.push(format!("{}: 今のペースだと約{}時間でリセット前に上限", window.name, round1(hours)));
+    }
+    (score, reasons)
+}
+
+fn round1(value: f64) -> f64 {
+    (value * 10.0).round() / 10.0
+}
+
+#[cfg(test)]
+mod tests {
+    use super::rank;
+    use crate::forecast::Forecast;
+    use crate::usage_parser::UsageWindow;
+
+    const HOUR: i64 = 60 * 60 * 1000;
+    const NOW: i64 = 1_000 * HOUR;
+
+    fn window(name: &str, utilization: f64, resets_at: i64) -> UsageWindow {
+        UsageWindow::new(
+            name.to_string(),
+            utilization,
+            Some(serde_json::json!(resets_at / 1000)),
+            None,
+            false,
+            None,
+        )
+    }
+
+    fn entry(id: &str, status: &str, windows: Vec<UsageWindow>) -> crate::UsageSnapshotEntry {
+        crate::UsageSnapshotEntry {
+            service: "claude".to_string(),
+            id: id.to_string(),
+            name: id.to_string(),
+            has_token: true,
+            label: None,
+            status: Some(status.to_string()),
+            windows,
+            error: None,
+        }
+    }
+
+    fn account(id: &str, priority: i32, reserve: bool) -> crate::AccountEntry {
+        crate::AccountEntry {
+            id: id.to_string(),
+            name: id.to_string(),
+            muted_until: None,
+            cli_linked: false,
+            cli_synced_at: None,
+            priority,
+            reserve,
+            relogin_reason: None,
+        }
+    }
+
+    #[test]
+    fn tightest_window_reset_and_burn_shape_the_ranking() {
+        let mut burning = window("5h", 30.0, NOW + 4 * HOUR);
+        burning.forecast = Some(Forecast {
+            rate_per_hour: 25.0,
+            exhausted_at: Some(NOW + 3 * HOUR),
+            before_reset: true,
+            samples: 5,
+        });
+        let entries = vec![
+            // Tightest window 7d: 40 left.
+            entry("a", "ok", vec![window("5h", 10.0, NOW + 4 * HOUR), window("7d", 60.0, NOW + 72 * HOUR)]),
+            // 90% used but resets in 15 minutes: 10 + 90 * 0.75 = 77.5.
+            entry("b", "warning", vec![window("5h", 90.0, NOW + HOUR / 4)]),
+            // 70 left, halved by the projection: 35.
+            entry("c", "ok", vec![burning]),
+            entry("d", "exhausted", vec![window("5h", 100.0, NOW + 2 * HOUR)]),
+        ];
+        let ranked = rank(&entries, &Default::default(), NOW);
+        let order: Vec<&str> = ranked.iter().map(|r| r.id.as_str()).collect();
+        assert_eq!(order, ["b", "a", "c", "d"]);
+        assert_eq!(ranked[0].score, Some(77.5));
+        assert_eq!(ranked[0].rank, 1);
+        assert!(ranked[0].reasons.iter().any(|r| r == "5h: 15分後にリセット"));
+        assert!(ranked[2].reasons.iter().any(|r| r.contains("リセット前に上限")));
+        assert!(!ranked[3].usable);
+        assert_eq!(ranked[3].rank, 4);
+    }
+
+    #[test]
+    fn priority_and_reserve_flags_apply() {
+        let entries = vec![
+            entry("personal", "ok", vec![window("5h", 0.0, NOW + 4 * HOUR)]),
+            entry("work", "ok", vec![window("5h", 50.0, NOW + 4 * HOUR)]),
+            entry("team", "ok", vec![window("5h", 60.0, NOW + 4 * HOUR)]),
+        ];
+        let services = [(
+            "claude".to_string(),
+            vec![account("personal", 0, true), account("team", 3, false)],
+        )]
+        .into_iter()
+        .collect();
+        let ranked = rank(&entries, &services, NOW);
+        let order: Vec<&str> = ranked.iter().map(|r| r.id.as_str()).collect();
+        // team: 40 + 15 beats work: 50; the reserve comes last despite its headroom.
+        assert_eq!(order, ["team", "work", "personal"]);
+        assert_eq!(ranked[0].score, Some(55.0));
+        assert!(ranked[2].usable && ranked[2].reserve);
+    }
+}
@@ -0,0 +1,229 @@
+//! Timers for the reset time of critical/exhausted windows. When one is due
+//! the poll scheduler fetches the account again, and the reset is announced
+//! once utilization has actually dropped.
+
+use crate::status::Status;
+use crate::usage_parser::UsageWindow;
+use crate::usage_state::UsageUpdate;
+use std::collections::BTreeMap;
+use std::sync::{Mutex, OnceLock};
+
+/// Wait after `resets_at` before fetching, so the upstream has rolled over.
+const RESET_GRACE_MS: i64 = 30_000;
+/// Retry interval while the fetch fails or utilization has not dropped yet.
+const RESET_RETRY_MS: i64 = 60_000;
+const RESET_MAX_ATTEMPTS: u32 = 5;
+
+#[derive(Debug, Clone, PartialEq)]
+struct Timer {
+    due_at: i64,
+    resets_at: i64,
+    /// Utilization when the timer was set.
+    before: f64,
+    attempts: u32,
+}
+
+/// (service, account id, window name)
+type Key = (String, String, String);
+
+#[derive(Debug, Default)]
+struct Timers(BTreeMap<Key, Timer>);
+
+#[derive(Debug, Clone, PartialEq)]
+pub struct RestoredWindow {
+    pub name: String,
+    pub before: f64,
+    pub after: f64,
+}
+
+static TIMERS: OnceLock<Mutex<Timers>> = OnceLock::new();
+
+fn timers() -> &'static Mutex<Timers> {
+    TIMERS.get_or_init(|| Mutex::new(Timers::default()))
+}
+
+fn is_severe(window: &UsageWindow) -> bool {
+    window
+        .status
+        .as_deref()
+        .and_then(Status::parse)
+        .is_some_and(|s| s >= Status::Critical)
+}
+
+impl Timers {
+    fn sync(&mut self, updates: &[UsageUpdate], now: i64) {
+        self.0
+            .retain(|(service, id, _), _| updates.iter().any(|u| &u.service == service && &u.id == id));
+        for update in updates {
+            for window in update.windows.iter().filter(|w| is_severe(w)) {
+                let Some(resets_at) = window.resets_at_millis().filter(|at| *at > now) else {
+                    continue;
+                };
+                let key = (update.service.clone(), update.id.clone(), window.name.clone());
+                let timer = Timer {
+                    due_at: resets_at + RESET_GRACE_MS,
+                    resets_at,
+                    before: window.utilization,
+                    attempts: 0,
+                };
+                match self.0.get_mut(&key) {
+                    // Already retrying after the reset: leave it alone.
+                    Some(existing) if existing.attempts > 0 => {}
+                    Some(existing) => *existing = timer,
+                    None => {
+                        self.0.insert(key, timer);
+                    }
+                }
+            }
+        }
+    }
+
+    fn next_due(&self) -> Option<i64> {
+        self.0.values().map(|t| t.due_at).min()
+    }
+
+    /// Accounts (service, id) with at least one due timer.
+    fn due_accounts(&self, now: i64) -> Vec<(String, String)> {
+        let mut accounts: Vec<(String, String)> = self
+            .0
+            .iter()
+            .filter(|(_, t)| t.due_at <= now)
+            .map(|((service, id, _), _)| (service.clone(), id.clone()))
+            .collect();
+        accounts.dedup();
+        accounts
+    }
+
+    fn resolve(&mut self, service: &str, id: &str, windows: Option<&[UsageWindow]>, now: i64) -> Vec<RestoredWindow> {
+        let mut restored = Vec::new();
+        self.0.retain(|(s, i, name), timer| {
+            if s != service || i != id || timer.resets_at > now {
+                return true;
+            }
+            if let Some(windows) = windows {
+                let Some(window) = windows.iter().find(|w| &w.name == name) else {
+                    return false;
+                };
+                if !is_severe(window) && window.utilization < timer.before {
+                    restored.push(RestoredWindow {
+                        name: name.clone(),
+                        before: timer.before,
+                        after: window.utilization,
+                    });
+                    return false;
+                }
+            }
+            timer.attempts += 1;
+            timer.due_at = now + RESET_RETRY_MS;
+            timer.attempts < RESET_MAX_ATTEMPTS
+        });
+        restored
+    }
+}
+
+/// Set timers for severe windows of the latest results and drop the ones of
+/// accounts that are gone.
+pub fn sync(updates: &[UsageUpdate], now: i64) {
+    if let Ok(mut timers) = timers().lock() {
+        timers.sync(updates, now);
+    }
+}
+
+/// Epoch millis of the earliest timer.
+pub fn next_due() -> Option<i64> {
+    timers().lock().ok()?.next_due()
+}
+
+pub fn due_accounts(now: i64) -> Vec<(String, String)> {
+    timers().lock().map(|t| t.due_accounts(now)).unwrap_or_default()
+}
+
+/// Settle the timers of an account whose reset time has passed, after a
+/// fetch (`None` when it failed or was skipped): returns the windows that
+/// reset; the rest are retried a few times.
+pub fn resolve(service: &str, id: &str, windows: Option<&[UsageWindow]>, now: i64) -> Vec<RestoredWindow> {
+    timers()
+        .lock()
+        .map(|mut t| t.resolve(service, id, windows, now))
+        .unwrap_or_default()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::{Timers, RESET_GRACE_MS, RESET_MAX_ATTEMPTS, RESET_RETRY_MS};
+    use crate::usage_parser::UsageWindow;
+    use crate::usage_state::UsageUpdate;
+
+    fn window(name: &str, utilization: f64, status: &str, resets_at_secs: i64) -> UsageWindow {
+        UsageWindow::new(
+            name.to_string(),
+            utilization,
+            Some(serde_json::json!(resets_at_secs)),
+            None,
+            false,
+            Some(status.to_string()),
+        )
+    }
+
+    fn update(windows: Vec<UsageWindow>) -> UsageUpdate {
+        UsageUpdate {
+            service: "claude".to_string(),
+            id: "a1".to_string(),
+            name: "Main".to_string(),
+            fetched_at: 0,
+            windows,
+            status: "critical".to_string(),
+            raw: None,
+            error: None,
+            rate_limit_until: None,
+            skipped: false,
+        }
+    }
+
+    #[test]
+    fn timers_fire_after_reset_and_report_dropped_windows() {
+        let mut timers = Timers::default();
+        let severe = update(vec![
+            window("5h", 100.0, "exhausted", 1_000),
+            window("7d", 60.0, "ok", 2_000),
+        ]);
+        timers.sync(&[severe], 0);
+        let due = 1_000_000 + RESET_GRACE_MS;
+        assert_eq!(timers.next_due(), Some(due));
+        assert!(timers.due_accounts(due - 1).is_empty());
+        assert_eq!(timers.due_accounts(due), vec![("claude".to_string(), "a1".to_string())]);
+
+        // A regular poll before the reset time leaves the timer alone.
+        let early = [window("5h", 100.0, "exhausted", 1_000)];
+        assert!(timers.resolve("claude", "a1", Some(&early), 999_999).is_empty());
+        assert_eq!(timers.next_due(), Some(due));
+
+        // Not rolled over yet: retried later.
+        let still = [window("5h", 100.0, "exhausted", 1_000)];
+        assert!(timers.resolve("claude", "a1", Some(&still), due).is_empty());
+        assert_eq!(timers.next_due(), Some(due + RESET_RETRY_MS));
+
+        let fresh = [window("5h", 2.0, "ok", 19_000)];
+        let restored = timers.resolve("claude", "a1", Some(&fresh), due + RESET_RETRY_MS);
+        assert_eq!(restored.len(), 1);
+        assert_eq!((restored[0].before, restored[0].after), (100.0, 2.0));
+        assert_eq!(timers.next_due(), None);
+    }
+
+    #[test]
+    fn failed_fetches_give_up_and_removed_accounts_are_dropped() {
+        let mut timers = Timers::default();
+        timers.sync(&[update(vec![window("5h", 95.0, "critical", 1_000)])], 0);
+        let mut now = 1_000_000 + RESET_GRACE_MS;
+        for _ in 0..RESET_MAX_ATTEMPTS {
+            assert!(timers.resolve("claude", "a1", None, now).is_empty());
+            now += RESET_RETRY_MS;
+        }
+        assert_eq!(timers.next_due(), None);
+
+        timers.sync(&[update(vec![window("5h", 95.0, "critical", 1_000)])], 0);
+        assert!(timers.next_due().is_some());
+        timers.sync(&[], 0);
+        assert_eq!(timers.next_due(), None);
+    }
+}
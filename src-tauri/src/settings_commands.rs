@@ -1,6 +1,18 @@
-use crate::store_repo::{read_store, write_store};
-use crate::error::AppResult;
-use crate::validation::{validate_discord_webhook_url, validate_export_path, validate_pushover_key};
+use crate::store_repo::{normalize_renotify_minutes, read_store, update_store};
+use crate::error::{AppError, AppResult};
+use crate::external_notify::DEFAULT_WEBHOOK_TEMPLATE;
+use crate::mcp_server;
+use crate::metrics_server;
+use crate::notify_schedule;
+use crate::poll_scheduler;
+use crate::token_store::{self, is_masked_secret, mask_secret};
+use crate::validation::{
+    validate_account_id, validate_bind_address, validate_discord_webhook_url, validate_export_path,
+    validate_ntfy_topic, validate_push_server_url, validate_push_token, validate_pushover_key,
+    validate_slack_webhook_url, validate_teams_webhook_url, validate_webhook_header,
+    validate_webhook_method, validate_webhook_name, validate_webhook_template, validate_webhook_url,
+};
+use std::sync::Mutex;
 use tauri::AppHandle;
 
 pub fn get_settings(app: AppHandle) -> AppResult<crate::Settings> {
@@ -8,8 +20,71 @@ pub fn get_settings(app: AppHandle) -> AppResult<crate::Settings> {
 }
 
 pub fn set_settings(app: AppHandle, payload: crate::SetSettingsPayload) -> AppResult<crate::Settings> {
-    let mut store = read_store(&app)?;
+    let notify_changed = payload.notify_settings.is_some();
+    let (previous, settings) = update_store(&app, |store| {
+        let previous = (store.settings.metrics.clone(), store.settings.mcp.clone());
+        apply_settings_patch(store, payload)?;
+        Ok((previous, store.settings.clone()))
+    })?;
 
+    poll_scheduler::reload();
+    if notify_changed {
+        poll_scheduler::reclassify();
+    }
+
+    let metrics = metrics_server::apply(&app);
+    let mcp = mcp_server::apply(&app);
+    if metrics.is_ok() && mcp.is_ok() {
+        return Ok(settings);
+    }
+    // Put back the listener settings that could not be applied, so the store
+    // keeps describing what is actually listening.
+    let (previous_metrics, previous_mcp) = previous;
+    update_store(&app, |store| {
+        if metrics.is_err() {
+            store.settings.metrics = previous_metrics;
+        }
+        if mcp.is_err() {
+            store.settings.mcp = previous_mcp;
+        }
+        Ok(())
+    })?;
+    if metrics.is_err() {
+        let _ = metrics_server::apply(&app);
+    }
+    if mcp.is_err() {
+        let _ = mcp_server::apply(&app);
+    }
+    metrics.and(mcp).map(|()| settings)
+}
+
+/// Listeners that failed to start with the app, kept for the UI to report.
+static STARTUP_LISTENER_ERRORS: Mutex<Vec<String>> = Mutex::new(Vec::new());
+
+/// Start the `/metrics` and MCP listeners at launch. Failures are kept for
+/// [`startup_listener_errors`] instead of aborting startup.
+pub fn start_listeners(app: &AppHandle) {
+    let errors: Vec<String> = [metrics_server::apply(app), mcp_server::apply(app)]
+        .into_iter()
+        .filter_map(Result::err)
+        .map(|e| e.to_string())
+        .collect();
+    for error in &errors {
+        eprintln!("listener: {error}");
+    }
+    if let Ok(mut slot) = STARTUP_LISTENER_ERRORS.lock() {
+        *slot = errors;
+    }
+}
+
+pub fn startup_listener_errors() -> Vec<String> {
+    STARTUP_LISTENER_ERRORS
+        .lock()
+        .map(|slot| slot.clone())
+        .unwrap_or_default()
+}
+
+fn apply_settings_patch(store: &mut crate::Store, payload: crate::SetSettingsPayload) -> AppResult<()> {
     if let Some(poll_interval) = payload.poll_interval {
         if (30..=3600).contains(&poll_interval) {
             store.settings.poll_interval = poll_interval;
@@ -30,6 +105,9 @@ pub fn set_settings(app: AppHandle, payload: crate::SetSettingsPayload) -> AppRe
         if let Some(v) = ns.warning {
             current.warning = v;
         }
+        if let Some(v) = ns.projected {
+            current.projected = v;
+        }
         if let Some(v) = ns.threshold_warning {
             if (1..=99).contains(&v) {
                 current.threshold_warning = v;
@@ -40,6 +118,31 @@ pub fn set_settings(app: AppHandle, payload: crate::SetSettingsPayload) -> AppRe
                 current.threshold_critical = v;
             }
         }
+        if let Some(v) = ns.hysteresis {
+            if (0..=crate::HYSTERESIS_MAX).contains(&v) {
+                current.hysteresis = v;
+            }
+        }
+        if let Some(v) = ns.cooldown_minutes {
+            if (0..=crate::NOTIFY_MINUTES_MAX).contains(&v) {
+                current.cooldown_minutes = v;
+            }
+        }
+        if let Some(v) = ns.renotify_minutes {
+            current.renotify_minutes = normalize_renotify_minutes(Some(v), 0);
+        }
+        if let Some(rules) = ns.quiet_hours {
+            if rules.len() > crate::MAX_QUIET_HOURS_RULES {
+                return Err(AppError::InvalidInput(format!(
+                    "Too many quiet hours rules (max {})",
+                    crate::MAX_QUIET_HOURS_RULES
+                )));
+            }
+            current.quiet_hours = rules
+                .iter()
+                .map(notify_schedule::rule_from_raw)
+                .collect::<AppResult<_>>()?;
+        }
     }
 
     if let Some(es) = payload.usage_export {
@@ -68,13 +171,13 @@ pub fn set_settings(app: AppHandle, payload: crate::SetSettingsPayload) -> AppRe
                 current.enabled = v;
             }
             if let Some(url) = discord.webhook_url {
-                let trimmed = url.trim().to_string();
-                if trimmed.is_empty() {
-                    current.webhook_url = String::new();
-                } else {
-                    validate_discord_webhook_url(&trimmed)?;
-                    current.webhook_url = trimmed;
-                }
+                patch_notify_secret(
+                    &mut current.webhook_url,
+                    &url,
+                    token_store::DISCORD_WEBHOOK_URL_SECRET,
+                    validate_discord_webhook_url,
+                )?;
+                current.has_secret = is_masked_secret(&current.webhook_url);
             }
             if let Some(v) = discord.critical {
                 current.critical = v;
@@ -85,6 +188,69 @@ pub fn set_settings(app: AppHandle, payload: crate::SetSettingsPayload) -> AppRe
             if let Some(v) = discord.warning {
                 current.warning = v;
             }
+            if let Some(v) = discord.projected {
+                current.projected = v;
+            }
+            if current.enabled && current.webhook_url.is_empty() {
+                current.enabled = false;
+            }
+        }
+        if let Some(slack) = en.slack {
+            let current = &mut store.settings.external_notify.slack;
+            if let Some(v) = slack.enabled {
+                current.enabled = v;
+            }
+            if let Some(url) = slack.webhook_url {
+                patch_notify_secret(
+                    &mut current.webhook_url,
+                    &url,
+                    token_store::SLACK_WEBHOOK_URL_SECRET,
+                    validate_slack_webhook_url,
+                )?;
+                current.has_secret = is_masked_secret(&current.webhook_url);
+            }
+            if let Some(v) = slack.critical {
+                current.critical = v;
+            }
+            if let Some(v) = slack.recovery {
+                current.recovery = v;
+            }
+            if let Some(v) = slack.warning {
+                current.warning = v;
+            }
+            if let Some(v) = slack.projected {
+                current.projected = v;
+            }
+            if current.enabled && current.webhook_url.is_empty() {
+                current.enabled = false;
+            }
+        }
+        if let Some(teams) = en.teams {
+            let current = &mut store.settings.external_notify.teams;
+            if let Some(v) = teams.enabled {
+                current.enabled = v;
+            }
+            if let Some(url) = teams.webhook_url {
+                patch_notify_secret(
+                    &mut current.webhook_url,
+                    &url,
+                    token_store::TEAMS_WEBHOOK_URL_SECRET,
+                    validate_teams_webhook_url,
+                )?;
+                current.has_secret = is_masked_secret(&current.webhook_url);
+            }
+            if let Some(v) = teams.critical {
+                current.critical = v;
+            }
+            if let Some(v) = teams.recovery {
+                current.recovery = v;
+            }
+            if let Some(v) = teams.warning {
+                current.warning = v;
+            }
+            if let Some(v) = teams.projected {
+                current.projected = v;
+            }
             if current.enabled && current.webhook_url.is_empty() {
                 current.enabled = false;
             }
@@ -97,21 +263,26 @@ pub fn set_settings(app: AppHandle, payload: crate::SetSettingsPayload) -> AppRe
             if let Some(token) = pushover.api_token {
                 let trimmed = token.trim().to_string();
                 if trimmed.is_empty() {
+                    token_store::delete_notify_secret(token_store::PUSHOVER_API_TOKEN_SECRET)?;
                     current.api_token = String::new();
-                } else {
+                } else if !is_masked_secret(&trimmed) {
                     validate_pushover_key(&trimmed, "Pushover API Token")?;
-                    current.api_token = trimmed;
+                    token_store::set_notify_secret(token_store::PUSHOVER_API_TOKEN_SECRET, &trimmed)?;
+                    current.api_token = mask_secret(&trimmed);
                 }
             }
             if let Some(key) = pushover.user_key {
                 let trimmed = key.trim().to_string();
                 if trimmed.is_empty() {
+                    token_store::delete_notify_secret(token_store::PUSHOVER_USER_KEY_SECRET)?;
                     current.user_key = String::new();
-                } else {
+                } else if !is_masked_secret(&trimmed) {
                     validate_pushover_key(&trimmed, "Pushover User Key")?;
-                    current.user_key = trimmed;
+                    token_store::set_notify_secret(token_store::PUSHOVER_USER_KEY_SECRET, &trimmed)?;
+                    current.user_key = mask_secret(&trimmed);
                 }
             }
+            current.has_secret = is_masked_secret(&current.api_token) && is_masked_secret(&current.user_key);
             if let Some(v) = pushover.critical {
                 current.critical = v;
             }
@@ -121,16 +292,293 @@ pub fn set_settings(app: AppHandle, payload: crate::SetSettingsPayload) -> AppRe
             if let Some(v) = pushover.warning {
                 current.warning = v;
             }
+            if let Some(v) = pushover.projected {
+                current.projected = v;
+            }
             if current.enabled
                 && (current.api_token.is_empty() || current.user_key.is_empty())
             {
                 current.enabled = false;
             }
         }
+        if let Some(ntfy) = en.ntfy {
+            let current = &mut store.settings.external_notify.ntfy;
+            if let Some(v) = ntfy.enabled {
+                current.enabled = v;
+            }
+            if let Some(url) = ntfy.server_url {
+                let trimmed = url.trim().trim_end_matches('/');
+                current.server_url = if trimmed.is_empty() {
+                    crate::NTFY_SERVER_DEFAULT.to_string()
+                } else {
+                    validate_push_server_url(trimmed, "ntfy")?;
+                    trimmed.to_string()
+                };
+            }
+            if let Some(topic) = ntfy.topic {
+                let trimmed = topic.trim();
+                if !trimmed.is_empty() {
+                    validate_ntfy_topic(trimmed)?;
+                }
+                current.topic = trimmed.to_string();
+            }
+            if let Some(token) = ntfy.access_token {
+                patch_notify_secret(
+                    &mut current.access_token,
+                    &token,
+                    token_store::NTFY_ACCESS_TOKEN_SECRET,
+                    |t| validate_push_token(t, "ntfy access token"),
+                )?;
+                current.has_secret = is_masked_secret(&current.access_token);
+            }
+            if let Some(v) = ntfy.critical {
+                current.critical = v;
+            }
+            if let Some(v) = ntfy.recovery {
+                current.recovery = v;
+            }
+            if let Some(v) = ntfy.warning {
+                current.warning = v;
+            }
+            if let Some(v) = ntfy.projected {
+                current.projected = v;
+            }
+            if current.enabled && current.topic.is_empty() {
+                current.enabled = false;
+            }
+        }
+        if let Some(gotify) = en.gotify {
+            let current = &mut store.settings.external_notify.gotify;
+            if let Some(v) = gotify.enabled {
+                current.enabled = v;
+            }
+            if let Some(url) = gotify.server_url {
+                let trimmed = url.trim().trim_end_matches('/');
+                if !trimmed.is_empty() {
+                    validate_push_server_url(trimmed, "Gotify")?;
+                }
+                current.server_url = trimmed.to_string();
+            }
+            if let Some(token) = gotify.app_token {
+                patch_notify_secret(
+                    &mut current.app_token,
+                    &token,
+                    token_store::GOTIFY_APP_TOKEN_SECRET,
+                    |t| validate_push_token(t, "Gotify app token"),
+                )?;
+                current.has_secret = is_masked_secret(&current.app_token);
+            }
+            if let Some(v) = gotify.critical {
+                current.critical = v;
+            }
+            if let Some(v) = gotify.recovery {
+                current.recovery = v;
+            }
+            if let Some(v) = gotify.warning {
+                current.warning = v;
+            }
+            if let Some(v) = gotify.projected {
+                current.projected = v;
+            }
+            if current.enabled && (current.server_url.is_empty() || current.app_token.is_empty()) {
+                current.enabled = false;
+            }
+        }
+        if let Some(hooks) = en.webhooks {
+            let current = &mut store.settings.external_notify.webhooks;
+            *current = patch_webhooks(current, hooks)?;
+        }
+    }
+
+    if let Some(hs) = payload.history {
+        let current = &mut store.settings.history;
+        if let Some(v) = hs.enabled {
+            current.enabled = v;
+        }
+        if let Some(v) = hs.retention_days {
+            if (1..=crate::HISTORY_RETENTION_DAYS_MAX).contains(&v) {
+                current.retention_days = v;
+            }
+        }
+    }
+
+    if let Some(ms) = payload.metrics {
+        let current = &mut store.settings.metrics;
+        if let Some(v) = ms.enabled {
+            current.enabled = v;
+        }
+        if let Some(addr) = ms.bind_address {
+            let trimmed = addr.trim().to_string();
+            validate_bind_address(&trimmed)?;
+            current.bind_address = trimmed;
+        }
+        if let Some(v) = ms.port {
+            if (1024..=65535).contains(&v) {
+                current.port = v;
+            }
+        }
+    }
+
+    if let Some(mcp) = payload.mcp {
+        let current = &mut store.settings.mcp;
+        if let Some(v) = mcp.enabled {
+            current.enabled = v;
+        }
+        if let Some(v) = mcp.port {
+            if (1024..=65535).contains(&v) {
+                current.port = v;
+            }
+        }
+    }
+
+    if let Some(cs) = payload.cli_switch {
+        if let Some(v) = cs.auto_switch {
+            store.settings.cli_switch.auto_switch = v;
+        }
+    }
+
+    if let Some(tr) = payload.token_refresh {
+        if let Some(v) = tr.lead_minutes {
+            if (1..=crate::TOKEN_REFRESH_LEAD_MINUTES_MAX).contains(&v) {
+                store.settings.token_refresh.lead_minutes = v;
+            }
+        }
+    }
+
+    Ok(())
+}
+
+/// Store a notification secret (webhook URL, token) in the keyring and keep
+/// its mask in `current`. Empty clears it; a masked value keeps what is stored.
+fn patch_notify_secret(
+    current: &mut String,
+    value: &str,
+    secret: &str,
+    validate: impl Fn(&str) -> AppResult<()>,
+) -> AppResult<()> {
+    let trimmed = value.trim();
+    if trimmed.is_empty() {
+        token_store::delete_notify_secret(secret)?;
+        current.clear();
+    } else if !is_masked_secret(trimmed) {
+        validate(trimmed)?;
+        token_store::set_notify_secret(secret, trimmed)?;
+        *current = mask_secret(trimmed);
+    }
+    Ok(())
+}
+
+/// Replace the webhook list. URLs and header values go to the keyring; masked
+/// values keep what is stored. Secrets of removed webhooks are deleted.
+fn patch_webhooks(
+    previous: &[crate::WebhookSettings],
+    patches: Vec<crate::WebhookSettingsPatch>,
+) -> AppResult<Vec<crate::WebhookSettings>> {
+    if patches.len() > crate::MAX_WEBHOOKS {
+        return Err(AppError::InvalidInput(format!(
+            "Too many webhooks (max {})",
+            crate::MAX_WEBHOOKS
+        )));
+    }
+    let mut next: Vec<crate::WebhookSettings> = Vec::new();
+    for patch in patches {
+        let id = match patch.id.map(|s| s.trim().to_string()).filter(|s| !s.is_empty()) {
+            Some(id) => {
+                validate_account_id(&id)?;
+                id
+            }
+            None => format!("wh-{:x}-{}", chrono::Utc::now().timestamp_millis(), next.len()),
+        };
+        if next.iter().any(|w| w.id == id) {
+            return Err(AppError::InvalidInput(format!("Duplicate webhook id: {id}")));
+        }
+        let old = previous.iter().find(|w| w.id == id);
+
+        let name = patch
+            .name
+            .map(|s| s.trim().to_string())
+            .or_else(|| old.map(|w| w.name.clone()))
+            .unwrap_or_else(|| "Webhook".to_string());
+        validate_webhook_name(&name)?;
+        let method = patch
+            .method
+            .map(|s| s.trim().to_ascii_uppercase())
+            .or_else(|| old.map(|w| w.method.clone()))
+            .unwrap_or_else(|| "POST".to_string());
+        validate_webhook_method(&method)?;
+        let body_template = patch
+            .body_template
+            .or_else(|| old.map(|w| w.body_template.clone()))
+            .unwrap_or_else(|| DEFAULT_WEBHOOK_TEMPLATE.to_string());
+        validate_webhook_template(&body_template)?;
+
+        let stored = token_store::get_webhook_secret(&id).unwrap_or_default();
+        let mut secret = stored.clone();
+        if let Some(url) = patch.url {
+            let trimmed = url.trim().to_string();
+            if trimmed.is_empty() {
+                secret.url.clear();
+            } else if !is_masked_secret(&trimmed) {
+                validate_webhook_url(&trimmed)?;
+                secret.url = trimmed;
+            }
+        }
+        if let Some(headers) = patch.headers {
+            let mut pairs = Vec::new();
+            for header in headers {
+                let name = header.name.trim().to_string();
+                let value = if is_masked_secret(&header.value) {
+                    match stored.headers.iter().find(|(n, _)| n.eq_ignore_ascii_case(&name)) {
+                        Some((_, v)) => v.clone(),
+                        None => continue,
+                    }
+                } else {
+                    header.value.trim().to_string()
+                };
+                validate_webhook_header(&name, &value)?;
+                pairs.push((name, value));
+            }
+            secret.headers = pairs;
+        }
+        if secret != stored {
+            if secret.url.is_empty() && secret.headers.is_empty() {
+                token_store::delete_webhook_secret(&id)?;
+            } else {
+                token_store::set_webhook_secret(&id, &secret)?;
+            }
+        }
+
+        let has_secret = !secret.url.is_empty();
+        let flag = |value: Option<bool>, old_value: Option<bool>, default: bool| {
+            value.or(old_value).unwrap_or(default)
+        };
+        next.push(crate::WebhookSettings {
+            enabled: flag(patch.enabled, old.map(|w| w.enabled), false) && has_secret,
+            url: if has_secret { mask_secret(&secret.url) } else { String::new() },
+            has_secret,
+            method,
+            headers: secret
+                .headers
+                .iter()
+                .map(|(name, value)| crate::WebhookHeader {
+                    name: name.clone(),
+                    value: mask_secret(value),
+                })
+                .collect(),
+            body_template,
+            critical: flag(patch.critical, old.map(|w| w.critical), true),
+            recovery: flag(patch.recovery, old.map(|w| w.recovery), true),
+            warning: flag(patch.warning, old.map(|w| w.warning), false),
+            projected: flag(patch.projected, old.map(|w| w.projected), false),
+            id,
+            name,
+        });
     }
 
-    write_store(&app, &store)?;
-    Ok(store.settings)
+    for removed in previous.iter().filter(|w| !next.iter().any(|n| n.id == w.id)) {
+        token_store::delete_webhook_secret(&removed.id)?;
+    }
+    Ok(next)
 }
 
 pub fn get_polling_state(app: AppHandle) -> AppResult<crate::PollingState> {
@@ -141,19 +589,19 @@ pub fn set_polling_state(
     app: AppHandle,
     payload: crate::SetPollingStatePayload,
 ) -> AppResult<crate::PollingState> {
-    let mut store = read_store(&app)?;
-    let current = &mut store.settings.polling_state;
+    let out = update_store(&app, |store| {
+        let current = &mut store.settings.polling_state;
 
-    current.active = payload.active.unwrap_or(false);
-    current.started_at = payload.started_at.filter(|n| *n > 0);
+        current.active = payload.active.unwrap_or(false);
+        current.started_at = payload.started_at.filter(|n| *n > 0);
 
-    if let Some(interval) = payload.interval {
-        if (30..=600).contains(&interval) {
-            current.interval = interval;
+        if let Some(interval) = payload.interval {
+            if (30..=600).contains(&interval) {
+                current.interval = interval;
+            }
         }
-    }
-
-    let out = current.clone();
-    write_store(&app, &store)?;
+        Ok(current.clone())
+    })?;
+    poll_scheduler::reload();
     Ok(out)
 }
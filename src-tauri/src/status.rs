@@ -0,0 +1,542 @@
+use crate::usage_parser::UsageWindow;
+use serde::{Deserialize, Serialize};
+
+/// Utilization (%) at or above which a window counts as exhausted.
+pub const EXHAUSTED_THRESHOLD: f64 = 100.0;
+
+/// Quota status, ordered from least to most severe.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord, Serialize, Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub enum Status {
+    Unknown,
+    Ok,
+    Warning,
+    /// Burn rate reaches 100% before the window resets.
+    Projected,
+    Critical,
+    Exhausted,
+}
+
+impl Status {
+    pub fn as_str(self) -> &'static str {
+        match self {
+            Status::Unknown => "unknown",
+            Status::Ok => "ok",
+            Status::Warning => "warning",
+            Status::Projected => "projected",
+            Status::Critical => "critical",
+            Status::Exhausted => "exhausted",
+        }
+    }
+
+    pub fn parse(value: &str) -> Option<Self> {
+        match value {
+            "unknown" => Some(Status::Unknown),
+            "ok" => Some(Status::Ok),
+            "warning" => Some(Status::Warning),
+            "projected" => Some(Status::Projected),
+            "critical" => Some(Status::Critical),
+            "exhausted" => Some(Status::Exhausted),
+            _ => None,
+        }
+    }
+}
+
+pub fn classify_utilization(pct: f64, settings: &crate::NotifySettings) -> Status {
+    if !pct.is_finite() {
+        return Status::Ok;
+    }
+    if pct >= EXHAUSTED_THRESHOLD {
+        Status::Exhausted
+    } else if pct >= f64::from(settings.threshold_critical) {
+        Status::Critical
+    } else if pct >= f64::from(settings.threshold_warning) {
+        Status::Warning
+    } else {
+        Status::Ok
+    }
+}
+
+/// Fill `status` on each window from the notify thresholds, raised to
+/// `projected` when the forecast runs out before the reset.
+pub fn classify_windows(windows: &mut [UsageWindow], settings: &crate::NotifySettings) {
+    for window in windows.iter_mut() {
+        let mut status = if window.force_exhausted.unwrap_or(false) {
+            Status::Exhausted
+        } else {
+            classify_utilization(window.utilization, settings)
+        };
+        if status < Status::Projected && window.forecast.as_ref().is_some_and(|f| f.before_reset) {
+            status = Status::Projected;
+        }
+        window.status = Some(status.as_str().to_string());
+    }
+}
+
+/// Keep a window at its previous threshold status until utilization falls
+/// `hysteresis` points below that threshold, so usage hovering around a
+/// threshold does not flap between statuses every poll.
+pub fn apply_hysteresis(windows: &mut [UsageWindow], previous: &[UsageWindow], settings: &crate::NotifySettings) {
+    if settings.hysteresis <= 0 {
+        return;
+    }
+    for window in windows.iter_mut() {
+        let Some(prev) = previous
+            .iter()
+            .find(|p| p.name == window.name)
+            .and_then(|p| p.status.as_deref())
+            .and_then(Status::parse)
+            .filter(|p| matches!(p, Status::Warning | Status::Critical | Status::Exhausted))
+        else {
+            continue;
+        };
+        let current = window.status.as_deref().and_then(Status::parse).unwrap_or(Status::Ok);
+        let held = prev.min(classify_utilization(
+            window.utilization + f64::from(settings.hysteresis),
+            settings,
+        ));
+        if held > current {
+            window.status = Some(held.as_str().to_string());
+        }
+    }
+}
+
+/// Account-level status: the most severe window status, `ok` when there are none.
+pub fn derive_service_status(windows: &[UsageWindow]) -> Status {
+    windows
+        .iter()
+        .filter_map(|w| w.status.as_deref().and_then(Status::parse))
+        .fold(Status::Ok, Status::max)
+}
+
+/// Account-level status string as shown on cards and in the snapshot export:
+/// `error` when the fetch failed and there are no windows to fall back on.
+pub fn account_status(windows: &[UsageWindow], error: Option<&str>) -> String {
+    if error.is_some() && windows.is_empty() {
+        return "error".to_string();
+    }
+    derive_service_status(windows).as_str().to_string()
+}
+
+/// Which transitions a notification channel wants to hear about.
+#[derive(Debug, Clone, Copy)]
+pub struct Triggers {
+    pub critical: bool,
+    pub recovery: bool,
+    pub warning: bool,
+    pub projected: bool,
+}
+
+impl From<&crate::NotifySettings> for Triggers {
+    fn from(s: &crate::NotifySettings) -> Self {
+        Triggers {
+            critical: s.critical,
+            recovery: s.recovery,
+            warning: s.warning,
+            projected: s.projected,
+        }
+    }
+}
+
+#[derive(Debug, Clone, PartialEq, Eq, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct Notification {
+    pub title: String,
+    pub body: String,
+}
+
+#[derive(Debug, Clone, PartialEq, Eq, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct TransitionLog {
+    /// `crit`, `warn` or `ok` (UI log levels).
+    pub level: &'static str,
+    pub message: String,
+}
+
+#[derive(Debug, Clone, Default, PartialEq, Eq, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct TransitionEffects {
+    pub notifications: Vec<Notification>,
+    pub logs: Vec<TransitionLog>,
+}
+
+fn is_severe(status: &str) -> bool {
+    status == "critical" || status == "exhausted"
+}
+
+/// Severe, or already warned about by a projection.
+fn is_escalated(status: &str) -> bool {
+    is_severe(status) || status == "projected"
+}
+
+/// `5時間: 18:40` for each window projected to run out before its reset.
+fn projection_detail(windows: &[UsageWindow]) -> String {
+    windows
+        .iter()
+        .filter_map(|w| {
+            let forecast = w.forecast.as_ref().filter(|f| f.before_reset)?;
+            let at = chrono::DateTime::from_timestamp_millis(forecast.exhausted_at?)?
+                .with_timezone(&chrono::Local);
+            Some(format!("{}: {}", w.name, at.format("%m/%d %H:%M")))
+        })
+        .collect::<Vec<_>>()
+        .join(", ")
+}
+
+/// Notifications and log lines for an account moving from `prev` to `next`.
+/// No effects when there is no previous status or it did not change.
+pub fn build_transition_effects(
+    prev: Option<&str>,
+    next: &str,
+    label: &str,
+    windows: &[UsageWindow],
+    triggers: Triggers,
+) -> TransitionEffects {
+    let mut effects = TransitionEffects::default();
+    let Some(prev) = prev.filter(|p| *p != next) else {
+        return effects;
+    };
+
+    let detail = windows
+        .iter()
+        .map(|w| format!("{}: {}%", w.name, w.utilization))
+        .collect::<Vec<_>>()
+        .join(", ");
+
+    if is_severe(next) && triggers.critical {
+        effects.notifications.push(Notification {
+            title: format!("{label} ⚠️"),
+            body: format!("ステータス: {next} — {detail}"),
+        });
+    }
+    if next == "projected" && !is_escalated(prev) && triggers.projected {
+        effects.notifications.push(Notification {
+            title: format!("{label} ⏳"),
+            body: format!("リセット前に上限到達の見込み — {}", projection_detail(windows)),
+        });
+    }
+    if next == "warning" && !is_escalated(prev) && triggers.warning {
+        effects.notifications.push(Notification {
+            title: format!("{label} ⚠"),
+            body: format!("ステータス: {next} — {detail}"),
+        });
+    }
+    if next == "ok" && is_severe(prev) && triggers.recovery {
+        effects.notifications.push(Notification {
+            title: format!("{label} ✅"),
+            body: "クォータが回復しました".to_string(),
+        });
+    }
+
+    if is_severe(next) {
+        effects.logs.push(TransitionLog {
+            level: "crit",
+            message: format!("{label} → {next}"),
+        });
+    } else if next == "projected" && !is_escalated(prev) {
+        effects.logs.push(TransitionLog {
+            level: "warn",
+            message: format!("{label} → {next} ({})", projection_detail(windows)),
+        });
+    } else if next == "warning" && !is_escalated(prev) {
+        effects.logs.push(TransitionLog {
+            level: "warn",
+            message: format!("{label} → {next}"),
+        });
+    } else if next == "ok" && is_severe(prev) {
+        effects.logs.push(TransitionLog {
+            level: "ok",
+            message: format!("{label} → ok (回復)"),
+        });
+    }
+
+    effects
+}
+
+/// Reminder for an account that is still critical or exhausted.
+pub fn build_reminder(status: &str, label: &str, windows: &[UsageWindow], triggers: Triggers) -> Option<Notification> {
+    if !is_severe(status) || !triggers.critical {
+        return None;
+    }
+    let detail = windows
+        .iter()
+        .map(|w| format!("{}: {}%", w.name, w.utilization))
+        .collect::<Vec<_>>()
+        .join(", ");
+    Some(Notification {
+        title: format!("{label} 🔁"),
+        body: format!("ステータス: {status} が継続中 — {detail}"),
+    })
+}
+
+/// Notice for windows that came back after their reset time.
+pub fn build_reset_notice(
+    label: &str,
+    restored: &[crate::reset_watch::RestoredWindow],
+    triggers: Triggers,
+) -> Option<Notification> {
+    if restored.is_empty() || !triggers.recovery {
+        return None;
+    }
+    let detail = restored
+        .iter()
+        .map(|w| format!("{}: {}% → {}%", w.name, w.before, w.after))
+        .collect::<Vec<_>>()
+        .join(", ");
+    Some(Notification {
+        title: format!("{label} 🔄"),
+        body: format!("クォータがリセットされました — {detail}"),
+    })
+}
+
+/// Notice for an account whose refresh token was rejected. Follows the
+/// critical trigger: polling that account stops working until a new login.
+pub fn build_relogin_notice(label: &str, reason: &str, triggers: Triggers) -> Option<Notification> {
+    triggers.critical.then(|| Notification {
+        title: format!("{label} 🔑"),
+        body: format!("再ログインが必要です — {reason}"),
+    })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::{
+        account_status, apply_hysteresis, build_relogin_notice, build_reminder, build_reset_notice,
+        build_transition_effects,
+        classify_utilization, classify_windows, derive_service_status, Status, TransitionLog, Triggers,
+    };
+    use crate::forecast::Forecast;
+    use crate::usage_parser::UsageWindow;
+
+    const ALL: Triggers = Triggers {
+        critical: true,
+        recovery: true,
+        warning: true,
+        projected: true,
+    };
+
+    fn window(name: &str, utilization: f64) -> UsageWindow {
+        UsageWindow::new(name.to_string(), utilization, None, None, false, None)
+    }
+
+    fn notify_settings() -> crate::NotifySettings {
+        crate::NotifySettings {
+            critical: true,
+            recovery: true,
+            warning: false,
+            projected: true,
+            threshold_warning: 75,
+            threshold_critical: 90,
+            quiet_hours: Vec::new(),
+            hysteresis: 0,
+            cooldown_minutes: 0,
+            renotify_minutes: 0,
+        }
+    }
+
+    #[test]
+    fn classify_utilization_uses_thresholds() {
+        let ns = notify_settings();
+        assert_eq!(classify_utilization(10.0, &ns), Status::Ok);
+        assert_eq!(classify_utilization(75.0, &ns), Status::Warning);
+        assert_eq!(classify_utilization(90.0, &ns), Status::Critical);
+        assert_eq!(classify_utilization(100.0, &ns), Status::Exhausted);
+        assert_eq!(classify_utilization(f64::NAN, &ns), Status::Ok);
+    }
+
+    #[test]
+    fn force_exhausted_wins_and_worst_window_is_derived() {
+        let mut windows = vec![
+            UsageWindow::new("5h".to_string(), 20.0, None, None, false, None),
+            UsageWindow::new("7d".to_string(), 10.0, None, None, true, None),
+        ];
+        classify_windows(&mut windows, &notify_settings());
+        assert_eq!(windows[0].status.as_deref(), Some("ok"));
+        assert_eq!(windows[1].status.as_deref(), Some("exhausted"));
+        assert_eq!(derive_service_status(&windows), Status::Exhausted);
+        assert_eq!(derive_service_status(&[]), Status::Ok);
+    }
+
+    #[test]
+    fn threshold_change_alters_account_status() {
+        let mut windows = vec![window("5h", 80.0), window("7d", 95.0)];
+        classify_windows(&mut windows, &notify_settings());
+        assert_eq!(derive_service_status(&windows), Status::Critical);
+
+        let raised = crate::NotifySettings {
+            threshold_warning: 85,
+            threshold_critical: 97,
+            ..notify_settings()
+        };
+        classify_windows(&mut windows, &raised);
+        assert_eq!(derive_service_status(&windows), Status::Warning);
+    }
+
+    #[test]
+    fn hysteresis_holds_status_until_usage_drops_below_the_margin() {
+        let ns = crate::NotifySettings {
+            hysteresis: 2,
+            ..notify_settings()
+        };
+        let mut previous = vec![window("5h", 90.5), window("7d", 76.0)];
+        classify_windows(&mut previous, &ns);
+
+        // 89 is within 2 points of the critical threshold; 74 of the warning one.
+        let mut windows = vec![window("5h", 89.0), window("7d", 74.0)];
+        classify_windows(&mut windows, &ns);
+        apply_hysteresis(&mut windows, &previous, &ns);
+        assert_eq!(windows[0].status.as_deref(), Some("critical"));
+        assert_eq!(windows[1].status.as_deref(), Some("warning"));
+
+        // Below the margin it steps down to whatever the thresholds say.
+        let mut windows = vec![window("5h", 87.9), window("7d", 72.0)];
+        classify_windows(&mut windows, &ns);
+        apply_hysteresis(&mut windows, &previous, &ns);
+        assert_eq!(windows[0].status.as_deref(), Some("warning"));
+        assert_eq!(windows[1].status.as_deref(), Some("ok"));
+
+        let off = notify_settings();
+        let mut windows = vec![window("5h", 89.0)];
+        classify_windows(&mut windows, &off);
+        apply_hysteresis(&mut windows, &previous, &off);
+        assert_eq!(windows[0].status.as_deref(), Some("warning"));
+    }
+
+    #[test]
+    fn reminder_only_for_severe_status() {
+        let windows = vec![window("5h", 95.0)];
+        let reminder = build_reminder("critical", "Claude Code: A", &windows, ALL).unwrap();
+        assert_eq!(reminder.title, "Claude Code: A 🔁");
+        assert!(reminder.body.contains("5h: 95%"));
+        assert!(build_reminder("warning", "Claude Code: A", &windows, ALL).is_none());
+        let no_critical = Triggers { critical: false, ..ALL };
+        assert!(build_reminder("exhausted", "Claude Code: A", &windows, no_critical).is_none());
+    }
+
+    #[test]
+    fn reset_notice_lists_restored_windows() {
+        let restored = vec![crate::reset_watch::RestoredWindow {
+            name: "5時間".to_string(),
+            before: 100.0,
+            after: 3.0,
+        }];
+        let notice = build_reset_notice("Codex: B", &restored, ALL).unwrap();
+        assert_eq!(notice.title, "Codex: B 🔄");
+        assert_eq!(notice.body, "クォータがリセットされました — 5時間: 100% → 3%");
+        assert!(build_reset_notice("Codex: B", &[], ALL).is_none());
+        let no_recovery = Triggers { recovery: false, ..ALL };
+        assert!(build_reset_notice("Codex: B", &restored, no_recovery).is_none());
+    }
+
+    #[test]
+    fn relogin_notice_follows_the_critical_trigger() {
+        let notice = build_relogin_notice("Claude Code: A", "invalid_grant", ALL).unwrap();
+        assert_eq!(notice.title, "Claude Code: A 🔑");
+        assert!(notice.body.contains("invalid_grant"));
+        let no_critical = Triggers { critical: false, ..ALL };
+        assert!(build_relogin_notice("Claude Code: A", "invalid_grant", no_critical).is_none());
+    }
+
+    fn projected_window(name: &str, utilization: f64) -> UsageWindow {
+        let mut w = window(name, utilization);
+        w.forecast = Some(Forecast {
+            rate_per_hour: 20.0,
+            exhausted_at: Some(1_700_000_000_000),
+            before_reset: true,
+            samples: 5,
+        });
+        w
+    }
+
+    #[test]
+    fn forecast_before_reset_raises_to_projected_only() {
+        let mut windows = vec![projected_window("5h", 40.0), projected_window("7d", 95.0)];
+        classify_windows(&mut windows, &notify_settings());
+        assert_eq!(windows[0].status.as_deref(), Some("projected"));
+        assert_eq!(windows[1].status.as_deref(), Some("critical"));
+
+        let mut late = vec![projected_window("5h", 40.0)];
+        late[0].forecast.as_mut().unwrap().before_reset = false;
+        classify_windows(&mut late, &notify_settings());
+        assert_eq!(late[0].status.as_deref(), Some("ok"));
+    }
+
+    #[test]
+    fn account_status_is_error_only_without_windows() {
+        assert_eq!(account_status(&[], Some("HTTP 500")), "error");
+        assert_eq!(account_status(&[], None), "ok");
+        let mut windows = vec![window("5h", 95.0)];
+        classify_windows(&mut windows, &notify_settings());
+        assert_eq!(account_status(&windows, Some("rate limited")), "critical");
+    }
+
+    #[test]
+    fn transition_to_critical_notifies_and_logs() {
+        let windows = [window("5時間", 91.0)];
+        let effects = build_transition_effects(Some("ok"), "critical", "Claude Code: A", &windows, ALL);
+        assert_eq!(effects.notifications.len(), 1);
+        assert_eq!(effects.notifications[0].title, "Claude Code: A ⚠️");
+        assert_eq!(effects.notifications[0].body, "ステータス: critical — 5時間: 91%");
+        assert_eq!(
+            effects.logs,
+            vec![TransitionLog {
+                level: "crit",
+                message: "Claude Code: A → critical".to_string()
+            }]
+        );
+    }
+
+    #[test]
+    fn warning_after_critical_is_suppressed() {
+        let windows = [window("7日間", 80.0)];
+        let effects = build_transition_effects(Some("critical"), "warning", "Codex: B", &windows, ALL);
+        assert!(effects.notifications.is_empty());
+        assert!(effects.logs.is_empty());
+    }
+
+    #[test]
+    fn projected_notifies_once_before_warning() {
+        let windows = [projected_window("5時間", 50.0)];
+        let effects = build_transition_effects(Some("ok"), "projected", "Claude Code: A", &windows, ALL);
+        assert_eq!(effects.notifications.len(), 1);
+        assert_eq!(effects.notifications[0].title, "Claude Code: A ⏳");
+        assert!(effects.notifications[0].body.contains("5時間: "));
+        assert_eq!(effects.logs[0].level, "warn");
+
+        let later = build_transition_effects(Some("projected"), "warning", "Claude Code: A", &windows, ALL);
+        assert!(later.notifications.is_empty());
+        assert!(later.logs.is_empty());
+
+        let quiet = Triggers {
+            projected: false,
+            ..ALL
+        };
+        let effects = build_transition_effects(Some("ok"), "projected", "Claude Code: A", &windows, quiet);
+        assert!(effects.notifications.is_empty());
+        assert_eq!(effects.logs.len(), 1);
+    }
+
+    #[test]
+    fn recovery_notifies_and_respects_triggers() {
+        let windows = [window("5時間", 10.0)];
+        let effects = build_transition_effects(Some("exhausted"), "ok", "Codex: C", &windows, ALL);
+        assert_eq!(effects.notifications.len(), 1);
+        assert!(effects.notifications[0].title.ends_with('✅'));
+        assert_eq!(effects.logs[0].message, "Codex: C → ok (回復)");
+
+        let quiet = Triggers {
+            recovery: false,
+            ..ALL
+        };
+        let effects = build_transition_effects(Some("exhausted"), "ok", "Codex: C", &[], quiet);
+        assert!(effects.notifications.is_empty());
+        assert_eq!(effects.logs.len(), 1);
+    }
+
+    #[test]
+    fn no_effects_without_change_or_previous_status() {
+        let unchanged = build_transition_effects(Some("warning"), "warning", "X", &[], ALL);
+        assert!(unchanged.notifications.is_empty());
+        assert_eq!(build_transition_effects(None, "critical", "X", &[], ALL), Default::default());
+    }
+}
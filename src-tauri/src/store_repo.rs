@@ -1,17 +1,53 @@
-use crate::validation::{validate_account_id, validate_account_name};
+use crate::fs_util::{self, Fingerprint};
+use crate::token_store::{self, is_masked_secret, mask_secret};
+use crate::validation::{
+    validate_account_id, validate_account_name, validate_webhook_header, validate_webhook_method,
+    validate_webhook_name, validate_webhook_template, validate_webhook_url,
+};
 use crate::error::{AppError, AppResult};
-use tauri::{AppHandle, Manager};
+use serde_json::Value;
+use tauri::{AppHandle, Emitter, Manager};
 use std::fs;
-use std::path::PathBuf;
+use std::path::{Path, PathBuf};
 use std::sync::{Mutex, OnceLock};
 
-static STORE_CACHE: OnceLock<Mutex<Option<crate::Store>>> = OnceLock::new();
+/// `schemaVersion` written to accounts.json. Files without the field are version 0.
+pub const STORE_SCHEMA_VERSION: u32 = 1;
+pub const EVENT_STORE_LOAD_FAILED: &str = "store-load-failed";
+
+type Migration = fn(&mut Value) -> Result<(), String>;
+
+/// `MIGRATIONS[n]` upgrades a version `n` document to version `n + 1`.
+const MIGRATIONS: &[Migration] = &[migrate_v0_to_v1];
+
+/// Last store read or written by this process, with the file fingerprint it
+/// corresponds to. A different fingerprint on disk (another instance, the CLI,
+/// a manual edit) invalidates it.
+struct CachedStore {
+    store: crate::Store,
+    fingerprint: Option<Fingerprint>,
+}
+
+static STORE_CACHE: OnceLock<Mutex<Option<CachedStore>>> = OnceLock::new();
+/// accounts.json of this process, for code without an `AppHandle`: the app's
+/// data directory once resolved, or the CLI's `--data-dir`.
+static STORE_FILE_PATH: OnceLock<PathBuf> = OnceLock::new();
+/// Set when accounts.json could not be loaded this run.
+static LOAD_ISSUE: OnceLock<Mutex<Option<crate::StoreLoadIssue>>> = OnceLock::new();
 
 fn clamp_int(value: Option<i32>, fallback: i32, min: i32, max: i32) -> i32 {
     let n = value.unwrap_or(fallback);
     n.clamp(min, max)
 }
 
+/// 0 (off) or a reminder interval of at least `RENOTIFY_MINUTES_MIN`.
+pub fn normalize_renotify_minutes(value: Option<i32>, fallback: i32) -> i32 {
+    match value.unwrap_or(fallback) {
+        n if n <= 0 => 0,
+        n => n.clamp(crate::RENOTIFY_MINUTES_MIN, crate::NOTIFY_MINUTES_MAX),
+    }
+}
+
 pub fn default_normal_bounds() -> crate::Bounds {
     crate::Bounds {
         width: crate::NORMAL_WINDOW_DEFAULT_W,
@@ -32,10 +68,11 @@ pub fn default_minimal_bounds() -> crate::Bounds {
 
 fn default_store() -> crate::Store {
     crate::Store {
-        services: crate::Services {
-            claude: Vec::new(),
-            codex: Vec::new(),
-        },
+        schema_version: STORE_SCHEMA_VERSION,
+        services: crate::providers::all()
+            .iter()
+            .map(|p| (p.id().to_string(), Vec::new()))
+            .collect(),
         settings: crate::Settings {
             poll_interval: 600,
             polling_state: crate::PollingState {
@@ -54,8 +91,13 @@ fn default_store() -> crate::Store {
                 critical: true,
                 recovery: true,
                 warning: false,
+                projected: true,
                 threshold_warning: 75,
                 threshold_critical: 90,
+                quiet_hours: Vec::new(),
+                hysteresis: crate::HYSTERESIS_DEFAULT,
+                cooldown_minutes: crate::COOLDOWN_MINUTES_DEFAULT,
+                renotify_minutes: 0,
             },
             usage_export: crate::UsageExportSettings {
                 enabled: false,
@@ -65,18 +107,79 @@ fn default_store() -> crate::Store {
                 discord: crate::DiscordSettings {
                     enabled: false,
                     webhook_url: String::new(),
+                    has_secret: false,
                     critical: true,
                     recovery: true,
                     warning: false,
+                    projected: false,
+                },
+                slack: crate::SlackSettings {
+                    enabled: false,
+                    webhook_url: String::new(),
+                    has_secret: false,
+                    critical: true,
+                    recovery: true,
+                    warning: false,
+                    projected: false,
+                },
+                teams: crate::TeamsSettings {
+                    enabled: false,
+                    webhook_url: String::new(),
+                    has_secret: false,
+                    critical: true,
+                    recovery: true,
+                    warning: false,
+                    projected: false,
                 },
                 pushover: crate::PushoverSettings {
                     enabled: false,
                     api_token: String::new(),
                     user_key: String::new(),
+                    has_secret: false,
+                    critical: true,
+                    recovery: true,
+                    warning: false,
+                    projected: false,
+                },
+                ntfy: crate::NtfySettings {
+                    enabled: false,
+                    server_url: crate::NTFY_SERVER_DEFAULT.to_string(),
+                    topic: String::new(),
+                    access_token: String::new(),
+                    has_secret: false,
                     critical: true,
                     recovery: true,
                     warning: false,
+                    projected: false,
                 },
+                gotify: crate::GotifySettings {
+                    enabled: false,
+                    server_url: String::new(),
+                    app_token: String::new(),
+                    has_secret: false,
+                    critical: true,
+                    recovery: true,
+                    warning: false,
+                    projected: false,
+                },
+                webhooks: Vec::new(),
+            },
+            history: crate::HistorySettings {
+                enabled: true,
+                retention_days: crate::HISTORY_RETENTION_DAYS_DEFAULT,
+            },
+            metrics: crate::MetricsSettings {
+                enabled: false,
+                bind_address: crate::METRICS_BIND_ADDRESS_DEFAULT.to_string(),
+                port: crate::METRICS_PORT_DEFAULT,
+            },
+            mcp: crate::McpSettings {
+                enabled: false,
+                port: crate::MCP_PORT_DEFAULT,
+            },
+            cli_switch: crate::CliSwitchSettings { auto_switch: false },
+            token_refresh: crate::TokenRefreshSettings {
+                lead_minutes: crate::TOKEN_REFRESH_LEAD_MINUTES_DEFAULT,
             },
         },
     }
@@ -136,7 +239,82 @@ fn normalize_accounts(raw: Option<&Vec<crate::AccountEntryRaw>>, service: &str)
         } else {
             fallback
         };
-        out.push(crate::AccountEntry { id, name });
+        let cli_linked = entry.cli_linked.unwrap_or(false) && crate::cli_sync::supports(service);
+        out.push(crate::AccountEntry {
+            id,
+            name,
+            muted_until: entry.muted_until.filter(|&until| until > 0),
+            cli_linked,
+            cli_synced_at: entry.cli_synced_at.filter(|&at| cli_linked && at > 0),
+            priority: clamp_int(entry.priority, 0, -crate::ACCOUNT_PRIORITY_MAX, crate::ACCOUNT_PRIORITY_MAX),
+            reserve: entry.reserve.unwrap_or(false),
+            relogin_reason: Some(crate::sanitize_string(entry.relogin_reason.as_deref(), ""))
+                .filter(|reason| !reason.is_empty()),
+        });
+    }
+    out
+}
+
+/// Drop webhooks without a usable id and any invalid field. URLs and header
+/// values are expected masked; a valid plaintext URL is kept but not sent.
+fn normalize_webhooks(raw: Option<&Vec<crate::WebhookSettingsRaw>>) -> Vec<crate::WebhookSettings> {
+    let mut out: Vec<crate::WebhookSettings> = Vec::new();
+    for hook in raw.into_iter().flatten() {
+        if out.len() >= crate::MAX_WEBHOOKS {
+            break;
+        }
+        let id = crate::sanitize_string(hook.id.as_deref(), "");
+        if validate_account_id(&id).is_err() || out.iter().any(|w| w.id == id) {
+            continue;
+        }
+        let url = hook
+            .url
+            .as_deref()
+            .map(str::trim)
+            .filter(|u| is_masked_secret(u) || validate_webhook_url(u).is_ok())
+            .unwrap_or_default()
+            .to_string();
+        let method = hook
+            .method
+            .as_deref()
+            .map(str::to_ascii_uppercase)
+            .filter(|m| validate_webhook_method(m).is_ok())
+            .unwrap_or_else(|| "POST".to_string());
+        let headers = hook
+            .headers
+            .iter()
+            .flatten()
+            .filter_map(|h| {
+                let name = h.name.as_deref()?.trim().to_string();
+                let value = h.value.as_deref()?.to_string();
+                (is_masked_secret(&value) && validate_webhook_header(&name, "").is_ok())
+                    .then_some(crate::WebhookHeader { name, value })
+            })
+            .collect();
+        let body_template = hook
+            .body_template
+            .clone()
+            .filter(|t| validate_webhook_template(t).is_ok())
+            .unwrap_or_else(|| crate::external_notify::DEFAULT_WEBHOOK_TEMPLATE.to_string());
+        let has_secret = is_masked_secret(&url);
+        out.push(crate::WebhookSettings {
+            name: hook
+                .name
+                .clone()
+                .filter(|n| validate_webhook_name(n).is_ok())
+                .unwrap_or_else(|| id.clone()),
+            id,
+            enabled: hook.enabled.unwrap_or(false) && has_secret,
+            url,
+            has_secret,
+            method,
+            headers,
+            body_template,
+            critical: hook.critical.unwrap_or(true),
+            recovery: hook.recovery.unwrap_or(true),
+            warning: hook.warning.unwrap_or(false),
+            projected: hook.projected.unwrap_or(false),
+        });
     }
     out
 }
@@ -147,18 +325,20 @@ fn normalize_store(raw: crate::StoreRaw) -> crate::Store {
     let services_raw = raw.services;
     let settings_raw = raw.settings;
 
-    let claude = normalize_accounts(
-        services_raw
-            .as_ref()
-            .and_then(|s| s.claude.as_ref()),
-        "claude",
-    );
-    let codex = normalize_accounts(
-        services_raw
-            .as_ref()
-            .and_then(|s| s.codex.as_ref()),
-        "codex",
-    );
+    // Accounts for providers that are no longer registered are dropped.
+    let services = crate::providers::all()
+        .iter()
+        .map(|p| {
+            let accounts = normalize_accounts(
+                services_raw
+                    .as_ref()
+                    .and_then(|s| s.get(p.id()))
+                    .and_then(|list| list.as_ref()),
+                p.id(),
+            );
+            (p.id().to_string(), accounts)
+        })
+        .collect();
 
     let poll_interval = clamp_int(
         settings_raw.as_ref().and_then(|s| s.poll_interval),
@@ -224,6 +404,9 @@ fn normalize_store(raw: crate::StoreRaw) -> crate::Store {
         warning: notify_raw
             .and_then(|n| n.warning)
             .unwrap_or(base.settings.notify_settings.warning),
+        projected: notify_raw
+            .and_then(|n| n.projected)
+            .unwrap_or(base.settings.notify_settings.projected),
         threshold_warning: clamp_int(
             notify_raw.and_then(|n| n.threshold_warning),
             base.settings.notify_settings.threshold_warning,
@@ -236,6 +419,32 @@ fn normalize_store(raw: crate::StoreRaw) -> crate::Store {
             1,
             99,
         ),
+        quiet_hours: notify_raw
+            .and_then(|n| n.quiet_hours.as_ref())
+            .map(|rules| {
+                rules
+                    .iter()
+                    .filter_map(|r| crate::notify_schedule::rule_from_raw(r).ok())
+                    .take(crate::MAX_QUIET_HOURS_RULES)
+                    .collect()
+            })
+            .unwrap_or_default(),
+        hysteresis: clamp_int(
+            notify_raw.and_then(|n| n.hysteresis),
+            base.settings.notify_settings.hysteresis,
+            0,
+            crate::HYSTERESIS_MAX,
+        ),
+        cooldown_minutes: clamp_int(
+            notify_raw.and_then(|n| n.cooldown_minutes),
+            base.settings.notify_settings.cooldown_minutes,
+            0,
+            crate::NOTIFY_MINUTES_MAX,
+        ),
+        renotify_minutes: normalize_renotify_minutes(
+            notify_raw.and_then(|n| n.renotify_minutes),
+            base.settings.notify_settings.renotify_minutes,
+        ),
     };
 
     let export_raw = settings_raw.as_ref().and_then(|s| s.usage_export.as_ref());
@@ -250,24 +459,57 @@ fn normalize_store(raw: crate::StoreRaw) -> crate::Store {
     let discord_webhook_url = discord_raw
         .and_then(|d| d.webhook_url.as_deref())
         .map(|s| s.trim().to_string())
-        .filter(|s| !s.is_empty() && crate::validation::validate_discord_webhook_url(s).is_ok())
+        .filter(|s| {
+            !s.is_empty() && (is_masked_secret(s) || crate::validation::validate_discord_webhook_url(s).is_ok())
+        })
         .unwrap_or_default();
     let discord_enabled = discord_raw.and_then(|d| d.enabled).unwrap_or(false)
         && !discord_webhook_url.is_empty();
     let discord_critical = discord_raw.and_then(|d| d.critical).unwrap_or(true);
     let discord_recovery = discord_raw.and_then(|d| d.recovery).unwrap_or(true);
     let discord_warning = discord_raw.and_then(|d| d.warning).unwrap_or(false);
+    let discord_projected = discord_raw.and_then(|d| d.projected).unwrap_or(false);
+
+    let slack_raw = en_raw.and_then(|e| e.slack.as_ref());
+    let slack_webhook_url = slack_raw
+        .and_then(|d| d.webhook_url.as_deref())
+        .map(|s| s.trim().to_string())
+        .filter(|s| {
+            !s.is_empty() && (is_masked_secret(s) || crate::validation::validate_slack_webhook_url(s).is_ok())
+        })
+        .unwrap_or_default();
+    let slack_enabled = slack_raw.and_then(|d| d.enabled).unwrap_or(false)
+        && !slack_webhook_url.is_empty();
+    let slack_critical = slack_raw.and_then(|d| d.critical).unwrap_or(true);
+    let slack_recovery = slack_raw.and_then(|d| d.recovery).unwrap_or(true);
+    let slack_warning = slack_raw.and_then(|d| d.warning).unwrap_or(false);
+    let slack_projected = slack_raw.and_then(|d| d.projected).unwrap_or(false);
+
+    let teams_raw = en_raw.and_then(|e| e.teams.as_ref());
+    let teams_webhook_url = teams_raw
+        .and_then(|d| d.webhook_url.as_deref())
+        .map(|s| s.trim().to_string())
+        .filter(|s| {
+            !s.is_empty() && (is_masked_secret(s) || crate::validation::validate_teams_webhook_url(s).is_ok())
+        })
+        .unwrap_or_default();
+    let teams_enabled = teams_raw.and_then(|d| d.enabled).unwrap_or(false)
+        && !teams_webhook_url.is_empty();
+    let teams_critical = teams_raw.and_then(|d| d.critical).unwrap_or(true);
+    let teams_recovery = teams_raw.and_then(|d| d.recovery).unwrap_or(true);
+    let teams_warning = teams_raw.and_then(|d| d.warning).unwrap_or(false);
+    let teams_projected = teams_raw.and_then(|d| d.projected).unwrap_or(false);
 
     let pushover_raw = en_raw.and_then(|e| e.pushover.as_ref());
     let pushover_api_token = pushover_raw
         .and_then(|p| p.api_token.as_deref())
         .map(|s| s.trim().to_string())
-        .filter(|s| !s.is_empty() && crate::validation::validate_pushover_key(s, "token").is_ok())
+        .filter(|s| !s.is_empty() && (is_masked_secret(s) || crate::validation::validate_pushover_key(s, "token").is_ok()))
         .unwrap_or_default();
     let pushover_user_key = pushover_raw
         .and_then(|p| p.user_key.as_deref())
         .map(|s| s.trim().to_string())
-        .filter(|s| !s.is_empty() && crate::validation::validate_pushover_key(s, "key").is_ok())
+        .filter(|s| !s.is_empty() && (is_masked_secret(s) || crate::validation::validate_pushover_key(s, "key").is_ok()))
         .unwrap_or_default();
     let pushover_enabled = pushover_raw.and_then(|p| p.enabled).unwrap_or(false)
         && !pushover_api_token.is_empty()
@@ -275,9 +517,97 @@ fn normalize_store(raw: crate::StoreRaw) -> crate::Store {
     let pushover_critical = pushover_raw.and_then(|p| p.critical).unwrap_or(true);
     let pushover_recovery = pushover_raw.and_then(|p| p.recovery).unwrap_or(true);
     let pushover_warning = pushover_raw.and_then(|p| p.warning).unwrap_or(false);
+    let pushover_projected = pushover_raw.and_then(|p| p.projected).unwrap_or(false);
+
+    let ntfy_raw = en_raw.and_then(|e| e.ntfy.as_ref());
+    let ntfy_server_url = ntfy_raw
+        .and_then(|n| n.server_url.as_deref())
+        .map(|s| s.trim().trim_end_matches('/').to_string())
+        .filter(|s| crate::validation::validate_push_server_url(s, "ntfy").is_ok())
+        .unwrap_or_else(|| crate::NTFY_SERVER_DEFAULT.to_string());
+    let ntfy_topic = ntfy_raw
+        .and_then(|n| n.topic.as_deref())
+        .map(|s| s.trim().to_string())
+        .filter(|s| crate::validation::validate_ntfy_topic(s).is_ok())
+        .unwrap_or_default();
+    let ntfy_access_token = ntfy_raw
+        .and_then(|n| n.access_token.as_deref())
+        .map(|s| s.trim().to_string())
+        .filter(|s| is_masked_secret(s) || crate::validation::validate_push_token(s, "token").is_ok())
+        .unwrap_or_default();
+    let ntfy_enabled = ntfy_raw.and_then(|n| n.enabled).unwrap_or(false) && !ntfy_topic.is_empty();
+
+    let gotify_raw = en_raw.and_then(|e| e.gotify.as_ref());
+    let gotify_server_url = gotify_raw
+        .and_then(|g| g.server_url.as_deref())
+        .map(|s| s.trim().trim_end_matches('/').to_string())
+        .filter(|s| crate::validation::validate_push_server_url(s, "Gotify").is_ok())
+        .unwrap_or_default();
+    let gotify_app_token = gotify_raw
+        .and_then(|g| g.app_token.as_deref())
+        .map(|s| s.trim().to_string())
+        .filter(|s| is_masked_secret(s) || crate::validation::validate_push_token(s, "token").is_ok())
+        .unwrap_or_default();
+    let gotify_enabled = gotify_raw.and_then(|g| g.enabled).unwrap_or(false)
+        && !gotify_server_url.is_empty()
+        && !gotify_app_token.is_empty();
+
+    let history_raw = settings_raw.as_ref().and_then(|s| s.history.as_ref());
+    let history = crate::HistorySettings {
+        enabled: history_raw.and_then(|h| h.enabled).unwrap_or(true),
+        retention_days: clamp_int(
+            history_raw.and_then(|h| h.retention_days),
+            crate::HISTORY_RETENTION_DAYS_DEFAULT,
+            1,
+            crate::HISTORY_RETENTION_DAYS_MAX,
+        ),
+    };
+
+    let metrics_raw = settings_raw.as_ref().and_then(|s| s.metrics.as_ref());
+    let metrics = crate::MetricsSettings {
+        enabled: metrics_raw.and_then(|m| m.enabled).unwrap_or(false),
+        bind_address: metrics_raw
+            .and_then(|m| m.bind_address.as_deref())
+            .map(|s| s.trim().to_string())
+            .filter(|s| crate::validation::validate_bind_address(s).is_ok())
+            .unwrap_or_else(|| crate::METRICS_BIND_ADDRESS_DEFAULT.to_string()),
+        port: clamp_int(
+            metrics_raw.and_then(|m| m.port),
+            crate::METRICS_PORT_DEFAULT,
+            1024,
+            65535,
+        ),
+    };
+
+    let mcp_raw = settings_raw.as_ref().and_then(|s| s.mcp.as_ref());
+    let mcp = crate::McpSettings {
+        enabled: mcp_raw.and_then(|m| m.enabled).unwrap_or(false),
+        port: clamp_int(mcp_raw.and_then(|m| m.port), crate::MCP_PORT_DEFAULT, 1024, 65535),
+    };
+
+    let cli_switch = crate::CliSwitchSettings {
+        auto_switch: settings_raw
+            .as_ref()
+            .and_then(|s| s.cli_switch.as_ref())
+            .and_then(|c| c.auto_switch)
+            .unwrap_or(false),
+    };
+
+    let token_refresh = crate::TokenRefreshSettings {
+        lead_minutes: clamp_int(
+            settings_raw
+                .as_ref()
+                .and_then(|s| s.token_refresh.as_ref())
+                .and_then(|t| t.lead_minutes),
+            crate::TOKEN_REFRESH_LEAD_MINUTES_DEFAULT,
+            1,
+            crate::TOKEN_REFRESH_LEAD_MINUTES_MAX,
+        ),
+    };
 
     crate::Store {
-        services: crate::Services { claude, codex },
+        schema_version: STORE_SCHEMA_VERSION,
+        services,
         settings: crate::Settings {
             poll_interval,
             polling_state: crate::PollingState {
@@ -300,24 +630,195 @@ fn normalize_store(raw: crate::StoreRaw) -> crate::Store {
             external_notify: crate::ExternalNotifySettings {
                 discord: crate::DiscordSettings {
                     enabled: discord_enabled,
+                    has_secret: is_masked_secret(&discord_webhook_url),
                     webhook_url: discord_webhook_url,
                     critical: discord_critical,
                     recovery: discord_recovery,
                     warning: discord_warning,
+                    projected: discord_projected,
+                },
+                slack: crate::SlackSettings {
+                    enabled: slack_enabled,
+                    has_secret: is_masked_secret(&slack_webhook_url),
+                    webhook_url: slack_webhook_url,
+                    critical: slack_critical,
+                    recovery: slack_recovery,
+                    warning: slack_warning,
+                    projected: slack_projected,
+                },
+                teams: crate::TeamsSettings {
+                    enabled: teams_enabled,
+                    has_secret: is_masked_secret(&teams_webhook_url),
+                    webhook_url: teams_webhook_url,
+                    critical: teams_critical,
+                    recovery: teams_recovery,
+                    warning: teams_warning,
+                    projected: teams_projected,
                 },
                 pushover: crate::PushoverSettings {
                     enabled: pushover_enabled,
+                    has_secret: is_masked_secret(&pushover_api_token) && is_masked_secret(&pushover_user_key),
                     api_token: pushover_api_token,
                     user_key: pushover_user_key,
                     critical: pushover_critical,
                     recovery: pushover_recovery,
                     warning: pushover_warning,
+                    projected: pushover_projected,
                 },
+                ntfy: crate::NtfySettings {
+                    enabled: ntfy_enabled,
+                    server_url: ntfy_server_url,
+                    topic: ntfy_topic,
+                    has_secret: is_masked_secret(&ntfy_access_token),
+                    access_token: ntfy_access_token,
+                    critical: ntfy_raw.and_then(|n| n.critical).unwrap_or(true),
+                    recovery: ntfy_raw.and_then(|n| n.recovery).unwrap_or(true),
+                    warning: ntfy_raw.and_then(|n| n.warning).unwrap_or(false),
+                    projected: ntfy_raw.and_then(|n| n.projected).unwrap_or(false),
+                },
+                gotify: crate::GotifySettings {
+                    enabled: gotify_enabled,
+                    server_url: gotify_server_url,
+                    has_secret: is_masked_secret(&gotify_app_token),
+                    app_token: gotify_app_token,
+                    critical: gotify_raw.and_then(|g| g.critical).unwrap_or(true),
+                    recovery: gotify_raw.and_then(|g| g.recovery).unwrap_or(true),
+                    warning: gotify_raw.and_then(|g| g.warning).unwrap_or(false),
+                    projected: gotify_raw.and_then(|g| g.projected).unwrap_or(false),
+                },
+                webhooks: normalize_webhooks(en_raw.and_then(|e| e.webhooks.as_ref())),
             },
+            history,
+            metrics,
+            mcp,
+            cli_switch,
+            token_refresh,
         },
     }
 }
 
+/// Unversioned files already use the v1 layout; reject anything that is not.
+fn migrate_v0_to_v1(doc: &mut Value) -> Result<(), String> {
+    for key in ["services", "settings"] {
+        match doc.get(key) {
+            None | Some(Value::Null) | Some(Value::Object(_)) => {}
+            Some(_) => return Err(format!("`{key}` is not an object")),
+        }
+    }
+    Ok(())
+}
+
+fn migrate(doc: &mut Value) -> Result<(), String> {
+    let root = doc.as_object().ok_or("top level is not an object")?;
+    let version = match root.get("schemaVersion") {
+        None => 0,
+        Some(v) => v
+            .as_u64()
+            .and_then(|v| u32::try_from(v).ok())
+            .ok_or("schemaVersion is not a number")?,
+    };
+    if version > STORE_SCHEMA_VERSION {
+        return Err(format!(
+            "schemaVersion {version} is newer than this app supports ({STORE_SCHEMA_VERSION})"
+        ));
+    }
+    for (from, step) in MIGRATIONS.iter().enumerate().skip(version as usize) {
+        step(doc).map_err(|e| format!("migration from v{from} failed: {e}"))?;
+    }
+    doc["schemaVersion"] = Value::from(STORE_SCHEMA_VERSION);
+    Ok(())
+}
+
+/// Why accounts.json could not be used as is.
+#[derive(Debug)]
+enum LoadFailure {
+    /// Written by a newer version of the app. Holds what this version can
+    /// read of it; the file itself must be left alone.
+    NewerSchema { message: String, store: Box<crate::Store> },
+    /// Corrupt, or not a store at all.
+    Invalid(String),
+}
+
+/// Parse, migrate and normalize accounts.json content.
+fn parse_store(bytes: &[u8]) -> Result<crate::Store, LoadFailure> {
+    let mut doc: Value = serde_json::from_slice(bytes)
+        .map_err(|e| LoadFailure::Invalid(format!("invalid JSON: {e}")))?;
+    let version = doc.get("schemaVersion").and_then(Value::as_u64).unwrap_or(0);
+    if version > u64::from(STORE_SCHEMA_VERSION) {
+        let store = serde_json::from_value::<crate::StoreRaw>(doc)
+            .map(normalize_store)
+            .unwrap_or_else(|_| default_store());
+        return Err(LoadFailure::NewerSchema {
+            message: format!("schemaVersion {version} is newer than this app supports ({STORE_SCHEMA_VERSION})"),
+            store: Box::new(store),
+        });
+    }
+    migrate(&mut doc).map_err(LoadFailure::Invalid)?;
+    let raw: crate::StoreRaw = serde_json::from_value(doc)
+        .map_err(|e| LoadFailure::Invalid(format!("unexpected structure: {e}")))?;
+    Ok(normalize_store(raw))
+}
+
+/// Move an unreadable store file to `<name>.<timestamp>.bak` next to it.
+fn quarantine(path: &Path) -> AppResult<PathBuf> {
+    let name = path
+        .file_name()
+        .map(|n| n.to_string_lossy().into_owned())
+        .unwrap_or_else(|| crate::STORE_FILE.to_string());
+    let stamp = chrono::Local::now().format("%Y%m%d-%H%M%S");
+    let backup = path.with_file_name(format!("{name}.{stamp}.bak"));
+    fs::rename(path, &backup).map_err(|e| {
+        AppError::Store(format!(
+            "Failed to move unreadable store to {}: {e}",
+            backup.display()
+        ))
+    })?;
+    Ok(backup)
+}
+
+/// Load the store, quarantining a file that cannot be parsed or migrated so
+/// that the following `write_store` does not overwrite it. A file from a newer
+/// version stays in place; the issue then marks the store read-only.
+fn load_or_quarantine(path: &Path) -> AppResult<(crate::Store, Option<crate::StoreLoadIssue>)> {
+    let bytes = match fs::read(path) {
+        Ok(bytes) => bytes,
+        Err(e) if e.kind() == std::io::ErrorKind::NotFound => return Ok((default_store(), None)),
+        Err(e) => return Err(AppError::Store(format!("Failed to read store: {e}"))),
+    };
+    let detected_at = chrono::Utc::now().timestamp_millis();
+    match parse_store(&bytes) {
+        Ok(store) => Ok((store, None)),
+        Err(LoadFailure::NewerSchema { message, store }) => {
+            let issue = crate::StoreLoadIssue {
+                kind: crate::StoreLoadIssueKind::NewerSchema,
+                message,
+                backup_path: None,
+                detected_at,
+            };
+            Ok((*store, Some(issue)))
+        }
+        Err(LoadFailure::Invalid(message)) => {
+            let backup = quarantine(path)?;
+            let issue = crate::StoreLoadIssue {
+                kind: crate::StoreLoadIssueKind::Quarantined,
+                message,
+                backup_path: Some(backup.display().to_string()),
+                detected_at,
+            };
+            Ok((default_store(), Some(issue)))
+        }
+    }
+}
+
+fn load_issue_slot() -> &'static Mutex<Option<crate::StoreLoadIssue>> {
+    LOAD_ISSUE.get_or_init(|| Mutex::new(None))
+}
+
+/// The load failure from this run, if accounts.json was quarantined or is read-only.
+pub fn load_issue() -> Option<crate::StoreLoadIssue> {
+    load_issue_slot().lock().ok().and_then(|slot| slot.clone())
+}
+
 fn store_path(app: &AppHandle) -> AppResult<PathBuf> {
     let mut dir = app
         .path()
@@ -326,71 +827,400 @@ fn store_path(app: &AppHandle) -> AppResult<PathBuf> {
     fs::create_dir_all(&dir)
         .map_err(|e| AppError::Store(format!("Failed to create app data directory: {e}")))?;
     dir.push(crate::STORE_FILE);
+    use_store_file(dir.clone());
     Ok(dir)
 }
 
-fn store_cache() -> &'static Mutex<Option<crate::Store>> {
+/// Store file used by [`set_relogin_reason`]. The first call wins.
+pub fn use_store_file(path: PathBuf) {
+    let _ = STORE_FILE_PATH.set(path);
+}
+
+/// Directory of [`current_store_file`], for lock files shared between processes.
+pub fn store_dir() -> AppResult<PathBuf> {
+    current_store_file()?
+        .parent()
+        .map(Path::to_path_buf)
+        .ok_or_else(|| AppError::Store("Failed to resolve store directory".to_string()))
+}
+
+fn current_store_file() -> AppResult<PathBuf> {
+    match STORE_FILE_PATH.get() {
+        Some(path) => Ok(path.clone()),
+        None => Ok(default_data_dir()?.join(crate::STORE_FILE)),
+    }
+}
+
+fn store_cache() -> &'static Mutex<Option<CachedStore>> {
     STORE_CACHE.get_or_init(|| Mutex::new(None))
 }
 
-pub fn read_store(app: &AppHandle) -> AppResult<crate::Store> {
-    if let Some(cached) = store_cache()
+fn cache_lock() -> AppResult<std::sync::MutexGuard<'static, Option<CachedStore>>> {
+    store_cache()
         .lock()
-        .map_err(|_| AppError::Store("Store cache lock is poisoned".to_string()))?
+        .map_err(|_| AppError::Store("Store cache lock is poisoned".to_string()))
+}
+
+/// Cached store, if the file has not changed since it was read or written.
+fn cached_if_fresh(path: &Path) -> AppResult<Option<crate::Store>> {
+    let current = fs_util::fingerprint(path);
+    Ok(cache_lock()?
         .as_ref()
-        .cloned()
-    {
-        return Ok(cached);
-    }
+        .filter(|cached| cached.fingerprint == current)
+        .map(|cached| cached.store.clone()))
+}
 
-    let path = store_path(app)?;
-    let raw = match fs::read_to_string(path) {
-        Ok(raw) => raw,
-        Err(_) => {
-            let fallback = default_store();
-            let mut cache = store_cache()
-                .lock()
-                .map_err(|_| AppError::Store("Store cache lock is poisoned".to_string()))?;
-            if cache.is_none() {
-                *cache = Some(fallback.clone());
-            }
-            return Ok(cache.as_ref().cloned().unwrap_or(fallback));
-        }
-    };
+/// Advisory lock shared by every process writing accounts.json. The store
+/// itself is replaced by rename on each write, so the lock is a sibling file.
+fn lock_store_file(path: &Path) -> AppResult<fs::File> {
+    let lock_path = path.with_file_name(format!("{}.lock", crate::STORE_FILE));
+    fs_util::lock_exclusive(&lock_path)
+        .map_err(|e| AppError::Store(format!("Failed to lock store: {e}")))
+}
 
-    let parsed = match serde_json::from_str::<crate::StoreRaw>(&raw) {
-        Ok(parsed) => parsed,
-        Err(_) => {
-            let fallback = default_store();
-            let mut cache = store_cache()
+/// Load from disk and refresh the cache. The caller holds the store file lock,
+/// so a corrupt file is quarantined only once. Returns whether the file may be
+/// written back.
+fn load_locked(app: &AppHandle, path: &Path) -> AppResult<(crate::Store, bool)> {
+    let fingerprint = fs_util::fingerprint(path);
+    let (store, issue) = load_or_quarantine(path)?;
+    let (fingerprint, writable) = match issue {
+        Some(issue) => {
+            let first = load_issue_slot()
                 .lock()
-                .map_err(|_| AppError::Store("Store cache lock is poisoned".to_string()))?;
-            if cache.is_none() {
-                *cache = Some(fallback.clone());
+                .map(|mut slot| slot.replace(issue.clone()).is_none())
+                .unwrap_or(true);
+            if first {
+                let _ = app.emit(EVENT_STORE_LOAD_FAILED, &issue);
+            }
+            match issue.kind {
+                crate::StoreLoadIssueKind::NewerSchema => (fingerprint, false),
+                // The file was moved aside.
+                crate::StoreLoadIssueKind::Quarantined => (None, true),
             }
-            return Ok(cache.as_ref().cloned().unwrap_or(fallback));
         }
+        None => (fingerprint, true),
     };
+    *cache_lock()? = Some(CachedStore {
+        store: store.clone(),
+        fingerprint,
+    });
+    Ok((store, writable))
+}
 
-    let normalized = normalize_store(parsed);
-    let mut cache = store_cache()
-        .lock()
-        .map_err(|_| AppError::Store("Store cache lock is poisoned".to_string()))?;
-    if let Some(existing) = cache.as_ref() {
-        return Ok(existing.clone());
+pub fn read_store(app: &AppHandle) -> AppResult<crate::Store> {
+    let path = store_path(app)?;
+    if let Some(store) = cached_if_fresh(&path)? {
+        return Ok(store);
+    }
+    let _lock = lock_store_file(&path)?;
+    // Another thread may have reloaded while we waited for the lock.
+    if let Some(store) = cached_if_fresh(&path)? {
+        return Ok(store);
     }
-    *cache = Some(normalized.clone());
-    Ok(normalized)
+    load_locked(app, &path).map(|(store, _)| store)
 }
 
-pub fn write_store(app: &AppHandle, store: &crate::Store) -> AppResult<()> {
+/// Read-modify-write accounts.json under the store lock. The store is re-read
+/// from disk first, so changes made by another process are never overwritten.
+/// Nothing is written when `f` fails.
+pub fn update_store<T>(
+    app: &AppHandle,
+    f: impl FnOnce(&mut crate::Store) -> AppResult<T>,
+) -> AppResult<T> {
     let path = store_path(app)?;
-    let body = serde_json::to_string_pretty(store)
+    let _lock = lock_store_file(&path)?;
+    let (mut store, writable) = load_locked(app, &path)?;
+    if !writable {
+        return Err(AppError::Store(
+            "accounts.json was saved by a newer version of the app and is read-only".to_string(),
+        ));
+    }
+    let out = f(&mut store)?;
+
+    let body = serde_json::to_vec_pretty(&store)
         .map_err(|e| AppError::Store(format!("Failed to serialize store: {e}")))?;
-    fs::write(path, body).map_err(|e| AppError::Store(format!("Failed to write store: {e}")))?;
-    let mut cache = store_cache()
-        .lock()
-        .map_err(|_| AppError::Store("Store cache lock is poisoned".to_string()))?;
-    *cache = Some(store.clone());
-    Ok(())
+    fs_util::atomic_write(&path, &body)
+        .map_err(|e| AppError::Store(format!("Failed to write store: {e}")))?;
+    *cache_lock()? = Some(CachedStore {
+        store,
+        fingerprint: fs_util::fingerprint(&path),
+    });
+    Ok(out)
+}
+
+/// Set or clear `relogin_reason` of one account directly in accounts.json, for
+/// code that runs without an `AppHandle` (token refresh, also used by the CLI).
+/// Returns the previous value when it changed; nothing is written otherwise,
+/// or when the account is unknown or the file cannot be loaded as is.
+pub fn set_relogin_reason(service: &str, id: &str, reason: Option<String>) -> AppResult<Option<Option<String>>> {
+    let path = current_store_file()?;
+    let _lock = lock_store_file(&path)?;
+    let bytes = match fs::read(&path) {
+        Ok(bytes) => bytes,
+        Err(e) if e.kind() == std::io::ErrorKind::NotFound => return Ok(None),
+        Err(e) => return Err(AppError::Store(format!("Failed to read store: {e}"))),
+    };
+    let mut store = parse_store(&bytes)
+        .map_err(|_| AppError::Store("accounts.json cannot be updated".to_string()))?;
+    let Some(entry) = store
+        .services
+        .get_mut(service)
+        .and_then(|list| list.iter_mut().find(|a| a.id == id))
+    else {
+        return Ok(None);
+    };
+    if entry.relogin_reason == reason {
+        return Ok(None);
+    }
+    let previous = std::mem::replace(&mut entry.relogin_reason, reason);
+
+    let body = serde_json::to_vec_pretty(&store)
+        .map_err(|e| AppError::Store(format!("Failed to serialize store: {e}")))?;
+    fs_util::atomic_write(&path, &body)
+        .map_err(|e| AppError::Store(format!("Failed to write store: {e}")))?;
+    // Next read_store reloads from disk.
+    *cache_lock()? = None;
+    Ok(Some(previous))
+}
+
+/// Read, migrate and normalize a store file without touching the in-process cache.
+/// A missing or unparsable file yields the default store; the file is left as is.
+pub fn load_store_file(path: &Path) -> crate::Store {
+    match fs::read(path).map(|bytes| parse_store(&bytes)) {
+        Ok(Ok(store)) => store,
+        Ok(Err(LoadFailure::NewerSchema { store, .. })) => *store,
+        _ => default_store(),
+    }
+}
+
+/// App data directory resolved without an `AppHandle`, for the CLI.
+/// Mirrors Tauri's `app_data_dir()` so both binaries share `accounts.json`.
+pub fn default_data_dir() -> AppResult<PathBuf> {
+    dirs::data_dir()
+        .map(|dir| dir.join(crate::APP_IDENTIFIER))
+        .ok_or_else(|| AppError::Store("Failed to resolve app data directory".to_string()))
+}
+
+/// Move a plaintext secret into the keyring and leave its mask in the store.
+/// Returns Ok(false) when the value is empty or already masked.
+fn migrate_secret(value: &mut String, name: &str) -> AppResult<bool> {
+    if value.is_empty() || is_masked_secret(value) {
+        return Ok(false);
+    }
+    token_store::set_notify_secret(name, value)?;
+    *value = mask_secret(value);
+    Ok(true)
+}
+
+/// One-time move of notification credentials written by older versions from
+/// `accounts.json` into the keyring. Returns whether the store changed.
+pub fn migrate_notify_secrets(store: &mut crate::Store) -> AppResult<bool> {
+    let en = &mut store.settings.external_notify;
+    let mut changed = migrate_secret(&mut en.discord.webhook_url, token_store::DISCORD_WEBHOOK_URL_SECRET)?;
+    en.discord.has_secret = is_masked_secret(&en.discord.webhook_url);
+    changed |= migrate_secret(&mut en.slack.webhook_url, token_store::SLACK_WEBHOOK_URL_SECRET)?;
+    en.slack.has_secret = is_masked_secret(&en.slack.webhook_url);
+    changed |= migrate_secret(&mut en.teams.webhook_url, token_store::TEAMS_WEBHOOK_URL_SECRET)?;
+    en.teams.has_secret = is_masked_secret(&en.teams.webhook_url);
+
+    changed |= migrate_secret(&mut en.pushover.api_token, token_store::PUSHOVER_API_TOKEN_SECRET)?;
+    changed |= migrate_secret(&mut en.pushover.user_key, token_store::PUSHOVER_USER_KEY_SECRET)?;
+    en.pushover.has_secret = is_masked_secret(&en.pushover.api_token) && is_masked_secret(&en.pushover.user_key);
+
+    changed |= migrate_secret(&mut en.ntfy.access_token, token_store::NTFY_ACCESS_TOKEN_SECRET)?;
+    en.ntfy.has_secret = is_masked_secret(&en.ntfy.access_token);
+    changed |= migrate_secret(&mut en.gotify.app_token, token_store::GOTIFY_APP_TOKEN_SECRET)?;
+    en.gotify.has_secret = is_masked_secret(&en.gotify.app_token);
+    Ok(changed)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::{load_or_quarantine, migrate, normalize_store, parse_store, LoadFailure, STORE_SCHEMA_VERSION};
+    use serde_json::json;
+
+    fn normalize(json: &str) -> crate::Store {
+        normalize_store(serde_json::from_str(json).expect("valid store json"))
+    }
+
+    #[test]
+    fn masked_notify_secrets_survive_normalization() {
+        let store = normalize(
+            r#"{"settings":{"externalNotify":{
+                "discord":{"enabled":true,"webhookUrl":"********abcd"},
+                "pushover":{"enabled":true,"apiToken":"********wxyz","userKey":""}
+            }}}"#,
+        );
+        let en = store.settings.external_notify;
+        assert_eq!(en.discord.webhook_url, "********abcd");
+        assert!(en.discord.has_secret);
+        assert!(en.discord.enabled);
+        assert!(!en.pushover.has_secret);
+        assert!(!en.pushover.enabled);
+    }
+
+    #[test]
+    fn slack_and_teams_keep_only_valid_urls() {
+        let store = normalize(
+            r#"{"settings":{"externalNotify":{
+                "slack":{"enabled":true,"webhookUrl":"https://example.com/services/x","warning":true},
+                "teams":{"enabled":true,"webhookUrl":"********abcd"}
+            }}}"#,
+        );
+        let en = store.settings.external_notify;
+        assert_eq!(en.slack.webhook_url, "");
+        assert!(!en.slack.enabled);
+        assert!(en.slack.warning);
+        assert!(en.teams.enabled && en.teams.has_secret);
+        assert!(en.teams.critical);
+    }
+
+    #[test]
+    fn self_hosted_push_servers_are_normalized() {
+        let store = normalize(
+            r#"{"settings":{"externalNotify":{
+                "ntfy":{"enabled":true,"serverUrl":"http://192.168.1.5:8080/","topic":"aiqm","accessToken":"********tk12"},
+                "gotify":{"enabled":true,"serverUrl":"http://gotify.example.com","appToken":"********abcd"}
+            }}}"#,
+        );
+        let en = store.settings.external_notify;
+        assert_eq!(en.ntfy.server_url, "http://192.168.1.5:8080");
+        assert!(en.ntfy.enabled && en.ntfy.has_secret);
+        assert_eq!(en.gotify.server_url, "");
+        assert!(!en.gotify.enabled);
+
+        let defaults = normalize(r#"{"settings":{}}"#).settings.external_notify;
+        assert_eq!(defaults.ntfy.server_url, "https://ntfy.sh");
+        assert!(!defaults.ntfy.enabled);
+    }
+
+    #[test]
+    fn webhooks_are_normalized() {
+        let store = normalize(
+            r#"{"settings":{"externalNotify":{"webhooks":[
+                {"id":"wh1","name":"Slack","enabled":true,"url":"********wxyz","method":"put",
+                 "headers":[{"name":"Authorization","value":"********abcd"},{"name":"Bad Name","value":"********"}]},
+                {"id":"wh1","url":"********dupe"},
+                {"id":"bad id!","url":"********abcd"},
+                {"id":"wh2","enabled":true,"url":"http://example.com/hook"}
+            ]}}}"#,
+        );
+        let hooks = store.settings.external_notify.webhooks;
+        assert_eq!(hooks.len(), 2);
+        assert!(hooks[0].enabled && hooks[0].has_secret);
+        assert_eq!(hooks[0].method, "PUT");
+        assert_eq!(hooks[0].headers.len(), 1);
+        assert!(hooks[0].body_template.contains("{{title}}"));
+        assert_eq!(hooks[1].url, "");
+        assert!(!hooks[1].enabled);
+    }
+
+    #[test]
+    fn legacy_plaintext_webhook_is_kept_for_migration() {
+        let store = normalize(
+            r#"{"settings":{"externalNotify":{"discord":{"enabled":true,"webhookUrl":"https://discord.com/api/webhooks/1/tok"}}}}"#,
+        );
+        let discord = store.settings.external_notify.discord;
+        assert_eq!(discord.webhook_url, "https://discord.com/api/webhooks/1/tok");
+        assert!(!discord.has_secret);
+    }
+
+    #[test]
+    fn cli_link_is_kept_only_for_linked_accounts() {
+        let store = normalize(
+            r#"{"services":{"claude":[
+                {"id":"a1","name":"Main","cliLinked":true,"cliSyncedAt":1700000000000},
+                {"id":"a2","name":"Sub","cliLinked":false,"cliSyncedAt":1700000000000}
+            ]},"settings":{}}"#,
+        );
+        let claude = &store.services["claude"];
+        assert!(claude[0].cli_linked);
+        assert_eq!(claude[0].cli_synced_at, Some(1_700_000_000_000));
+        assert!(!claude[1].cli_linked);
+        assert_eq!(claude[1].cli_synced_at, None);
+    }
+
+    #[test]
+    fn token_refresh_lead_is_clamped() {
+        let store = normalize(r#"{"settings":{"tokenRefresh":{"leadMinutes":600}}}"#);
+        assert_eq!(store.settings.token_refresh.lead_minutes, crate::TOKEN_REFRESH_LEAD_MINUTES_MAX);
+        let store = normalize(r#"{"settings":{}}"#);
+        assert_eq!(store.settings.token_refresh.lead_minutes, crate::TOKEN_REFRESH_LEAD_MINUTES_DEFAULT);
+    }
+
+    #[test]
+    fn blank_relogin_reason_is_dropped() {
+        let store = normalize(
+            r#"{"services":{"codex":[
+                {"id":"a1","name":"Main","reloginReason":"invalid_grant"},
+                {"id":"a2","name":"Sub","reloginReason":"  "}
+            ]},"settings":{}}"#,
+        );
+        let codex = &store.services["codex"];
+        assert_eq!(codex[0].relogin_reason.as_deref(), Some("invalid_grant"));
+        assert_eq!(codex[1].relogin_reason, None);
+    }
+
+    #[test]
+    fn unversioned_store_is_migrated_to_current_schema() {
+        assert_eq!(super::MIGRATIONS.len(), STORE_SCHEMA_VERSION as usize);
+        let mut doc = json!({"services": {"claude": [{"id": "a1", "name": "Main"}]}, "settings": {}});
+        migrate(&mut doc).expect("migrates");
+        assert_eq!(doc["schemaVersion"], json!(STORE_SCHEMA_VERSION));
+
+        let store = parse_store(doc.to_string().as_bytes()).expect("parses");
+        assert_eq!(store.schema_version, STORE_SCHEMA_VERSION);
+        assert_eq!(store.services["claude"][0].name, "Main");
+    }
+
+    #[test]
+    fn unexpected_shapes_are_rejected() {
+        assert!(parse_store(b"{not json").is_err());
+        assert!(parse_store(b"[]").is_err());
+        assert!(parse_store(br#"{"services": []}"#).is_err());
+        assert!(parse_store(br#"{"schemaVersion": "one"}"#).is_err());
+    }
+
+    #[test]
+    fn newer_store_is_left_in_place() {
+        let dir = std::env::temp_dir().join(format!("aiqm-store-newer-test-{}", std::process::id()));
+        std::fs::create_dir_all(&dir).expect("temp dir");
+        let path = dir.join("accounts.json");
+        let body = format!(
+            r#"{{"schemaVersion": {}, "services": {{"claude": [{{"id": "a1", "name": "Main"}}]}}, "settings": {{}}}}"#,
+            STORE_SCHEMA_VERSION + 1
+        );
+        std::fs::write(&path, &body).expect("write");
+
+        assert!(matches!(parse_store(body.as_bytes()), Err(LoadFailure::NewerSchema { .. })));
+        let (store, issue) = load_or_quarantine(&path).expect("load");
+        let issue = issue.expect("issue reported");
+        assert!(matches!(issue.kind, crate::StoreLoadIssueKind::NewerSchema));
+        assert_eq!(issue.backup_path, None);
+        assert_eq!(std::fs::read_to_string(&path).expect("file kept"), body);
+        assert_eq!(store.services["claude"][0].name, "Main");
+        let _ = std::fs::remove_dir_all(&dir);
+    }
+
+    #[test]
+    fn corrupt_store_is_moved_aside() {
+        let dir = std::env::temp_dir().join(format!("aiqm-store-test-{}", std::process::id()));
+        std::fs::create_dir_all(&dir).expect("temp dir");
+        let path = dir.join("accounts.json");
+        std::fs::write(&path, b"{\"services\": {\"claude\": [").expect("write");
+
+        let (store, issue) = load_or_quarantine(&path).expect("load");
+        let issue = issue.expect("issue reported");
+        assert!(!path.exists());
+        assert_eq!(
+            std::fs::read(issue.backup_path.expect("backup path")).expect("backup"),
+            b"{\"services\": {\"claude\": ["
+        );
+        assert!(store.services.values().all(|accounts| accounts.is_empty()));
+
+        let (_, issue) = load_or_quarantine(&path).expect("missing file");
+        assert!(issue.is_none());
+        let _ = std::fs::remove_dir_all(&dir);
+    }
 }
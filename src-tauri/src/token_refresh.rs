@@ -1,19 +1,149 @@
-use crate::oauth;
+use crate::oauth::RefreshError;
+use crate::fs_util;
+use crate::providers;
+use crate::store_repo;
 use crate::token_store;
+use std::collections::HashMap;
+use std::sync::{Arc, Mutex, OnceLock};
+use std::time::Duration;
+use tokio::sync::mpsc::{unbounded_channel, UnboundedReceiver, UnboundedSender};
 use zeroize::Zeroize;
 
+/// Refresh this long before `expires_at` (matches `get_token_status().needs_refresh`).
+const REFRESH_LEAD_MS: i64 = 5 * 60 * 1000;
+
+/// Retries while the request could not be sent at all, with exponential backoff.
+/// Anything later (timeout, cut-off response, 5xx) is not resent: the refresh
+/// token may already have been rotated, and resending it would be rejected.
+const MAX_RETRIES: u32 = 3;
+const RETRY_BASE_MS: u64 = 1000;
+
+/// One async lock per `service:id`. Codex rotates refresh tokens on every use,
+/// so two concurrent refreshes would burn the second one and log the account out.
+/// Other processes (the CLI, a second app instance) are kept out by a lock file
+/// next to accounts.json, see [`lock_account`].
+static REFRESH_LOCKS: OnceLock<Mutex<HashMap<String, Arc<tokio::sync::Mutex<()>>>>> = OnceLock::new();
+
+/// A change of an account's "needs re-login" state, already written to the store.
+#[derive(Debug, Clone)]
+pub struct ReloginChange {
+    pub service: String,
+    pub id: String,
+    /// Set while the account needs a new login.
+    pub reason: Option<String>,
+    /// The account was fine before this change.
+    pub newly_marked: bool,
+}
+
+/// Receiver side is the app; the CLI only writes the store.
+static RELOGIN_TX: OnceLock<UnboundedSender<ReloginChange>> = OnceLock::new();
+
+/// Receive every change recorded by [`record_relogin`]. Only the first caller
+/// gets a receiver.
+pub fn subscribe_relogin() -> Option<UnboundedReceiver<ReloginChange>> {
+    let (tx, rx) = unbounded_channel();
+    RELOGIN_TX.set(tx).ok()?;
+    Some(rx)
+}
+
+/// Write the "needs re-login" state of an account to the store (`None`
+/// clears it) and pass an actual change on to the subscriber.
+pub fn record_relogin(service: &str, id: &str, reason: Option<String>) {
+    let Ok(Some(previous)) = store_repo::set_relogin_reason(service, id, reason.clone()) else {
+        return;
+    };
+    if let Some(tx) = RELOGIN_TX.get() {
+        let _ = tx.send(ReloginChange {
+            service: service.to_string(),
+            id: id.to_string(),
+            newly_marked: previous.is_none() && reason.is_some(),
+            reason,
+        });
+    }
+}
+
+fn account_lock(service: &str, id: &str) -> Arc<tokio::sync::Mutex<()>> {
+    let locks = REFRESH_LOCKS.get_or_init(|| Mutex::new(HashMap::new()));
+    let mut map = match locks.lock() {
+        Ok(map) => map,
+        Err(poisoned) => poisoned.into_inner(),
+    };
+    map.entry(format!("{service}:{id}")).or_default().clone()
+}
+
+/// Both refresh locks of an account. The lock file is released first.
+pub struct AccountGuard {
+    _file: std::fs::File,
+    _task: tokio::sync::OwnedMutexGuard<()>,
+}
+
+/// Take the in-process lock of an account, then its lock file
+/// `refresh-<service>-<id>.lock` shared with every process using the same
+/// accounts.json. Stored tokens must be read only after this returns.
+async fn lock_account(service: &str, id: &str) -> Result<AccountGuard, RefreshError> {
+    let task = account_lock(service, id).lock_owned().await;
+    let dir = store_repo::store_dir().map_err(|e| RefreshError::Other(e.to_string()))?;
+    let path = dir.join(lock_file_name(service, id));
+    let file = tokio::task::spawn_blocking(move || {
+        std::fs::create_dir_all(&dir)?;
+        fs_util::lock_exclusive(&path)
+    })
+    .await
+    .map_err(|e| RefreshError::Other(format!("Failed to lock token refresh: {e}")))?
+    .map_err(|e| RefreshError::Other(format!("Failed to lock token refresh: {e}")))?;
+    Ok(AccountGuard { _file: file, _task: task })
+}
+
+/// Account ids may contain any printable character; the rest become `_`.
+/// Two ids mapping to the same name merely share a lock.
+fn lock_file_name(service: &str, id: &str) -> String {
+    let safe = |s: &str| -> String {
+        s.chars()
+            .map(|c| if c.is_ascii_alphanumeric() || c == '-' || c == '_' { c } else { '_' })
+            .collect()
+    };
+    format!("refresh-{}-{}.lock", safe(service), safe(id))
+}
+
+/// Hold the refresh locks of an account, e.g. while its tokens are handed to
+/// another program.
+pub async fn hold_account(service: &str, id: &str) -> Result<AccountGuard, RefreshError> {
+    lock_account(service, id).await
+}
+
 /// Force a token refresh using the stored refresh_token.
 /// Returns Ok(true) on success.
-pub async fn do_refresh(service: &str, id: &str) -> Result<bool, String> {
-    let mut refresh_tok = match token_store::get_refresh_token(service, id) {
-        Some(t) => t,
-        None => return Err("No refresh token available. Please log in again.".into()),
-    };
+pub async fn do_refresh(service: &str, id: &str) -> Result<bool, RefreshError> {
+    let _guard = lock_account(service, id).await?;
+    refresh_locked(service, id).await
+}
+
+async fn refresh_locked(service: &str, id: &str) -> Result<bool, RefreshError> {
+    let result = refresh_with_retry(service, id).await;
+    match &result {
+        Ok(_) => record_relogin(service, id, None),
+        Err(RefreshError::Revoked(reason)) => record_relogin(service, id, Some(reason.clone())),
+        Err(_) => {}
+    }
+    result
+}
 
-    let result = match service {
-        "claude" => oauth::claude::refresh_token(&refresh_tok).await,
-        "codex" => oauth::codex::refresh_token(&refresh_tok).await,
-        _ => return Err(format!("Unsupported service: {service}")),
+async fn refresh_with_retry(service: &str, id: &str) -> Result<bool, RefreshError> {
+    let mut refresh_tok = token_store::get_refresh_token(service, id).ok_or(RefreshError::MissingToken)?;
+
+    let Some(provider) = providers::get(service) else {
+        refresh_tok.zeroize();
+        return Err(RefreshError::Other(format!("Unsupported service: {service}")));
+    };
+    let mut attempt = 0;
+    let result = loop {
+        match provider.refresh_token(&refresh_tok).await {
+            Err(e) if e.can_resend() && attempt < MAX_RETRIES => {
+                tokio::time::sleep(retry_delay(attempt, rand::random())).await;
+                attempt += 1;
+            }
+            result => break result,
+        }
     };
 
     refresh_tok.zeroize();
@@ -22,21 +152,108 @@ pub async fn do_refresh(service: &str, id: &str) -> Result<bool, String> {
 
     // Store new access token
     token_store::set_token(service, id, &tokens.access_token)
-        .map_err(|e| format!("Failed to store refreshed token: {e}"))?;
+        .map_err(|e| RefreshError::Other(format!("Failed to store refreshed token: {e}")))?;
 
     // Store new refresh token (rotation: new one replaces old)
     if let Some(ref new_refresh) = tokens.refresh_token {
         token_store::set_refresh_token(service, id, new_refresh)
-            .map_err(|e| format!("Failed to store new refresh token: {e}"))?;
+            .map_err(|e| RefreshError::Other(format!("Failed to store new refresh token: {e}")))?;
     }
 
     // Store new expiry
     if let Some(exp) = tokens.expires_at {
         token_store::set_expires_at(service, id, exp)
-            .map_err(|e| format!("Failed to store new expiry: {e}"))?;
+            .map_err(|e| RefreshError::Other(format!("Failed to store new expiry: {e}")))?;
     }
 
     tokens.access_token.zeroize();
 
     Ok(true)
 }
+
+/// Delay before retry `attempt` (0-based): 1s, 2s, 4s, each with the upper
+/// half drawn from `jitter` (0..1) so that accounts do not retry in lockstep.
+fn retry_delay(attempt: u32, jitter: f64) -> Duration {
+    let full = RETRY_BASE_MS << attempt.min(8);
+    let half = full / 2;
+    Duration::from_millis(half + (half as f64 * jitter.clamp(0.0, 1.0)) as u64)
+}
+
+/// Refresh the access token when it is about to expire and a refresh_token is stored.
+/// Returns Ok(false) when no refresh was needed.
+pub async fn refresh_if_expiring(service: &str, id: &str) -> Result<bool, RefreshError> {
+    refresh_if_expiring_within(service, id, REFRESH_LEAD_MS).await
+}
+
+/// Like [`refresh_if_expiring`], with the lead time before `expires_at` given
+/// in milliseconds (the background scheduler's setting).
+pub async fn refresh_if_expiring_within(service: &str, id: &str, lead_ms: i64) -> Result<bool, RefreshError> {
+    if !is_expiring(service, id, lead_ms) {
+        return Ok(false);
+    }
+    let _guard = lock_account(service, id).await?;
+    // Another task or process may have refreshed while we waited for the lock.
+    if !is_expiring(service, id, lead_ms) {
+        return Ok(false);
+    }
+    refresh_locked(service, id).await
+}
+
+fn is_expiring(service: &str, id: &str, lead_ms: i64) -> bool {
+    let Some(expires_at) = token_store::get_expires_at(service, id) else {
+        return false;
+    };
+    let now = std::time::SystemTime::now()
+        .duration_since(std::time::UNIX_EPOCH)
+        .unwrap_or_default()
+        .as_millis() as i64;
+    now >= expires_at - lead_ms && token_store::get_refresh_token(service, id).is_some()
+}
+
+/// Recover from HTTP 401 for `rejected_token`. If the stored access token has
+/// already changed (a concurrent fetch refreshed it), no refresh is sent.
+pub async fn refresh_after_unauthorized(
+    service: &str,
+    id: &str,
+    rejected_token: &str,
+) -> Result<(), RefreshError> {
+    let _guard = lock_account(service, id).await?;
+
+    let mut current = token_store::get_token(service, id).unwrap_or_default();
+    let already_refreshed = !current.is_empty() && current != rejected_token;
+    current.zeroize();
+    if already_refreshed {
+        return Ok(());
+    }
+    refresh_locked(service, id).await.map(|_| ())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::{account_lock, lock_file_name, retry_delay};
+    use std::sync::Arc;
+    use std::time::Duration;
+
+    #[test]
+    fn account_lock_is_shared_per_account() {
+        let a = account_lock("codex", "lock-test");
+        let b = account_lock("codex", "lock-test");
+        let other = account_lock("claude", "lock-test");
+        assert!(Arc::ptr_eq(&a, &b));
+        assert!(!Arc::ptr_eq(&a, &other));
+    }
+
+    #[test]
+    fn lock_file_name_stays_in_the_directory() {
+        assert_eq!(lock_file_name("codex", "acc-1_a"), "refresh-codex-acc-1_a.lock");
+        assert_eq!(lock_file_name("claude", "../x y"), "refresh-claude-___x_y.lock");
+    }
+
+    #[test]
+    fn retry_delay_doubles_with_jitter_in_the_upper_half() {
+        assert_eq!(retry_delay(0, 0.0), Duration::from_millis(500));
+        assert_eq!(retry_delay(0, 1.0), Duration::from_secs(1));
+        assert_eq!(retry_delay(1, 0.5), Duration::from_millis(1500));
+        assert_eq!(retry_delay(2, 1.0), Duration::from_secs(4));
+    }
+}
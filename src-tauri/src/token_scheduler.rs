@@ -0,0 +1,179 @@
+//! Background token refresh. Every account with a refresh token and a known
+//! expiry is refreshed `lead_minutes` before the access token expires, whether
+//! or not polling runs, so the first fetch after a long idle does not fail.
+//! Refreshes go through `token_refresh`, which serializes them per account.
+
+use crate::store_repo::read_store;
+use crate::token_refresh;
+use crate::token_store;
+use crate::usage_state;
+use serde::Serialize;
+use std::collections::{HashMap, HashSet};
+use std::sync::{Arc, Mutex, MutexGuard};
+use std::time::Duration;
+use tauri::{AppHandle, Emitter};
+
+pub const EVENT_TOKEN_REFRESHED: &str = "token-refreshed";
+
+/// Longest sleep between scans, so new logins and setting changes are picked up.
+const RESCAN_INTERVAL_MS: i64 = 5 * 60 * 1000;
+/// After a failed refresh (retries included), wait this long before trying again.
+const FAILURE_RETRY_MS: i64 = 5 * 60 * 1000;
+
+#[derive(Debug, Clone, Serialize)]
+#[serde(rename_all = "camelCase")]
+struct TokenRefreshed {
+    service: String,
+    id: String,
+    name: String,
+    /// New expiry on success.
+    expires_at: Option<i64>,
+    /// Set when the refresh failed.
+    error: Option<String>,
+}
+
+/// Per-account state shared with the spawned refreshes.
+#[derive(Default)]
+struct Pending {
+    /// Earliest retry after a failed refresh, by account key.
+    retry_at: HashMap<String, i64>,
+    /// Accounts whose refresh is still running.
+    in_flight: HashSet<String>,
+}
+
+type SharedPending = Arc<Mutex<Pending>>;
+
+fn lock(pending: &SharedPending) -> MutexGuard<'_, Pending> {
+    pending.lock().unwrap_or_else(|poisoned| poisoned.into_inner())
+}
+
+/// Spawn the refresh timer.
+pub fn start(app: AppHandle) {
+    tauri::async_runtime::spawn(async move {
+        let pending = SharedPending::default();
+        loop {
+            let next = refresh_due(&app, &pending);
+            let wait = (next - now_millis()).clamp(1000, RESCAN_INTERVAL_MS);
+            tokio::time::sleep(Duration::from_millis(wait as u64)).await;
+        }
+    });
+}
+
+/// Start a refresh for every account that is due and return when the next
+/// one will be. Each account refreshes in its own task, so one slow token
+/// endpoint does not hold up the others; `token_refresh` still serializes
+/// refreshes of the same account.
+fn refresh_due(app: &AppHandle, pending: &SharedPending) -> i64 {
+    let now = now_millis();
+    let mut next = now + RESCAN_INTERVAL_MS;
+    let Ok(store) = read_store(app) else {
+        return next;
+    };
+    let lead_ms = i64::from(store.settings.token_refresh.lead_minutes) * 60 * 1000;
+    let mut configured = Vec::new();
+    let mut state = lock(pending);
+    for provider in crate::providers::all() {
+        let service = provider.id();
+        for account in store.services.get(service).into_iter().flatten() {
+            let key = usage_state::account_key(service, &account.id);
+            configured.push(key.clone());
+            // Linked accounts are refreshed by their CLI; rejected ones wait for a new login.
+            if account.cli_linked || account.relogin_reason.is_some() || state.in_flight.contains(&key) {
+                continue;
+            }
+            let Some(due) = due_at(
+                token_store::get_expires_at(service, &account.id),
+                token_store::get_refresh_token(service, &account.id).is_some(),
+                lead_ms,
+                state.retry_at.get(&key).copied(),
+            ) else {
+                continue;
+            };
+            if due > now {
+                next = next.min(due);
+                continue;
+            }
+            state.in_flight.insert(key.clone());
+            tauri::async_runtime::spawn(refresh_account(
+                app.clone(),
+                pending.clone(),
+                service,
+                account.clone(),
+                lead_ms,
+                key,
+            ));
+        }
+    }
+    state.retry_at.retain(|key, _| configured.contains(key));
+    next
+}
+
+async fn refresh_account(
+    app: AppHandle,
+    pending: SharedPending,
+    service: &'static str,
+    account: crate::AccountEntry,
+    lead_ms: i64,
+    key: String,
+) {
+    let result = token_refresh::refresh_if_expiring_within(service, &account.id, lead_ms).await;
+    {
+        let mut state = lock(&pending);
+        state.in_flight.remove(&key);
+        match &result {
+            Ok(_) => state.retry_at.remove(&key),
+            Err(_) => state.retry_at.insert(key, now_millis() + FAILURE_RETRY_MS),
+        };
+    }
+    // Ok(false): a poll refreshed it while we waited for the lock.
+    if matches!(result, Ok(false)) {
+        return;
+    }
+    // A rejected refresh token is reported by `token_refresh` itself; this
+    // event only updates the expiry or error shown for the account.
+    let _ = app.emit(
+        EVENT_TOKEN_REFRESHED,
+        TokenRefreshed {
+            service: service.to_string(),
+            id: account.id.clone(),
+            name: account.name.clone(),
+            expires_at: result
+                .as_ref()
+                .ok()
+                .and_then(|_| token_store::get_expires_at(service, &account.id)),
+            error: result.as_ref().err().map(ToString::to_string),
+        },
+    );
+}
+
+/// When the account should be refreshed, or `None` when it cannot be
+/// refreshed here (no refresh token or unknown expiry). A pending retry after
+/// a failure pushes the time back.
+fn due_at(expires_at: Option<i64>, has_refresh_token: bool, lead_ms: i64, retry_at: Option<i64>) -> Option<i64> {
+    if !has_refresh_token {
+        return None;
+    }
+    let due = expires_at? - lead_ms;
+    Some(retry_at.map_or(due, |at| due.max(at)))
+}
+
+fn now_millis() -> i64 {
+    chrono::Utc::now().timestamp_millis()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::due_at;
+
+    const MIN: i64 = 60 * 1000;
+
+    #[test]
+    fn due_time_is_lead_before_expiry_or_the_pending_retry() {
+        assert_eq!(due_at(Some(60 * MIN), true, 5 * MIN, None), Some(55 * MIN));
+        // A retry after a failure waits even when the token is already due.
+        assert_eq!(due_at(Some(60 * MIN), true, 5 * MIN, Some(58 * MIN)), Some(58 * MIN));
+        assert_eq!(due_at(Some(60 * MIN), true, 5 * MIN, Some(10 * MIN)), Some(55 * MIN));
+        assert_eq!(due_at(None, true, 5 * MIN, None), None);
+        assert_eq!(due_at(Some(60 * MIN), false, 5 * MIN, None), None);
+    }
+}
@@ -1,4 +1,5 @@
 use crate::error::{AppError, AppResult};
+use serde::{Deserialize, Serialize};
 
 // Keep each chunk comfortably below the Windows CredentialBlob size limit (2560 bytes).
 // keyring backends may store UTF-16 internally, so we use a conservative char count.
@@ -6,11 +7,19 @@ const TOKEN_CHUNK_SIZE: usize = 1000;
 const TOKEN_PARTS_SUFFIX: &str = ":parts";
 const TOKEN_PART_PREFIX: &str = ":part:";
 
+/// Keyring names for notification channel credentials.
+pub const DISCORD_WEBHOOK_URL_SECRET: &str = "discord:webhook_url";
+pub const SLACK_WEBHOOK_URL_SECRET: &str = "slack:webhook_url";
+pub const TEAMS_WEBHOOK_URL_SECRET: &str = "teams:webhook_url";
+pub const PUSHOVER_API_TOKEN_SECRET: &str = "pushover:api_token";
+pub const PUSHOVER_USER_KEY_SECRET: &str = "pushover:user_key";
+pub const NTFY_ACCESS_TOKEN_SECRET: &str = "ntfy:access_token";
+pub const GOTIFY_APP_TOKEN_SECRET: &str = "gotify:app_token";
+const SECRET_MASK: &str = "********";
+const SECRET_MASK_VISIBLE_CHARS: usize = 4;
+
 pub fn ensure_service(service: &str) -> AppResult<()> {
-    match service {
-        "claude" | "codex" => Ok(()),
-        _ => Err(AppError::UnsupportedService),
-    }
+    crate::providers::require(service).map(|_| ())
 }
 
 fn token_key(service: &str, id: &str) -> String {
@@ -69,24 +78,22 @@ fn restore_chunked_token(base_key: &str) -> Option<String> {
     Some(token)
 }
 
-pub fn get_token(service: &str, id: &str) -> Option<String> {
-    let base_key = token_key(service, id);
-
-    if let Some(token) = restore_chunked_token(&base_key) {
+fn get_chunked(base_key: &str) -> Option<String> {
+    if let Some(token) = restore_chunked_token(base_key) {
         return Some(token);
     }
 
-    let entry = keyring::Entry::new(crate::APP_NAME, &base_key).ok()?;
+    let entry = keyring::Entry::new(crate::APP_NAME, base_key).ok()?;
     entry.get_password().ok()
 }
 
-pub fn set_token(service: &str, id: &str, token: &str) -> AppResult<()> {
-    let base_key = token_key(service, id);
-    delete_token(service, id)?;
+/// Store `token` under `base_key`, split into parts when it is too long for one entry.
+/// The caller removes any previous value first.
+fn set_chunked(base_key: &str, token: &str) -> AppResult<()> {
     let token_len = token.len();
 
     if token.len() <= TOKEN_CHUNK_SIZE {
-        let entry = open_entry(&base_key)?;
+        let entry = open_entry(base_key)?;
         return entry
             .set_password(token)
             .map_err(|e| AppError::Keyring(format!(
@@ -96,9 +103,9 @@ pub fn set_token(service: &str, id: &str, token: &str) -> AppResult<()> {
 
     let parts = split_token_for_storage(token);
     for (index, part) in parts.iter().enumerate() {
-        let part_entry = open_entry(&token_part_key(&base_key, index))?;
+        let part_entry = open_entry(&token_part_key(base_key, index))?;
         if let Err(e) = part_entry.set_password(part) {
-            let _ = delete_token(service, id);
+            let _ = delete_chunked(base_key);
             return Err(AppError::Keyring(format!(
                 "Failed to store token in keyring: {e} (len={token_len}, part={index}, part_len={})",
                 part.len()
@@ -106,9 +113,9 @@ pub fn set_token(service: &str, id: &str, token: &str) -> AppResult<()> {
         }
     }
 
-    let meta_entry = open_entry(&token_parts_key(&base_key))?;
+    let meta_entry = open_entry(&token_parts_key(base_key))?;
     if let Err(e) = meta_entry.set_password(&parts.len().to_string()) {
-        let _ = delete_token(service, id);
+        let _ = delete_chunked(base_key);
         return Err(AppError::Keyring(format!(
             "Failed to store token in keyring: {e} (len={token_len}, part_count={})",
             parts.len()
@@ -118,15 +125,23 @@ pub fn set_token(service: &str, id: &str, token: &str) -> AppResult<()> {
     Ok(())
 }
 
-pub fn delete_token(service: &str, id: &str) -> AppResult<()> {
-    let base_key = token_key(service, id);
+pub fn get_token(service: &str, id: &str) -> Option<String> {
+    get_chunked(&token_key(service, id))
+}
+
+pub fn set_token(service: &str, id: &str, token: &str) -> AppResult<()> {
+    delete_token(service, id)?;
+    set_chunked(&token_key(service, id), token)
+}
+
+fn delete_chunked(base_key: &str) -> AppResult<()> {
     let mut first_error: Option<AppError> = None;
 
-    if let Err(e) = delete_key(&base_key) {
+    if let Err(e) = delete_key(base_key) {
         first_error.get_or_insert(e);
     }
 
-    let parts_key = token_parts_key(&base_key);
+    let parts_key = token_parts_key(base_key);
     let part_count = match open_entry(&parts_key)?.get_password() {
         Ok(raw_count) => raw_count.parse::<usize>().unwrap_or(0),
         Err(keyring::Error::NoEntry) => 0,
@@ -139,7 +154,7 @@ pub fn delete_token(service: &str, id: &str) -> AppResult<()> {
     };
 
     for index in 0..part_count {
-        if let Err(e) = delete_key(&token_part_key(&base_key, index)) {
+        if let Err(e) = delete_key(&token_part_key(base_key, index)) {
             first_error.get_or_insert(e);
         }
     }
@@ -148,16 +163,22 @@ pub fn delete_token(service: &str, id: &str) -> AppResult<()> {
         first_error.get_or_insert(e);
     }
 
-    // Also clean up refresh token and expiry metadata
-    let _ = delete_key(&refresh_token_key(service, id));
-    let _ = delete_key(&expires_at_key(service, id));
-
     match first_error {
         Some(e) => Err(e),
         None => Ok(()),
     }
 }
 
+pub fn delete_token(service: &str, id: &str) -> AppResult<()> {
+    let result = delete_chunked(&token_key(service, id));
+
+    // Also clean up refresh token and expiry metadata
+    let _ = delete_key(&refresh_token_key(service, id));
+    let _ = delete_key(&expires_at_key(service, id));
+
+    result
+}
+
 // ── Refresh token & expiry helpers ──
 
 fn refresh_token_key(service: &str, id: &str) -> String {
@@ -169,12 +190,7 @@ fn expires_at_key(service: &str, id: &str) -> String {
 }
 
 pub fn get_refresh_token(service: &str, id: &str) -> Option<String> {
-    let key = refresh_token_key(service, id);
-    if let Some(token) = restore_chunked_token(&key) {
-        return Some(token);
-    }
-    let entry = keyring::Entry::new(crate::APP_NAME, &key).ok()?;
-    entry.get_password().ok()
+    get_chunked(&refresh_token_key(service, id))
 }
 
 pub fn set_refresh_token(service: &str, id: &str, token: &str) -> AppResult<()> {
@@ -186,6 +202,10 @@ pub fn set_refresh_token(service: &str, id: &str, token: &str) -> AppResult<()>
         .map_err(|e| AppError::Keyring(format!("Failed to store refresh token: {e}")))
 }
 
+pub fn delete_refresh_token(service: &str, id: &str) -> AppResult<()> {
+    delete_key(&refresh_token_key(service, id))
+}
+
 pub fn get_expires_at(service: &str, id: &str) -> Option<i64> {
     let key = expires_at_key(service, id);
     let entry = keyring::Entry::new(crate::APP_NAME, &key).ok()?;
@@ -201,9 +221,70 @@ pub fn set_expires_at(service: &str, id: &str, epoch_ms: i64) -> AppResult<()> {
         .map_err(|e| AppError::Keyring(format!("Failed to store token expiry: {e}")))
 }
 
+// ── Notification channel secrets ──
+
+fn notify_secret_key(name: &str) -> String {
+    format!("notify:{name}")
+}
+
+pub fn get_notify_secret(name: &str) -> Option<String> {
+    get_chunked(&notify_secret_key(name))
+}
+
+pub fn set_notify_secret(name: &str, value: &str) -> AppResult<()> {
+    let key = notify_secret_key(name);
+    delete_chunked(&key)?;
+    set_chunked(&key, value)
+}
+
+pub fn delete_notify_secret(name: &str) -> AppResult<()> {
+    delete_chunked(&notify_secret_key(name))
+}
+
+/// URL and header values of one generic webhook, kept together as JSON.
+#[derive(Debug, Clone, Default, PartialEq, Eq, Serialize, Deserialize)]
+pub struct WebhookSecret {
+    pub url: String,
+    /// `(name, value)` pairs.
+    pub headers: Vec<(String, String)>,
+}
+
+fn webhook_secret_name(id: &str) -> String {
+    format!("webhook:{id}")
+}
+
+pub fn get_webhook_secret(id: &str) -> Option<WebhookSecret> {
+    get_notify_secret(&webhook_secret_name(id)).and_then(|json| serde_json::from_str(&json).ok())
+}
+
+pub fn set_webhook_secret(id: &str, secret: &WebhookSecret) -> AppResult<()> {
+    let json = serde_json::to_string(secret)
+        .map_err(|e| AppError::Keyring(format!("Failed to serialize webhook secret: {e}")))?;
+    set_notify_secret(&webhook_secret_name(id), &json)
+}
+
+pub fn delete_webhook_secret(id: &str) -> AppResult<()> {
+    delete_notify_secret(&webhook_secret_name(id))
+}
+
+/// Display form of a secret kept in the keyring: a fixed mask plus the last few chars.
+pub fn mask_secret(value: &str) -> String {
+    let chars: Vec<char> = value.chars().collect();
+    if chars.len() <= SECRET_MASK_VISIBLE_CHARS * 2 {
+        return SECRET_MASK.to_string();
+    }
+    let tail: String = chars[chars.len() - SECRET_MASK_VISIBLE_CHARS..].iter().collect();
+    format!("{SECRET_MASK}{tail}")
+}
+
+/// True for values produced by [`mask_secret`]. Webhook URLs and Pushover keys never start with `*`.
+pub fn is_masked_secret(value: &str) -> bool {
+    value.starts_with(SECRET_MASK)
+}
+
 #[cfg(test)]
 mod tests {
-    use super::{split_token_for_storage, TOKEN_CHUNK_SIZE};
+    use super::{is_masked_secret, mask_secret, split_token_for_storage, TOKEN_CHUNK_SIZE};
 
     #[test]
     fn split_token_for_storage_keeps_original_content() {
@@ -215,4 +296,14 @@ mod tests {
         assert_eq!(chunks[2].len(), 13);
         assert_eq!(chunks.concat(), input);
     }
+
+    #[test]
+    fn mask_secret_hides_all_but_the_tail() {
+        let masked = mask_secret("https://discord.com/api/webhooks/123/abcdefgh");
+        assert_eq!(masked, "********efgh");
+        assert!(is_masked_secret(&masked));
+        assert_eq!(mask_secret("short"), "********");
+        assert!(!is_masked_secret("https://discord.com/api/webhooks/123/abc"));
+        assert!(!is_masked_secret("uQiRzpo4DXghDmr9QzzfQu27cmVRsG"));
+    }
 }
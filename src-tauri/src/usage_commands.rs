@@ -1,7 +1,9 @@
-use crate::api_client::{fetch_normalized_usage, FetchUsageResponse};
+use crate::api_client::{fetch_normalized_usage, ApiError, FetchUsageResponse};
 use crate::error::{AppError, AppResult};
-use crate::store_repo::{read_store, write_store};
-use crate::token_store::{ensure_service, get_token, set_token};
+use crate::history_store;
+use crate::store_repo::update_store;
+use crate::token_refresh;
+use crate::token_store::{ensure_service, get_refresh_token, get_token, set_token};
 use crate::validation::{
     enforce_fetch_usage_rate_limit, validate_account_id, validate_account_name, validate_token,
 };
@@ -22,43 +24,75 @@ pub async fn fetch_usage(
     let name = crate::sanitize_string(payload.name.as_deref(), &fallback_name);
     validate_account_name(&name)?;
 
-    let mut store = read_store(&app)?;
-    let list = match service.as_str() {
-        "claude" => &mut store.services.claude,
-        "codex" => &mut store.services.codex,
-        _ => return Err(AppError::UnsupportedService),
-    };
+    update_store(&app, |store| {
+        let list = store.services.entry(service.clone()).or_default();
 
-    if let Some(existing) = list.iter_mut().find(|x| x.id == id) {
-        if existing.name != name {
-            existing.name = name.clone();
+        if let Some(existing) = list.iter_mut().find(|x| x.id == id) {
+            if existing.name != name {
+                existing.name = name.clone();
+            }
+        } else {
+            list.push(crate::AccountEntry {
+                id: id.clone(),
+                name: name.clone(),
+                muted_until: None,
+                cli_linked: false,
+                cli_synced_at: None,
+                priority: 0,
+                reserve: false,
+                relogin_reason: None,
+            });
         }
-    } else {
-        list.push(crate::AccountEntry {
-            id: id.clone(),
-            name: name.clone(),
-        });
-    }
 
-    if let Some(mut token_input) = payload.token.take() {
-        let mut trimmed = token_input.trim().to_string();
-        if !trimmed.is_empty() {
-            validate_token(&trimmed)?;
-            set_token(&service, &id, &trimmed)?;
+        if let Some(mut token_input) = payload.token.take() {
+            let mut trimmed = token_input.trim().to_string();
+            if !trimmed.is_empty() {
+                validate_token(&trimmed)?;
+                set_token(&service, &id, &trimmed)?;
+            }
+            trimmed.zeroize();
+            token_input.zeroize();
         }
-        trimmed.zeroize();
-        token_input.zeroize();
-    }
+        Ok(())
+    })?;
 
-    write_store(&app, &store)?;
+    let response = fetch_stored_usage(&service, &id).await?;
+    let fetched_at = chrono::Utc::now().timestamp_millis();
+    let _ = history_store::record_usage(&app, &service, &id, &response.windows, fetched_at);
+    Ok(response)
+}
 
-    let mut token = get_token(&service, &id)
+/// Fetch usage for an account using the token stored in the keyring.
+/// On HTTP 401 the token is refreshed (when a refresh_token is stored) and the fetch retried once.
+pub async fn fetch_stored_usage(service: &str, id: &str) -> AppResult<FetchUsageResponse> {
+    let mut token = get_token(service, id)
         .ok_or_else(|| AppError::InvalidInput("Token is not set for this account".to_string()))?;
-    if let Err(e) = enforce_fetch_usage_rate_limit(&service, &token) {
-        token.zeroize();
-        return Err(e);
-    }
-    let fetch_result = fetch_normalized_usage(&service, &token, crate::ANTHROPIC_OAUTH_BETA).await;
+    let first = fetch_with_token(service, &token).await;
+
+    let result = match first {
+        Err(AppError::Api(ApiError::Unauthorized(message)))
+            if get_refresh_token(service, id).is_some() =>
+        {
+            match token_refresh::refresh_after_unauthorized(service, id, &token).await {
+                Ok(()) => {
+                    token.zeroize();
+                    token = get_token(service, id).ok_or_else(|| {
+                        AppError::InvalidInput("Token is not set for this account".to_string())
+                    })?;
+                    fetch_with_token(service, &token).await
+                }
+                Err(e) => Err(AppError::Api(ApiError::Unauthorized(format!(
+                    "{message} (token refresh failed: {e})"
+                )))),
+            }
+        }
+        other => other,
+    };
     token.zeroize();
-    fetch_result.map_err(AppError::from)
+    result
+}
+
+async fn fetch_with_token(service: &str, token: &str) -> AppResult<FetchUsageResponse> {
+    enforce_fetch_usage_rate_limit(service, token)?;
+    fetch_normalized_usage(service, token).await.map_err(AppError::from)
 }
@@ -12,6 +12,9 @@ pub struct UsageWindow {
     #[serde(skip_serializing_if = "Option::is_none")]
     pub force_exhausted: Option<bool>,
     pub window_seconds: Option<f64>,
+    /// Burn-rate projection; set by the poll scheduler from recent history.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub forecast: Option<crate::forecast::Forecast>,
 }
 
 impl UsageWindow {
@@ -30,6 +33,18 @@ impl UsageWindow {
             status,
             force_exhausted: if force_exhausted { Some(true) } else { None },
             window_seconds,
+            forecast: None,
+        }
+    }
+
+    /// `resets_at` as epoch milliseconds. Numbers are epoch seconds, strings RFC 3339.
+    pub fn resets_at_millis(&self) -> Option<i64> {
+        match self.resets_at.as_ref()? {
+            Value::Number(n) => n.as_f64().map(|secs| (secs * 1000.0) as i64),
+            Value::String(s) => chrono::DateTime::parse_from_rfc3339(s.trim())
+                .ok()
+                .map(|dt| dt.timestamp_millis()),
+            _ => None,
         }
     }
 
@@ -41,6 +56,7 @@ impl UsageWindow {
             status: Some("unknown".to_string()),
             force_exhausted: None,
             window_seconds: None,
+            forecast: None,
         }
     }
 }
@@ -0,0 +1,113 @@
+use crate::usage_parser::UsageWindow;
+use serde::Serialize;
+use serde_json::Value;
+use std::collections::BTreeMap;
+use std::sync::{Mutex, OnceLock};
+
+/// Latest fetch result per account, keyed by `service:id`.
+static LATEST_USAGE: OnceLock<Mutex<BTreeMap<String, UsageUpdate>>> = OnceLock::new();
+/// Monotonic fetch counters per account since startup, keyed by `service:id`.
+static FETCH_COUNTS: OnceLock<Mutex<BTreeMap<String, FetchCounts>>> = OnceLock::new();
+
+#[derive(Debug, Clone, Copy, Default)]
+pub struct FetchCounts {
+    pub total: u64,
+    pub errors: u64,
+}
+
+#[derive(Debug, Clone, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct UsageUpdate {
+    pub service: String,
+    pub id: String,
+    pub name: String,
+    /// Epoch milliseconds when this result was produced.
+    pub fetched_at: i64,
+    /// Windows carry `status` from the notify thresholds at the time of classification.
+    pub windows: Vec<UsageWindow>,
+    /// Account-level status (`ok` .. `exhausted`, or `error` with no windows).
+    pub status: String,
+    pub raw: Option<Value>,
+    pub error: Option<String>,
+    /// Epoch milliseconds until which the scheduler skips this account after HTTP 429.
+    pub rate_limit_until: Option<i64>,
+    /// True when no request was sent because the account is still backing off.
+    pub skipped: bool,
+}
+
+impl UsageUpdate {
+    /// Entry in the snapshot file format (usage cache, MCP tools).
+    pub fn to_snapshot_entry(&self) -> crate::UsageSnapshotEntry {
+        crate::UsageSnapshotEntry {
+            service: self.service.clone(),
+            id: self.id.clone(),
+            name: self.name.clone(),
+            has_token: true,
+            label: Some(self.name.clone()),
+            status: Some(self.status.clone()),
+            windows: self.windows.clone(),
+            error: self.error.clone(),
+        }
+    }
+}
+
+pub fn account_key(service: &str, id: &str) -> String {
+    format!("{service}:{id}")
+}
+
+fn latest_store() -> &'static Mutex<BTreeMap<String, UsageUpdate>> {
+    LATEST_USAGE.get_or_init(|| Mutex::new(BTreeMap::new()))
+}
+
+fn counts_store() -> &'static Mutex<BTreeMap<String, FetchCounts>> {
+    FETCH_COUNTS.get_or_init(|| Mutex::new(BTreeMap::new()))
+}
+
+pub fn record(update: &UsageUpdate) {
+    if !update.skipped {
+        if let Ok(mut lock) = counts_store().lock() {
+            let counts = lock.entry(account_key(&update.service, &update.id)).or_default();
+            counts.total += 1;
+            if update.error.is_some() {
+                counts.errors += 1;
+            }
+        }
+    }
+    set_latest(update);
+}
+
+/// Replace the latest result without counting it as a fetch (reclassification).
+pub fn set_latest(update: &UsageUpdate) {
+    if let Ok(mut lock) = latest_store().lock() {
+        lock.insert(account_key(&update.service, &update.id), update.clone());
+    }
+}
+
+pub fn fetch_counts(service: &str, id: &str) -> FetchCounts {
+    counts_store()
+        .lock()
+        .ok()
+        .and_then(|lock| lock.get(&account_key(service, id)).copied())
+        .unwrap_or_default()
+}
+
+pub fn get(service: &str, id: &str) -> Option<UsageUpdate> {
+    latest_store()
+        .lock()
+        .ok()
+        .and_then(|lock| lock.get(&account_key(service, id)).cloned())
+}
+
+pub fn latest() -> Vec<UsageUpdate> {
+    latest_store()
+        .lock()
+        .map(|lock| lock.values().cloned().collect())
+        .unwrap_or_default()
+}
+
+/// Drop results for accounts that are no longer configured.
+pub fn retain_accounts(keys: &[String]) {
+    if let Ok(mut lock) = latest_store().lock() {
+        lock.retain(|key, _| keys.contains(key));
+    }
+}
@@ -1,4 +1,5 @@
 use std::collections::HashMap;
+use std::net::IpAddr;
 use std::sync::{Mutex, OnceLock};
 use std::time::{Duration, Instant};
 use crate::error::{AppError, AppResult};
@@ -94,38 +95,43 @@ pub fn validate_upstream_url(url: &str) -> AppResult<()> {
     let host = parsed
         .host_str()
         .ok_or_else(|| AppError::InvalidInput("Upstream URL must include host".to_string()))?;
-    match host {
-        "api.anthropic.com" | "chatgpt.com" | "console.anthropic.com" | "auth.openai.com" | "claude.ai" => Ok(()),
-        _ => Err(AppError::InvalidInput(
+    if crate::providers::is_allowed_host(host) {
+        Ok(())
+    } else {
+        Err(AppError::InvalidInput(
             "Upstream host is not allowlisted".to_string(),
-        )),
+        ))
     }
 }
 
 const MAX_WEBHOOK_URL_LEN: usize = 2048;
 const MAX_PUSHOVER_KEY_LEN: usize = 64;
 
-pub fn validate_discord_webhook_url(url: &str) -> AppResult<()> {
+/// Checks shared by the chat webhook validators; `label` names the URL in errors.
+fn parse_https_webhook_url(url: &str, label: &str) -> AppResult<reqwest::Url> {
     if url.is_empty() {
-        return Err(AppError::InvalidInput("Discord webhook URL is required".to_string()));
+        return Err(AppError::InvalidInput(format!("{label} is required")));
     }
     if url.len() > MAX_WEBHOOK_URL_LEN {
         return Err(AppError::InvalidInput(format!(
-            "Discord webhook URL is too long (max {MAX_WEBHOOK_URL_LEN} chars)"
+            "{label} is too long (max {MAX_WEBHOOK_URL_LEN} chars)"
         )));
     }
     if has_control_chars(url) {
-        return Err(AppError::InvalidInput(
-            "Discord webhook URL contains control characters".to_string(),
-        ));
+        return Err(AppError::InvalidInput(format!(
+            "{label} contains control characters"
+        )));
     }
     let parsed = reqwest::Url::parse(url)
-        .map_err(|e| AppError::InvalidInput(format!("Invalid Discord webhook URL: {e}")))?;
+        .map_err(|e| AppError::InvalidInput(format!("Invalid {label}: {e}")))?;
     if parsed.scheme() != "https" {
-        return Err(AppError::InvalidInput(
-            "Discord webhook URL must use https".to_string(),
-        ));
+        return Err(AppError::InvalidInput(format!("{label} must use https")));
     }
+    Ok(parsed)
+}
+
+pub fn validate_discord_webhook_url(url: &str) -> AppResult<()> {
+    let parsed = parse_https_webhook_url(url, "Discord webhook URL")?;
     let host = parsed.host_str().unwrap_or("");
     if host != "discord.com" && host != "discordapp.com" {
         return Err(AppError::InvalidInput(
@@ -140,6 +146,48 @@ pub fn validate_discord_webhook_url(url: &str) -> AppResult<()> {
     Ok(())
 }
 
+/// Slack incoming webhook (`https://hooks.slack.com/services/...`).
+pub fn validate_slack_webhook_url(url: &str) -> AppResult<()> {
+    let parsed = parse_https_webhook_url(url, "Slack webhook URL")?;
+    let host = parsed.host_str().unwrap_or("");
+    if host != "hooks.slack.com" && host != "hooks.slack-gov.com" {
+        return Err(AppError::InvalidInput(
+            "Slack webhook URL must be from hooks.slack.com".to_string(),
+        ));
+    }
+    if !parsed.path().starts_with("/services/") {
+        return Err(AppError::InvalidInput(
+            "Invalid Slack webhook URL path".to_string(),
+        ));
+    }
+    Ok(())
+}
+
+/// Teams webhook: a Workflows (Power Automate) trigger URL, or a legacy
+/// Office 365 connector URL.
+pub fn validate_teams_webhook_url(url: &str) -> AppResult<()> {
+    let parsed = parse_https_webhook_url(url, "Teams webhook URL")?;
+    let host = parsed.host_str().unwrap_or("");
+    let path = parsed.path();
+    let valid = if host.ends_with(".webhook.office.com") {
+        path.starts_with("/webhookb2/")
+    } else if host.ends_with(".logic.azure.com") {
+        path.starts_with("/workflows/")
+    } else if host.ends_with(".api.powerplatform.com") {
+        path.starts_with("/powerautomate/")
+    } else {
+        return Err(AppError::InvalidInput(
+            "Teams webhook URL must be a Workflows or office.com webhook".to_string(),
+        ));
+    };
+    if !valid {
+        return Err(AppError::InvalidInput(
+            "Invalid Teams webhook URL path".to_string(),
+        ));
+    }
+    Ok(())
+}
+
 pub fn validate_pushover_key(key: &str, label: &str) -> AppResult<()> {
     if key.is_empty() {
         return Err(AppError::InvalidInput(format!("{label} is required")));
@@ -162,6 +210,251 @@ pub fn validate_pushover_key(key: &str, label: &str) -> AppResult<()> {
     Ok(())
 }
 
+const MAX_WEBHOOK_NAME_LEN: usize = 64;
+const MAX_WEBHOOK_HEADER_NAME_LEN: usize = 128;
+const MAX_WEBHOOK_HEADER_VALUE_LEN: usize = 2048;
+const MAX_WEBHOOK_TEMPLATE_LEN: usize = 8192;
+pub const WEBHOOK_METHODS: [&str; 3] = ["POST", "PUT", "PATCH"];
+
+/// Generic webhook URL: https, or plain http to a loopback host for local relays.
+pub fn validate_webhook_url(url: &str) -> AppResult<()> {
+    if url.is_empty() {
+        return Err(AppError::InvalidInput("Webhook URL is required".to_string()));
+    }
+    if url.len() > MAX_WEBHOOK_URL_LEN {
+        return Err(AppError::InvalidInput(format!(
+            "Webhook URL is too long (max {MAX_WEBHOOK_URL_LEN} chars)"
+        )));
+    }
+    if has_control_chars(url) {
+        return Err(AppError::InvalidInput(
+            "Webhook URL contains control characters".to_string(),
+        ));
+    }
+    let parsed = reqwest::Url::parse(url)
+        .map_err(|e| AppError::InvalidInput(format!("Invalid webhook URL: {e}")))?;
+    let loopback = match parsed.host() {
+        Some(url::Host::Domain(host)) => host.eq_ignore_ascii_case("localhost"),
+        Some(url::Host::Ipv4(ip)) => ip.is_loopback(),
+        Some(url::Host::Ipv6(ip)) => ip.is_loopback(),
+        None => false,
+    };
+    match parsed.scheme() {
+        "https" => Ok(()),
+        "http" if loopback => Ok(()),
+        _ => Err(AppError::InvalidInput(
+            "Webhook URL must use https (http is allowed only for localhost)".to_string(),
+        )),
+    }
+}
+
+const MAX_NTFY_TOPIC_LEN: usize = 64;
+const MAX_PUSH_TOKEN_LEN: usize = 256;
+
+/// Hosts on this machine or the LAN: loopback, private / link-local / ULA
+/// addresses, single-label names and `.local`, `.lan`, `.home.arpa`, `.internal`.
+fn is_local_network_host(host: &url::Host<&str>) -> bool {
+    match host {
+        url::Host::Domain(name) => {
+            let name = name.trim_end_matches('.').to_ascii_lowercase();
+            !name.contains('.')
+                || [".local", ".lan", ".home.arpa", ".internal"]
+                    .iter()
+                    .any(|suffix| name.ends_with(suffix))
+        }
+        url::Host::Ipv4(ip) => ip.is_loopback() || ip.is_private() || ip.is_link_local(),
+        url::Host::Ipv6(ip) => {
+            ip.is_loopback() || (ip.segments()[0] & 0xfe00) == 0xfc00 || (ip.segments()[0] & 0xffc0) == 0xfe80
+        }
+    }
+}
+
+/// Base URL of a self-hosted push server (ntfy / Gotify). Any host is allowed;
+/// plain http only for LAN hosts, since the token travels in a header.
+pub fn validate_push_server_url(url: &str, label: &str) -> AppResult<()> {
+    if url.is_empty() {
+        return Err(AppError::InvalidInput(format!("{label} server URL is required")));
+    }
+    if url.len() > MAX_WEBHOOK_URL_LEN {
+        return Err(AppError::InvalidInput(format!(
+            "{label} server URL is too long (max {MAX_WEBHOOK_URL_LEN} chars)"
+        )));
+    }
+    if has_control_chars(url) {
+        return Err(AppError::InvalidInput(format!(
+            "{label} server URL contains control characters"
+        )));
+    }
+    let parsed = reqwest::Url::parse(url)
+        .map_err(|e| AppError::InvalidInput(format!("Invalid {label} server URL: {e}")))?;
+    if !parsed.username().is_empty() || parsed.password().is_some() {
+        return Err(AppError::InvalidInput(format!(
+            "{label} server URL must not contain credentials"
+        )));
+    }
+    if parsed.query().is_some() || parsed.fragment().is_some() {
+        return Err(AppError::InvalidInput(format!(
+            "{label} server URL must not have a query or fragment"
+        )));
+    }
+    let Some(host) = parsed.host() else {
+        return Err(AppError::InvalidInput(format!("{label} server URL has no host")));
+    };
+    match parsed.scheme() {
+        "https" => Ok(()),
+        "http" if is_local_network_host(&host) => Ok(()),
+        _ => Err(AppError::InvalidInput(format!(
+            "{label} server URL must use https (http is allowed only for LAN hosts)"
+        ))),
+    }
+}
+
+pub fn validate_ntfy_topic(topic: &str) -> AppResult<()> {
+    if topic.is_empty() {
+        return Err(AppError::InvalidInput("ntfy topic is required".to_string()));
+    }
+    if topic.len() > MAX_NTFY_TOPIC_LEN {
+        return Err(AppError::InvalidInput(format!(
+            "ntfy topic is too long (max {MAX_NTFY_TOPIC_LEN} chars)"
+        )));
+    }
+    if !topic.chars().all(|c| c.is_ascii_alphanumeric() || c == '-' || c == '_') {
+        return Err(AppError::InvalidInput(
+            "ntfy topic may contain only letters, digits, '-' and '_'".to_string(),
+        ));
+    }
+    Ok(())
+}
+
+/// ntfy access token or Gotify application token; sent as a header value.
+pub fn validate_push_token(token: &str, label: &str) -> AppResult<()> {
+    if token.is_empty() {
+        return Err(AppError::InvalidInput(format!("{label} is required")));
+    }
+    if token.len() > MAX_PUSH_TOKEN_LEN {
+        return Err(AppError::InvalidInput(format!(
+            "{label} is too long (max {MAX_PUSH_TOKEN_LEN} chars)"
+        )));
+    }
+    if !token.chars().all(|c| c.is_ascii_graphic()) {
+        return Err(AppError::InvalidInput(format!(
+            "{label} contains invalid characters"
+        )));
+    }
+    Ok(())
+}
+
+pub const WEEKDAYS: [&str; 7] = ["mon", "tue", "wed", "thu", "fri", "sat", "sun"];
+/// Channel names a quiet-hours rule may target, besides `webhook:<id>`.
+pub const NOTIFY_CHANNELS: [&str; 7] = ["desktop", "discord", "slack", "teams", "pushover", "ntfy", "gotify"];
+
+/// `HH:MM`, 24-hour clock.
+pub fn validate_time_of_day(value: &str) -> AppResult<()> {
+    let valid = value.len() == 5 && chrono::NaiveTime::parse_from_str(value, "%H:%M").is_ok();
+    if !valid {
+        return Err(AppError::InvalidInput(format!(
+            "Time must be HH:MM (got {value:?})"
+        )));
+    }
+    Ok(())
+}
+
+/// IANA time zone name, e.g. `Asia/Tokyo`.
+pub fn validate_timezone(name: &str) -> AppResult<()> {
+    name.parse::<chrono_tz::Tz>()
+        .map(|_| ())
+        .map_err(|_| AppError::InvalidInput(format!("Unknown time zone: {name}")))
+}
+
+pub fn validate_notify_channel(channel: &str) -> AppResult<()> {
+    let valid = NOTIFY_CHANNELS.contains(&channel)
+        || channel
+            .strip_prefix("webhook:")
+            .is_some_and(|id| validate_account_id(id).is_ok());
+    if !valid {
+        return Err(AppError::InvalidInput(format!(
+            "Unknown notification channel: {channel}"
+        )));
+    }
+    Ok(())
+}
+
+pub fn validate_webhook_name(name: &str) -> AppResult<()> {
+    if name.chars().count() > MAX_WEBHOOK_NAME_LEN {
+        return Err(AppError::InvalidInput(format!(
+            "Webhook name is too long (max {MAX_WEBHOOK_NAME_LEN} chars)"
+        )));
+    }
+    if has_control_chars(name) {
+        return Err(AppError::InvalidInput(
+            "Webhook name contains control characters".to_string(),
+        ));
+    }
+    Ok(())
+}
+
+pub fn validate_webhook_method(method: &str) -> AppResult<()> {
+    if WEBHOOK_METHODS.contains(&method) {
+        Ok(())
+    } else {
+        Err(AppError::InvalidInput(format!(
+            "Webhook method must be one of {}",
+            WEBHOOK_METHODS.join(", ")
+        )))
+    }
+}
+
+/// Header names are HTTP tokens; hop-by-hop and framing headers are set by the client.
+pub fn validate_webhook_header(name: &str, value: &str) -> AppResult<()> {
+    if name.is_empty() || name.len() > MAX_WEBHOOK_HEADER_NAME_LEN {
+        return Err(AppError::InvalidInput(format!(
+            "Header name must be 1-{MAX_WEBHOOK_HEADER_NAME_LEN} chars"
+        )));
+    }
+    if !name
+        .chars()
+        .all(|c| c.is_ascii_alphanumeric() || "!#$%&'*+-.^_`|~".contains(c))
+    {
+        return Err(AppError::InvalidInput(format!(
+            "Header name contains invalid characters: {name}"
+        )));
+    }
+    if ["host", "content-length", "connection", "transfer-encoding"]
+        .contains(&name.to_ascii_lowercase().as_str())
+    {
+        return Err(AppError::InvalidInput(format!("Header cannot be overridden: {name}")));
+    }
+    if value.len() > MAX_WEBHOOK_HEADER_VALUE_LEN {
+        return Err(AppError::InvalidInput(format!(
+            "Header value is too long (max {MAX_WEBHOOK_HEADER_VALUE_LEN} chars)"
+        )));
+    }
+    if has_control_chars(value) {
+        return Err(AppError::InvalidInput(format!(
+            "Header value contains control characters: {name}"
+        )));
+    }
+    Ok(())
+}
+
+/// Body templates may span lines; other control characters are rejected.
+pub fn validate_webhook_template(template: &str) -> AppResult<()> {
+    if template.len() > MAX_WEBHOOK_TEMPLATE_LEN {
+        return Err(AppError::InvalidInput(format!(
+            "Webhook body template is too long (max {MAX_WEBHOOK_TEMPLATE_LEN} chars)"
+        )));
+    }
+    if template
+        .chars()
+        .any(|c| c.is_control() && !matches!(c, '\n' | '\r' | '\t'))
+    {
+        return Err(AppError::InvalidInput(
+            "Webhook body template contains control characters".to_string(),
+        ));
+    }
+    Ok(())
+}
+
 pub fn validate_export_path(path: &str) -> AppResult<()> {
     if path.is_empty() {
         return Err(AppError::InvalidInput("Export path is required".to_string()));
@@ -179,6 +472,12 @@ pub fn validate_export_path(path: &str) -> AppResult<()> {
     Ok(())
 }
 
+/// Metrics listener address. Must be a literal IP so nothing is resolved at bind time.
+pub fn validate_bind_address(addr: &str) -> AppResult<IpAddr> {
+    addr.parse::<IpAddr>()
+        .map_err(|_| AppError::InvalidInput("Bind address must be an IP address".to_string()))
+}
+
 pub fn enforce_fetch_usage_rate_limit(service: &str, token: &str) -> AppResult<()> {
     let mut hasher = Sha256::new();
     hasher.update(token.as_bytes());
@@ -228,6 +527,14 @@ mod tests {
         assert!(validate_export_path("quota.json").is_ok());
     }
 
+    #[test]
+    fn validate_bind_address_requires_ip_literal() {
+        assert!(validate_bind_address("127.0.0.1").is_ok());
+        assert!(validate_bind_address("::1").is_ok());
+        assert!(validate_bind_address("localhost").is_err());
+        assert!(validate_bind_address("").is_err());
+    }
+
     #[test]
     fn validate_discord_webhook_url_accepts_valid() {
         assert!(validate_discord_webhook_url(
@@ -246,6 +553,77 @@ mod tests {
         assert!(validate_discord_webhook_url("https://discord.com/other/path").is_err());
     }
 
+    #[test]
+    fn validate_slack_webhook_url_checks_host_and_path() {
+        assert!(validate_slack_webhook_url("https://hooks.slack.com/services/T0/B0/abc").is_ok());
+        assert!(validate_slack_webhook_url("http://hooks.slack.com/services/T0/B0/abc").is_err());
+        assert!(validate_slack_webhook_url("https://hooks.slack.com/workflows/T0/A0").is_err());
+        assert!(validate_slack_webhook_url("https://slack.com.example/services/T0/B0/abc").is_err());
+    }
+
+    #[test]
+    fn validate_teams_webhook_url_accepts_workflow_and_connector_urls() {
+        assert!(validate_teams_webhook_url(
+            "https://prod-12.japaneast.logic.azure.com:443/workflows/abc/triggers/manual/paths/invoke?sig=x"
+        ).is_ok());
+        assert!(validate_teams_webhook_url(
+            "https://default1.environment.api.powerplatform.com/powerautomate/automations/direct/workflows/abc"
+        ).is_ok());
+        assert!(validate_teams_webhook_url("https://contoso.webhook.office.com/webhookb2/abc/IncomingWebhook/x").is_ok());
+        assert!(validate_teams_webhook_url("https://contoso.webhook.office.com/other").is_err());
+        assert!(validate_teams_webhook_url("https://example.com/workflows/abc").is_err());
+        assert!(validate_teams_webhook_url("").is_err());
+    }
+
+    #[test]
+    fn validate_push_server_url_allows_http_only_on_lan() {
+        assert!(validate_push_server_url("https://ntfy.example.com", "ntfy").is_ok());
+        assert!(validate_push_server_url("https://push.example.com/gotify/", "Gotify").is_ok());
+        assert!(validate_push_server_url("http://192.168.1.10:8080", "ntfy").is_ok());
+        assert!(validate_push_server_url("http://nas.local", "ntfy").is_ok());
+        assert!(validate_push_server_url("http://gotify:80", "Gotify").is_ok());
+        assert!(validate_push_server_url("http://[fd00::5]", "ntfy").is_ok());
+        assert!(validate_push_server_url("http://ntfy.example.com", "ntfy").is_err());
+        assert!(validate_push_server_url("http://8.8.8.8", "ntfy").is_err());
+        assert!(validate_push_server_url("https://user:pw@ntfy.example.com", "ntfy").is_err());
+        assert!(validate_push_server_url("https://ntfy.example.com/?x=1", "ntfy").is_err());
+        assert!(validate_push_server_url("ftp://nas.local", "ntfy").is_err());
+    }
+
+    #[test]
+    fn validate_ntfy_topic_and_push_token() {
+        assert!(validate_ntfy_topic("aiqm_alerts-1").is_ok());
+        assert!(validate_ntfy_topic("").is_err());
+        assert!(validate_ntfy_topic("a/b").is_err());
+        assert!(validate_push_token("tk_AgQdq7mVBoFD37zQVN29RhuMzNIz2", "token").is_ok());
+        assert!(validate_push_token("has space", "token").is_err());
+        assert!(validate_push_token("", "token").is_err());
+    }
+
+    #[test]
+    fn validate_webhook_url_requires_https_except_loopback() {
+        assert!(validate_webhook_url("https://hooks.slack.com/services/T/B/x").is_ok());
+        assert!(validate_webhook_url("http://127.0.0.1:8080/alert").is_ok());
+        assert!(validate_webhook_url("http://localhost/alert").is_ok());
+        assert!(validate_webhook_url("http://[::1]/alert").is_ok());
+        assert!(validate_webhook_url("http://example.com/alert").is_err());
+        assert!(validate_webhook_url("ftp://example.com/alert").is_err());
+        assert!(validate_webhook_url("https://example.com/a\nb").is_err());
+        assert!(validate_webhook_url(&format!("https://example.com/{}", "a".repeat(2048))).is_err());
+    }
+
+    #[test]
+    fn validate_webhook_header_and_template() {
+        assert!(validate_webhook_header("Authorization", "Bearer abc").is_ok());
+        assert!(validate_webhook_header("X Bad", "v").is_err());
+        assert!(validate_webhook_header("Host", "example.com").is_err());
+        assert!(validate_webhook_header("X-Token", "a\r\nInjected: 1").is_err());
+        assert!(validate_webhook_template("{\n  \"text\": \"{{title}}\"\n}").is_ok());
+        assert!(validate_webhook_template("a\u{7}b").is_err());
+        assert!(validate_webhook_method("PUT").is_ok());
+        assert!(validate_webhook_method("GET").is_err());
+    }
+
     #[test]
     fn validate_pushover_key_accepts_alphanumeric() {
         assert!(validate_pushover_key("abc123DEF", "API Token").is_ok());
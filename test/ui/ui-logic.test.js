@@ -2,96 +2,14 @@ const test = require('node:test');
 const assert = require('node:assert/strict');
 
 const {
-  classifyUtilization,
-  classifyWindows,
-  deriveServiceStatus,
-  buildTransitionEffects,
   deriveTokenInputValue,
   normalizeAccountToken,
   calcElapsedPct,
   computePollingState,
+  parseHeaderLines,
+  formatHeaderLines,
 } = require('../../public/ui-logic.js');
 
-const BASE_NOTIFY = {
-  critical: true,
-  recovery: true,
-  warning: true,
-  thresholdWarning: 75,
-  thresholdCritical: 90,
-};
-
-test('classifyUtilization respects warning/critical/exhausted thresholds', () => {
-  assert.equal(classifyUtilization(20, BASE_NOTIFY), 'ok');
-  assert.equal(classifyUtilization(80, BASE_NOTIFY), 'warning');
-  assert.equal(classifyUtilization(91, BASE_NOTIFY), 'critical');
-  assert.equal(classifyUtilization(100, BASE_NOTIFY), 'exhausted');
-});
-
-test('classifyWindows prioritizes forceExhausted and updates status in-place', () => {
-  const windows = [
-    { utilization: 95, forceExhausted: false, status: null },
-    { utilization: 10, forceExhausted: true, status: null },
-  ];
-
-  classifyWindows(windows, BASE_NOTIFY);
-
-  assert.equal(windows[0].status, 'critical');
-  assert.equal(windows[1].status, 'exhausted');
-});
-
-test('threshold change alters service status classification', () => {
-  const windows = [{ utilization: 80, status: null }, { utilization: 95, status: null }];
-
-  classifyWindows(windows, {
-    ...BASE_NOTIFY,
-    thresholdWarning: 75,
-    thresholdCritical: 90,
-  });
-  assert.equal(deriveServiceStatus(windows), 'critical');
-
-  classifyWindows(windows, {
-    ...BASE_NOTIFY,
-    thresholdWarning: 85,
-    thresholdCritical: 97,
-  });
-  assert.equal(deriveServiceStatus(windows), 'warning');
-});
-
-test('buildTransitionEffects emits critical notification and log', () => {
-  const windows = [{ name: '5時間', utilization: 91 }];
-
-  const effects = buildTransitionEffects('ok', 'critical', 'Claude Code: A', windows, BASE_NOTIFY);
-
-  assert.equal(effects.notifications.length, 1);
-  assert.match(effects.notifications[0].title, /⚠️/);
-  assert.match(effects.notifications[0].body, /ステータス: critical/);
-  assert.deepEqual(effects.logs, [{ level: 'crit', message: 'Claude Code: A → critical' }]);
-});
-
-test('buildTransitionEffects suppresses warning after critical/exhausted', () => {
-  const windows = [{ name: '7日間', utilization: 80 }];
-
-  const effects = buildTransitionEffects('critical', 'warning', 'Codex: B', windows, BASE_NOTIFY);
-
-  assert.deepEqual(effects.notifications, []);
-  assert.deepEqual(effects.logs, []);
-});
-
-test('buildTransitionEffects emits recovery notification and log', () => {
-  const windows = [{ name: '5時間', utilization: 10 }];
-
-  const effects = buildTransitionEffects('exhausted', 'ok', 'Codex: C', windows, BASE_NOTIFY);
-
-  assert.equal(effects.notifications.length, 1);
-  assert.match(effects.notifications[0].title, /✅/);
-  assert.deepEqual(effects.logs, [{ level: 'ok', message: 'Codex: C → ok (回復)' }]);
-});
-
-test('buildTransitionEffects returns no effects when unchanged', () => {
-  const effects = buildTransitionEffects('warning', 'warning', 'X', [], BASE_NOTIFY);
-  assert.deepEqual(effects, { notifications: [], logs: [] });
-});
-
 test('deriveTokenInputValue masks saved token without exposing plaintext', () => {
   const masked = deriveTokenInputValue({
     hasToken: true,
@@ -192,3 +110,13 @@ test('computePollingState returns progress and display metadata', () => {
   assert.equal(safe.color, 'var(--ok)');
   assert.ok(safe.fraction > 0.3);
 });
+
+test('parseHeaderLines reads "Name: value" lines and round-trips', () => {
+  const headers = parseHeaderLines('Authorization: Bearer a:b\n\nbroken line\n X-Env : prod ');
+  assert.deepEqual(headers, [
+    { name: 'Authorization', value: 'Bearer a:b' },
+    { name: 'X-Env', value: 'prod' },
+  ]);
+  assert.equal(formatHeaderLines(headers), 'Authorization: Bearer a:b\nX-Env: prod');
+  assert.equal(formatHeaderLines(undefined), '');
+});